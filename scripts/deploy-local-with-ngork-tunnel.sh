@@ -1,6 +1,8 @@
 #!/bin/bash
 
-dfx deploy --no-wallet
+source .env
+
+dfx deploy --no-wallet --argument "(opt record { webhook_secret = opt \"$TELEGRAM_SECRET_TOKEN\" })"
 
 backend_canister_id=$(dfx canister id backend)
 icx_proxy_port=$(dfx info webserver-port)
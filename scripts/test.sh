@@ -1,3 +1,3 @@
 #!/bin/bash
 
-TELEGRAM_SECRET_TOKEN=test-secret-token cargo test
+cargo test
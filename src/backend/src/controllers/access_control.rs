@@ -0,0 +1,57 @@
+use candid::Principal;
+use ic_cdk::{api::msg_caller, update};
+
+use crate::{
+    repositories::{
+        BackupRepositoryImpl, ConfigRepositoryImpl, SetWebhookSecretRequest,
+        WebhookSecretRepositoryImpl,
+    },
+    services::{AccessControlService, AccessControlServiceImpl, BackupService, BackupServiceImpl},
+};
+
+#[update]
+fn set_webhook_secret(req: SetWebhookSecretRequest) -> Result<(), String> {
+    let calling_principal = msg_caller();
+
+    AccessControlController::default().set_webhook_secret(calling_principal, req)
+}
+
+struct AccessControlController<A: AccessControlService, B: BackupService> {
+    access_control_service: A,
+    backup_service: B,
+}
+
+impl Default
+    for AccessControlController<
+        AccessControlServiceImpl<WebhookSecretRepositoryImpl, ConfigRepositoryImpl>,
+        BackupServiceImpl<BackupRepositoryImpl>,
+    >
+{
+    fn default() -> Self {
+        Self::new(
+            AccessControlServiceImpl::default(),
+            BackupServiceImpl::default(),
+        )
+    }
+}
+
+impl<A: AccessControlService, B: BackupService> AccessControlController<A, B> {
+    fn new(access_control_service: A, backup_service: B) -> Self {
+        Self {
+            access_control_service,
+            backup_service,
+        }
+    }
+
+    fn set_webhook_secret(
+        &self,
+        calling_principal: Principal,
+        req: SetWebhookSecretRequest,
+    ) -> Result<(), String> {
+        self.access_control_service
+            .assert_caller_is_controller(&calling_principal);
+        self.backup_service.assert_not_in_maintenance_mode();
+
+        self.access_control_service.set_webhook_secret(req)
+    }
+}
@@ -0,0 +1,133 @@
+use candid::Principal;
+use ic_cdk::{api::msg_caller, query, update};
+
+use crate::{
+    repositories::{
+        AccessPolicy, AccessPolicyEntries, AccessPolicyMode, AccessPolicyRepositoryImpl,
+        BackupRepositoryImpl, ConfigRepositoryImpl, WebhookSecretRepositoryImpl,
+    },
+    services::{
+        AccessControlService, AccessControlServiceImpl, AccessPolicyService,
+        AccessPolicyServiceImpl, BackupService, BackupServiceImpl,
+    },
+};
+
+#[query]
+fn get_access_policy() -> AccessPolicy {
+    let calling_principal = msg_caller();
+
+    AccessPolicyController::default().get_access_policy(calling_principal)
+}
+
+#[update]
+fn set_access_policy_mode(mode: AccessPolicyMode) {
+    let calling_principal = msg_caller();
+
+    AccessPolicyController::default().set_access_policy_mode(calling_principal, mode)
+}
+
+#[update]
+fn add_access_policy_entries(entries: AccessPolicyEntries) {
+    let calling_principal = msg_caller();
+
+    AccessPolicyController::default().add_access_policy_entries(calling_principal, entries)
+}
+
+#[update]
+fn remove_access_policy_entries(entries: AccessPolicyEntries) {
+    let calling_principal = msg_caller();
+
+    AccessPolicyController::default().remove_access_policy_entries(calling_principal, entries)
+}
+
+#[update]
+fn set_invite_code(invite_code: Option<String>) -> Result<(), String> {
+    let calling_principal = msg_caller();
+
+    AccessPolicyController::default().set_invite_code(calling_principal, invite_code)
+}
+
+struct AccessPolicyController<A: AccessControlService, L: AccessPolicyService, B: BackupService> {
+    access_control_service: A,
+    access_policy_service: L,
+    backup_service: B,
+}
+
+impl Default
+    for AccessPolicyController<
+        AccessControlServiceImpl<WebhookSecretRepositoryImpl, ConfigRepositoryImpl>,
+        AccessPolicyServiceImpl<AccessPolicyRepositoryImpl>,
+        BackupServiceImpl<BackupRepositoryImpl>,
+    >
+{
+    fn default() -> Self {
+        Self::new(
+            AccessControlServiceImpl::default(),
+            AccessPolicyServiceImpl::default(),
+            BackupServiceImpl::default(),
+        )
+    }
+}
+
+impl<A: AccessControlService, L: AccessPolicyService, B: BackupService>
+    AccessPolicyController<A, L, B>
+{
+    fn new(access_control_service: A, access_policy_service: L, backup_service: B) -> Self {
+        Self {
+            access_control_service,
+            access_policy_service,
+            backup_service,
+        }
+    }
+
+    fn get_access_policy(&self, calling_principal: Principal) -> AccessPolicy {
+        self.access_control_service
+            .assert_caller_is_controller(&calling_principal);
+
+        self.access_policy_service.get_access_policy()
+    }
+
+    fn set_access_policy_mode(&self, calling_principal: Principal, mode: AccessPolicyMode) {
+        self.access_control_service
+            .assert_caller_is_controller(&calling_principal);
+        self.backup_service.assert_not_in_maintenance_mode();
+
+        self.access_policy_service.set_mode(mode);
+    }
+
+    fn add_access_policy_entries(
+        &self,
+        calling_principal: Principal,
+        entries: AccessPolicyEntries,
+    ) {
+        self.access_control_service
+            .assert_caller_is_controller(&calling_principal);
+        self.backup_service.assert_not_in_maintenance_mode();
+
+        self.access_policy_service.add_entries(entries);
+    }
+
+    fn remove_access_policy_entries(
+        &self,
+        calling_principal: Principal,
+        entries: AccessPolicyEntries,
+    ) {
+        self.access_control_service
+            .assert_caller_is_controller(&calling_principal);
+        self.backup_service.assert_not_in_maintenance_mode();
+
+        self.access_policy_service.remove_entries(entries);
+    }
+
+    fn set_invite_code(
+        &self,
+        calling_principal: Principal,
+        invite_code: Option<String>,
+    ) -> Result<(), String> {
+        self.access_control_service
+            .assert_caller_is_controller(&calling_principal);
+        self.backup_service.assert_not_in_maintenance_mode();
+
+        self.access_policy_service.set_invite_code(invite_code)
+    }
+}
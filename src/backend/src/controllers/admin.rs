@@ -0,0 +1,218 @@
+use candid::Principal;
+use ic_cdk::{api::msg_caller, query, update};
+
+use crate::{
+    repositories::{
+        BackupRepositoryImpl, ChatId, ChatMemberSession, ChatRoleRepositoryImpl,
+        ChatSessionRepositoryImpl, ChatSettingsRepositoryImpl, CheckChatsConsistencyResponse,
+        ConfigRepositoryImpl, DriveRepositoryImpl, FileSystemNode, FilesystemRepositoryImpl,
+        JournalRepositoryImpl, ListChatIdsRequest, ListChatIdsResponse,
+        ProcessedUpdateRepositoryImpl, QuarantinedFileSystemNode, RateLimitRepositoryImpl,
+        ShareRepositoryImpl, WebhookSecretRepositoryImpl,
+    },
+    services::{
+        AccessControlService, AccessControlServiceImpl, AdminService, AdminServiceImpl,
+        BackupService, BackupServiceImpl, ChatRoleServiceImpl, ChatSessionService,
+        ChatSessionServiceImpl, ChatSettingsServiceImpl, DriveServiceImpl, FilesystemServiceImpl,
+        JournalServiceImpl, ProcessedUpdateServiceImpl, RateLimitServiceImpl, ShareServiceImpl,
+    },
+};
+
+#[query]
+fn list_chat_ids(req: ListChatIdsRequest) -> ListChatIdsResponse {
+    let calling_principal = msg_caller();
+
+    AdminController::default().list_chat_ids(calling_principal, req)
+}
+
+#[query]
+fn get_chat_sessions(chat_id: ChatId) -> Vec<ChatMemberSession> {
+    let calling_principal = msg_caller();
+
+    AdminController::default().get_chat_sessions(calling_principal, chat_id)
+}
+
+#[query]
+fn get_chat_filesystem(chat_id: ChatId) -> Result<Option<FileSystemNode>, String> {
+    let calling_principal = msg_caller();
+
+    AdminController::default().get_chat_filesystem(calling_principal, chat_id)
+}
+
+#[query]
+fn get_quarantined_chat_filesystem(chat_id: ChatId) -> Vec<QuarantinedFileSystemNode> {
+    let calling_principal = msg_caller();
+
+    AdminController::default().get_quarantined_chat_filesystem(calling_principal, chat_id)
+}
+
+#[update]
+fn reset_chat_session(chat_id: ChatId) {
+    let calling_principal = msg_caller();
+
+    AdminController::default().reset_chat_session(calling_principal, chat_id)
+}
+
+#[update]
+fn delete_chat_data(chat_id: ChatId) {
+    let calling_principal = msg_caller();
+
+    AdminController::default().delete_chat_data(calling_principal, chat_id)
+}
+
+#[query]
+fn check_chat_consistency(chat_id: ChatId) -> Vec<String> {
+    let calling_principal = msg_caller();
+
+    AdminController::default().check_chat_consistency(calling_principal, chat_id)
+}
+
+#[query]
+fn check_chats_consistency(req: ListChatIdsRequest) -> CheckChatsConsistencyResponse {
+    let calling_principal = msg_caller();
+
+    AdminController::default().check_chats_consistency(calling_principal, req)
+}
+
+struct AdminController<
+    A: AccessControlService,
+    S: AdminService,
+    C: ChatSessionService,
+    B: BackupService,
+> {
+    access_control_service: A,
+    admin_service: S,
+    chat_session_service: C,
+    backup_service: B,
+}
+
+impl Default
+    for AdminController<
+        AccessControlServiceImpl<WebhookSecretRepositoryImpl, ConfigRepositoryImpl>,
+        AdminServiceImpl<ChatSessionRepositoryImpl, FilesystemRepositoryImpl>,
+        ChatSessionServiceImpl<
+            ChatSessionRepositoryImpl,
+            FilesystemServiceImpl<FilesystemRepositoryImpl, ConfigRepositoryImpl>,
+            JournalServiceImpl<JournalRepositoryImpl, ConfigRepositoryImpl>,
+            ProcessedUpdateServiceImpl<ProcessedUpdateRepositoryImpl, ConfigRepositoryImpl>,
+            ChatRoleServiceImpl<ChatRoleRepositoryImpl>,
+            ShareServiceImpl<
+                ShareRepositoryImpl,
+                FilesystemServiceImpl<FilesystemRepositoryImpl, ConfigRepositoryImpl>,
+            >,
+            DriveServiceImpl<
+                DriveRepositoryImpl,
+                FilesystemServiceImpl<FilesystemRepositoryImpl, ConfigRepositoryImpl>,
+            >,
+            ConfigRepositoryImpl,
+            ChatSettingsServiceImpl<ChatSettingsRepositoryImpl>,
+            RateLimitServiceImpl<RateLimitRepositoryImpl, ConfigRepositoryImpl>,
+        >,
+        BackupServiceImpl<BackupRepositoryImpl>,
+    >
+{
+    fn default() -> Self {
+        Self::new(
+            AccessControlServiceImpl::default(),
+            AdminServiceImpl::default(),
+            ChatSessionServiceImpl::default(),
+            BackupServiceImpl::default(),
+        )
+    }
+}
+
+impl<A: AccessControlService, S: AdminService, C: ChatSessionService, B: BackupService>
+    AdminController<A, S, C, B>
+{
+    fn new(
+        access_control_service: A,
+        admin_service: S,
+        chat_session_service: C,
+        backup_service: B,
+    ) -> Self {
+        Self {
+            access_control_service,
+            admin_service,
+            chat_session_service,
+            backup_service,
+        }
+    }
+
+    fn list_chat_ids(
+        &self,
+        calling_principal: Principal,
+        req: ListChatIdsRequest,
+    ) -> ListChatIdsResponse {
+        self.access_control_service
+            .assert_caller_is_controller(&calling_principal);
+
+        self.admin_service.list_chat_ids(req)
+    }
+
+    fn get_chat_sessions(
+        &self,
+        calling_principal: Principal,
+        chat_id: ChatId,
+    ) -> Vec<ChatMemberSession> {
+        self.access_control_service
+            .assert_caller_is_controller(&calling_principal);
+
+        self.admin_service.get_chat_sessions(&chat_id)
+    }
+
+    fn get_chat_filesystem(
+        &self,
+        calling_principal: Principal,
+        chat_id: ChatId,
+    ) -> Result<Option<FileSystemNode>, String> {
+        self.access_control_service
+            .assert_caller_is_controller(&calling_principal);
+
+        self.admin_service.get_filesystem(&chat_id)
+    }
+
+    fn get_quarantined_chat_filesystem(
+        &self,
+        calling_principal: Principal,
+        chat_id: ChatId,
+    ) -> Vec<QuarantinedFileSystemNode> {
+        self.access_control_service
+            .assert_caller_is_controller(&calling_principal);
+
+        self.admin_service.get_quarantined_filesystem(&chat_id)
+    }
+
+    fn reset_chat_session(&self, calling_principal: Principal, chat_id: ChatId) {
+        self.access_control_service
+            .assert_caller_is_controller(&calling_principal);
+        self.backup_service.assert_not_in_maintenance_mode();
+
+        self.admin_service.reset_chat_session(chat_id)
+    }
+
+    fn delete_chat_data(&self, calling_principal: Principal, chat_id: ChatId) {
+        self.access_control_service
+            .assert_caller_is_controller(&calling_principal);
+        self.backup_service.assert_not_in_maintenance_mode();
+
+        self.chat_session_service.delete_chat_data(&chat_id)
+    }
+
+    fn check_chat_consistency(&self, calling_principal: Principal, chat_id: ChatId) -> Vec<String> {
+        self.access_control_service
+            .assert_caller_is_controller(&calling_principal);
+
+        self.admin_service.check_chat_consistency(&chat_id)
+    }
+
+    fn check_chats_consistency(
+        &self,
+        calling_principal: Principal,
+        req: ListChatIdsRequest,
+    ) -> CheckChatsConsistencyResponse {
+        self.access_control_service
+            .assert_caller_is_controller(&calling_principal);
+
+        self.admin_service.check_chats_consistency(req)
+    }
+}
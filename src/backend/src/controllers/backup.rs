@@ -0,0 +1,134 @@
+use candid::Principal;
+use ic_cdk::{api::msg_caller, query, update};
+
+use crate::{
+    repositories::{
+        BackupChunk, BackupChunkRequest, BackupManifest, BackupRepositoryImpl,
+        ConfigRepositoryImpl, WebhookSecretRepositoryImpl,
+    },
+    services::{AccessControlService, AccessControlServiceImpl, BackupService, BackupServiceImpl},
+};
+
+#[update]
+fn start_backup() -> Result<BackupManifest, String> {
+    let calling_principal = msg_caller();
+
+    BackupController::default().start_backup(calling_principal)
+}
+
+#[query]
+fn get_backup_chunk(req: BackupChunkRequest) -> Result<BackupChunk, String> {
+    let calling_principal = msg_caller();
+
+    BackupController::default().get_backup_chunk(calling_principal, req)
+}
+
+#[update]
+fn finish_backup() -> Result<(), String> {
+    let calling_principal = msg_caller();
+
+    BackupController::default().finish_backup(calling_principal)
+}
+
+#[update]
+fn start_restore(manifest: BackupManifest) -> Result<(), String> {
+    let calling_principal = msg_caller();
+
+    BackupController::default().start_restore(calling_principal, manifest)
+}
+
+#[update]
+fn restore_chunk(chunk: BackupChunk) -> Result<(), String> {
+    let calling_principal = msg_caller();
+
+    BackupController::default().restore_chunk(calling_principal, chunk)
+}
+
+#[update]
+fn finish_restore() -> Result<(), String> {
+    let calling_principal = msg_caller();
+
+    BackupController::default().finish_restore(calling_principal)
+}
+
+struct BackupController<A: AccessControlService, B: BackupService> {
+    access_control_service: A,
+    backup_service: B,
+}
+
+impl Default
+    for BackupController<
+        AccessControlServiceImpl<WebhookSecretRepositoryImpl, ConfigRepositoryImpl>,
+        BackupServiceImpl<BackupRepositoryImpl>,
+    >
+{
+    fn default() -> Self {
+        Self::new(
+            AccessControlServiceImpl::default(),
+            BackupServiceImpl::default(),
+        )
+    }
+}
+
+impl<A: AccessControlService, B: BackupService> BackupController<A, B> {
+    fn new(access_control_service: A, backup_service: B) -> Self {
+        Self {
+            access_control_service,
+            backup_service,
+        }
+    }
+
+    fn start_backup(&self, calling_principal: Principal) -> Result<BackupManifest, String> {
+        self.access_control_service
+            .assert_caller_is_controller(&calling_principal);
+
+        self.backup_service.start_backup()
+    }
+
+    fn get_backup_chunk(
+        &self,
+        calling_principal: Principal,
+        req: BackupChunkRequest,
+    ) -> Result<BackupChunk, String> {
+        self.access_control_service
+            .assert_caller_is_controller(&calling_principal);
+
+        self.backup_service.get_backup_chunk(req)
+    }
+
+    fn finish_backup(&self, calling_principal: Principal) -> Result<(), String> {
+        self.access_control_service
+            .assert_caller_is_controller(&calling_principal);
+
+        self.backup_service.finish_backup()
+    }
+
+    fn start_restore(
+        &self,
+        calling_principal: Principal,
+        manifest: BackupManifest,
+    ) -> Result<(), String> {
+        self.access_control_service
+            .assert_caller_is_controller(&calling_principal);
+
+        self.backup_service.start_restore(manifest)
+    }
+
+    fn restore_chunk(
+        &self,
+        calling_principal: Principal,
+        chunk: BackupChunk,
+    ) -> Result<(), String> {
+        self.access_control_service
+            .assert_caller_is_controller(&calling_principal);
+
+        self.backup_service.restore_chunk(chunk)
+    }
+
+    fn finish_restore(&self, calling_principal: Principal) -> Result<(), String> {
+        self.access_control_service
+            .assert_caller_is_controller(&calling_principal);
+
+        self.backup_service.finish_restore()
+    }
+}
@@ -2,10 +2,16 @@ use candid::Principal;
 use ic_cdk::{api::msg_caller, query};
 
 use crate::{
-    repositories::{ChatSessionRepositoryImpl, FilesystemRepositoryImpl},
+    repositories::{
+        ChatRoleRepositoryImpl, ChatSessionRepositoryImpl, ChatSettingsRepositoryImpl,
+        ConfigRepositoryImpl, DriveRepositoryImpl, FilesystemRepositoryImpl, JournalRepositoryImpl,
+        ProcessedUpdateRepositoryImpl, RateLimitRepositoryImpl, ShareRepositoryImpl,
+        WebhookSecretRepositoryImpl,
+    },
     services::{
-        AccessControlService, AccessControlServiceImpl, ChatSessionService, ChatSessionServiceImpl,
-        FilesystemServiceImpl,
+        AccessControlService, AccessControlServiceImpl, ChatRoleServiceImpl, ChatSessionService,
+        ChatSessionServiceImpl, ChatSettingsServiceImpl, DriveServiceImpl, FilesystemServiceImpl,
+        JournalServiceImpl, ProcessedUpdateServiceImpl, RateLimitServiceImpl, ShareServiceImpl,
     },
 };
 
@@ -23,10 +29,24 @@ struct ChatSessionController<A: AccessControlService, C: ChatSessionService> {
 
 impl Default
     for ChatSessionController<
-        AccessControlServiceImpl,
+        AccessControlServiceImpl<WebhookSecretRepositoryImpl, ConfigRepositoryImpl>,
         ChatSessionServiceImpl<
             ChatSessionRepositoryImpl,
-            FilesystemServiceImpl<FilesystemRepositoryImpl>,
+            FilesystemServiceImpl<FilesystemRepositoryImpl, ConfigRepositoryImpl>,
+            JournalServiceImpl<JournalRepositoryImpl, ConfigRepositoryImpl>,
+            ProcessedUpdateServiceImpl<ProcessedUpdateRepositoryImpl, ConfigRepositoryImpl>,
+            ChatRoleServiceImpl<ChatRoleRepositoryImpl>,
+            ShareServiceImpl<
+                ShareRepositoryImpl,
+                FilesystemServiceImpl<FilesystemRepositoryImpl, ConfigRepositoryImpl>,
+            >,
+            DriveServiceImpl<
+                DriveRepositoryImpl,
+                FilesystemServiceImpl<FilesystemRepositoryImpl, ConfigRepositoryImpl>,
+            >,
+            ConfigRepositoryImpl,
+            ChatSettingsServiceImpl<ChatSettingsRepositoryImpl>,
+            RateLimitServiceImpl<RateLimitRepositoryImpl, ConfigRepositoryImpl>,
         >,
     >
 {
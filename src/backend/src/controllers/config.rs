@@ -0,0 +1,73 @@
+use candid::Principal;
+use ic_cdk::{api::msg_caller, query, update};
+
+use crate::{
+    repositories::{
+        BackupRepositoryImpl, Config, ConfigRepositoryImpl, WebhookSecretRepositoryImpl,
+    },
+    services::{
+        AccessControlService, AccessControlServiceImpl, BackupService, BackupServiceImpl,
+        ConfigService, ConfigServiceImpl,
+    },
+};
+
+#[query]
+fn get_config() -> Config {
+    let calling_principal = msg_caller();
+
+    ConfigController::default().get_config(calling_principal)
+}
+
+#[update]
+fn set_config(config: Config) -> Result<(), String> {
+    let calling_principal = msg_caller();
+
+    ConfigController::default().set_config(calling_principal, config)
+}
+
+struct ConfigController<A: AccessControlService, C: ConfigService, B: BackupService> {
+    access_control_service: A,
+    config_service: C,
+    backup_service: B,
+}
+
+impl Default
+    for ConfigController<
+        AccessControlServiceImpl<WebhookSecretRepositoryImpl, ConfigRepositoryImpl>,
+        ConfigServiceImpl<ConfigRepositoryImpl>,
+        BackupServiceImpl<BackupRepositoryImpl>,
+    >
+{
+    fn default() -> Self {
+        Self::new(
+            AccessControlServiceImpl::default(),
+            ConfigServiceImpl::default(),
+            BackupServiceImpl::default(),
+        )
+    }
+}
+
+impl<A: AccessControlService, C: ConfigService, B: BackupService> ConfigController<A, C, B> {
+    fn new(access_control_service: A, config_service: C, backup_service: B) -> Self {
+        Self {
+            access_control_service,
+            config_service,
+            backup_service,
+        }
+    }
+
+    fn get_config(&self, calling_principal: Principal) -> Config {
+        self.access_control_service
+            .assert_caller_is_controller(&calling_principal);
+
+        self.config_service.get_config()
+    }
+
+    fn set_config(&self, calling_principal: Principal, config: Config) -> Result<(), String> {
+        self.access_control_service
+            .assert_caller_is_controller(&calling_principal);
+        self.backup_service.assert_not_in_maintenance_mode();
+
+        self.config_service.set_config(config)
+    }
+}
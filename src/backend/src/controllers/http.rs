@@ -4,16 +4,27 @@ use ic_cdk::{query, update};
 use crate::{
     custom_print,
     repositories::{
-        ChatId, ChatSessionRepositoryImpl, FilesystemRepositoryImpl, HeaderField, HttpRequest,
-        HttpResponse, HttpUpdateRequest,
+        update_sender, AccessDecision, AccessPolicyRepositoryImpl, BackupRepositoryImpl, ChatId,
+        ChatRoleRepositoryImpl, ChatSessionRepositoryImpl, ChatSettingsRepositoryImpl,
+        ConfigRepositoryImpl, DriveRepositoryImpl, FilesystemRepositoryImpl, HeaderField,
+        HttpRequest, HttpResponse, HttpUpdateRequest, JournalRepositoryImpl, Language,
+        ProcessedUpdateRepositoryImpl, RateLimitDecision, RateLimitRepositoryImpl,
+        ShareRepositoryImpl, WebhookSecretRepositoryImpl,
     },
     services::{
-        AccessControlService, AccessControlServiceImpl, ChatSessionService, ChatSessionServiceImpl,
-        FilesystemServiceImpl,
+        AccessControlService, AccessControlServiceImpl, AccessPolicyService,
+        AccessPolicyServiceImpl, BackupService, BackupServiceImpl, ChatRoleServiceImpl,
+        ChatSessionService, ChatSessionServiceImpl, ChatSettingsService, ChatSettingsServiceImpl,
+        DriveServiceImpl, FilesystemServiceImpl, JournalServiceImpl, ProcessedUpdateService,
+        ProcessedUpdateServiceImpl, RateLimitService, RateLimitServiceImpl, ShareServiceImpl,
     },
     utils::{
-        http::{error500, ok200},
-        MessageParams,
+        http::{error500, error503, ok200},
+        messages::{
+            access_denied_message, chat_throttled_message, enrolled_message,
+            global_throttled_message, update_error_message,
+        },
+        set_language, MessageParams,
     },
 };
 
@@ -33,24 +44,61 @@ fn http_request_update(req: HttpUpdateRequest) -> HttpResponse {
     HttpController::default().http_request(req)
 }
 
-struct HttpController<A: AccessControlService, C: ChatSessionService> {
+struct HttpController<
+    A: AccessControlService,
+    C: ChatSessionService,
+    B: BackupService,
+    P: ProcessedUpdateService,
+    R: RateLimitService,
+    L: AccessPolicyService,
+    S: ChatSettingsService,
+> {
     access_control_service: A,
     chat_session_service: C,
+    backup_service: B,
+    processed_update_service: P,
+    rate_limit_service: R,
+    access_policy_service: L,
+    chat_settings_service: S,
 }
 
 impl Default
     for HttpController<
-        AccessControlServiceImpl,
+        AccessControlServiceImpl<WebhookSecretRepositoryImpl, ConfigRepositoryImpl>,
         ChatSessionServiceImpl<
             ChatSessionRepositoryImpl,
-            FilesystemServiceImpl<FilesystemRepositoryImpl>,
+            FilesystemServiceImpl<FilesystemRepositoryImpl, ConfigRepositoryImpl>,
+            JournalServiceImpl<JournalRepositoryImpl, ConfigRepositoryImpl>,
+            ProcessedUpdateServiceImpl<ProcessedUpdateRepositoryImpl, ConfigRepositoryImpl>,
+            ChatRoleServiceImpl<ChatRoleRepositoryImpl>,
+            ShareServiceImpl<
+                ShareRepositoryImpl,
+                FilesystemServiceImpl<FilesystemRepositoryImpl, ConfigRepositoryImpl>,
+            >,
+            DriveServiceImpl<
+                DriveRepositoryImpl,
+                FilesystemServiceImpl<FilesystemRepositoryImpl, ConfigRepositoryImpl>,
+            >,
+            ConfigRepositoryImpl,
+            ChatSettingsServiceImpl<ChatSettingsRepositoryImpl>,
+            RateLimitServiceImpl<RateLimitRepositoryImpl, ConfigRepositoryImpl>,
         >,
+        BackupServiceImpl<BackupRepositoryImpl>,
+        ProcessedUpdateServiceImpl<ProcessedUpdateRepositoryImpl, ConfigRepositoryImpl>,
+        RateLimitServiceImpl<RateLimitRepositoryImpl, ConfigRepositoryImpl>,
+        AccessPolicyServiceImpl<AccessPolicyRepositoryImpl>,
+        ChatSettingsServiceImpl<ChatSettingsRepositoryImpl>,
     >
 {
     fn default() -> Self {
         Self::new(
             AccessControlServiceImpl::default(),
             ChatSessionServiceImpl::default(),
+            BackupServiceImpl::default(),
+            ProcessedUpdateServiceImpl::default(),
+            RateLimitServiceImpl::default(),
+            AccessPolicyServiceImpl::default(),
+            ChatSettingsServiceImpl::default(),
         )
     }
 }
@@ -58,23 +106,62 @@ impl Default
 fn http_response(message_params: &MessageParams) -> Result<HttpResponse, String> {
     let value = message_params.json_value()?;
 
-    Ok(HttpResponse {
+    Ok(json_http_response(
+        serde_json::to_vec(&value).map_err(|err| err.to_string())?,
+    ))
+}
+
+/// Replies to the chat with a plain text message.
+fn text_http_response(chat_id: ChatId, text: String) -> HttpResponse {
+    let mut params = MessageParams::new_send(chat_id);
+    params.set_text(text);
+    params.set_parse_mode(None);
+    http_response(&params).unwrap_or_else(|err| {
+        custom_print!("Error: {}", err);
+        ok200()
+    })
+}
+
+fn json_http_response(body: Vec<u8>) -> HttpResponse {
+    HttpResponse {
         status_code: 200,
         headers: vec![HeaderField(
             String::from("content-type"),
             String::from("application/json"),
         )],
-        body: serde_json::to_vec(&value).map_err(|err| err.to_string())?,
+        body,
         upgrade: Some(false),
         streaming_strategy: None,
-    })
+    }
 }
 
-impl<A: AccessControlService, C: ChatSessionService> HttpController<A, C> {
-    fn new(access_control_service: A, chat_session_service: C) -> Self {
+impl<
+        A: AccessControlService,
+        C: ChatSessionService,
+        B: BackupService,
+        P: ProcessedUpdateService,
+        R: RateLimitService,
+        L: AccessPolicyService,
+        S: ChatSettingsService,
+    > HttpController<A, C, B, P, R, L, S>
+{
+    fn new(
+        access_control_service: A,
+        chat_session_service: C,
+        backup_service: B,
+        processed_update_service: P,
+        rate_limit_service: R,
+        access_policy_service: L,
+        chat_settings_service: S,
+    ) -> Self {
         Self {
             access_control_service,
             chat_session_service,
+            backup_service,
+            processed_update_service,
+            rate_limit_service,
+            access_policy_service,
+            chat_settings_service,
         }
     }
 
@@ -97,16 +184,74 @@ impl<A: AccessControlService, C: ChatSessionService> HttpController<A, C> {
             return ok200();
         }
 
+        if self.backup_service.is_in_maintenance_mode() {
+            custom_print!("Maintenance mode, the update will be retried");
+            // Telegram retries the request later, when the backup or restore is finished
+            return error503();
+        }
+
         let update: Update = match serde_json::from_slice(&req.body) {
             Ok(update) => update,
             Err(err) => return error500(Some(err)),
         };
 
+        // Telegram retries the deliveries that it considers failed, e.g. after a timeout
+        if let Some(processed_update) = self
+            .processed_update_service
+            .get_processed_update(update.update_id)
+        {
+            custom_print!("Update {} already processed", update.update_id);
+            return processed_update
+                .response_body
+                .map(json_http_response)
+                .unwrap_or_else(ok200);
+        }
+
+        let chat_id = ChatId::try_from(&update.content).ok();
+        // the replies are in the language chosen for the chat, or else in the language of the sender
+        set_language(chat_id.as_ref().map_or_else(Language::default, |chat_id| {
+            self.chat_settings_service
+                .get_language(chat_id, update_sender(&update.content))
+        }));
+        let throttled_message = match self.rate_limit_service.check_update(chat_id.as_ref()) {
+            RateLimitDecision::Allowed => None,
+            RateLimitDecision::ChatThrottled => Some(chat_throttled_message()),
+            RateLimitDecision::GlobalThrottled => Some(global_throttled_message()),
+        };
+        if let Some(text) = throttled_message {
+            custom_print!("Update {} throttled", update.update_id);
+            let Some(chat_id) = chat_id else {
+                return ok200();
+            };
+            return text_http_response(chat_id, text);
+        }
+
+        // rejected updates are not recorded, so that nothing is stored about the rejected users
+        if let Some(chat_id) = &chat_id {
+            match self
+                .access_policy_service
+                .check_update(chat_id, &update.content)
+            {
+                AccessDecision::Allowed => {}
+                AccessDecision::Enrolled => {
+                    return text_http_response(chat_id.clone(), enrolled_message());
+                }
+                AccessDecision::Rejected => {
+                    custom_print!("Update {} rejected by the access policy", update.update_id);
+                    return text_http_response(chat_id.clone(), access_denied_message());
+                }
+            }
+        }
+
+        self.processed_update_service
+            .record_update(update.update_id, chat_id);
+
         match self.process_tg_update_content(update.content) {
-            Ok(params) => http_response(&params),
+            Ok(Some(params)) => http_response(&params),
+            Ok(None) => Ok(ok200()),
             Err((err, None)) => Err(err),
             Err((err, Some(chat_id))) => {
-                let err_msg = format!("Error processing update content: {}", err);
+                let err_msg = update_error_message(err);
                 custom_print!("{}", err_msg);
                 let mut params = MessageParams::new_send(chat_id);
                 params.set_text(err_msg);
@@ -114,6 +259,10 @@ impl<A: AccessControlService, C: ChatSessionService> HttpController<A, C> {
                 http_response(&params)
             }
         }
+        .inspect(|res| {
+            self.processed_update_service
+                .set_update_response(update.update_id, res.body.clone());
+        })
         .unwrap_or_else(|err| {
             custom_print!("Error: {}", err);
             // returning 500 causes Telegram to retry the request, which is not what we want in this case
@@ -124,17 +273,51 @@ impl<A: AccessControlService, C: ChatSessionService> HttpController<A, C> {
     fn process_tg_update_content(
         &self,
         update_content: UpdateContent,
-    ) -> Result<MessageParams, (String, Option<ChatId>)> {
+    ) -> Result<Option<MessageParams>, (String, Option<ChatId>)> {
         let chat_id = ChatId::try_from(&update_content).map_err(|err| (err, None))?;
         custom_print!("Message from chat_id: {}", chat_id);
 
         let res = match update_content {
-            UpdateContent::Message(msg) => self
-                .chat_session_service
-                .handle_update_content_message(chat_id.clone(), msg),
+            UpdateContent::Message(msg) if !self.chat_session_service.is_addressed_to_bot(&msg) => {
+                custom_print!("Ignoring a command addressed to another bot");
+                Ok(None)
+            }
+            UpdateContent::Message(msg) => {
+                // the files of an album arrive in separate messages, only the first one is answered
+                if self
+                    .chat_session_service
+                    .add_media_group_file(&chat_id, &msg)
+                {
+                    Ok(None)
+                } else {
+                    self.chat_session_service
+                        .handle_update_content_message(chat_id.clone(), msg)
+                        .map(Some)
+                }
+            }
             UpdateContent::CallbackQuery(query) => self
                 .chat_session_service
-                .handle_update_content_callback_query(chat_id.clone(), query),
+                .handle_update_content_callback_query(chat_id.clone(), query)
+                .map(Some),
+            UpdateContent::EditedMessage(msg) => {
+                self.chat_session_service
+                    .handle_update_content_edited_message(chat_id.clone(), msg);
+                Ok(None)
+            }
+            UpdateContent::InlineQuery(query) => self
+                .chat_session_service
+                .handle_update_content_inline_query(chat_id.clone(), query)
+                .map(Some),
+            UpdateContent::MyChatMember(chat_member_updated) => {
+                self.chat_session_service
+                    .handle_update_content_chat_member(chat_member_updated, true);
+                Ok(None)
+            }
+            UpdateContent::ChatMember(chat_member_updated) => {
+                self.chat_session_service
+                    .handle_update_content_chat_member(chat_member_updated, false);
+                Ok(None)
+            }
             _ => Err("Unsupported update content".to_string()),
         };
 
@@ -0,0 +1,154 @@
+use ic_cdk::{
+    api::{global_timer_set, time},
+    futures::{in_executor_context, spawn},
+    init,
+    management_canister::raw_rand,
+    post_upgrade, trap,
+};
+
+use crate::{
+    custom_print,
+    repositories::{CanisterArgs, SetWebhookSecretRequest},
+    services::{
+        AccessControlService, AccessControlServiceImpl, BackupService, BackupServiceImpl,
+        ChatSessionService, ChatSessionServiceImpl, ConfigService, ConfigServiceImpl,
+        FilesystemService, FilesystemServiceImpl, ShareService, ShareServiceImpl,
+    },
+};
+
+#[init]
+fn init(args: Option<CanisterArgs>) {
+    let args = args.unwrap_or_default();
+    set_config(&args);
+    set_webhook_secret(args, Some(0));
+    // the randomness can only be requested once the canister is installed
+    global_timer_set(time());
+}
+
+/// The legacy filesystems migrated in a single message, so that a large
+/// number of them doesn't exceed the instruction limit of the upgrade.
+/// The chats that send an update are migrated before their batch comes.
+const LEGACY_FILESYSTEMS_BATCH_SIZE: usize = 100;
+
+/// The chats whose text files stored before the index are indexed in a single message.
+const TEXT_FILES_INDEX_BATCH_SIZE: usize = 100;
+
+#[post_upgrade]
+fn post_upgrade(args: Option<CanisterArgs>) {
+    migrate_legacy_filesystems();
+    let migrated_count = ChatSessionServiceImpl::default().migrate_legacy_chat_sessions();
+    custom_print!(
+        "post_upgrade: migrated {} legacy chat sessions",
+        migrated_count
+    );
+
+    let args = args.unwrap_or_default();
+    set_config(&args);
+    // the current secret is still accepted for the configured rotation period
+    set_webhook_secret(args, None);
+
+    if !ShareServiceImpl::default().has_token_salt()
+        || FilesystemServiceImpl::default().has_unindexed_text_files()
+    {
+        global_timer_set(time());
+    }
+}
+
+/// How long the timer waits for a backup or a restore to finish.
+const MAINTENANCE_MODE_RETRY_DELAY_NANOS: u64 = 60 * 1_000_000_000;
+
+/// How long the generation of the share token salt waits after a failure.
+const SHARE_TOKEN_SALT_RETRY_DELAY_NANOS: u64 = 10 * 1_000_000_000;
+
+/// Migrates a batch of legacy filesystems, and schedules the next batch if there are more.
+fn migrate_legacy_filesystems() {
+    if BackupServiceImpl::default().is_in_maintenance_mode() {
+        global_timer_set(time() + MAINTENANCE_MODE_RETRY_DELAY_NANOS);
+        return;
+    }
+
+    let filesystem_service = FilesystemServiceImpl::default();
+    let migrated_count =
+        filesystem_service.migrate_legacy_filesystems(LEGACY_FILESYSTEMS_BATCH_SIZE);
+    custom_print!("Migrated {} legacy filesystems", migrated_count);
+
+    if filesystem_service.has_legacy_filesystems() {
+        global_timer_set(time());
+    }
+}
+
+/// Indexes the text files of a batch of chats, and schedules the next batch if there are more.
+fn index_text_files() {
+    if BackupServiceImpl::default().is_in_maintenance_mode() {
+        global_timer_set(time() + MAINTENANCE_MODE_RETRY_DELAY_NANOS);
+        return;
+    }
+
+    let filesystem_service = FilesystemServiceImpl::default();
+    let indexed_count = filesystem_service.index_text_files(TEXT_FILES_INDEX_BATCH_SIZE);
+    custom_print!("Indexed the text files of {} chats", indexed_count);
+
+    if filesystem_service.has_unindexed_text_files() {
+        global_timer_set(time());
+    }
+}
+
+/// Generates the salt of the share tokens, if it's not there yet. Retried at the next timer on failure.
+async fn generate_share_token_salt() {
+    // the salt would overwrite the one being restored
+    if BackupServiceImpl::default().is_in_maintenance_mode() {
+        global_timer_set(time() + MAINTENANCE_MODE_RETRY_DELAY_NANOS);
+        return;
+    }
+
+    let share_service = ShareServiceImpl::default();
+    if share_service.has_token_salt() {
+        return;
+    }
+
+    match raw_rand().await {
+        // a restore may have started while waiting for the randomness
+        Ok(_) if BackupServiceImpl::default().is_in_maintenance_mode() => {
+            global_timer_set(time() + MAINTENANCE_MODE_RETRY_DELAY_NANOS);
+        }
+        Ok(salt) => share_service.set_token_salt(salt),
+        Err(err) => {
+            custom_print!("Failed to generate the share token salt: {err}");
+            global_timer_set(time() + SHARE_TOKEN_SALT_RETRY_DELAY_NANOS);
+        }
+    }
+}
+
+#[export_name = "canister_global_timer"]
+fn global_timer() {
+    in_executor_context(|| {
+        migrate_legacy_filesystems();
+        index_text_files();
+        spawn(generate_share_token_salt());
+    });
+}
+
+fn set_config(args: &CanisterArgs) {
+    let Some(config) = args.config.clone() else {
+        return;
+    };
+
+    if let Err(err) = ConfigServiceImpl::default().set_config(config) {
+        trap(&err);
+    }
+}
+
+fn set_webhook_secret(args: CanisterArgs, rotation_period_seconds: Option<u64>) {
+    let Some(secret) = args.webhook_secret else {
+        return;
+    };
+
+    if let Err(err) =
+        AccessControlServiceImpl::default().set_webhook_secret(SetWebhookSecretRequest {
+            secret,
+            rotation_period_seconds,
+        })
+    {
+        trap(&err);
+    }
+}
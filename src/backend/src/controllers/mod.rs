@@ -1,2 +1,9 @@
+mod access_control;
+mod access_policy;
+mod admin;
+mod backup;
 mod chat_session;
+mod config;
 mod http;
+mod lifecycle;
+mod rate_limit;
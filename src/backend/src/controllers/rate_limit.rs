@@ -0,0 +1,54 @@
+use candid::Principal;
+use ic_cdk::{api::msg_caller, query};
+
+use crate::{
+    repositories::{
+        ConfigRepositoryImpl, RateLimitCounters, RateLimitRepositoryImpl,
+        WebhookSecretRepositoryImpl,
+    },
+    services::{
+        AccessControlService, AccessControlServiceImpl, RateLimitService, RateLimitServiceImpl,
+    },
+};
+
+#[query]
+fn get_rate_limit_counters() -> RateLimitCounters {
+    let calling_principal = msg_caller();
+
+    RateLimitController::default().get_rate_limit_counters(calling_principal)
+}
+
+struct RateLimitController<A: AccessControlService, R: RateLimitService> {
+    access_control_service: A,
+    rate_limit_service: R,
+}
+
+impl Default
+    for RateLimitController<
+        AccessControlServiceImpl<WebhookSecretRepositoryImpl, ConfigRepositoryImpl>,
+        RateLimitServiceImpl<RateLimitRepositoryImpl, ConfigRepositoryImpl>,
+    >
+{
+    fn default() -> Self {
+        Self::new(
+            AccessControlServiceImpl::default(),
+            RateLimitServiceImpl::default(),
+        )
+    }
+}
+
+impl<A: AccessControlService, R: RateLimitService> RateLimitController<A, R> {
+    fn new(access_control_service: A, rate_limit_service: R) -> Self {
+        Self {
+            access_control_service,
+            rate_limit_service,
+        }
+    }
+
+    fn get_rate_limit_counters(&self, calling_principal: Principal) -> RateLimitCounters {
+        self.access_control_service
+            .assert_caller_is_controller(&calling_principal);
+
+        self.rate_limit_service.get_counters()
+    }
+}
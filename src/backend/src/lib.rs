@@ -2,3 +2,29 @@ mod controllers;
 mod repositories;
 mod services;
 mod utils;
+
+// the types of the exported methods, for the candid interface
+#[allow(unused_imports)]
+use repositories::*;
+
+ic_cdk::export_candid!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candid_parser::utils::{service_equal, CandidSource};
+    use std::path::Path;
+
+    #[test]
+    fn candid_interface_matches_did_file() {
+        let did_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("backend.did");
+
+        service_equal(
+            CandidSource::Text(&__export_service()),
+            CandidSource::File(&did_path),
+        )
+        .unwrap_or_else(|err| {
+            panic!("backend.did is out of date with the exported methods: {err}")
+        });
+    }
+}
@@ -0,0 +1,66 @@
+use std::cell::RefCell;
+
+use crate::custom_print;
+
+use super::{
+    init_access_policy, AccessPolicy, AccessPolicyMemory, AccessPolicyMode, VersionedValue,
+};
+
+pub trait AccessPolicyRepository {
+    fn get_access_policy(&self) -> AccessPolicy;
+
+    fn set_access_policy(&self, access_policy: AccessPolicy);
+}
+
+pub struct AccessPolicyRepositoryImpl {}
+
+impl Default for AccessPolicyRepositoryImpl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AccessPolicyRepository for AccessPolicyRepositoryImpl {
+    fn get_access_policy(&self) -> AccessPolicy {
+        // an undecodable policy is replaced with an empty allowlist, so that the bot doesn't open up
+        STATE
+            .with_borrow(|s| s.access_policy.get().decode())
+            .unwrap_or_else(|err| {
+                custom_print!("Discarding undecodable access policy: {err}");
+                AccessPolicy {
+                    mode: AccessPolicyMode::Allowlist,
+                    ..AccessPolicy::default()
+                }
+            })
+    }
+
+    fn set_access_policy(&self, access_policy: AccessPolicy) {
+        STATE.with_borrow_mut(|s| {
+            s.access_policy
+                .set(VersionedValue::new(&access_policy))
+                .unwrap()
+        });
+    }
+}
+
+impl AccessPolicyRepositoryImpl {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+struct AccessPolicyState {
+    access_policy: AccessPolicyMemory,
+}
+
+impl Default for AccessPolicyState {
+    fn default() -> Self {
+        Self {
+            access_policy: init_access_policy(),
+        }
+    }
+}
+
+thread_local! {
+    static STATE: RefCell<AccessPolicyState> = RefCell::new(AccessPolicyState::default());
+}
@@ -0,0 +1,137 @@
+use std::cell::RefCell;
+
+use ic_stable_structures::Memory;
+
+use super::{
+    get_memory_region, get_memory_region_ids, is_memory_region_empty, MaintenanceMode,
+    MemoryRegionId, MemoryRegionInfo,
+};
+
+const WASM_PAGE_SIZE: u64 = 65536;
+
+pub trait BackupRepository {
+    fn get_memory_regions(&self) -> Vec<MemoryRegionInfo>;
+
+    fn read_memory_region(
+        &self,
+        memory_id: MemoryRegionId,
+        offset: u64,
+        length: u64,
+    ) -> Result<Vec<u8>, String>;
+
+    /// Whether the region holds no data, only the empty structure that the canister creates on its own.
+    fn is_memory_region_empty(&self, memory_id: MemoryRegionId) -> bool;
+
+    /// Grows the region so that it's at least `size` bytes long.
+    fn grow_memory_region(&self, memory_id: MemoryRegionId, size: u64) -> Result<(), String>;
+
+    fn write_memory_region(
+        &self,
+        memory_id: MemoryRegionId,
+        offset: u64,
+        data: &[u8],
+    ) -> Result<(), String>;
+
+    fn get_maintenance_mode(&self) -> Option<MaintenanceMode>;
+
+    fn set_maintenance_mode(&self, maintenance_mode: Option<MaintenanceMode>);
+}
+
+pub struct BackupRepositoryImpl {}
+
+impl Default for BackupRepositoryImpl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BackupRepository for BackupRepositoryImpl {
+    fn get_memory_regions(&self) -> Vec<MemoryRegionInfo> {
+        get_memory_region_ids()
+            .iter()
+            .filter_map(|memory_id| {
+                get_memory_region(*memory_id).map(|memory| MemoryRegionInfo {
+                    memory_id: *memory_id,
+                    size: memory.size() * WASM_PAGE_SIZE,
+                })
+            })
+            .collect()
+    }
+
+    fn read_memory_region(
+        &self,
+        memory_id: MemoryRegionId,
+        offset: u64,
+        length: u64,
+    ) -> Result<Vec<u8>, String> {
+        let memory = get_memory_region_or_err(memory_id)?;
+        let size = memory.size() * WASM_PAGE_SIZE;
+        if offset.checked_add(length).is_none_or(|end| end > size) {
+            return Err(format!("Range out of bounds of memory region {memory_id}"));
+        }
+
+        let mut data = vec![0; length as usize];
+        memory.read(offset, &mut data);
+        Ok(data)
+    }
+
+    fn is_memory_region_empty(&self, memory_id: MemoryRegionId) -> bool {
+        is_memory_region_empty(memory_id)
+    }
+
+    fn grow_memory_region(&self, memory_id: MemoryRegionId, size: u64) -> Result<(), String> {
+        let memory = get_memory_region_or_err(memory_id)?;
+        let pages = size.div_ceil(WASM_PAGE_SIZE).saturating_sub(memory.size());
+        if pages > 0 && memory.grow(pages) < 0 {
+            return Err(format!("Cannot grow memory region {memory_id}"));
+        }
+        Ok(())
+    }
+
+    fn write_memory_region(
+        &self,
+        memory_id: MemoryRegionId,
+        offset: u64,
+        data: &[u8],
+    ) -> Result<(), String> {
+        let memory = get_memory_region_or_err(memory_id)?;
+        let size = memory.size() * WASM_PAGE_SIZE;
+        if offset
+            .checked_add(data.len() as u64)
+            .is_none_or(|end| end > size)
+        {
+            return Err(format!("Range out of bounds of memory region {memory_id}"));
+        }
+
+        memory.write(offset, data);
+        Ok(())
+    }
+
+    fn get_maintenance_mode(&self) -> Option<MaintenanceMode> {
+        STATE.with_borrow(|s| s.maintenance_mode)
+    }
+
+    fn set_maintenance_mode(&self, maintenance_mode: Option<MaintenanceMode>) {
+        STATE.with_borrow_mut(|s| s.maintenance_mode = maintenance_mode);
+    }
+}
+
+impl BackupRepositoryImpl {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+fn get_memory_region_or_err(memory_id: MemoryRegionId) -> Result<impl Memory, String> {
+    get_memory_region(memory_id).ok_or_else(|| format!("Unknown memory region {memory_id}"))
+}
+
+/// Kept on the heap only: an upgrade ends any backup or restore in progress.
+#[derive(Default)]
+struct BackupState {
+    maintenance_mode: Option<MaintenanceMode>,
+}
+
+thread_local! {
+    static STATE: RefCell<BackupState> = RefCell::new(BackupState::default());
+}
@@ -0,0 +1,83 @@
+use std::cell::RefCell;
+
+use crate::custom_print;
+
+use super::{init_chat_role, ChatId, ChatMemberId, ChatRole, ChatRoleMemory, VersionedValue};
+
+pub trait ChatRoleRepository {
+    fn get_chat_role(&self, chat_member_id: &ChatMemberId) -> Option<ChatRole>;
+
+    fn set_chat_role(&self, chat_member_id: ChatMemberId, chat_role: ChatRole);
+
+    fn remove_chat_role(&self, chat_member_id: &ChatMemberId);
+
+    fn remove_chat_roles_by_chat_id(&self, chat_id: &ChatId);
+}
+
+pub struct ChatRoleRepositoryImpl {}
+
+impl Default for ChatRoleRepositoryImpl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ChatRoleRepository for ChatRoleRepositoryImpl {
+    fn get_chat_role(&self, chat_member_id: &ChatMemberId) -> Option<ChatRole> {
+        let stored_chat_role = STATE.with_borrow(|s| s.chat_role.get(chat_member_id))?;
+        stored_chat_role
+            .decode()
+            .inspect_err(|err| {
+                custom_print!(
+                    "Discarding undecodable role of user {} in chat {}: {err}",
+                    chat_member_id.user_id,
+                    chat_member_id.chat_id
+                );
+            })
+            .ok()
+    }
+
+    fn set_chat_role(&self, chat_member_id: ChatMemberId, chat_role: ChatRole) {
+        STATE.with_borrow_mut(|s| {
+            s.chat_role
+                .insert(chat_member_id, VersionedValue::new(&chat_role))
+        });
+    }
+
+    fn remove_chat_role(&self, chat_member_id: &ChatMemberId) {
+        STATE.with_borrow_mut(|s| s.chat_role.remove(chat_member_id));
+    }
+
+    fn remove_chat_roles_by_chat_id(&self, chat_id: &ChatId) {
+        let (lowest, highest) = ChatMemberId::chat_bounds(chat_id);
+        STATE.with_borrow_mut(|s| {
+            let chat_member_ids: Vec<ChatMemberId> =
+                s.chat_role.keys_range(lowest..=highest).collect();
+            for chat_member_id in chat_member_ids {
+                s.chat_role.remove(&chat_member_id);
+            }
+        });
+    }
+}
+
+impl ChatRoleRepositoryImpl {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+struct ChatRoleState {
+    chat_role: ChatRoleMemory,
+}
+
+impl Default for ChatRoleState {
+    fn default() -> Self {
+        Self {
+            chat_role: init_chat_role(),
+        }
+    }
+}
+
+thread_local! {
+    static STATE: RefCell<ChatRoleState> = RefCell::new(ChatRoleState::default());
+}
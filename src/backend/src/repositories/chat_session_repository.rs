@@ -1,13 +1,31 @@
-use std::cell::RefCell;
+use std::{cell::RefCell, ops::Bound};
 
-use super::{init_chat_session, ChatId, ChatSession, ChatSessionMemory};
+use crate::custom_print;
+
+use super::{
+    init_chat_session, init_legacy_chat_session, ChatId, ChatMemberId, ChatMemberSession,
+    ChatSession, ChatSessionMemory, LegacyChatSessionMemory, VersionedValue,
+};
 
 pub trait ChatSessionRepository {
-    fn get_chat_session_by_chat_id(&self, chat_id: &ChatId) -> Option<ChatSession>;
+    /// A session that can't be decoded anymore is discarded,
+    /// so that the member starts again from a fresh session.
+    fn get_chat_session(&self, chat_member_id: &ChatMemberId) -> Option<ChatSession>;
 
-    fn set_chat_session_by_chat_id(&self, chat_id: ChatId, chat_session: ChatSession);
+    fn set_chat_session(&self, chat_member_id: ChatMemberId, chat_session: ChatSession);
 
     fn get_chat_session_count(&self) -> u64;
+
+    /// Returns the sessions of all the members of the chat.
+    fn get_chat_sessions_by_chat_id(&self, chat_id: &ChatId) -> Vec<ChatMemberSession>;
+
+    /// Returns up to `limit` chat ids in ascending order, starting after `start_after`.
+    fn list_chat_ids(&self, start_after: Option<&ChatId>, limit: usize) -> Vec<ChatId>;
+
+    fn remove_chat_sessions_by_chat_id(&self, chat_id: &ChatId);
+
+    /// Removes and returns a session stored with the legacy per-chat layout, if any.
+    fn pop_legacy_chat_session(&self) -> Option<(ChatId, ChatSession)>;
 }
 
 pub struct ChatSessionRepositoryImpl {}
@@ -19,17 +37,91 @@ impl Default for ChatSessionRepositoryImpl {
 }
 
 impl ChatSessionRepository for ChatSessionRepositoryImpl {
-    fn get_chat_session_by_chat_id(&self, chat_id: &ChatId) -> Option<ChatSession> {
-        STATE.with_borrow(|s| s.chat_session.get(chat_id))
+    fn get_chat_session(&self, chat_member_id: &ChatMemberId) -> Option<ChatSession> {
+        let stored_chat_session = STATE.with_borrow(|s| s.chat_session.get(chat_member_id))?;
+        stored_chat_session
+            .decode()
+            .inspect_err(|err| {
+                custom_print!(
+                    "Discarding undecodable chat session of user {} in chat {}: {err}",
+                    chat_member_id.user_id,
+                    chat_member_id.chat_id
+                );
+            })
+            .ok()
     }
 
-    fn set_chat_session_by_chat_id(&self, chat_id: ChatId, chat_session: ChatSession) {
-        STATE.with_borrow_mut(|s| s.chat_session.insert(chat_id, chat_session));
+    fn set_chat_session(&self, chat_member_id: ChatMemberId, chat_session: ChatSession) {
+        STATE.with_borrow_mut(|s| {
+            s.chat_session
+                .insert(chat_member_id, VersionedValue::new(&chat_session))
+        });
     }
 
     fn get_chat_session_count(&self) -> u64 {
         STATE.with_borrow(|s| s.chat_session.len())
     }
+
+    fn get_chat_sessions_by_chat_id(&self, chat_id: &ChatId) -> Vec<ChatMemberSession> {
+        let (lowest, highest) = ChatMemberId::chat_bounds(chat_id);
+        let chat_member_ids: Vec<ChatMemberId> =
+            STATE.with_borrow(|s| s.chat_session.keys_range(lowest..=highest).collect());
+        chat_member_ids
+            .into_iter()
+            .filter_map(|chat_member_id| {
+                let chat_session = self.get_chat_session(&chat_member_id)?;
+                Some(ChatMemberSession {
+                    user_id: chat_member_id.user_id,
+                    chat_session,
+                })
+            })
+            .collect()
+    }
+
+    fn list_chat_ids(&self, start_after: Option<&ChatId>, limit: usize) -> Vec<ChatId> {
+        let start = match start_after {
+            Some(chat_id) => Bound::Excluded(ChatMemberId::chat_bounds(chat_id).1),
+            None => Bound::Unbounded,
+        };
+        STATE.with_borrow(|s| {
+            let mut chat_ids: Vec<ChatId> = vec![];
+            // the sessions of the same chat are next to each other
+            for chat_member_id in s.chat_session.keys_range((start, Bound::Unbounded)) {
+                if chat_ids.last() == Some(&chat_member_id.chat_id) {
+                    continue;
+                }
+                if chat_ids.len() >= limit {
+                    break;
+                }
+                chat_ids.push(chat_member_id.chat_id);
+            }
+            chat_ids
+        })
+    }
+
+    fn remove_chat_sessions_by_chat_id(&self, chat_id: &ChatId) {
+        let (lowest, highest) = ChatMemberId::chat_bounds(chat_id);
+        STATE.with_borrow_mut(|s| {
+            let chat_member_ids: Vec<ChatMemberId> =
+                s.chat_session.keys_range(lowest..=highest).collect();
+            for chat_member_id in chat_member_ids {
+                s.chat_session.remove(&chat_member_id);
+            }
+        });
+    }
+
+    fn pop_legacy_chat_session(&self) -> Option<(ChatId, ChatSession)> {
+        loop {
+            let (chat_id, stored_chat_session) =
+                STATE.with_borrow_mut(|s| s.legacy_chat_session.pop_first())?;
+            match stored_chat_session.decode() {
+                Ok(chat_session) => return Some((chat_id, chat_session)),
+                Err(err) => {
+                    custom_print!("Discarding undecodable chat session of chat {chat_id}: {err}");
+                }
+            }
+        }
+    }
 }
 
 impl ChatSessionRepositoryImpl {
@@ -39,12 +131,14 @@ impl ChatSessionRepositoryImpl {
 }
 
 struct SessionState {
+    legacy_chat_session: LegacyChatSessionMemory,
     chat_session: ChatSessionMemory,
 }
 
 impl Default for SessionState {
     fn default() -> Self {
         Self {
+            legacy_chat_session: init_legacy_chat_session(),
             chat_session: init_chat_session(),
         }
     }
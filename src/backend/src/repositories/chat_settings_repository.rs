@@ -0,0 +1,66 @@
+use std::cell::RefCell;
+
+use crate::custom_print;
+
+use super::{init_chat_settings, ChatId, ChatSettings, ChatSettingsMemory, VersionedValue};
+
+pub trait ChatSettingsRepository {
+    fn get_chat_settings(&self, chat_id: &ChatId) -> Option<ChatSettings>;
+
+    fn set_chat_settings(&self, chat_id: ChatId, chat_settings: ChatSettings);
+
+    fn remove_chat_settings(&self, chat_id: &ChatId);
+}
+
+pub struct ChatSettingsRepositoryImpl {}
+
+impl Default for ChatSettingsRepositoryImpl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ChatSettingsRepository for ChatSettingsRepositoryImpl {
+    fn get_chat_settings(&self, chat_id: &ChatId) -> Option<ChatSettings> {
+        let stored_chat_settings = STATE.with_borrow(|s| s.chat_settings.get(chat_id))?;
+        stored_chat_settings
+            .decode()
+            .inspect_err(|err| {
+                custom_print!("Discarding undecodable settings of chat {chat_id}: {err}");
+            })
+            .ok()
+    }
+
+    fn set_chat_settings(&self, chat_id: ChatId, chat_settings: ChatSettings) {
+        STATE.with_borrow_mut(|s| {
+            s.chat_settings
+                .insert(chat_id, VersionedValue::new(&chat_settings))
+        });
+    }
+
+    fn remove_chat_settings(&self, chat_id: &ChatId) {
+        STATE.with_borrow_mut(|s| s.chat_settings.remove(chat_id));
+    }
+}
+
+impl ChatSettingsRepositoryImpl {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+struct ChatSettingsState {
+    chat_settings: ChatSettingsMemory,
+}
+
+impl Default for ChatSettingsState {
+    fn default() -> Self {
+        Self {
+            chat_settings: init_chat_settings(),
+        }
+    }
+}
+
+thread_local! {
+    static STATE: RefCell<ChatSettingsState> = RefCell::new(ChatSettingsState::default());
+}
@@ -0,0 +1,56 @@
+use std::cell::RefCell;
+
+use crate::custom_print;
+
+use super::{init_config, Config, ConfigMemory, VersionedValue};
+
+pub trait ConfigRepository {
+    fn get_config(&self) -> Config;
+
+    fn set_config(&self, config: Config);
+}
+
+pub struct ConfigRepositoryImpl {}
+
+impl Default for ConfigRepositoryImpl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConfigRepository for ConfigRepositoryImpl {
+    fn get_config(&self) -> Config {
+        STATE
+            .with_borrow(|s| s.config.get().decode())
+            .inspect_err(|err| {
+                custom_print!("Discarding undecodable config: {err}");
+            })
+            .unwrap_or_default()
+    }
+
+    fn set_config(&self, config: Config) {
+        STATE.with_borrow_mut(|s| s.config.set(VersionedValue::new(&config)).unwrap());
+    }
+}
+
+impl ConfigRepositoryImpl {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+struct ConfigState {
+    config: ConfigMemory,
+}
+
+impl Default for ConfigState {
+    fn default() -> Self {
+        Self {
+            config: init_config(),
+        }
+    }
+}
+
+thread_local! {
+    static STATE: RefCell<ConfigState> = RefCell::new(ConfigState::default());
+}
@@ -0,0 +1,115 @@
+use std::cell::RefCell;
+
+use crate::custom_print;
+
+use super::{
+    init_drive, init_next_drive_id, ChatId, Drive, DriveId, DriveKey, DriveMemory,
+    NextDriveIdMemory, VersionedValue,
+};
+
+pub trait DriveRepository {
+    fn get_drive(&self, chat_id: &ChatId, drive_id: DriveId) -> Option<Drive>;
+
+    /// Returns the drives of the chat, in creation order.
+    fn get_drives_by_chat_id(&self, chat_id: &ChatId) -> Vec<(DriveId, Drive)>;
+
+    /// Stores a new drive of the chat and returns its id. The ids of the deleted drives are not reused.
+    fn create_drive(&self, chat_id: ChatId, drive: Drive) -> DriveId;
+
+    fn remove_drive(&self, chat_id: &ChatId, drive_id: DriveId);
+
+    fn remove_drives_by_chat_id(&self, chat_id: &ChatId);
+}
+
+pub struct DriveRepositoryImpl {}
+
+impl Default for DriveRepositoryImpl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DriveRepository for DriveRepositoryImpl {
+    fn get_drive(&self, chat_id: &ChatId, drive_id: DriveId) -> Option<Drive> {
+        let stored_drive =
+            STATE.with_borrow(|s| s.drive.get(&DriveKey::new(chat_id.clone(), drive_id)))?;
+        stored_drive
+            .decode()
+            .inspect_err(|err| {
+                custom_print!("Discarding undecodable drive {drive_id} of chat {chat_id}: {err}");
+            })
+            .ok()
+    }
+
+    fn get_drives_by_chat_id(&self, chat_id: &ChatId) -> Vec<(DriveId, Drive)> {
+        let (lowest, highest) = DriveKey::chat_bounds(chat_id);
+        let drive_ids: Vec<DriveId> = STATE.with_borrow(|s| {
+            s.drive
+                .keys_range(lowest..=highest)
+                .map(|key| key.drive_id)
+                .collect()
+        });
+        drive_ids
+            .into_iter()
+            .filter_map(|drive_id| Some((drive_id, self.get_drive(chat_id, drive_id)?)))
+            .collect()
+    }
+
+    fn create_drive(&self, chat_id: ChatId, drive: Drive) -> DriveId {
+        let (lowest, highest) = DriveKey::chat_bounds(&chat_id);
+        STATE.with_borrow_mut(|s| {
+            // the drives created before the counter was stored have lower ids than the next one
+            let drive_id = s
+                .drive
+                .keys_range(lowest..=highest)
+                .next_back()
+                .map_or(1, |key| key.drive_id + 1)
+                .max(s.next_drive_id.get(&chat_id).unwrap_or(1));
+            s.next_drive_id.insert(chat_id.clone(), drive_id + 1);
+            s.drive.insert(
+                DriveKey::new(chat_id, drive_id),
+                VersionedValue::new(&drive),
+            );
+            drive_id
+        })
+    }
+
+    fn remove_drive(&self, chat_id: &ChatId, drive_id: DriveId) {
+        STATE.with_borrow_mut(|s| s.drive.remove(&DriveKey::new(chat_id.clone(), drive_id)));
+    }
+
+    fn remove_drives_by_chat_id(&self, chat_id: &ChatId) {
+        let (lowest, highest) = DriveKey::chat_bounds(chat_id);
+        STATE.with_borrow_mut(|s| {
+            let keys: Vec<DriveKey> = s.drive.keys_range(lowest..=highest).collect();
+            for key in keys {
+                s.drive.remove(&key);
+            }
+            s.next_drive_id.remove(chat_id);
+        });
+    }
+}
+
+impl DriveRepositoryImpl {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+struct DriveState {
+    drive: DriveMemory,
+    next_drive_id: NextDriveIdMemory,
+}
+
+impl Default for DriveState {
+    fn default() -> Self {
+        Self {
+            drive: init_drive(),
+            next_drive_id: init_next_drive_id(),
+        }
+    }
+}
+
+thread_local! {
+    static STATE: RefCell<DriveState> = RefCell::new(DriveState::default());
+}
@@ -1,11 +1,74 @@
-use std::cell::RefCell;
+use std::{cell::RefCell, collections::BTreeSet};
 
-use super::{init_filesystem, ChatId, FileSystem, FilesystemMemory};
+use crate::custom_print;
+use ic_stable_structures::Storable;
+
+use super::{
+    init_drive_nodes, init_filesystem_nodes, init_legacy_filesystem, init_next_node_id,
+    init_quarantined_filesystem, init_text_file_index, init_text_file_index_progress, ChatId,
+    DriveId, DriveKey, DriveNodeKey, DriveNodesMemory, FileSystem, FileSystemNodeKey,
+    FileSystemNodeLoader, FilesystemNodesMemory, LegacyFileSystem, LegacyFilesystemMemory,
+    MessageId, NextNodeIdMemory, NodeId, QuarantinedFileSystemNode, QuarantinedFilesystemMemory,
+    QuarantinedNodeKey, StoredFileSystemNode, TextFileIndexMemory, TextFileIndexProgress,
+    TextFileIndexProgressMemory, TextFileKey, VersionedValue, ROOT_NODE_ID,
+};
 
 pub trait FilesystemRepository {
     fn get_filesystem_by_chat_id(&self, chat_id: &ChatId) -> Option<FileSystem>;
 
     fn set_filesystem_by_chat_id(&self, chat_id: ChatId, filesystem: FileSystem);
+
+    /// Removes and returns the first filesystem stored with the legacy whole-tree layout, if any.
+    fn pop_legacy_filesystem(&self) -> Option<(ChatId, VersionedValue<LegacyFileSystem>)>;
+
+    fn has_legacy_filesystems(&self) -> bool;
+
+    /// Removes and returns the chat's filesystem stored with the legacy whole-tree layout, if any.
+    fn remove_legacy_filesystem(
+        &self,
+        chat_id: &ChatId,
+    ) -> Option<VersionedValue<LegacyFileSystem>>;
+
+    /// Moves a legacy filesystem that can't be decoded to the quarantine region,
+    /// where its bytes are kept as the chat's root node.
+    fn quarantine_legacy_filesystem(
+        &self,
+        chat_id: ChatId,
+        legacy_filesystem: VersionedValue<LegacyFileSystem>,
+    );
+
+    /// Moves all the stored nodes of the chat's filesystem to the quarantine region,
+    /// where they are kept as they are for inspection, next to the ones of the earlier quarantines.
+    /// Returns the number of moved nodes.
+    fn quarantine_filesystem_by_chat_id(&self, chat_id: &ChatId) -> u64;
+
+    /// Returns up to `limit` ids of the chats that have a filesystem, in ascending order,
+    /// starting after `start_after`.
+    fn list_chat_ids(&self, start_after: Option<&ChatId>, limit: usize) -> Vec<ChatId>;
+
+    /// Returns the ids of all the stored nodes of the chat's filesystem, reachable or not.
+    fn get_node_ids_by_chat_id(&self, chat_id: &ChatId) -> BTreeSet<NodeId>;
+
+    fn get_quarantined_nodes_by_chat_id(&self, chat_id: &ChatId) -> Vec<QuarantinedFileSystemNode>;
+
+    /// Removes all the nodes of the chat's filesystem, including the quarantined ones,
+    /// the ones of its drives and its legacy filesystem that hasn't been migrated yet.
+    fn remove_filesystem_by_chat_id(&self, chat_id: &ChatId);
+
+    fn get_drive_filesystem(&self, chat_id: &ChatId, drive_id: DriveId) -> Option<FileSystem>;
+
+    fn set_drive_filesystem(&self, chat_id: ChatId, drive_id: DriveId, filesystem: FileSystem);
+
+    /// Removes all the nodes of the drive's filesystem, without decoding them.
+    fn remove_drive_filesystem(&self, chat_id: &ChatId, drive_id: DriveId);
+
+    /// Indexes the text files of up to `limit` chats, starting after `start_after`, including the files
+    /// stored before the index was kept. Returns the ids of the indexed chats, in ascending order.
+    fn index_stored_text_files(&self, start_after: Option<&ChatId>, limit: usize) -> Vec<ChatId>;
+
+    fn get_text_file_index_progress(&self) -> TextFileIndexProgress;
+
+    fn set_text_file_index_progress(&self, progress: TextFileIndexProgress);
 }
 
 pub struct FilesystemRepositoryImpl {}
@@ -18,11 +81,330 @@ impl Default for FilesystemRepositoryImpl {
 
 impl FilesystemRepository for FilesystemRepositoryImpl {
     fn get_filesystem_by_chat_id(&self, chat_id: &ChatId) -> Option<FileSystem> {
-        STATE.with_borrow(|s| s.filesystem.get(chat_id))
+        let next_node_id = STATE.with_borrow(|s| {
+            let last_node_id = s
+                .filesystem_nodes
+                .keys_range(chat_node_keys_range(chat_id))
+                .next_back()?
+                .node_id();
+            Some(stored_next_node_id(
+                &s.next_node_id,
+                chat_id,
+                None,
+                last_node_id,
+            ))
+        })?;
+
+        Some(FileSystem::with_loader(
+            next_node_id,
+            Box::new(ChatFileSystemNodeLoader {
+                chat_id: chat_id.clone(),
+            }),
+        ))
     }
 
     fn set_filesystem_by_chat_id(&self, chat_id: ChatId, filesystem: FileSystem) {
-        STATE.with_borrow_mut(|s| s.filesystem.insert(chat_id, filesystem));
+        let next_node_id = filesystem.next_node_id();
+        let (nodes, removed_node_ids) = filesystem.into_changes();
+        STATE.with_borrow_mut(|s| {
+            store_next_node_id(&mut s.next_node_id, &chat_id, None, next_node_id);
+            for node_id in removed_node_ids {
+                if let Some(stored_node) = s
+                    .filesystem_nodes
+                    .remove(&FileSystemNodeKey::new(chat_id.clone(), node_id))
+                {
+                    unindex_text_file(
+                        &mut s.text_file_index,
+                        &chat_id,
+                        None,
+                        node_id,
+                        &stored_node,
+                    );
+                }
+            }
+            for (node_id, node) in nodes {
+                index_text_file(&mut s.text_file_index, &chat_id, None, node_id, &node);
+                s.filesystem_nodes.insert(
+                    FileSystemNodeKey::new(chat_id.clone(), node_id),
+                    VersionedValue::new(&node),
+                );
+            }
+        });
+    }
+
+    fn pop_legacy_filesystem(&self) -> Option<(ChatId, VersionedValue<LegacyFileSystem>)> {
+        STATE.with_borrow_mut(|s| s.legacy_filesystem.pop_first())
+    }
+
+    fn has_legacy_filesystems(&self) -> bool {
+        STATE.with_borrow(|s| !s.legacy_filesystem.is_empty())
+    }
+
+    fn remove_legacy_filesystem(
+        &self,
+        chat_id: &ChatId,
+    ) -> Option<VersionedValue<LegacyFileSystem>> {
+        STATE.with_borrow_mut(|s| s.legacy_filesystem.remove(chat_id))
+    }
+
+    fn quarantine_legacy_filesystem(
+        &self,
+        chat_id: ChatId,
+        legacy_filesystem: VersionedValue<LegacyFileSystem>,
+    ) {
+        STATE.with_borrow_mut(|s| {
+            let generation = next_quarantine_generation(&s.quarantined_filesystem, &chat_id);
+            s.quarantined_filesystem.insert(
+                QuarantinedNodeKey::new(chat_id, generation, ROOT_NODE_ID),
+                VersionedValue::from_bytes(legacy_filesystem.to_bytes()),
+            )
+        });
+    }
+
+    fn quarantine_filesystem_by_chat_id(&self, chat_id: &ChatId) -> u64 {
+        STATE.with_borrow_mut(|s| {
+            let stored_nodes: Vec<_> = s
+                .filesystem_nodes
+                .range(chat_node_keys_range(chat_id))
+                .collect();
+            let count = stored_nodes.len() as u64;
+            remove_text_files(
+                &mut s.text_file_index,
+                text_file_keys_range(chat_id, Some(None)),
+            );
+            let generation = next_quarantine_generation(&s.quarantined_filesystem, chat_id);
+            for (key, stored_node) in stored_nodes {
+                s.filesystem_nodes.remove(&key);
+                s.quarantined_filesystem.insert(
+                    QuarantinedNodeKey::new(chat_id.clone(), generation, key.node_id()),
+                    stored_node,
+                );
+            }
+            count
+        })
+    }
+
+    fn list_chat_ids(&self, start_after: Option<&ChatId>, limit: usize) -> Vec<ChatId> {
+        let mut next_chat_id = match start_after {
+            Some(chat_id) => chat_id.0.checked_add(1).map(ChatId),
+            None => Some(ChatId(u64::MIN)),
+        };
+        let mut chat_ids = vec![];
+        STATE.with_borrow(|s| {
+            // jumps from the first node of a chat to the first node of the next one
+            while let Some(start) = next_chat_id.take() {
+                if chat_ids.len() >= limit {
+                    break;
+                }
+                let Some(key) = s
+                    .filesystem_nodes
+                    .keys_range(FileSystemNodeKey::new(start, NodeId::MIN)..)
+                    .next()
+                else {
+                    break;
+                };
+                next_chat_id = key.chat_id().0.checked_add(1).map(ChatId);
+                chat_ids.push(key.chat_id().clone());
+            }
+        });
+        chat_ids
+    }
+
+    fn get_node_ids_by_chat_id(&self, chat_id: &ChatId) -> BTreeSet<NodeId> {
+        STATE.with_borrow(|s| {
+            s.filesystem_nodes
+                .keys_range(chat_node_keys_range(chat_id))
+                .map(|key| key.node_id())
+                .collect()
+        })
+    }
+
+    fn get_quarantined_nodes_by_chat_id(&self, chat_id: &ChatId) -> Vec<QuarantinedFileSystemNode> {
+        STATE.with_borrow(|s| {
+            s.quarantined_filesystem
+                .range(quarantined_node_keys_range(chat_id))
+                .map(|(key, stored_node)| QuarantinedFileSystemNode {
+                    generation: key.generation,
+                    node_id: key.node_id,
+                    bytes: stored_node.to_bytes().into_owned(),
+                })
+                .collect()
+        })
+    }
+
+    fn remove_filesystem_by_chat_id(&self, chat_id: &ChatId) {
+        STATE.with_borrow_mut(|s| {
+            // otherwise the migration would bring the deleted data back
+            s.legacy_filesystem.remove(chat_id);
+            let keys: Vec<_> = s
+                .filesystem_nodes
+                .keys_range(chat_node_keys_range(chat_id))
+                .collect();
+            for key in keys {
+                s.filesystem_nodes.remove(&key);
+            }
+            let quarantined_keys: Vec<_> = s
+                .quarantined_filesystem
+                .keys_range(quarantined_node_keys_range(chat_id))
+                .collect();
+            for key in quarantined_keys {
+                s.quarantined_filesystem.remove(&key);
+            }
+            let drive_keys: Vec<_> = s
+                .drive_nodes
+                .keys_range(chat_drive_node_keys_range(chat_id))
+                .collect();
+            for key in drive_keys {
+                s.drive_nodes.remove(&key);
+            }
+            let (lowest, highest) = DriveKey::chat_bounds(chat_id);
+            let next_node_id_keys: Vec<_> = s.next_node_id.keys_range(lowest..=highest).collect();
+            for key in next_node_id_keys {
+                s.next_node_id.remove(&key);
+            }
+            remove_text_files(&mut s.text_file_index, text_file_keys_range(chat_id, None));
+        });
+    }
+
+    fn get_drive_filesystem(&self, chat_id: &ChatId, drive_id: DriveId) -> Option<FileSystem> {
+        let next_node_id = STATE.with_borrow(|s| {
+            let last_node_id = s
+                .drive_nodes
+                .keys_range(drive_node_keys_range(chat_id, drive_id))
+                .next_back()?
+                .node_id;
+            Some(stored_next_node_id(
+                &s.next_node_id,
+                chat_id,
+                Some(drive_id),
+                last_node_id,
+            ))
+        })?;
+
+        Some(FileSystem::with_loader(
+            next_node_id,
+            Box::new(DriveFileSystemNodeLoader {
+                chat_id: chat_id.clone(),
+                drive_id,
+            }),
+        ))
+    }
+
+    fn set_drive_filesystem(&self, chat_id: ChatId, drive_id: DriveId, filesystem: FileSystem) {
+        let next_node_id = filesystem.next_node_id();
+        let (nodes, removed_node_ids) = filesystem.into_changes();
+        STATE.with_borrow_mut(|s| {
+            store_next_node_id(&mut s.next_node_id, &chat_id, Some(drive_id), next_node_id);
+            for node_id in removed_node_ids {
+                if let Some(stored_node) =
+                    s.drive_nodes
+                        .remove(&DriveNodeKey::new(chat_id.clone(), drive_id, node_id))
+                {
+                    unindex_text_file(
+                        &mut s.text_file_index,
+                        &chat_id,
+                        Some(drive_id),
+                        node_id,
+                        &stored_node,
+                    );
+                }
+            }
+            for (node_id, node) in nodes {
+                index_text_file(
+                    &mut s.text_file_index,
+                    &chat_id,
+                    Some(drive_id),
+                    node_id,
+                    &node,
+                );
+                s.drive_nodes.insert(
+                    DriveNodeKey::new(chat_id.clone(), drive_id, node_id),
+                    VersionedValue::new(&node),
+                );
+            }
+        });
+    }
+
+    fn remove_drive_filesystem(&self, chat_id: &ChatId, drive_id: DriveId) {
+        STATE.with_borrow_mut(|s| {
+            let keys: Vec<_> = s
+                .drive_nodes
+                .keys_range(drive_node_keys_range(chat_id, drive_id))
+                .collect();
+            for key in keys {
+                s.drive_nodes.remove(&key);
+            }
+            s.next_node_id
+                .remove(&next_node_id_key(chat_id, Some(drive_id)));
+            remove_text_files(
+                &mut s.text_file_index,
+                text_file_keys_range(chat_id, Some(Some(drive_id))),
+            );
+        });
+    }
+
+    fn index_stored_text_files(&self, start_after: Option<&ChatId>, limit: usize) -> Vec<ChatId> {
+        let mut next_chat_id = match start_after {
+            Some(chat_id) => chat_id.0.checked_add(1).map(ChatId),
+            None => Some(ChatId(u64::MIN)),
+        };
+        let mut chat_ids = vec![];
+        STATE.with_borrow_mut(|s| {
+            while let Some(start) = next_chat_id.take() {
+                if chat_ids.len() >= limit {
+                    break;
+                }
+                let Some(chat_id) = next_chat_id_with_nodes(s, start) else {
+                    break;
+                };
+                // the nodes that can't be decoded are left to the consistency checks
+                for (key, stored_node) in s.filesystem_nodes.range(chat_node_keys_range(&chat_id)) {
+                    if let Ok(node) = stored_node.decode() {
+                        index_text_file(
+                            &mut s.text_file_index,
+                            &chat_id,
+                            None,
+                            key.node_id(),
+                            &node,
+                        );
+                    }
+                }
+                for (key, stored_node) in s.drive_nodes.range(chat_drive_node_keys_range(&chat_id))
+                {
+                    if let Ok(node) = stored_node.decode() {
+                        index_text_file(
+                            &mut s.text_file_index,
+                            &chat_id,
+                            Some(key.drive_id),
+                            key.node_id,
+                            &node,
+                        );
+                    }
+                }
+                next_chat_id = chat_id.0.checked_add(1).map(ChatId);
+                chat_ids.push(chat_id);
+            }
+        });
+        chat_ids
+    }
+
+    fn get_text_file_index_progress(&self) -> TextFileIndexProgress {
+        STATE
+            .with_borrow(|s| s.text_file_index_progress.get().decode())
+            .unwrap_or_else(|err| {
+                custom_print!(
+                    "Indexing the text files again, the progress can't be decoded: {err}"
+                );
+                TextFileIndexProgress::default()
+            })
+    }
+
+    fn set_text_file_index_progress(&self, progress: TextFileIndexProgress) {
+        STATE.with_borrow_mut(|s| {
+            s.text_file_index_progress
+                .set(VersionedValue::new(&progress))
+                .unwrap()
+        });
     }
 }
 
@@ -30,16 +412,308 @@ impl FilesystemRepositoryImpl {
     pub fn new() -> Self {
         Self {}
     }
+
+    /// Replaces the stored node with bytes that can't be decoded.
+    #[cfg(test)]
+    pub fn corrupt_node(&self, chat_id: &ChatId, node_id: NodeId) {
+        STATE.with_borrow_mut(|s| {
+            s.filesystem_nodes.insert(
+                FileSystemNodeKey::new(chat_id.clone(), node_id),
+                VersionedValue::from_bytes(std::borrow::Cow::Owned(vec![0xc0, 1, 1])),
+            )
+        });
+    }
+
+    #[cfg(test)]
+    pub fn insert_legacy_filesystem(
+        &self,
+        chat_id: ChatId,
+        legacy_filesystem: VersionedValue<LegacyFileSystem>,
+    ) {
+        STATE.with_borrow_mut(|s| s.legacy_filesystem.insert(chat_id, legacy_filesystem));
+    }
+
+    /// Forgets the indexed text files, as if they were stored before the index was kept.
+    #[cfg(test)]
+    pub fn clear_text_file_index(&self) {
+        STATE.with_borrow_mut(|s| s.text_file_index.clear_new());
+    }
+
+    #[cfg(test)]
+    pub fn get_text_file_node_ids(
+        &self,
+        chat_id: &ChatId,
+        drive_id: Option<DriveId>,
+        message_id: MessageId,
+    ) -> Vec<NodeId> {
+        text_file_node_ids(chat_id, drive_id, None, message_id)
+    }
+}
+
+fn chat_node_keys_range(chat_id: &ChatId) -> std::ops::RangeInclusive<FileSystemNodeKey> {
+    FileSystemNodeKey::new(chat_id.clone(), NodeId::MIN)
+        ..=FileSystemNodeKey::new(chat_id.clone(), NodeId::MAX)
+}
+
+fn quarantined_node_keys_range(chat_id: &ChatId) -> std::ops::RangeInclusive<QuarantinedNodeKey> {
+    QuarantinedNodeKey::new(chat_id.clone(), u64::MIN, NodeId::MIN)
+        ..=QuarantinedNodeKey::new(chat_id.clone(), u64::MAX, NodeId::MAX)
+}
+
+/// Returns the generation following the one of the chat's last quarantine.
+fn next_quarantine_generation(
+    quarantined_filesystem: &QuarantinedFilesystemMemory,
+    chat_id: &ChatId,
+) -> u64 {
+    quarantined_filesystem
+        .keys_range(quarantined_node_keys_range(chat_id))
+        .next_back()
+        .map_or(0, |key| key.generation + 1)
+}
+
+fn drive_node_keys_range(
+    chat_id: &ChatId,
+    drive_id: DriveId,
+) -> std::ops::RangeInclusive<DriveNodeKey> {
+    DriveNodeKey::new(chat_id.clone(), drive_id, NodeId::MIN)
+        ..=DriveNodeKey::new(chat_id.clone(), drive_id, NodeId::MAX)
+}
+
+/// The keys of the nodes of all the chat's drives.
+fn chat_drive_node_keys_range(chat_id: &ChatId) -> std::ops::RangeInclusive<DriveNodeKey> {
+    DriveNodeKey::new(chat_id.clone(), DriveId::MIN, NodeId::MIN)
+        ..=DriveNodeKey::new(chat_id.clone(), DriveId::MAX, NodeId::MAX)
+}
+
+/// Returns the first chat from `start` on that has nodes, in its main drive or in its drives.
+fn next_chat_id_with_nodes(s: &FilesystemState, start: ChatId) -> Option<ChatId> {
+    let main_drive_chat_id = s
+        .filesystem_nodes
+        .keys_range(FileSystemNodeKey::new(start.clone(), NodeId::MIN)..)
+        .next()
+        .map(|key| key.chat_id().clone());
+    let drive_chat_id = s
+        .drive_nodes
+        .keys_range(DriveNodeKey::new(start, DriveId::MIN, NodeId::MIN)..)
+        .next()
+        .map(|key| key.chat_id);
+    main_drive_chat_id.into_iter().chain(drive_chat_id).min()
+}
+
+/// The key of the text file in the index, if the node is a text file.
+fn text_file_key(
+    owner_chat_id: &ChatId,
+    drive_id: Option<DriveId>,
+    node_id: NodeId,
+    node: &StoredFileSystemNode,
+) -> Option<TextFileKey> {
+    let (source_chat_id, message_id) = node.text_message()?;
+    Some(TextFileKey::new(
+        owner_chat_id.clone(),
+        drive_id.unwrap_or(0),
+        source_chat_id.unwrap_or(owner_chat_id).clone(),
+        message_id,
+        node_id,
+    ))
+}
+
+fn index_text_file(
+    text_file_index: &mut TextFileIndexMemory,
+    owner_chat_id: &ChatId,
+    drive_id: Option<DriveId>,
+    node_id: NodeId,
+    node: &StoredFileSystemNode,
+) {
+    if let Some(key) = text_file_key(owner_chat_id, drive_id, node_id, node) {
+        text_file_index.insert(key, ());
+    }
+}
+
+/// A node that can't be decoded is left in the index, the lookups skip the missing nodes.
+fn unindex_text_file(
+    text_file_index: &mut TextFileIndexMemory,
+    owner_chat_id: &ChatId,
+    drive_id: Option<DriveId>,
+    node_id: NodeId,
+    stored_node: &VersionedValue<StoredFileSystemNode>,
+) {
+    if let Some(key) = stored_node
+        .decode()
+        .ok()
+        .and_then(|node| text_file_key(owner_chat_id, drive_id, node_id, &node))
+    {
+        text_file_index.remove(&key);
+    }
+}
+
+/// The keys of the text files of the chat's drive, the main drive when `Some(None)`, or of all its drives when `None`.
+fn text_file_keys_range(
+    owner_chat_id: &ChatId,
+    drive_id: Option<Option<DriveId>>,
+) -> std::ops::RangeInclusive<TextFileKey> {
+    let (lowest_drive_id, highest_drive_id) = match drive_id {
+        Some(drive_id) => (drive_id.unwrap_or(0), drive_id.unwrap_or(0)),
+        None => (DriveId::MIN, DriveId::MAX),
+    };
+    TextFileKey::new(
+        owner_chat_id.clone(),
+        lowest_drive_id,
+        ChatId(u64::MIN),
+        MessageId::MIN,
+        NodeId::MIN,
+    )
+        ..=TextFileKey::new(
+            owner_chat_id.clone(),
+            highest_drive_id,
+            ChatId(u64::MAX),
+            MessageId::MAX,
+            NodeId::MAX,
+        )
+}
+
+fn remove_text_files(
+    text_file_index: &mut TextFileIndexMemory,
+    keys_range: std::ops::RangeInclusive<TextFileKey>,
+) {
+    let keys: Vec<_> = text_file_index.keys_range(keys_range).collect();
+    for key in keys {
+        text_file_index.remove(&key);
+    }
+}
+
+/// Returns the ids of the text files of the message in the owner's drive, according to the index.
+fn text_file_node_ids(
+    owner_chat_id: &ChatId,
+    drive_id: Option<DriveId>,
+    chat_id: Option<&ChatId>,
+    message_id: MessageId,
+) -> Vec<NodeId> {
+    let key = |node_id| {
+        TextFileKey::new(
+            owner_chat_id.clone(),
+            drive_id.unwrap_or(0),
+            chat_id.unwrap_or(owner_chat_id).clone(),
+            message_id,
+            node_id,
+        )
+    };
+    STATE.with_borrow(|s| {
+        s.text_file_index
+            .keys_range(key(NodeId::MIN)..=key(NodeId::MAX))
+            .map(|key| key.node_id)
+            .collect()
+    })
+}
+
+/// The key of the next node id of the drive's filesystem, the main drive when `None`.
+/// The drive ids start from 1, so the main drive takes the id 0.
+fn next_node_id_key(chat_id: &ChatId, drive_id: Option<DriveId>) -> DriveKey {
+    DriveKey::new(chat_id.clone(), drive_id.unwrap_or(0))
+}
+
+/// Returns the id of the next node of the filesystem. The filesystems stored before
+/// the ids were kept start after their highest node.
+fn stored_next_node_id(
+    next_node_id: &NextNodeIdMemory,
+    chat_id: &ChatId,
+    drive_id: Option<DriveId>,
+    last_node_id: NodeId,
+) -> NodeId {
+    next_node_id
+        .get(&next_node_id_key(chat_id, drive_id))
+        .unwrap_or_default()
+        .max(last_node_id + 1)
+}
+
+/// Stores the id of the next node of the filesystem, never going back to an id already given,
+/// e.g. when the filesystem is recreated after a quarantine.
+fn store_next_node_id(
+    next_node_id: &mut NextNodeIdMemory,
+    chat_id: &ChatId,
+    drive_id: Option<DriveId>,
+    new_next_node_id: NodeId,
+) {
+    let key = next_node_id_key(chat_id, drive_id);
+    if next_node_id
+        .get(&key)
+        .is_none_or(|id| id < new_next_node_id)
+    {
+        next_node_id.insert(key, new_next_node_id);
+    }
+}
+
+struct ChatFileSystemNodeLoader {
+    chat_id: ChatId,
+}
+
+impl FileSystemNodeLoader for ChatFileSystemNodeLoader {
+    fn load_node(&self, node_id: NodeId) -> Result<Option<StoredFileSystemNode>, String> {
+        STATE
+            .with_borrow(|s| {
+                s.filesystem_nodes
+                    .get(&FileSystemNodeKey::new(self.chat_id.clone(), node_id))
+            })
+            .map(|stored_node| stored_node.decode())
+            .transpose()
+    }
+
+    fn load_text_file_node_ids(
+        &self,
+        chat_id: Option<&ChatId>,
+        message_id: MessageId,
+    ) -> Vec<NodeId> {
+        text_file_node_ids(&self.chat_id, None, chat_id, message_id)
+    }
+}
+
+struct DriveFileSystemNodeLoader {
+    chat_id: ChatId,
+    drive_id: DriveId,
+}
+
+impl FileSystemNodeLoader for DriveFileSystemNodeLoader {
+    fn load_node(&self, node_id: NodeId) -> Result<Option<StoredFileSystemNode>, String> {
+        STATE
+            .with_borrow(|s| {
+                s.drive_nodes.get(&DriveNodeKey::new(
+                    self.chat_id.clone(),
+                    self.drive_id,
+                    node_id,
+                ))
+            })
+            .map(|stored_node| stored_node.decode())
+            .transpose()
+    }
+
+    fn load_text_file_node_ids(
+        &self,
+        chat_id: Option<&ChatId>,
+        message_id: MessageId,
+    ) -> Vec<NodeId> {
+        text_file_node_ids(&self.chat_id, Some(self.drive_id), chat_id, message_id)
+    }
 }
 
 struct FilesystemState {
-    filesystem: FilesystemMemory,
+    legacy_filesystem: LegacyFilesystemMemory,
+    filesystem_nodes: FilesystemNodesMemory,
+    quarantined_filesystem: QuarantinedFilesystemMemory,
+    drive_nodes: DriveNodesMemory,
+    next_node_id: NextNodeIdMemory,
+    text_file_index: TextFileIndexMemory,
+    text_file_index_progress: TextFileIndexProgressMemory,
 }
 
 impl Default for FilesystemState {
     fn default() -> Self {
         Self {
-            filesystem: init_filesystem(),
+            legacy_filesystem: init_legacy_filesystem(),
+            filesystem_nodes: init_filesystem_nodes(),
+            quarantined_filesystem: init_quarantined_filesystem(),
+            drive_nodes: init_drive_nodes(),
+            next_node_id: init_next_node_id(),
+            text_file_index: init_text_file_index(),
+            text_file_index_progress: init_text_file_index_progress(),
         }
     }
 }
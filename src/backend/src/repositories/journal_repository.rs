@@ -0,0 +1,63 @@
+use std::cell::RefCell;
+
+use crate::custom_print;
+
+use super::{init_journal, ChatId, Journal, JournalMemory, VersionedValue};
+
+pub trait JournalRepository {
+    fn get_journal_by_chat_id(&self, chat_id: &ChatId) -> Option<Journal>;
+
+    fn set_journal_by_chat_id(&self, chat_id: ChatId, journal: Journal);
+
+    fn remove_journal_by_chat_id(&self, chat_id: &ChatId);
+}
+
+pub struct JournalRepositoryImpl {}
+
+impl Default for JournalRepositoryImpl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl JournalRepository for JournalRepositoryImpl {
+    fn get_journal_by_chat_id(&self, chat_id: &ChatId) -> Option<Journal> {
+        let stored_journal = STATE.with_borrow(|s| s.journal.get(chat_id))?;
+        stored_journal
+            .decode()
+            .inspect_err(|err| {
+                custom_print!("Discarding undecodable journal of chat {chat_id}: {err}");
+            })
+            .ok()
+    }
+
+    fn set_journal_by_chat_id(&self, chat_id: ChatId, journal: Journal) {
+        STATE.with_borrow_mut(|s| s.journal.insert(chat_id, VersionedValue::new(&journal)));
+    }
+
+    fn remove_journal_by_chat_id(&self, chat_id: &ChatId) {
+        STATE.with_borrow_mut(|s| s.journal.remove(chat_id));
+    }
+}
+
+impl JournalRepositoryImpl {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+struct JournalState {
+    journal: JournalMemory,
+}
+
+impl Default for JournalState {
+    fn default() -> Self {
+        Self {
+            journal: init_journal(),
+        }
+    }
+}
+
+thread_local! {
+    static STATE: RefCell<JournalState> = RefCell::new(JournalState::default());
+}
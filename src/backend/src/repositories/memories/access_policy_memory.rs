@@ -0,0 +1,17 @@
+use super::{Memory, ACCESS_POLICY_MEMORY_ID, MEMORY_MANAGER};
+use crate::repositories::{AccessPolicy, VersionedValue};
+use ic_stable_structures::Cell;
+
+pub type AccessPolicyMemory = Cell<VersionedValue<AccessPolicy>, Memory>;
+
+pub fn init_access_policy() -> AccessPolicyMemory {
+    AccessPolicyMemory::init(
+        get_access_policy_memory(),
+        VersionedValue::new(&AccessPolicy::default()),
+    )
+    .unwrap()
+}
+
+fn get_access_policy_memory() -> Memory {
+    MEMORY_MANAGER.with(|m| m.borrow().get(ACCESS_POLICY_MEMORY_ID))
+}
@@ -0,0 +1,13 @@
+use super::{Memory, CHAT_ROLE_MEMORY_ID, MEMORY_MANAGER};
+use crate::repositories::{ChatMemberId, ChatRole, VersionedValue};
+use ic_stable_structures::BTreeMap;
+
+pub type ChatRoleMemory = BTreeMap<ChatMemberId, VersionedValue<ChatRole>, Memory>;
+
+pub fn init_chat_role() -> ChatRoleMemory {
+    ChatRoleMemory::init(get_chat_role_memory())
+}
+
+fn get_chat_role_memory() -> Memory {
+    MEMORY_MANAGER.with(|m| m.borrow().get(CHAT_ROLE_MEMORY_ID))
+}
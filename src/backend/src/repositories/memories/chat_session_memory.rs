@@ -1,13 +1,23 @@
-use super::{Memory, CHAT_SESSION_MEMORY_ID, MEMORY_MANAGER};
-use crate::repositories::{ChatId, ChatSession};
+use super::{Memory, CHAT_SESSION_MEMORY_ID, LEGACY_CHAT_SESSION_MEMORY_ID, MEMORY_MANAGER};
+use crate::repositories::{ChatId, ChatMemberId, ChatSession, VersionedValue};
 use ic_stable_structures::BTreeMap;
 
-pub type ChatSessionMemory = BTreeMap<ChatId, ChatSession, Memory>;
+pub type LegacyChatSessionMemory = BTreeMap<ChatId, VersionedValue<ChatSession>, Memory>;
+
+pub type ChatSessionMemory = BTreeMap<ChatMemberId, VersionedValue<ChatSession>, Memory>;
+
+pub fn init_legacy_chat_session() -> LegacyChatSessionMemory {
+    LegacyChatSessionMemory::init(get_legacy_chat_session_memory())
+}
 
 pub fn init_chat_session() -> ChatSessionMemory {
     ChatSessionMemory::init(get_chat_session_memory())
 }
 
+fn get_legacy_chat_session_memory() -> Memory {
+    MEMORY_MANAGER.with(|m| m.borrow().get(LEGACY_CHAT_SESSION_MEMORY_ID))
+}
+
 fn get_chat_session_memory() -> Memory {
     MEMORY_MANAGER.with(|m| m.borrow().get(CHAT_SESSION_MEMORY_ID))
 }
@@ -0,0 +1,13 @@
+use super::{Memory, CHAT_SETTINGS_MEMORY_ID, MEMORY_MANAGER};
+use crate::repositories::{ChatId, ChatSettings, VersionedValue};
+use ic_stable_structures::BTreeMap;
+
+pub type ChatSettingsMemory = BTreeMap<ChatId, VersionedValue<ChatSettings>, Memory>;
+
+pub fn init_chat_settings() -> ChatSettingsMemory {
+    ChatSettingsMemory::init(get_chat_settings_memory())
+}
+
+fn get_chat_settings_memory() -> Memory {
+    MEMORY_MANAGER.with(|m| m.borrow().get(CHAT_SETTINGS_MEMORY_ID))
+}
@@ -0,0 +1,13 @@
+use super::{Memory, CONFIG_MEMORY_ID, MEMORY_MANAGER};
+use crate::repositories::{Config, VersionedValue};
+use ic_stable_structures::Cell;
+
+pub type ConfigMemory = Cell<VersionedValue<Config>, Memory>;
+
+pub fn init_config() -> ConfigMemory {
+    ConfigMemory::init(get_config_memory(), VersionedValue::new(&Config::default())).unwrap()
+}
+
+fn get_config_memory() -> Memory {
+    MEMORY_MANAGER.with(|m| m.borrow().get(CONFIG_MEMORY_ID))
+}
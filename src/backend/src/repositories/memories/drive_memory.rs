@@ -0,0 +1,23 @@
+use super::{Memory, DRIVE_MEMORY_ID, MEMORY_MANAGER, NEXT_DRIVE_ID_MEMORY_ID};
+use crate::repositories::{ChatId, Drive, DriveId, DriveKey, VersionedValue};
+use ic_stable_structures::BTreeMap;
+
+pub type DriveMemory = BTreeMap<DriveKey, VersionedValue<Drive>, Memory>;
+
+pub type NextDriveIdMemory = BTreeMap<ChatId, DriveId, Memory>;
+
+pub fn init_drive() -> DriveMemory {
+    DriveMemory::init(get_drive_memory())
+}
+
+pub fn init_next_drive_id() -> NextDriveIdMemory {
+    NextDriveIdMemory::init(get_next_drive_id_memory())
+}
+
+fn get_drive_memory() -> Memory {
+    MEMORY_MANAGER.with(|m| m.borrow().get(DRIVE_MEMORY_ID))
+}
+
+fn get_next_drive_id_memory() -> Memory {
+    MEMORY_MANAGER.with(|m| m.borrow().get(NEXT_DRIVE_ID_MEMORY_ID))
+}
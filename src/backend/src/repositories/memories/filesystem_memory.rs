@@ -1,13 +1,87 @@
-use super::{Memory, FILESYSTEM_MEMORY_ID, MEMORY_MANAGER};
-use crate::repositories::{ChatId, FileSystem};
-use ic_stable_structures::BTreeMap;
+use super::{
+    Memory, DRIVE_NODES_MEMORY_ID, FILESYSTEM_NODES_MEMORY_ID, LEGACY_FILESYSTEM_MEMORY_ID,
+    MEMORY_MANAGER, NEXT_NODE_ID_MEMORY_ID, QUARANTINED_FILESYSTEM_MEMORY_ID,
+    TEXT_FILE_INDEX_MEMORY_ID, TEXT_FILE_INDEX_PROGRESS_MEMORY_ID,
+};
+use crate::repositories::{
+    ChatId, DriveKey, DriveNodeKey, FileSystemNodeKey, LegacyFileSystem, NodeId,
+    QuarantinedNodeKey, StoredFileSystemNode, TextFileIndexProgress, TextFileKey, VersionedValue,
+};
+use ic_stable_structures::{BTreeMap, Cell};
 
-pub type FilesystemMemory = BTreeMap<ChatId, FileSystem, Memory>;
+pub type LegacyFilesystemMemory = BTreeMap<ChatId, VersionedValue<LegacyFileSystem>, Memory>;
 
-pub fn init_filesystem() -> FilesystemMemory {
-    FilesystemMemory::init(get_filesystem_memory())
+pub type FilesystemNodesMemory =
+    BTreeMap<FileSystemNodeKey, VersionedValue<StoredFileSystemNode>, Memory>;
+
+pub type QuarantinedFilesystemMemory =
+    BTreeMap<QuarantinedNodeKey, VersionedValue<StoredFileSystemNode>, Memory>;
+
+pub type DriveNodesMemory = BTreeMap<DriveNodeKey, VersionedValue<StoredFileSystemNode>, Memory>;
+
+/// The main drive of each chat is stored with the drive id 0, which the drives never get.
+pub type NextNodeIdMemory = BTreeMap<DriveKey, NodeId, Memory>;
+
+pub type TextFileIndexMemory = BTreeMap<TextFileKey, (), Memory>;
+
+pub type TextFileIndexProgressMemory = Cell<VersionedValue<TextFileIndexProgress>, Memory>;
+
+pub fn init_legacy_filesystem() -> LegacyFilesystemMemory {
+    LegacyFilesystemMemory::init(get_legacy_filesystem_memory())
+}
+
+pub fn init_filesystem_nodes() -> FilesystemNodesMemory {
+    FilesystemNodesMemory::init(get_filesystem_nodes_memory())
+}
+
+pub fn init_quarantined_filesystem() -> QuarantinedFilesystemMemory {
+    QuarantinedFilesystemMemory::init(get_quarantined_filesystem_memory())
+}
+
+pub fn init_drive_nodes() -> DriveNodesMemory {
+    DriveNodesMemory::init(get_drive_nodes_memory())
+}
+
+pub fn init_next_node_id() -> NextNodeIdMemory {
+    NextNodeIdMemory::init(get_next_node_id_memory())
+}
+
+pub fn init_text_file_index() -> TextFileIndexMemory {
+    TextFileIndexMemory::init(get_text_file_index_memory())
+}
+
+pub fn init_text_file_index_progress() -> TextFileIndexProgressMemory {
+    TextFileIndexProgressMemory::init(
+        get_text_file_index_progress_memory(),
+        VersionedValue::new(&TextFileIndexProgress::default()),
+    )
+    .unwrap()
+}
+
+fn get_legacy_filesystem_memory() -> Memory {
+    MEMORY_MANAGER.with(|m| m.borrow().get(LEGACY_FILESYSTEM_MEMORY_ID))
+}
+
+fn get_filesystem_nodes_memory() -> Memory {
+    MEMORY_MANAGER.with(|m| m.borrow().get(FILESYSTEM_NODES_MEMORY_ID))
+}
+
+fn get_quarantined_filesystem_memory() -> Memory {
+    MEMORY_MANAGER.with(|m| m.borrow().get(QUARANTINED_FILESYSTEM_MEMORY_ID))
+}
+
+fn get_drive_nodes_memory() -> Memory {
+    MEMORY_MANAGER.with(|m| m.borrow().get(DRIVE_NODES_MEMORY_ID))
+}
+
+fn get_next_node_id_memory() -> Memory {
+    MEMORY_MANAGER.with(|m| m.borrow().get(NEXT_NODE_ID_MEMORY_ID))
+}
+
+fn get_text_file_index_memory() -> Memory {
+    MEMORY_MANAGER.with(|m| m.borrow().get(TEXT_FILE_INDEX_MEMORY_ID))
 }
 
-fn get_filesystem_memory() -> Memory {
-    MEMORY_MANAGER.with(|m| m.borrow().get(FILESYSTEM_MEMORY_ID))
+fn get_text_file_index_progress_memory() -> Memory {
+    MEMORY_MANAGER.with(|m| m.borrow().get(TEXT_FILE_INDEX_PROGRESS_MEMORY_ID))
 }
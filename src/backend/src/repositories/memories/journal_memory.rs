@@ -0,0 +1,13 @@
+use super::{Memory, JOURNAL_MEMORY_ID, MEMORY_MANAGER};
+use crate::repositories::{ChatId, Journal, VersionedValue};
+use ic_stable_structures::BTreeMap;
+
+pub type JournalMemory = BTreeMap<ChatId, VersionedValue<Journal>, Memory>;
+
+pub fn init_journal() -> JournalMemory {
+    JournalMemory::init(get_journal_memory())
+}
+
+fn get_journal_memory() -> Memory {
+    MEMORY_MANAGER.with(|m| m.borrow().get(JOURNAL_MEMORY_ID))
+}
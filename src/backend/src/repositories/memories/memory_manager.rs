@@ -11,5 +11,78 @@ thread_local! {
 
 // memory IDs are kept together to ensure that the same ID is not used more than once
 // everything else related to each memory region is kept in the appropriate file
-pub(super) const FILESYSTEM_MEMORY_ID: MemoryId = MemoryId::new(0);
-pub(super) const CHAT_SESSION_MEMORY_ID: MemoryId = MemoryId::new(1);
+// the legacy filesystem region is only read to migrate its data to the per-node layout
+// the legacy chat session region is only read to migrate the sessions to the per-member layout
+// the quarantined filesystem region keeps the nodes of filesystems that could not be decoded
+// the chat share index region references each share from both its owner and its recipient
+// the drive nodes region keeps the filesystems of the drives, the main drive of each chat is in the filesystem nodes region
+// the share token salt region keeps the random bytes that the share tokens are derived from
+// the next node id region keeps the id of the next node of each filesystem, so that the ids of the removed nodes are not reused
+// the next drive id region keeps the id of the next drive of each chat, so that the ids of the deleted drives are not reused
+// the text file index region references the text files by message, so that the files of an edited message are found without walking the trees
+// the text file index progress region keeps how far the files stored before the index have been indexed
+pub(super) const LEGACY_FILESYSTEM_MEMORY_ID: MemoryId = MemoryId::new(0);
+pub(super) const LEGACY_CHAT_SESSION_MEMORY_ID: MemoryId = MemoryId::new(1);
+pub(super) const JOURNAL_MEMORY_ID: MemoryId = MemoryId::new(2);
+pub(super) const FILESYSTEM_NODES_MEMORY_ID: MemoryId = MemoryId::new(3);
+pub(super) const QUARANTINED_FILESYSTEM_MEMORY_ID: MemoryId = MemoryId::new(4);
+pub(super) const PROCESSED_UPDATE_MEMORY_ID: MemoryId = MemoryId::new(5);
+pub(super) const WEBHOOK_SECRET_MEMORY_ID: MemoryId = MemoryId::new(6);
+pub(super) const CONFIG_MEMORY_ID: MemoryId = MemoryId::new(7);
+pub(super) const ACCESS_POLICY_MEMORY_ID: MemoryId = MemoryId::new(8);
+pub(super) const CHAT_SESSION_MEMORY_ID: MemoryId = MemoryId::new(9);
+pub(super) const CHAT_ROLE_MEMORY_ID: MemoryId = MemoryId::new(10);
+pub(super) const SHARE_MEMORY_ID: MemoryId = MemoryId::new(11);
+pub(super) const CHAT_SHARE_INDEX_MEMORY_ID: MemoryId = MemoryId::new(12);
+pub(super) const DRIVE_MEMORY_ID: MemoryId = MemoryId::new(13);
+pub(super) const DRIVE_NODES_MEMORY_ID: MemoryId = MemoryId::new(14);
+pub(super) const CHAT_SETTINGS_MEMORY_ID: MemoryId = MemoryId::new(15);
+pub(super) const SHARE_TOKEN_SALT_MEMORY_ID: MemoryId = MemoryId::new(16);
+pub(super) const NEXT_NODE_ID_MEMORY_ID: MemoryId = MemoryId::new(17);
+pub(super) const NEXT_DRIVE_ID_MEMORY_ID: MemoryId = MemoryId::new(18);
+pub(super) const TEXT_FILE_INDEX_MEMORY_ID: MemoryId = MemoryId::new(19);
+pub(super) const TEXT_FILE_INDEX_PROGRESS_MEMORY_ID: MemoryId = MemoryId::new(20);
+
+// the raw ids of all the regions above, so that they can be backed up and restored
+// a new region must be added here as well
+pub(super) const MEMORY_REGION_IDS: [u8; 21] = [
+    0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    #[rstest]
+    fn memory_region_ids_match_memory_ids() {
+        let memory_ids = [
+            LEGACY_FILESYSTEM_MEMORY_ID,
+            LEGACY_CHAT_SESSION_MEMORY_ID,
+            JOURNAL_MEMORY_ID,
+            FILESYSTEM_NODES_MEMORY_ID,
+            QUARANTINED_FILESYSTEM_MEMORY_ID,
+            PROCESSED_UPDATE_MEMORY_ID,
+            WEBHOOK_SECRET_MEMORY_ID,
+            CONFIG_MEMORY_ID,
+            ACCESS_POLICY_MEMORY_ID,
+            CHAT_SESSION_MEMORY_ID,
+            CHAT_ROLE_MEMORY_ID,
+            SHARE_MEMORY_ID,
+            CHAT_SHARE_INDEX_MEMORY_ID,
+            DRIVE_MEMORY_ID,
+            DRIVE_NODES_MEMORY_ID,
+            CHAT_SETTINGS_MEMORY_ID,
+            SHARE_TOKEN_SALT_MEMORY_ID,
+            NEXT_NODE_ID_MEMORY_ID,
+            NEXT_DRIVE_ID_MEMORY_ID,
+            TEXT_FILE_INDEX_MEMORY_ID,
+            TEXT_FILE_INDEX_PROGRESS_MEMORY_ID,
+        ];
+
+        assert_eq!(
+            MEMORY_REGION_IDS.map(MemoryId::new).to_vec(),
+            memory_ids.to_vec()
+        );
+    }
+}
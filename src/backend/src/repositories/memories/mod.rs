@@ -1,8 +1,28 @@
+mod access_policy_memory;
+mod chat_role_memory;
 mod chat_session_memory;
+mod chat_settings_memory;
+mod config_memory;
+mod drive_memory;
 mod filesystem_memory;
+mod journal_memory;
 mod memory_manager;
+mod processed_update_memory;
+mod region_memory;
+mod share_memory;
+mod webhook_secret_memory;
 
 use memory_manager::*;
 
+pub(super) use access_policy_memory::*;
+pub(super) use chat_role_memory::*;
 pub(super) use chat_session_memory::*;
+pub(super) use chat_settings_memory::*;
+pub(super) use config_memory::*;
+pub(super) use drive_memory::*;
 pub(super) use filesystem_memory::*;
+pub(super) use journal_memory::*;
+pub(super) use processed_update_memory::*;
+pub(super) use region_memory::*;
+pub(super) use share_memory::*;
+pub(super) use webhook_secret_memory::*;
@@ -0,0 +1,13 @@
+use super::{Memory, MEMORY_MANAGER, PROCESSED_UPDATE_MEMORY_ID};
+use crate::repositories::{ProcessedUpdate, UpdateId, VersionedValue};
+use ic_stable_structures::BTreeMap;
+
+pub type ProcessedUpdateMemory = BTreeMap<UpdateId, VersionedValue<ProcessedUpdate>, Memory>;
+
+pub fn init_processed_update() -> ProcessedUpdateMemory {
+    ProcessedUpdateMemory::init(get_processed_update_memory())
+}
+
+fn get_processed_update_memory() -> Memory {
+    MEMORY_MANAGER.with(|m| m.borrow().get(PROCESSED_UPDATE_MEMORY_ID))
+}
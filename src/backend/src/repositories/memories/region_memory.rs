@@ -0,0 +1,85 @@
+use super::{
+    ChatRoleMemory, ChatSessionMemory, ChatSettingsMemory, ChatShareIndexMemory, DriveMemory,
+    DriveNodesMemory, FilesystemNodesMemory, JournalMemory, LegacyChatSessionMemory,
+    LegacyFilesystemMemory, Memory, NextDriveIdMemory, NextNodeIdMemory, ProcessedUpdateMemory,
+    QuarantinedFilesystemMemory, ShareMemory, TextFileIndexMemory, ACCESS_POLICY_MEMORY_ID,
+    CHAT_ROLE_MEMORY_ID, CHAT_SESSION_MEMORY_ID, CHAT_SETTINGS_MEMORY_ID,
+    CHAT_SHARE_INDEX_MEMORY_ID, CONFIG_MEMORY_ID, DRIVE_MEMORY_ID, DRIVE_NODES_MEMORY_ID,
+    FILESYSTEM_NODES_MEMORY_ID, JOURNAL_MEMORY_ID, LEGACY_CHAT_SESSION_MEMORY_ID,
+    LEGACY_FILESYSTEM_MEMORY_ID, MEMORY_MANAGER, MEMORY_REGION_IDS, NEXT_DRIVE_ID_MEMORY_ID,
+    NEXT_NODE_ID_MEMORY_ID, PROCESSED_UPDATE_MEMORY_ID, QUARANTINED_FILESYSTEM_MEMORY_ID,
+    SHARE_MEMORY_ID, SHARE_TOKEN_SALT_MEMORY_ID, TEXT_FILE_INDEX_MEMORY_ID,
+    TEXT_FILE_INDEX_PROGRESS_MEMORY_ID, WEBHOOK_SECRET_MEMORY_ID,
+};
+use crate::repositories::{
+    AccessPolicy, Config, MemoryRegionId, VersionedStorable, VersionedValue, WebhookSecrets,
+};
+use ic_stable_structures::{memory_manager::MemoryId, Cell, Memory as _, Storable};
+
+pub fn get_memory_region_ids() -> &'static [MemoryRegionId] {
+    &MEMORY_REGION_IDS
+}
+
+/// Returns the raw memory of a region, or `None` if the region is not in use.
+pub fn get_memory_region(memory_id: MemoryRegionId) -> Option<Memory> {
+    MEMORY_REGION_IDS
+        .contains(&memory_id)
+        .then(|| MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(memory_id))))
+}
+
+/// Whether the region holds no data, e.g. only the empty structure that the canister
+/// creates the first time it reads the region. A restore can overwrite such a region.
+pub fn is_memory_region_empty(memory_id: MemoryRegionId) -> bool {
+    let Some(memory) = get_memory_region(memory_id) else {
+        return false;
+    };
+    if memory.size() == 0 {
+        return true;
+    }
+
+    match MemoryId::new(memory_id) {
+        LEGACY_FILESYSTEM_MEMORY_ID => LegacyFilesystemMemory::load(memory).is_empty(),
+        LEGACY_CHAT_SESSION_MEMORY_ID => LegacyChatSessionMemory::load(memory).is_empty(),
+        JOURNAL_MEMORY_ID => JournalMemory::load(memory).is_empty(),
+        FILESYSTEM_NODES_MEMORY_ID => FilesystemNodesMemory::load(memory).is_empty(),
+        QUARANTINED_FILESYSTEM_MEMORY_ID => QuarantinedFilesystemMemory::load(memory).is_empty(),
+        PROCESSED_UPDATE_MEMORY_ID => ProcessedUpdateMemory::load(memory).is_empty(),
+        WEBHOOK_SECRET_MEMORY_ID => holds_default_value(memory, &WebhookSecrets::default()),
+        CONFIG_MEMORY_ID => holds_default_value(memory, &Config::default()),
+        ACCESS_POLICY_MEMORY_ID => holds_default_value(memory, &AccessPolicy::default()),
+        CHAT_SESSION_MEMORY_ID => ChatSessionMemory::load(memory).is_empty(),
+        CHAT_ROLE_MEMORY_ID => ChatRoleMemory::load(memory).is_empty(),
+        SHARE_MEMORY_ID => ShareMemory::load(memory).is_empty(),
+        CHAT_SHARE_INDEX_MEMORY_ID => ChatShareIndexMemory::load(memory).is_empty(),
+        DRIVE_MEMORY_ID => DriveMemory::load(memory).is_empty(),
+        DRIVE_NODES_MEMORY_ID => DriveNodesMemory::load(memory).is_empty(),
+        CHAT_SETTINGS_MEMORY_ID => ChatSettingsMemory::load(memory).is_empty(),
+        // the salt is generated right after the install, it can be replaced until a token is derived from it
+        SHARE_TOKEN_SALT_MEMORY_ID => {
+            ShareMemory::load(MEMORY_MANAGER.with(|m| m.borrow().get(SHARE_MEMORY_ID))).is_empty()
+        }
+        NEXT_NODE_ID_MEMORY_ID => NextNodeIdMemory::load(memory).is_empty(),
+        NEXT_DRIVE_ID_MEMORY_ID => NextDriveIdMemory::load(memory).is_empty(),
+        TEXT_FILE_INDEX_MEMORY_ID => TextFileIndexMemory::load(memory).is_empty(),
+        // the timer of a fresh canister indexes its files right away, it can be replaced until a file is indexed
+        TEXT_FILE_INDEX_PROGRESS_MEMORY_ID => TextFileIndexMemory::load(
+            MEMORY_MANAGER.with(|m| m.borrow().get(TEXT_FILE_INDEX_MEMORY_ID)),
+        )
+        .is_empty(),
+        _ => false,
+    }
+}
+
+/// Whether the cell in the memory holds the value that it's initialized with.
+fn holds_default_value<T: VersionedStorable>(memory: Memory, default_value: &T) -> bool {
+    // a memory without the cell's magic would be overwritten by `Cell::init`
+    let mut magic = [0; 3];
+    memory.read(0, &mut magic);
+    if &magic != b"SCL" {
+        return false;
+    }
+
+    let default_value = VersionedValue::new(default_value);
+    Cell::init(memory, default_value.clone())
+        .is_ok_and(|cell| cell.get().to_bytes() == default_value.to_bytes())
+}
@@ -0,0 +1,36 @@
+use super::{
+    Memory, CHAT_SHARE_INDEX_MEMORY_ID, MEMORY_MANAGER, SHARE_MEMORY_ID, SHARE_TOKEN_SALT_MEMORY_ID,
+};
+use crate::repositories::{ChatShareKey, Share, ShareId, VersionedValue};
+use ic_stable_structures::{BTreeMap, Cell};
+
+pub type ShareMemory = BTreeMap<ShareId, VersionedValue<Share>, Memory>;
+
+pub type ChatShareIndexMemory = BTreeMap<ChatShareKey, (), Memory>;
+
+/// Empty until the salt is generated.
+pub type ShareTokenSaltMemory = Cell<Vec<u8>, Memory>;
+
+pub fn init_share() -> ShareMemory {
+    ShareMemory::init(get_share_memory())
+}
+
+pub fn init_chat_share_index() -> ChatShareIndexMemory {
+    ChatShareIndexMemory::init(get_chat_share_index_memory())
+}
+
+pub fn init_share_token_salt() -> ShareTokenSaltMemory {
+    ShareTokenSaltMemory::init(get_share_token_salt_memory(), Vec::new()).unwrap()
+}
+
+fn get_share_memory() -> Memory {
+    MEMORY_MANAGER.with(|m| m.borrow().get(SHARE_MEMORY_ID))
+}
+
+fn get_chat_share_index_memory() -> Memory {
+    MEMORY_MANAGER.with(|m| m.borrow().get(CHAT_SHARE_INDEX_MEMORY_ID))
+}
+
+fn get_share_token_salt_memory() -> Memory {
+    MEMORY_MANAGER.with(|m| m.borrow().get(SHARE_TOKEN_SALT_MEMORY_ID))
+}
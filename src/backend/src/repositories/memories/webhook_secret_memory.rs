@@ -0,0 +1,17 @@
+use super::{Memory, MEMORY_MANAGER, WEBHOOK_SECRET_MEMORY_ID};
+use crate::repositories::{VersionedValue, WebhookSecrets};
+use ic_stable_structures::Cell;
+
+pub type WebhookSecretMemory = Cell<VersionedValue<WebhookSecrets>, Memory>;
+
+pub fn init_webhook_secret() -> WebhookSecretMemory {
+    WebhookSecretMemory::init(
+        get_webhook_secret_memory(),
+        VersionedValue::new(&WebhookSecrets::default()),
+    )
+    .unwrap()
+}
+
+fn get_webhook_secret_memory() -> Memory {
+    MEMORY_MANAGER.with(|m| m.borrow().get(WEBHOOK_SECRET_MEMORY_ID))
+}
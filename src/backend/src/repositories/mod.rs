@@ -1,9 +1,31 @@
+mod access_policy_repository;
+mod backup_repository;
+mod chat_role_repository;
 mod chat_session_repository;
+mod chat_settings_repository;
+mod config_repository;
+mod drive_repository;
 mod filesystem_repository;
+mod journal_repository;
 mod memories;
+mod processed_update_repository;
+mod rate_limit_repository;
+mod share_repository;
 mod types;
+mod webhook_secret_repository;
 
+pub use access_policy_repository::*;
+pub use backup_repository::*;
+pub use chat_role_repository::*;
 pub use chat_session_repository::*;
+pub use chat_settings_repository::*;
+pub use config_repository::*;
+pub use drive_repository::*;
 pub use filesystem_repository::*;
+pub use journal_repository::*;
 use memories::*;
+pub use processed_update_repository::*;
+pub use rate_limit_repository::*;
+pub use share_repository::*;
 pub use types::*;
+pub use webhook_secret_repository::*;
@@ -0,0 +1,113 @@
+use std::cell::RefCell;
+
+use crate::custom_print;
+
+use super::{
+    init_processed_update, ChatId, ProcessedUpdate, ProcessedUpdateMemory, UpdateId, VersionedValue,
+};
+
+pub trait ProcessedUpdateRepository {
+    fn get_processed_update(&self, update_id: UpdateId) -> Option<ProcessedUpdate>;
+
+    fn set_processed_update(&self, update_id: UpdateId, processed_update: ProcessedUpdate);
+
+    fn get_processed_updates_count(&self) -> u64;
+
+    /// Returns the processed update with the highest update id.
+    fn get_latest_processed_update(&self) -> Option<(UpdateId, ProcessedUpdate)>;
+
+    /// Removes the processed update with the lowest update id.
+    fn remove_oldest_processed_update(&self);
+
+    fn remove_processed_updates_by_chat_id(&self, chat_id: &ChatId);
+}
+
+pub struct ProcessedUpdateRepositoryImpl {}
+
+impl Default for ProcessedUpdateRepositoryImpl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProcessedUpdateRepository for ProcessedUpdateRepositoryImpl {
+    fn get_processed_update(&self, update_id: UpdateId) -> Option<ProcessedUpdate> {
+        let stored_processed_update = STATE.with_borrow(|s| s.processed_updates.get(&update_id))?;
+        decode_processed_update(update_id, &stored_processed_update)
+    }
+
+    fn set_processed_update(&self, update_id: UpdateId, processed_update: ProcessedUpdate) {
+        STATE.with_borrow_mut(|s| {
+            s.processed_updates
+                .insert(update_id, VersionedValue::new(&processed_update))
+        });
+    }
+
+    fn get_processed_updates_count(&self) -> u64 {
+        STATE.with_borrow(|s| s.processed_updates.len())
+    }
+
+    fn get_latest_processed_update(&self) -> Option<(UpdateId, ProcessedUpdate)> {
+        let (update_id, stored_processed_update) =
+            STATE.with_borrow(|s| s.processed_updates.last_key_value())?;
+        decode_processed_update(update_id, &stored_processed_update)
+            .map(|processed_update| (update_id, processed_update))
+    }
+
+    fn remove_oldest_processed_update(&self) {
+        STATE.with_borrow_mut(|s| s.processed_updates.pop_first());
+    }
+
+    fn remove_processed_updates_by_chat_id(&self, chat_id: &ChatId) {
+        STATE.with_borrow_mut(|s| {
+            // the records are bounded, so scanning all of them is cheap
+            let update_ids: Vec<UpdateId> = s
+                .processed_updates
+                .iter()
+                .filter(|(update_id, stored_processed_update)| {
+                    decode_processed_update(*update_id, stored_processed_update).is_some_and(
+                        |processed_update| processed_update.chat_id.as_ref() == Some(chat_id),
+                    )
+                })
+                .map(|(update_id, _)| update_id)
+                .collect();
+            for update_id in update_ids {
+                s.processed_updates.remove(&update_id);
+            }
+        });
+    }
+}
+
+impl ProcessedUpdateRepositoryImpl {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+fn decode_processed_update(
+    update_id: UpdateId,
+    stored_processed_update: &VersionedValue<ProcessedUpdate>,
+) -> Option<ProcessedUpdate> {
+    stored_processed_update
+        .decode()
+        .inspect_err(|err| {
+            custom_print!("Discarding undecodable processed update {update_id}: {err}");
+        })
+        .ok()
+}
+
+struct ProcessedUpdateState {
+    processed_updates: ProcessedUpdateMemory,
+}
+
+impl Default for ProcessedUpdateState {
+    fn default() -> Self {
+        Self {
+            processed_updates: init_processed_update(),
+        }
+    }
+}
+
+thread_local! {
+    static STATE: RefCell<ProcessedUpdateState> = RefCell::new(ProcessedUpdateState::default());
+}
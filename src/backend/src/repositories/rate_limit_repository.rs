@@ -0,0 +1,88 @@
+use std::{cell::RefCell, collections::BTreeMap};
+
+use super::{ChatId, RateLimitCounters, TokenBucket};
+
+pub trait RateLimitRepository {
+    fn get_global_bucket(&self) -> Option<TokenBucket>;
+
+    fn set_global_bucket(&self, bucket: TokenBucket);
+
+    fn get_chat_bucket(&self, chat_id: &ChatId) -> Option<TokenBucket>;
+
+    fn set_chat_bucket(&self, chat_id: ChatId, bucket: TokenBucket);
+
+    fn remove_chat_bucket(&self, chat_id: &ChatId);
+
+    fn get_chat_buckets_count(&self) -> u64;
+
+    /// Keeps only the chat buckets for which `f` returns `true`.
+    fn retain_chat_buckets(&self, f: impl Fn(&ChatId, &TokenBucket) -> bool);
+
+    fn get_counters(&self) -> RateLimitCounters;
+
+    fn set_counters(&self, counters: RateLimitCounters);
+}
+
+pub struct RateLimitRepositoryImpl {}
+
+impl Default for RateLimitRepositoryImpl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RateLimitRepository for RateLimitRepositoryImpl {
+    fn get_global_bucket(&self) -> Option<TokenBucket> {
+        STATE.with_borrow(|s| s.global_bucket.clone())
+    }
+
+    fn set_global_bucket(&self, bucket: TokenBucket) {
+        STATE.with_borrow_mut(|s| s.global_bucket = Some(bucket));
+    }
+
+    fn get_chat_bucket(&self, chat_id: &ChatId) -> Option<TokenBucket> {
+        STATE.with_borrow(|s| s.chat_buckets.get(chat_id).cloned())
+    }
+
+    fn set_chat_bucket(&self, chat_id: ChatId, bucket: TokenBucket) {
+        STATE.with_borrow_mut(|s| s.chat_buckets.insert(chat_id, bucket));
+    }
+
+    fn remove_chat_bucket(&self, chat_id: &ChatId) {
+        STATE.with_borrow_mut(|s| s.chat_buckets.remove(chat_id));
+    }
+
+    fn get_chat_buckets_count(&self) -> u64 {
+        STATE.with_borrow(|s| s.chat_buckets.len() as u64)
+    }
+
+    fn retain_chat_buckets(&self, f: impl Fn(&ChatId, &TokenBucket) -> bool) {
+        STATE.with_borrow_mut(|s| s.chat_buckets.retain(|chat_id, bucket| f(chat_id, bucket)));
+    }
+
+    fn get_counters(&self) -> RateLimitCounters {
+        STATE.with_borrow(|s| s.counters.clone())
+    }
+
+    fn set_counters(&self, counters: RateLimitCounters) {
+        STATE.with_borrow_mut(|s| s.counters = counters);
+    }
+}
+
+impl RateLimitRepositoryImpl {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+/// The buckets only matter for a few seconds, so they are kept in the heap and reset on upgrades.
+#[derive(Default)]
+struct RateLimitState {
+    global_bucket: Option<TokenBucket>,
+    chat_buckets: BTreeMap<ChatId, TokenBucket>,
+    counters: RateLimitCounters,
+}
+
+thread_local! {
+    static STATE: RefCell<RateLimitState> = RefCell::new(RateLimitState::default());
+}
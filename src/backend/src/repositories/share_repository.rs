@@ -0,0 +1,155 @@
+use std::cell::RefCell;
+
+use crate::custom_print;
+
+use super::{
+    init_chat_share_index, init_share, init_share_token_salt, ChatId, ChatShareIndexMemory,
+    ChatShareKey, Share, ShareId, ShareMemory, ShareTokenSaltMemory, VersionedValue,
+};
+
+pub trait ShareRepository {
+    fn get_share(&self, share_id: ShareId) -> Option<Share>;
+
+    /// Stores a new share and returns its id.
+    fn create_share(&self, share: Share) -> ShareId;
+
+    /// Replaces the share, keeping the index in sync with its recipient.
+    fn update_share(&self, share_id: ShareId, share: Share);
+
+    fn remove_share(&self, share_id: ShareId);
+
+    /// Returns the shares that the chat owns or has accepted, in creation order.
+    fn get_shares_by_chat_id(&self, chat_id: &ChatId) -> Vec<(ShareId, Share)>;
+
+    /// Returns the salt of the share tokens, `None` until it's generated.
+    fn get_token_salt(&self) -> Option<Vec<u8>>;
+
+    fn set_token_salt(&self, salt: Vec<u8>);
+}
+
+pub struct ShareRepositoryImpl {}
+
+impl Default for ShareRepositoryImpl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ShareRepository for ShareRepositoryImpl {
+    fn get_share(&self, share_id: ShareId) -> Option<Share> {
+        let stored_share = STATE.with_borrow(|s| s.share.get(&share_id))?;
+        stored_share
+            .decode()
+            .inspect_err(|err| {
+                custom_print!("Discarding undecodable share {share_id}: {err}");
+            })
+            .ok()
+    }
+
+    fn create_share(&self, share: Share) -> ShareId {
+        STATE.with_borrow_mut(|s| {
+            let share_id = s
+                .share
+                .last_key_value()
+                .map_or(0, |(share_id, _)| share_id + 1);
+            s.insert_share(share_id, &share);
+            share_id
+        })
+    }
+
+    fn update_share(&self, share_id: ShareId, share: Share) {
+        STATE.with_borrow_mut(|s| {
+            s.remove_share(share_id);
+            s.insert_share(share_id, &share);
+        });
+    }
+
+    fn remove_share(&self, share_id: ShareId) {
+        STATE.with_borrow_mut(|s| s.remove_share(share_id));
+    }
+
+    fn get_shares_by_chat_id(&self, chat_id: &ChatId) -> Vec<(ShareId, Share)> {
+        let (lowest, highest) = ChatShareKey::chat_bounds(chat_id);
+        let share_ids: Vec<ShareId> = STATE.with_borrow(|s| {
+            s.chat_share_index
+                .keys_range(lowest..=highest)
+                .map(|key| key.share_id)
+                .collect()
+        });
+        share_ids
+            .into_iter()
+            .filter_map(|share_id| Some((share_id, self.get_share(share_id)?)))
+            .collect()
+    }
+
+    fn get_token_salt(&self) -> Option<Vec<u8>> {
+        let salt = STATE.with_borrow(|s| s.token_salt.get().clone());
+        (!salt.is_empty()).then_some(salt)
+    }
+
+    fn set_token_salt(&self, salt: Vec<u8>) {
+        STATE.with_borrow_mut(|s| s.token_salt.set(salt).unwrap());
+    }
+}
+
+impl ShareRepositoryImpl {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+struct ShareState {
+    share: ShareMemory,
+    chat_share_index: ChatShareIndexMemory,
+    token_salt: ShareTokenSaltMemory,
+}
+
+impl ShareState {
+    fn insert_share(&mut self, share_id: ShareId, share: &Share) {
+        self.share.insert(share_id, VersionedValue::new(share));
+        for chat_id in share_chat_ids(share) {
+            self.chat_share_index
+                .insert(ChatShareKey::new(chat_id, share_id), ());
+        }
+    }
+
+    fn remove_share(&mut self, share_id: ShareId) {
+        let Some(stored_share) = self.share.remove(&share_id) else {
+            return;
+        };
+        match stored_share.decode() {
+            Ok(share) => {
+                for chat_id in share_chat_ids(&share) {
+                    self.chat_share_index
+                        .remove(&ChatShareKey::new(chat_id, share_id));
+                }
+            }
+            // the entries of the index can't be found without the share, they are skipped when listing
+            Err(err) => {
+                custom_print!("Removed undecodable share {share_id}: {err}");
+            }
+        }
+    }
+}
+
+fn share_chat_ids(share: &Share) -> Vec<ChatId> {
+    let mut chat_ids = vec![share.owner_chat_id.clone()];
+    if let Some(recipient) = &share.recipient {
+        chat_ids.push(recipient.chat_id.clone());
+    }
+    chat_ids
+}
+
+impl Default for ShareState {
+    fn default() -> Self {
+        Self {
+            share: init_share(),
+            chat_share_index: init_chat_share_index(),
+            token_salt: init_share_token_salt(),
+        }
+    }
+}
+
+thread_local! {
+    static STATE: RefCell<ShareState> = RefCell::new(ShareState::default());
+}
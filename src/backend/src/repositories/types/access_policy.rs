@@ -0,0 +1,122 @@
+use std::collections::BTreeSet;
+
+use candid::{CandidType, Deserialize};
+
+use super::{ChatId, Migration, SchemaVersion, UserId, VersionedStorable};
+
+#[derive(Debug, CandidType, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AccessPolicyMode {
+    /// Everyone can use the bot.
+    #[default]
+    Open,
+    /// Only the listed users and chats can use the bot.
+    Allowlist,
+    /// Everyone but the listed users and chats can use the bot.
+    Denylist,
+}
+
+/// Decides who can use the bot. The same lists are used as allowlist or denylist, depending on the mode.
+#[derive(Debug, CandidType, Deserialize, Clone, PartialEq, Eq, Default)]
+pub struct AccessPolicy {
+    pub mode: AccessPolicyMode,
+    pub user_ids: BTreeSet<UserId>,
+    pub chat_ids: BTreeSet<ChatId>,
+    /// Lets the users add themselves to the allowlist with `/join <invite-code>`.
+    pub invite_code: Option<String>,
+}
+
+impl AccessPolicy {
+    pub fn is_allowed(&self, user_id: Option<&UserId>, chat_id: &ChatId) -> bool {
+        let is_listed = self.chat_ids.contains(chat_id)
+            || user_id.is_some_and(|user_id| self.user_ids.contains(user_id));
+
+        match self.mode {
+            AccessPolicyMode::Open => true,
+            AccessPolicyMode::Allowlist => is_listed,
+            AccessPolicyMode::Denylist => !is_listed,
+        }
+    }
+}
+
+impl VersionedStorable for AccessPolicy {
+    const SCHEMA_VERSION: SchemaVersion = 1;
+    const MIGRATIONS: &'static [Migration] = &[];
+}
+
+#[derive(Debug, CandidType, Deserialize, Clone, PartialEq, Eq, Default)]
+pub struct AccessPolicyEntries {
+    pub user_ids: Vec<UserId>,
+    pub chat_ids: Vec<ChatId>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessDecision {
+    Allowed,
+    /// The user has just joined with the invite code.
+    Enrolled,
+    Rejected,
+}
+
+/// Checks that the invite code can be typed after the `/join` command:
+/// 1-64 characters, only `A-Z`, `a-z`, `0-9`, `_` and `-` are allowed.
+pub fn validate_invite_code(invite_code: &str) -> Result<(), String> {
+    if invite_code.is_empty() || invite_code.len() > 64 {
+        return Err("Invite code must be between 1 and 64 characters long".to_string());
+    }
+
+    if !invite_code
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        return Err(
+            "Invite code can only contain the characters A-Z, a-z, 0-9, _ and -".to_string(),
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::{decode_versioned, encode_versioned};
+    use rstest::*;
+
+    #[rstest]
+    fn access_policy_storable_impl() {
+        let access_policy = AccessPolicy {
+            mode: AccessPolicyMode::Allowlist,
+            user_ids: BTreeSet::from([UserId(1)]),
+            chat_ids: BTreeSet::from([ChatId(2)]),
+            invite_code: Some("invite".to_string()),
+        };
+
+        let serialized_access_policy = encode_versioned(&access_policy);
+        let deserialized_access_policy = decode_versioned(&serialized_access_policy);
+
+        assert_eq!(deserialized_access_policy, Ok(access_policy));
+    }
+
+    #[rstest]
+    #[case(AccessPolicyMode::Open, [true, true, true, true])]
+    #[case(AccessPolicyMode::Allowlist, [true, true, false, false])]
+    #[case(AccessPolicyMode::Denylist, [false, false, true, true])]
+    fn access_policy_is_allowed(#[case] mode: AccessPolicyMode, #[case] expected: [bool; 4]) {
+        let access_policy = AccessPolicy {
+            mode,
+            user_ids: BTreeSet::from([UserId(1)]),
+            chat_ids: BTreeSet::from([ChatId(2)]),
+            invite_code: None,
+        };
+
+        assert_eq!(
+            [
+                access_policy.is_allowed(Some(&UserId(1)), &ChatId(3)),
+                access_policy.is_allowed(Some(&UserId(4)), &ChatId(2)),
+                access_policy.is_allowed(Some(&UserId(4)), &ChatId(3)),
+                access_policy.is_allowed(None, &ChatId(3)),
+            ],
+            expected
+        );
+    }
+}
@@ -0,0 +1,39 @@
+use candid::{CandidType, Deserialize};
+
+use super::{ChatId, NodeId};
+
+#[derive(CandidType, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ListChatIdsRequest {
+    /// The last chat id of the previous page, if any.
+    pub start_after: Option<ChatId>,
+    pub limit: Option<u32>,
+}
+
+#[derive(CandidType, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ListChatIdsResponse {
+    pub chat_ids: Vec<ChatId>,
+    /// The `start_after` of the next page, or `None` if this is the last page.
+    pub next_start_after: Option<ChatId>,
+}
+
+#[derive(CandidType, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ChatConsistencyReport {
+    pub chat_id: ChatId,
+    pub issues: Vec<String>,
+}
+
+#[derive(CandidType, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CheckChatsConsistencyResponse {
+    /// Only the chats with at least one issue are reported.
+    pub reports: Vec<ChatConsistencyReport>,
+    pub next_start_after: Option<ChatId>,
+}
+
+/// A quarantined node, as it was found in stable memory.
+#[derive(CandidType, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct QuarantinedFileSystemNode {
+    /// The quarantine of the chat the node was set aside by, starting from 0.
+    pub generation: u64,
+    pub node_id: NodeId,
+    pub bytes: Vec<u8>,
+}
@@ -0,0 +1,90 @@
+use candid::{CandidType, Deserialize};
+use sha2::{Digest, Sha256};
+
+/// The id of a `MemoryManager` region.
+pub type MemoryRegionId = u8;
+
+#[derive(CandidType, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MemoryRegionInfo {
+    pub memory_id: MemoryRegionId,
+    /// The size of the region in bytes, always a multiple of the WebAssembly page size.
+    pub size: u64,
+}
+
+#[derive(CandidType, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BackupManifest {
+    /// The maximum size of the chunks returned by `get_backup_chunk`.
+    pub chunk_size: u64,
+    pub regions: Vec<MemoryRegionInfo>,
+}
+
+#[derive(CandidType, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BackupChunkRequest {
+    pub memory_id: MemoryRegionId,
+    pub offset: u64,
+}
+
+#[derive(CandidType, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BackupChunk {
+    pub memory_id: MemoryRegionId,
+    pub offset: u64,
+    pub data: Vec<u8>,
+    /// The SHA-256 digest of `data`.
+    pub sha256: Vec<u8>,
+}
+
+impl BackupChunk {
+    pub fn new(memory_id: MemoryRegionId, offset: u64, data: Vec<u8>) -> Self {
+        Self {
+            memory_id,
+            offset,
+            sha256: Sha256::digest(&data).to_vec(),
+            data,
+        }
+    }
+
+    pub fn verify_checksum(&self) -> Result<(), String> {
+        if Sha256::digest(&self.data)[..] != self.sha256[..] {
+            return Err(format!(
+                "Checksum mismatch for chunk at offset {} of memory region {}",
+                self.offset, self.memory_id
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// While a backup or a restore is in progress, the bot doesn't process updates,
+/// so that the stable memory doesn't change under it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MaintenanceMode {
+    Backup,
+    Restore,
+    /// The restore is finished, but the stable structures cached on the heap still
+    /// describe the memory as it was before it. Only an upgrade reloads them.
+    Restored,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    #[rstest]
+    fn backup_chunk_verify_checksum() {
+        let chunk = BackupChunk::new(1, 0, vec![1, 2, 3]);
+
+        assert_eq!(chunk.verify_checksum(), Ok(()));
+    }
+
+    #[rstest]
+    fn backup_chunk_verify_checksum_mismatch() {
+        let mut chunk = BackupChunk::new(1, 65536, vec![1, 2, 3]);
+        chunk.data[0] = 0;
+
+        assert_eq!(
+            chunk.verify_checksum(),
+            Err("Checksum mismatch for chunk at offset 65536 of memory region 1".to_string())
+        );
+    }
+}
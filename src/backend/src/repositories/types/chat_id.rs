@@ -32,6 +32,11 @@ impl ChatId {
     pub fn into_tg_chat_id(self) -> TgChatId {
         self.into()
     }
+
+    /// Telegram uses negative ids for groups and channels, and positive ids for users.
+    pub fn is_private(&self) -> bool {
+        (self.0 as i64) > 0
+    }
 }
 
 impl Storable for ChatId {
@@ -51,7 +56,9 @@ impl TryFrom<&UpdateContent> for ChatId {
 
     fn try_from(update_content: &UpdateContent) -> Result<Self, Self::Error> {
         match update_content {
-            UpdateContent::Message(msg) => Ok(ChatId::from(msg.chat.id)),
+            UpdateContent::Message(msg) | UpdateContent::EditedMessage(msg) => {
+                Ok(ChatId::from(msg.chat.id))
+            }
             UpdateContent::CallbackQuery(query) => Ok(ChatId::from(
                 match query
                     .message
@@ -65,6 +72,12 @@ impl TryFrom<&UpdateContent> for ChatId {
                 }
                 .id,
             )),
+            UpdateContent::MyChatMember(chat_member_updated)
+            | UpdateContent::ChatMember(chat_member_updated) => {
+                Ok(ChatId::from(chat_member_updated.chat.id))
+            }
+            // inline queries are sent from any chat, the files are the ones of the private chat
+            UpdateContent::InlineQuery(query) => Ok(ChatId(query.from.id)),
             _ => Err("Unsupported update content".to_string()),
         }
     }
@@ -0,0 +1,160 @@
+use std::{borrow::Cow, fmt, str::FromStr};
+
+use candid::{CandidType, Deserialize};
+use frankenstein::types::{ChatMember, User};
+use ic_stable_structures::{storable::Bound, Storable};
+
+use super::{ChatId, ChatSession, Migration, SchemaVersion, UserId, VersionedStorable};
+
+/// Identifies a user inside a chat. In private chats, the user id is the same as the chat id.
+#[derive(Debug, CandidType, Deserialize, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ChatMemberId {
+    pub chat_id: ChatId,
+    pub user_id: UserId,
+}
+
+impl ChatMemberId {
+    pub fn new(chat_id: ChatId, user_id: UserId) -> Self {
+        Self { chat_id, user_id }
+    }
+
+    /// Uses the chat id when the sender is not known, e.g. for the posts in channels.
+    pub fn from_sender(chat_id: ChatId, from: Option<&User>) -> Self {
+        let user_id = from
+            .map(|user| UserId(user.id))
+            .unwrap_or_else(|| UserId(chat_id.0));
+        Self::new(chat_id, user_id)
+    }
+
+    /// The lowest and the highest ids of the members of the chat, to iterate over them.
+    pub fn chat_bounds(chat_id: &ChatId) -> (Self, Self) {
+        (
+            Self::new(chat_id.clone(), UserId(u64::MIN)),
+            Self::new(chat_id.clone(), UserId(u64::MAX)),
+        )
+    }
+}
+
+impl Storable for ChatMemberId {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        let mut bytes = self.chat_id.to_bytes().into_owned();
+        bytes.extend_from_slice(&self.user_id.to_bytes());
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        let (chat_id_bytes, user_id_bytes) = bytes.split_at(8);
+        Self {
+            chat_id: ChatId::from_bytes(Cow::Borrowed(chat_id_bytes)),
+            user_id: UserId::from_bytes(Cow::Borrowed(user_id_bytes)),
+        }
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: 16,
+        is_fixed_size: true,
+    };
+}
+
+/// What a member can do with the filesystem of a group.
+/// In private chats, the user is always the admin.
+#[derive(Debug, CandidType, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ChatRole {
+    /// Can only browse the files.
+    Viewer,
+    /// Can also save, move and rename files and create directories.
+    Editor,
+    /// Can also set the roles of the other members and delete the data of the group.
+    Admin,
+}
+
+impl ChatRole {
+    /// The role given by the Telegram status of the member, if any.
+    pub fn from_chat_member(chat_member: &ChatMember) -> Option<Self> {
+        match chat_member {
+            ChatMember::Creator(_) | ChatMember::Administrator(_) => Some(ChatRole::Admin),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ChatRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                ChatRole::Viewer => "viewer",
+                ChatRole::Editor => "editor",
+                ChatRole::Admin => "admin",
+            }
+        )
+    }
+}
+
+impl FromStr for ChatRole {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "viewer" => Ok(ChatRole::Viewer),
+            "editor" => Ok(ChatRole::Editor),
+            "admin" => Ok(ChatRole::Admin),
+            _ => Err(format!("Unknown role {s:?}")),
+        }
+    }
+}
+
+impl VersionedStorable for ChatRole {
+    const SCHEMA_VERSION: SchemaVersion = 1;
+    const MIGRATIONS: &'static [Migration] = &[];
+}
+
+#[derive(Debug, CandidType, Deserialize, Clone, PartialEq, Eq)]
+pub struct ChatMemberSession {
+    pub user_id: UserId,
+    pub chat_session: ChatSession,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    #[rstest]
+    fn chat_member_id_storable_impl() {
+        let chat_member_id = ChatMemberId::new(ChatId::from(-123i64), UserId(456));
+
+        let serialized_chat_member_id = chat_member_id.to_bytes();
+        let deserialized_chat_member_id = ChatMemberId::from_bytes(serialized_chat_member_id);
+
+        assert_eq!(deserialized_chat_member_id, chat_member_id);
+    }
+
+    #[rstest]
+    fn chat_member_id_bytes_ordering() {
+        // the stable BTreeMap orders the keys by their bytes
+        let chat_member_ids = [
+            ChatMemberId::new(ChatId(1), UserId(u64::MAX)),
+            ChatMemberId::new(ChatId(2), UserId(0)),
+            ChatMemberId::new(ChatId(2), UserId(7)),
+            ChatMemberId::new(ChatId(2), UserId(u64::MAX)),
+            ChatMemberId::new(ChatId(3), UserId(0)),
+        ];
+
+        for pair in chat_member_ids.windows(2) {
+            assert!(pair[0].to_bytes() < pair[1].to_bytes());
+        }
+    }
+
+    #[rstest]
+    fn chat_role_from_str() {
+        for role in [ChatRole::Viewer, ChatRole::Editor, ChatRole::Admin] {
+            assert_eq!(role.to_string().parse(), Ok(role));
+        }
+        assert_eq!(
+            "owner".parse::<ChatRole>(),
+            Err("Unknown role \"owner\"".to_string())
+        );
+    }
+}
@@ -1,7 +1,10 @@
-use std::{borrow::Cow, fmt, path::PathBuf};
+use std::{
+    collections::BTreeSet,
+    fmt,
+    path::{Path, PathBuf},
+};
 
-use candid::{CandidType, Decode, Deserialize, Encode};
-use ic_stable_structures::{storable::Bound, Storable};
+use candid::{CandidType, Deserialize};
 
 use crate::{
     custom_print,
@@ -9,18 +12,104 @@ use crate::{
         filesystem::root_path,
         is_absolute,
         messages::{
-            BACK_BUTTON_TEXT, CURRENT_DIR_BUTTON_TEXT, DELETE_DIR_BUTTON_TEXT, MKDIR_BUTTON_TEXT,
-            PARENT_DIR_BUTTON_TEXT,
+            auto_naming_text, conflict_policy_text, language_button_text, setting_text,
+            sort_order_text,
         },
+        t,
     },
 };
 
-use super::FileSystemNode;
+use super::{
+    AutoNaming, ChatRole, ConflictPolicy, DriveId, FileSystemNode, Language, MessageId, Migration,
+    SchemaVersion, ShareId, SharePermission, SortOrder, VersionedStorable,
+};
+
+const REVOKE_SHARE_ACTION_PREFIX: &str = "revoke-share-";
+const SWITCH_DRIVE_ACTION_PREFIX: &str = "switch-drive-";
+const DELETE_DRIVE_ACTION_PREFIX: &str = "delete-drive-";
+/// Identifies the main drive in the switch drive actions.
+const MAIN_DRIVE_ACTION_SUFFIX: &str = "main";
+const SET_LANGUAGE_ACTION_PREFIX: &str = "set-language-";
+/// Identifies the automatic language in the set language actions.
+const AUTO_LANGUAGE_ACTION_SUFFIX: &str = "auto";
+const EDIT_SETTING_ACTION_PREFIX: &str = "edit-setting-";
+const SET_SORT_ORDER_ACTION_PREFIX: &str = "set-sort-order-";
+const SET_CONFLICT_POLICY_ACTION_PREFIX: &str = "set-conflict-policy-";
+const SET_AUTO_NAMING_ACTION_PREFIX: &str = "set-auto-naming-";
 
 #[derive(Debug, CandidType, Deserialize, Clone, PartialEq, Eq)]
 pub enum ChatSessionWaitReply {
     DirectoryName,
     FileName,
+    FinalConfirmation,
+    DriveName,
+    TagName,
+}
+
+/// An operation applied to all the files selected in the explorer.
+#[derive(Debug, CandidType, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum BulkOperation {
+    Move,
+    Copy,
+    Delete,
+    Tag,
+}
+
+/// A setting of the /settings menu, see [super::ChatSettings].
+#[derive(Debug, CandidType, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum Setting {
+    SaveDir,
+    Inbox,
+    SortOrder,
+    ConflictPolicy,
+    Language,
+    TimeZone,
+    AutoNaming,
+    NameTemplate,
+}
+
+impl Setting {
+    pub const ALL: [Setting; 8] = [
+        Setting::SaveDir,
+        Setting::Inbox,
+        Setting::SortOrder,
+        Setting::ConflictPolicy,
+        Setting::Language,
+        Setting::TimeZone,
+        Setting::AutoNaming,
+        Setting::NameTemplate,
+    ];
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Setting::SaveDir => "save-dir",
+            Setting::Inbox => "inbox",
+            Setting::SortOrder => "sort-order",
+            Setting::ConflictPolicy => "conflict-policy",
+            Setting::Language => "language",
+            Setting::TimeZone => "time-zone",
+            Setting::AutoNaming => "auto-naming",
+            Setting::NameTemplate => "name-template",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|setting| setting.code() == code)
+    }
+}
+
+/// A file received in a message, with the name it was sent with, if any.
+#[derive(Debug, CandidType, Deserialize, Clone, PartialEq, Eq)]
+pub struct ReceivedFile {
+    pub node: FileSystemNode,
+    pub file_name: Option<String>,
+}
+
+/// The files of an album, gathered from its messages to be saved together.
+#[derive(Debug, CandidType, Deserialize, Clone, PartialEq, Eq)]
+pub struct MediaGroup {
+    pub media_group_id: String,
+    pub files: Vec<ReceivedFile>,
 }
 
 #[derive(Debug, CandidType, Deserialize, Clone, PartialEq, Eq)]
@@ -36,22 +125,132 @@ pub enum ChatSessionAction {
     DeleteFile,
     FileOrDir(PathBuf),
     Back,
+    ForgetMe(Option<ChatSessionWaitReply>),
+    Confirm,
+    Cancel,
+    /// The directory to share, once selected.
+    Share(Option<PathBuf>),
+    GrantShare(SharePermission),
+    Shares,
+    RevokeShare(ShareId),
+    Drives,
+    /// The main drive when `None`.
+    SwitchDrive(Option<DriveId>),
+    CreateDrive(Option<ChatSessionWaitReply>),
+    /// The drive to delete, once selected.
+    DeleteDrive(Option<DriveId>),
+    SaveMediaGroup(Option<MediaGroup>, Option<ChatSessionWaitReply>),
+    /// Saves the files of an album with the names they were sent with.
+    KeepFileNames,
+    /// Toggles the files clicked in the explorer, see [ChatSession::selection].
+    SelectFiles,
+    Bulk(BulkOperation, Option<ChatSessionWaitReply>),
+    Languages,
+    /// Lets each member use their own language when `None`.
+    SetLanguage(Option<Language>),
+    Settings,
+    /// Opens the choices of the setting, or turns the inbox on and off. As the current action,
+    /// the save folder is being selected or the time zone or the name template is awaited.
+    EditSetting(Setting),
+    SetSortOrder(SortOrder),
+    SetConflictPolicy(ConflictPolicy),
+    SetAutoNaming(AutoNaming),
+    /// Moves and renames the file just saved in the inbox, once its path is set:
+    /// the destination directory is selected, then the new name is awaited.
+    RefineFile(Option<PathBuf>, Option<ChatSessionWaitReply>),
 }
 
 impl ChatSessionAction {
     pub fn beautified(&self) -> String {
         match self {
-            ChatSessionAction::MkDir(_) => MKDIR_BUTTON_TEXT.to_string(),
+            ChatSessionAction::MkDir(_) => t("button.mkdir").to_string(),
             ChatSessionAction::SaveFile(_, _) => "".to_string(),
-            ChatSessionAction::CurrentDir => CURRENT_DIR_BUTTON_TEXT.to_string(),
-            ChatSessionAction::ParentDir => PARENT_DIR_BUTTON_TEXT.to_string(),
-            ChatSessionAction::DeleteDir => DELETE_DIR_BUTTON_TEXT.to_string(),
+            ChatSessionAction::CurrentDir => t("button.current_dir").to_string(),
+            ChatSessionAction::ParentDir => t("button.parent_dir").to_string(),
+            ChatSessionAction::DeleteDir => t("button.delete_dir").to_string(),
             ChatSessionAction::Explorer => "".to_string(),
             ChatSessionAction::RenameFile(_) => "".to_string(),
             ChatSessionAction::MoveFile(_) => "".to_string(),
             ChatSessionAction::DeleteFile => "".to_string(),
             ChatSessionAction::FileOrDir(path) => path.to_string_lossy().to_string(),
-            ChatSessionAction::Back => BACK_BUTTON_TEXT.to_string(),
+            ChatSessionAction::Back => t("button.back").to_string(),
+            ChatSessionAction::ForgetMe(_) => "".to_string(),
+            ChatSessionAction::Confirm => t("button.confirm").to_string(),
+            ChatSessionAction::Cancel => t("button.cancel").to_string(),
+            ChatSessionAction::Share(_) => "".to_string(),
+            ChatSessionAction::GrantShare(SharePermission::ReadOnly) => {
+                t("button.read_only").to_string()
+            }
+            ChatSessionAction::GrantShare(SharePermission::ReadWrite) => {
+                t("button.read_write").to_string()
+            }
+            ChatSessionAction::Shares => "".to_string(),
+            ChatSessionAction::RevokeShare(_) => "".to_string(),
+            ChatSessionAction::Drives => "".to_string(),
+            ChatSessionAction::SwitchDrive(_) => "".to_string(),
+            ChatSessionAction::CreateDrive(_) => t("button.create_drive").to_string(),
+            ChatSessionAction::DeleteDrive(_) => t("button.delete_drive").to_string(),
+            ChatSessionAction::SaveMediaGroup(_, _) => "".to_string(),
+            ChatSessionAction::KeepFileNames => t("button.keep_file_names").to_string(),
+            ChatSessionAction::SelectFiles => t("button.select_files").to_string(),
+            ChatSessionAction::Bulk(BulkOperation::Move, _) => t("button.bulk_move").to_string(),
+            ChatSessionAction::Bulk(BulkOperation::Copy, _) => t("button.bulk_copy").to_string(),
+            ChatSessionAction::Bulk(BulkOperation::Delete, _) => {
+                t("button.bulk_delete").to_string()
+            }
+            ChatSessionAction::Bulk(BulkOperation::Tag, _) => t("button.bulk_tag").to_string(),
+            ChatSessionAction::Languages => "".to_string(),
+            ChatSessionAction::SetLanguage(language) => language_button_text(*language),
+            ChatSessionAction::Settings => "".to_string(),
+            ChatSessionAction::EditSetting(setting) => setting_text(*setting).to_string(),
+            ChatSessionAction::SetSortOrder(sort_order) => sort_order_text(*sort_order).to_string(),
+            ChatSessionAction::SetConflictPolicy(conflict_policy) => {
+                conflict_policy_text(*conflict_policy).to_string()
+            }
+            ChatSessionAction::SetAutoNaming(auto_naming) => {
+                auto_naming_text(*auto_naming).to_string()
+            }
+            ChatSessionAction::RefineFile(_, _) => t("button.refine_file").to_string(),
+        }
+    }
+
+    /// The role needed to go on with the action.
+    pub fn required_role(&self) -> ChatRole {
+        match self {
+            ChatSessionAction::MkDir(_)
+            | ChatSessionAction::SaveFile(_, _)
+            | ChatSessionAction::SaveMediaGroup(_, _)
+            | ChatSessionAction::KeepFileNames
+            | ChatSessionAction::RefineFile(_, _)
+            | ChatSessionAction::SelectFiles
+            | ChatSessionAction::Bulk(_, _)
+            | ChatSessionAction::DeleteDir
+            | ChatSessionAction::RenameFile(_)
+            | ChatSessionAction::MoveFile(_)
+            | ChatSessionAction::DeleteFile
+            | ChatSessionAction::CreateDrive(_) => ChatRole::Editor,
+            ChatSessionAction::ForgetMe(_)
+            | ChatSessionAction::DeleteDrive(_)
+            | ChatSessionAction::Share(_)
+            | ChatSessionAction::GrantShare(_)
+            | ChatSessionAction::Shares
+            | ChatSessionAction::RevokeShare(_)
+            | ChatSessionAction::Languages
+            | ChatSessionAction::SetLanguage(_)
+            | ChatSessionAction::Settings
+            | ChatSessionAction::EditSetting(_)
+            | ChatSessionAction::SetSortOrder(_)
+            | ChatSessionAction::SetConflictPolicy(_)
+            | ChatSessionAction::SetAutoNaming(_) => ChatRole::Admin,
+            ChatSessionAction::CurrentDir
+            | ChatSessionAction::ParentDir
+            | ChatSessionAction::Explorer
+            | ChatSessionAction::FileOrDir(_)
+            | ChatSessionAction::Back
+            | ChatSessionAction::Confirm
+            | ChatSessionAction::Cancel
+            | ChatSessionAction::Drives
+            | ChatSessionAction::SwitchDrive(_) => ChatRole::Viewer,
         }
     }
 }
@@ -79,6 +278,51 @@ impl fmt::Display for ChatSessionAction {
                 ChatSessionAction::DeleteFile => "delete-file-action".to_string(),
                 ChatSessionAction::FileOrDir(path) => path.to_string_lossy().to_string(),
                 ChatSessionAction::Back => "back-action".to_string(),
+                ChatSessionAction::ForgetMe(_) => "forget-me-action".to_string(),
+                ChatSessionAction::Confirm => "confirm-action".to_string(),
+                ChatSessionAction::Cancel => "cancel-action".to_string(),
+                ChatSessionAction::Share(_) => "share-action".to_string(),
+                ChatSessionAction::GrantShare(SharePermission::ReadOnly) =>
+                    "grant-read-only-share-action".to_string(),
+                ChatSessionAction::GrantShare(SharePermission::ReadWrite) =>
+                    "grant-read-write-share-action".to_string(),
+                ChatSessionAction::Shares => "shares-action".to_string(),
+                ChatSessionAction::RevokeShare(share_id) =>
+                    format!("{REVOKE_SHARE_ACTION_PREFIX}{share_id}"),
+                ChatSessionAction::Drives => "drives-action".to_string(),
+                ChatSessionAction::SwitchDrive(None) =>
+                    format!("{SWITCH_DRIVE_ACTION_PREFIX}{MAIN_DRIVE_ACTION_SUFFIX}"),
+                ChatSessionAction::SwitchDrive(Some(drive_id)) =>
+                    format!("{SWITCH_DRIVE_ACTION_PREFIX}{drive_id}"),
+                ChatSessionAction::CreateDrive(_) => "create-drive-action".to_string(),
+                ChatSessionAction::DeleteDrive(None) => "delete-drive-action".to_string(),
+                ChatSessionAction::DeleteDrive(Some(drive_id)) =>
+                    format!("{DELETE_DRIVE_ACTION_PREFIX}{drive_id}"),
+                ChatSessionAction::SaveMediaGroup(_, _) => "save-media-group-action".to_string(),
+                ChatSessionAction::KeepFileNames => "keep-file-names-action".to_string(),
+                ChatSessionAction::SelectFiles => "select-files-action".to_string(),
+                ChatSessionAction::Bulk(BulkOperation::Move, _) => "bulk-move-action".to_string(),
+                ChatSessionAction::Bulk(BulkOperation::Copy, _) => "bulk-copy-action".to_string(),
+                ChatSessionAction::Bulk(BulkOperation::Delete, _) =>
+                    "bulk-delete-action".to_string(),
+                ChatSessionAction::Bulk(BulkOperation::Tag, _) => "bulk-tag-action".to_string(),
+                ChatSessionAction::Languages => "languages-action".to_string(),
+                ChatSessionAction::SetLanguage(None) =>
+                    format!("{SET_LANGUAGE_ACTION_PREFIX}{AUTO_LANGUAGE_ACTION_SUFFIX}"),
+                ChatSessionAction::SetLanguage(Some(language)) =>
+                    format!("{SET_LANGUAGE_ACTION_PREFIX}{}", language.code()),
+                ChatSessionAction::Settings => "settings-action".to_string(),
+                ChatSessionAction::EditSetting(setting) =>
+                    format!("{EDIT_SETTING_ACTION_PREFIX}{}", setting.code()),
+                ChatSessionAction::SetSortOrder(sort_order) =>
+                    format!("{SET_SORT_ORDER_ACTION_PREFIX}{}", sort_order.code()),
+                ChatSessionAction::SetConflictPolicy(conflict_policy) => format!(
+                    "{SET_CONFLICT_POLICY_ACTION_PREFIX}{}",
+                    conflict_policy.code()
+                ),
+                ChatSessionAction::SetAutoNaming(auto_naming) =>
+                    format!("{SET_AUTO_NAMING_ACTION_PREFIX}{}", auto_naming.code()),
+                ChatSessionAction::RefineFile(_, _) => "refine-file-action".to_string(),
             }
         )
     }
@@ -97,15 +341,95 @@ impl From<String> for ChatSessionAction {
             "move-file-action" => ChatSessionAction::MoveFile(None),
             "delete-file-action" => ChatSessionAction::DeleteFile,
             "back-action" => ChatSessionAction::Back,
-            _ => ChatSessionAction::FileOrDir(PathBuf::from(val)),
+            "forget-me-action" => ChatSessionAction::ForgetMe(None),
+            "confirm-action" => ChatSessionAction::Confirm,
+            "cancel-action" => ChatSessionAction::Cancel,
+            "share-action" => ChatSessionAction::Share(None),
+            "grant-read-only-share-action" => {
+                ChatSessionAction::GrantShare(SharePermission::ReadOnly)
+            }
+            "grant-read-write-share-action" => {
+                ChatSessionAction::GrantShare(SharePermission::ReadWrite)
+            }
+            "shares-action" => ChatSessionAction::Shares,
+            "drives-action" => ChatSessionAction::Drives,
+            "create-drive-action" => ChatSessionAction::CreateDrive(None),
+            "delete-drive-action" => ChatSessionAction::DeleteDrive(None),
+            "save-media-group-action" => ChatSessionAction::SaveMediaGroup(None, None),
+            "keep-file-names-action" => ChatSessionAction::KeepFileNames,
+            "select-files-action" => ChatSessionAction::SelectFiles,
+            "bulk-move-action" => ChatSessionAction::Bulk(BulkOperation::Move, None),
+            "bulk-copy-action" => ChatSessionAction::Bulk(BulkOperation::Copy, None),
+            "bulk-delete-action" => ChatSessionAction::Bulk(BulkOperation::Delete, None),
+            "bulk-tag-action" => ChatSessionAction::Bulk(BulkOperation::Tag, None),
+            "languages-action" => ChatSessionAction::Languages,
+            "settings-action" => ChatSessionAction::Settings,
+            "refine-file-action" => ChatSessionAction::RefineFile(None, None),
+            _ => parse_prefixed_action(&val)
+                .unwrap_or_else(|| ChatSessionAction::FileOrDir(PathBuf::from(val))),
+        }
+    }
+}
+
+/// Parses the actions that carry an id.
+fn parse_prefixed_action(val: &str) -> Option<ChatSessionAction> {
+    if let Some(share_id) = val.strip_prefix(REVOKE_SHARE_ACTION_PREFIX) {
+        return share_id.parse().ok().map(ChatSessionAction::RevokeShare);
+    }
+    if let Some(drive_id) = val.strip_prefix(SWITCH_DRIVE_ACTION_PREFIX) {
+        if drive_id == MAIN_DRIVE_ACTION_SUFFIX {
+            return Some(ChatSessionAction::SwitchDrive(None));
+        }
+        return drive_id
+            .parse()
+            .ok()
+            .map(|drive_id| ChatSessionAction::SwitchDrive(Some(drive_id)));
+    }
+    if let Some(drive_id) = val.strip_prefix(DELETE_DRIVE_ACTION_PREFIX) {
+        return drive_id
+            .parse()
+            .ok()
+            .map(|drive_id| ChatSessionAction::DeleteDrive(Some(drive_id)));
+    }
+    if let Some(code) = val.strip_prefix(SET_LANGUAGE_ACTION_PREFIX) {
+        if code == AUTO_LANGUAGE_ACTION_SUFFIX {
+            return Some(ChatSessionAction::SetLanguage(None));
         }
+        return Language::from_code(code)
+            .map(|language| ChatSessionAction::SetLanguage(Some(language)));
     }
+    if let Some(code) = val.strip_prefix(EDIT_SETTING_ACTION_PREFIX) {
+        return Setting::from_code(code).map(ChatSessionAction::EditSetting);
+    }
+    if let Some(code) = val.strip_prefix(SET_SORT_ORDER_ACTION_PREFIX) {
+        return SortOrder::from_code(code).map(ChatSessionAction::SetSortOrder);
+    }
+    if let Some(code) = val.strip_prefix(SET_CONFLICT_POLICY_ACTION_PREFIX) {
+        return ConflictPolicy::from_code(code).map(ChatSessionAction::SetConflictPolicy);
+    }
+    if let Some(code) = val.strip_prefix(SET_AUTO_NAMING_ACTION_PREFIX) {
+        return AutoNaming::from_code(code).map(ChatSessionAction::SetAutoNaming);
+    }
+    None
 }
 
 #[derive(Debug, CandidType, Deserialize, Clone, PartialEq, Eq)]
 pub struct ChatSession {
     current_path: PathBuf,
     action: Option<ChatSessionAction>,
+    /// In groups, the message that the keyboard of the current action replies to,
+    /// to tell apart the keyboards opened by the members.
+    /// Optional, so that the sessions stored before it was added still decode.
+    keyboard_anchor: Option<MessageId>,
+    /// The active drive, the main drive when `None`. Kept when the session is reset.
+    drive_id: Option<DriveId>,
+    /// The files selected in the explorer, to apply a [BulkOperation] to all of them.
+    selection: Option<BTreeSet<PathBuf>>,
+    /// The directory the paths passed to the commands are relative to, the root when `None`.
+    /// Kept when the session is reset.
+    working_dir: Option<PathBuf>,
+    /// The name the file being saved was sent with, if any.
+    received_file_name: Option<String>,
 }
 
 impl ChatSession {
@@ -136,9 +460,87 @@ impl ChatSession {
         self.current_path = path
     }
 
+    pub fn keyboard_anchor(&self) -> Option<MessageId> {
+        self.keyboard_anchor
+    }
+
+    pub fn set_keyboard_anchor(&mut self, message_id: MessageId) {
+        self.keyboard_anchor = Some(message_id);
+    }
+
+    pub fn drive_id(&self) -> Option<DriveId> {
+        self.drive_id
+    }
+
+    /// Switches to the drive, starting from its root directory.
+    pub fn set_drive_id(&mut self, drive_id: Option<DriveId>) {
+        self.drive_id = drive_id;
+        self.set_current_path(root_path());
+        self.working_dir = None;
+    }
+
+    /// The working directory as it was set, which may not exist anymore.
+    pub fn working_dir(&self) -> PathBuf {
+        self.working_dir.clone().unwrap_or_else(root_path)
+    }
+
+    /// Follows the move of the working directory or of one of its parents.
+    pub fn move_working_dir(&mut self, from: &Path, to: &Path) {
+        let Some(relative_path) = self
+            .working_dir
+            .as_ref()
+            .and_then(|working_dir| working_dir.strip_prefix(from).ok())
+        else {
+            return;
+        };
+        let working_dir = if relative_path.as_os_str().is_empty() {
+            to.to_path_buf()
+        } else {
+            to.join(relative_path)
+        };
+        self.working_dir = Some(working_dir);
+    }
+
+    pub fn set_working_dir(&mut self, path: PathBuf) {
+        if !is_absolute(&path) {
+            panic!("Path is not absolute");
+        }
+        self.working_dir = Some(path);
+    }
+
+    pub fn selection(&self) -> BTreeSet<PathBuf> {
+        self.selection.clone().unwrap_or_default()
+    }
+
+    /// Adds the path to the selection, or removes it if already selected.
+    /// Returns whether the path is selected.
+    pub fn toggle_selection(&mut self, path: PathBuf) -> bool {
+        let selection = self.selection.get_or_insert_with(BTreeSet::new);
+        if selection.remove(&path) {
+            false
+        } else {
+            selection.insert(path)
+        }
+    }
+
+    pub fn clear_selection(&mut self) {
+        self.selection = None;
+    }
+
+    pub fn received_file_name(&self) -> Option<String> {
+        self.received_file_name.clone()
+    }
+
+    pub fn set_received_file_name(&mut self, file_name: Option<String>) {
+        self.received_file_name = file_name;
+    }
+
     pub fn reset(&mut self) {
         self.set_current_path(root_path());
         self.action = None;
+        self.keyboard_anchor = None;
+        self.selection = None;
+        self.received_file_name = None;
     }
 }
 
@@ -147,20 +549,18 @@ impl Default for ChatSession {
         Self {
             current_path: root_path(),
             action: None,
+            keyboard_anchor: None,
+            drive_id: None,
+            selection: None,
+            working_dir: None,
+            received_file_name: None,
         }
     }
 }
 
-impl Storable for ChatSession {
-    fn to_bytes(&self) -> Cow<[u8]> {
-        Cow::Owned(Encode!(self).unwrap())
-    }
-
-    fn from_bytes(bytes: Cow<[u8]>) -> Self {
-        Decode!(bytes.as_ref(), Self).unwrap()
-    }
-
-    const BOUND: Bound = Bound::Unbounded;
+impl VersionedStorable for ChatSession {
+    const SCHEMA_VERSION: SchemaVersion = 1;
+    const MIGRATIONS: &'static [Migration] = &[];
 }
 
 pub fn with_clear_action_on_error<F: FnOnce(&mut ChatSession) -> Result<R, String>, R>(
@@ -178,16 +578,141 @@ pub fn with_clear_action_on_error<F: FnOnce(&mut ChatSession) -> Result<R, Strin
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::repositories::{decode_versioned, encode_versioned};
     use rstest::*;
 
     #[rstest]
     fn storable_impl() {
         let chat_session = ChatSession::default();
 
-        let serialized_chat_session = chat_session.to_bytes();
-        let deserialized_chat_session = ChatSession::from_bytes(serialized_chat_session);
+        let serialized_chat_session = encode_versioned(&chat_session);
+        let deserialized_chat_session = decode_versioned(&serialized_chat_session);
+
+        assert_eq!(deserialized_chat_session, Ok(chat_session));
+    }
+
+    // ChatSession values encoded by the release that stored them without a versioned envelope
+    const CHAT_SESSION_DEFAULT_FIXTURE: &str = "4449444c0d6c02b6f798b20101abed858a0b716e026b0bba82ef8a0103a3ccf388027f9f8af4cc027fc78ca8df027fe7e0e6ed087fc283c99c097fefe8a1e60903b48de0ee0b7fcdec93810c05f9c89c890d06aed78cc80d716e046b0287aed4df037fb8b6f0c80b7f6e716c02000701036e086b029cf5d3f40209cda4df900b0a6c04c1c1cee20478e5a88eea0505aaacd9d0067893a5bf950d756c02aaacd9d00678b1f0fafd090b6d0c6c0200710108010000012f";
+    const CHAT_SESSION_SAVE_FILE_FIXTURE: &str = "4449444c0d6c02b6f798b20101abed858a0b716e026b0bba82ef8a0103a3ccf388027f9f8af4cc027fc78ca8df027fe7e0e6ed087fc283c99c097fefe8a1e60903b48de0ee0b7fcdec93810c05f9c89c890d06aed78cc80d716e046b0287aed4df037fb8b6f0c80b7f6e716c02000701036e086b029cf5d3f40209cda4df900b0a6c04c1c1cee20478e5a88eea0505aaacd9d0067893a5bf950d756c02aaacd9d00678b1f0fafd090b6d0c6c0200710108010001090100050000000000000001136170706c69636174696f6e2f74672b7465787400002a36fe9c97170700000001000a2f446f63756d656e7473";
+    const CHAT_SESSION_MOVE_FILE_FIXTURE: &str = "4449444c0d6c02b6f798b20101abed858a0b716e026b0bba82ef8a0103a3ccf388027f9f8af4cc027fc78ca8df027fe7e0e6ed087fc283c99c097fefe8a1e60903b48de0ee0b7fcdec93810c05f9c89c890d06aed78cc80d716e046b0287aed4df037fb8b6f0c80b7f6e716c02000701036e086b029cf5d3f40209cda4df900b0a6c04c1c1cee20478e5a88eea0505aaacd9d0067893a5bf950d756c02aaacd9d00678b1f0fafd090b6d0c6c02007101080100010801152f446f63756d656e74732f7265706f72742e706466152f446f63756d656e74732f7265706f72742e706466";
+
+    fn decode_hex(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[rstest]
+    fn storable_impl_decodes_unversioned_fixtures() {
+        let chat_session: ChatSession =
+            decode_versioned(&decode_hex(CHAT_SESSION_DEFAULT_FIXTURE)).unwrap();
+        assert_eq!(chat_session, ChatSession::default());
+
+        let chat_session: ChatSession =
+            decode_versioned(&decode_hex(CHAT_SESSION_SAVE_FILE_FIXTURE)).unwrap();
+        assert_eq!(chat_session.current_path(), &PathBuf::from("/Documents"));
+        assert_eq!(
+            chat_session.action(),
+            Some(ChatSessionAction::SaveFile(
+                Some(FileSystemNode::File {
+                    message_id: 7,
+                    created_at: 1_700_000_000_000_000_000,
+                    size: 5,
+                    mime_type: Some("application/tg+text".to_string()),
+                    telegram_file: None,
+                    tags: None,
+                }),
+                Some(ChatSessionWaitReply::FileName)
+            ))
+        );
+
+        let chat_session: ChatSession =
+            decode_versioned(&decode_hex(CHAT_SESSION_MOVE_FILE_FIXTURE)).unwrap();
+        assert_eq!(
+            chat_session.current_path(),
+            &PathBuf::from("/Documents/report.pdf")
+        );
+        assert_eq!(
+            chat_session.action(),
+            Some(ChatSessionAction::MoveFile(Some(PathBuf::from(
+                "/Documents/report.pdf"
+            ))))
+        );
+    }
+
+    #[rstest]
+    #[case(ChatSessionAction::RevokeShare(7))]
+    #[case(ChatSessionAction::SwitchDrive(None))]
+    #[case(ChatSessionAction::SwitchDrive(Some(3)))]
+    #[case(ChatSessionAction::DeleteDrive(None))]
+    #[case(ChatSessionAction::DeleteDrive(Some(3)))]
+    #[case(ChatSessionAction::SaveMediaGroup(None, None))]
+    #[case(ChatSessionAction::KeepFileNames)]
+    #[case(ChatSessionAction::SelectFiles)]
+    #[case(ChatSessionAction::Bulk(BulkOperation::Move, None))]
+    #[case(ChatSessionAction::Bulk(BulkOperation::Copy, None))]
+    #[case(ChatSessionAction::Bulk(BulkOperation::Delete, None))]
+    #[case(ChatSessionAction::Bulk(BulkOperation::Tag, None))]
+    #[case(ChatSessionAction::Languages)]
+    #[case(ChatSessionAction::SetLanguage(None))]
+    #[case(ChatSessionAction::SetLanguage(Some(Language::Italian)))]
+    #[case(ChatSessionAction::Settings)]
+    #[case(ChatSessionAction::EditSetting(Setting::SaveDir))]
+    #[case(ChatSessionAction::EditSetting(Setting::TimeZone))]
+    #[case(ChatSessionAction::EditSetting(Setting::NameTemplate))]
+    #[case(ChatSessionAction::SetSortOrder(SortOrder::NameDescending))]
+    #[case(ChatSessionAction::SetConflictPolicy(ConflictPolicy::Overwrite))]
+    #[case(ChatSessionAction::SetAutoNaming(AutoNaming::OriginalName))]
+    #[case(ChatSessionAction::RefineFile(None, None))]
+    #[case(ChatSessionAction::FileOrDir(PathBuf::from("/switch-drive-x")))]
+    fn action_callback_data_round_trip(#[case] action: ChatSessionAction) {
+        assert_eq!(ChatSessionAction::from(action.to_string()), action);
+    }
+
+    #[rstest]
+    fn set_drive_id() {
+        let mut chat_session = ChatSession::default();
+        chat_session.set_current_path(PathBuf::from("/test"));
+        chat_session.set_working_dir(PathBuf::from("/test"));
+        chat_session.set_drive_id(Some(3));
+        assert_eq!(chat_session.current_path(), &root_path());
+        assert_eq!(chat_session.working_dir(), root_path());
+
+        chat_session.reset();
+        assert_eq!(chat_session.drive_id(), Some(3));
+    }
+
+    #[rstest]
+    fn move_working_dir() {
+        let mut chat_session = ChatSession::default();
+        chat_session.set_working_dir(PathBuf::from("/Docs/2026"));
+
+        chat_session.move_working_dir(Path::new("/Other"), Path::new("/Archive"));
+        assert_eq!(chat_session.working_dir(), PathBuf::from("/Docs/2026"));
+        chat_session.move_working_dir(Path::new("/Docs"), Path::new("/Archive/Docs"));
+        assert_eq!(
+            chat_session.working_dir(),
+            PathBuf::from("/Archive/Docs/2026")
+        );
+        chat_session.move_working_dir(Path::new("/Archive/Docs/2026"), Path::new("/2026"));
+        assert_eq!(chat_session.working_dir().to_string_lossy(), "/2026");
+    }
+
+    #[rstest]
+    fn toggle_selection() {
+        let mut chat_session = ChatSession::default();
+        let path = PathBuf::from("/file-a");
+        assert!(chat_session.toggle_selection(path.clone()));
+        assert!(chat_session.toggle_selection(PathBuf::from("/file-b")));
+        assert!(!chat_session.toggle_selection(path.clone()));
+        assert_eq!(
+            chat_session.selection(),
+            BTreeSet::from([PathBuf::from("/file-b")])
+        );
 
-        assert_eq!(deserialized_chat_session, chat_session);
+        chat_session.reset();
+        assert!(chat_session.selection().is_empty());
     }
 
     #[rstest]
@@ -0,0 +1,364 @@
+use std::{fmt, path::PathBuf, str::FromStr};
+
+use candid::{CandidType, Deserialize};
+
+use crate::utils::filesystem::root_path;
+
+use super::{Migration, SchemaVersion, VersionedStorable};
+
+/// The largest offsets in use, from `UTC-12:00` to `UTC+14:00`.
+const MIN_UTC_OFFSET_MINUTES: i16 = -12 * 60;
+const MAX_UTC_OFFSET_MINUTES: i16 = 14 * 60;
+
+/// The name of the files saved in the inbox without a name or a caption,
+/// see [crate::utils::DATE_PLACEHOLDER] and [crate::utils::TYPE_PLACEHOLDER].
+pub const DEFAULT_NAME_TEMPLATE: &str = "{date}_{type}";
+
+/// A language in which the bot can reply.
+#[derive(Debug, CandidType, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Language {
+    #[default]
+    English,
+    Italian,
+}
+
+impl Language {
+    pub const ALL: [Language; 2] = [Language::English, Language::Italian];
+
+    /// The ISO 639-1 code of the language.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Language::English => "en",
+            Language::Italian => "it",
+        }
+    }
+
+    /// The name of the language in the language itself, so that it can be found by the users that speak it.
+    pub fn native_name(&self) -> &'static str {
+        match self {
+            Language::English => "English",
+            Language::Italian => "Italiano",
+        }
+    }
+
+    /// Reads the IETF language tag sent by Telegram, e.g. `it-IT`, looking at its primary subtag only.
+    pub fn from_code(code: &str) -> Option<Self> {
+        let primary_subtag = code.split(['-', '_']).next()?;
+        Self::ALL
+            .into_iter()
+            .find(|language| language.code().eq_ignore_ascii_case(primary_subtag))
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.native_name())
+    }
+}
+
+impl FromStr for Language {
+    type Err = String;
+
+    /// Accepts both the code and the native name of the language.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_code(s)
+            .or_else(|| {
+                Self::ALL
+                    .into_iter()
+                    .find(|language| language.native_name().eq_ignore_ascii_case(s))
+            })
+            .ok_or_else(|| format!("Unknown language {s:?}"))
+    }
+}
+
+/// How the directories and the files are listed.
+#[derive(Debug, CandidType, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortOrder {
+    #[default]
+    Name,
+    NameDescending,
+    /// The most recently created first.
+    Newest,
+    Oldest,
+}
+
+impl SortOrder {
+    pub const ALL: [SortOrder; 4] = [
+        SortOrder::Name,
+        SortOrder::NameDescending,
+        SortOrder::Newest,
+        SortOrder::Oldest,
+    ];
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            SortOrder::Name => "name",
+            SortOrder::NameDescending => "name-desc",
+            SortOrder::Newest => "newest",
+            SortOrder::Oldest => "oldest",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|value| value.code() == code)
+    }
+}
+
+/// What to do when a received file is saved with the name of a file that already exists.
+#[derive(Debug, CandidType, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConflictPolicy {
+    /// Doesn't save the file.
+    #[default]
+    Reject,
+    /// Saves the file with a number after its name, e.g. `report (1).pdf`.
+    Rename,
+    /// Replaces the existing file, which can be restored with /undo.
+    Overwrite,
+}
+
+impl ConflictPolicy {
+    pub const ALL: [ConflictPolicy; 3] = [
+        ConflictPolicy::Reject,
+        ConflictPolicy::Rename,
+        ConflictPolicy::Overwrite,
+    ];
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            ConflictPolicy::Reject => "reject",
+            ConflictPolicy::Rename => "rename",
+            ConflictPolicy::Overwrite => "overwrite",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|value| value.code() == code)
+    }
+}
+
+/// How a received file is named once its directory is chosen.
+#[derive(Debug, CandidType, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AutoNaming {
+    /// Asks for the name.
+    #[default]
+    Ask,
+    /// Keeps the name the file was sent with, asking for a name when it has none.
+    OriginalName,
+    /// Names the file after the time it's saved at.
+    Timestamp,
+}
+
+impl AutoNaming {
+    pub const ALL: [AutoNaming; 3] = [
+        AutoNaming::Ask,
+        AutoNaming::OriginalName,
+        AutoNaming::Timestamp,
+    ];
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            AutoNaming::Ask => "ask",
+            AutoNaming::OriginalName => "original",
+            AutoNaming::Timestamp => "timestamp",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|value| value.code() == code)
+    }
+}
+
+/// A fixed offset from UTC, in minutes.
+#[derive(Debug, CandidType, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub struct UtcOffset(i16);
+
+impl UtcOffset {
+    pub fn minutes(&self) -> i16 {
+        self.0
+    }
+}
+
+impl fmt::Display for UtcOffset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0 == 0 {
+            return write!(f, "UTC");
+        }
+        let sign = if self.0 < 0 { '-' } else { '+' };
+        let minutes = self.0.unsigned_abs();
+        write!(f, "UTC{sign}{:02}:{:02}", minutes / 60, minutes % 60)
+    }
+}
+
+impl FromStr for UtcOffset {
+    type Err = String;
+
+    /// Accepts offsets like `+2`, `-05:30` and `UTC+0100`, and `UTC` alone.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid UTC offset {s:?}");
+        let offset = s.trim();
+        let offset = ["UTC", "utc", "GMT", "gmt"]
+            .into_iter()
+            .find_map(|prefix| offset.strip_prefix(prefix))
+            .unwrap_or(offset)
+            .trim();
+        if offset.is_empty() {
+            return Ok(Self(0));
+        }
+
+        let (sign, offset) = if let Some(offset) = offset.strip_prefix('+') {
+            (1, offset)
+        } else if let Some(offset) = offset.strip_prefix('-') {
+            (-1, offset)
+        } else {
+            return Err(invalid());
+        };
+        if !offset.bytes().all(|b| b.is_ascii_digit() || b == b':') {
+            return Err(invalid());
+        }
+        let (hours, minutes) = match offset.split_once(':') {
+            Some((hours, minutes)) => (hours, minutes),
+            None if offset.len() > 2 => offset.split_at(offset.len() - 2),
+            None => (offset, "0"),
+        };
+        let hours = hours.parse::<i32>().map_err(|_| invalid())?;
+        let minutes = minutes.parse::<i32>().map_err(|_| invalid())?;
+        if minutes >= 60 {
+            return Err(invalid());
+        }
+
+        let offset_minutes = sign * (hours * 60 + minutes);
+        i16::try_from(offset_minutes)
+            .ok()
+            .filter(|minutes| (MIN_UTC_OFFSET_MINUTES..=MAX_UTC_OFFSET_MINUTES).contains(minutes))
+            .map(Self)
+            .ok_or_else(invalid)
+    }
+}
+
+/// The preferences of a chat, shared by all its members.
+/// The settings that weren't changed are `None`, and read through the accessors with their defaults.
+#[derive(Debug, CandidType, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct ChatSettings {
+    /// Overrides the language of the Telegram app of each member.
+    pub language: Option<Language>,
+    /// The directory in which the received files are saved, in the active drive.
+    pub save_dir: Option<PathBuf>,
+    pub sort_order: Option<SortOrder>,
+    pub conflict_policy: Option<ConflictPolicy>,
+    /// The time zone of the dates shown to the members.
+    pub time_zone: Option<UtcOffset>,
+    pub auto_naming: Option<AutoNaming>,
+    /// Saves the received files in the save directory right away.
+    pub inbox: Option<bool>,
+    /// The name of the files saved in the inbox without a name or a caption.
+    pub name_template: Option<String>,
+}
+
+impl ChatSettings {
+    /// The root directory by default.
+    pub fn save_dir(&self) -> PathBuf {
+        self.save_dir.clone().unwrap_or_else(root_path)
+    }
+
+    pub fn sort_order(&self) -> SortOrder {
+        self.sort_order.unwrap_or_default()
+    }
+
+    pub fn conflict_policy(&self) -> ConflictPolicy {
+        self.conflict_policy.unwrap_or_default()
+    }
+
+    /// UTC by default.
+    pub fn time_zone(&self) -> UtcOffset {
+        self.time_zone.unwrap_or_default()
+    }
+
+    pub fn auto_naming(&self) -> AutoNaming {
+        self.auto_naming.unwrap_or_default()
+    }
+
+    /// Off by default.
+    pub fn inbox(&self) -> bool {
+        self.inbox.unwrap_or_default()
+    }
+
+    /// [DEFAULT_NAME_TEMPLATE] by default.
+    pub fn name_template(&self) -> String {
+        self.name_template
+            .clone()
+            .unwrap_or_else(|| DEFAULT_NAME_TEMPLATE.to_string())
+    }
+}
+
+impl VersionedStorable for ChatSettings {
+    const SCHEMA_VERSION: SchemaVersion = 1;
+    const MIGRATIONS: &'static [Migration] = &[];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    #[rstest]
+    #[case("en", Some(Language::English))]
+    #[case("en-US", Some(Language::English))]
+    #[case("it", Some(Language::Italian))]
+    #[case("IT-it", Some(Language::Italian))]
+    #[case("it_CH", Some(Language::Italian))]
+    #[case("de", None)]
+    #[case("", None)]
+    fn language_from_code(#[case] code: &str, #[case] expected: Option<Language>) {
+        assert_eq!(Language::from_code(code), expected);
+    }
+
+    #[rstest]
+    #[case("it", Ok(Language::Italian))]
+    #[case("italiano", Ok(Language::Italian))]
+    #[case("English", Ok(Language::English))]
+    #[case("klingon", Err("Unknown language \"klingon\"".to_string()))]
+    fn language_from_str(#[case] s: &str, #[case] expected: Result<Language, String>) {
+        assert_eq!(s.parse::<Language>(), expected);
+    }
+
+    #[rstest]
+    #[case("UTC", Ok(0))]
+    #[case(" utc ", Ok(0))]
+    #[case("+2", Ok(120))]
+    #[case("-05:30", Ok(-330))]
+    #[case("UTC+0100", Ok(60))]
+    #[case("GMT-12", Ok(-720))]
+    #[case("+14:00", Ok(840))]
+    #[case("+14:30", Err("Invalid UTC offset \"+14:30\"".to_string()))]
+    #[case("+02:60", Err("Invalid UTC offset \"+02:60\"".to_string()))]
+    #[case("2", Err("Invalid UTC offset \"2\"".to_string()))]
+    #[case("+-2", Err("Invalid UTC offset \"+-2\"".to_string()))]
+    #[case("Europe/Rome", Err("Invalid UTC offset \"Europe/Rome\"".to_string()))]
+    fn utc_offset_from_str(#[case] s: &str, #[case] expected: Result<i16, String>) {
+        assert_eq!(
+            s.parse::<UtcOffset>().map(|offset| offset.minutes()),
+            expected
+        );
+    }
+
+    #[rstest]
+    #[case(0, "UTC")]
+    #[case(120, "UTC+02:00")]
+    #[case(-330, "UTC-05:30")]
+    fn utc_offset_display(#[case] minutes: i16, #[case] expected: &str) {
+        assert_eq!(UtcOffset(minutes).to_string(), expected);
+    }
+
+    #[rstest]
+    fn chat_settings_defaults() {
+        let chat_settings = ChatSettings::default();
+
+        assert_eq!(chat_settings.save_dir(), root_path());
+        assert_eq!(chat_settings.sort_order(), SortOrder::Name);
+        assert_eq!(chat_settings.conflict_policy(), ConflictPolicy::Reject);
+        assert_eq!(chat_settings.time_zone(), UtcOffset::default());
+        assert_eq!(chat_settings.auto_naming(), AutoNaming::Ask);
+        assert!(!chat_settings.inbox());
+        assert_eq!(chat_settings.name_template(), "{date}_{type}");
+    }
+}
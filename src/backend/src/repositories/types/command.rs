@@ -1,6 +1,10 @@
 use frankenstein::types::{Message, MessageEntityType};
 
-#[derive(Debug)]
+use crate::utils::messages::unclosed_quote_error;
+
+use super::ChatRole;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Command {
     Start,
     Help,
@@ -11,6 +15,63 @@ pub enum Command {
     MoveFile,
     DeleteDir,
     DeleteFile,
+    Undo,
+    History,
+    ForgetMe,
+    Join,
+    Role,
+    Share,
+    Shares,
+    Drive,
+    Ls,
+    Cd,
+    Pwd,
+    Mv,
+    Language,
+    Settings,
+}
+
+impl Command {
+    /// The role needed to run the command.
+    pub fn required_role(&self) -> ChatRole {
+        match self {
+            Command::MkDir
+            | Command::RenameFile
+            | Command::MoveFile
+            | Command::DeleteDir
+            | Command::DeleteFile
+            | Command::Mv
+            | Command::Undo => ChatRole::Editor,
+            Command::ForgetMe
+            | Command::Role
+            | Command::Share
+            | Command::Shares
+            | Command::Language
+            | Command::Settings => ChatRole::Admin,
+            Command::Start
+            | Command::Help
+            | Command::Info
+            | Command::Explorer
+            | Command::History
+            | Command::Join
+            | Command::Drive
+            | Command::Ls
+            | Command::Cd
+            | Command::Pwd => ChatRole::Viewer,
+        }
+    }
+
+    /// How to pass the arguments to the commands that take paths.
+    pub fn usage(&self) -> Option<&'static str> {
+        match self {
+            Command::MkDir => Some("/mkdir <path>"),
+            Command::Ls => Some("/ls [path]"),
+            Command::Cd => Some("/cd [path]"),
+            Command::Pwd => Some("/pwd"),
+            Command::Mv => Some("/mv <from> <to>"),
+            _ => None,
+        }
+    }
 }
 
 impl TryFrom<Message> for Command {
@@ -33,6 +94,10 @@ impl TryFrom<Message> for Command {
         let offset = entity.offset as usize;
         let length = entity.length as usize;
         let command = &text_command[offset..offset + length];
+        // in groups, commands can be addressed to a bot with the /command@botname form
+        let command = command
+            .split_once('@')
+            .map_or(command, |(command, _)| command);
 
         match command {
             "/start" => Ok(Command::Start),
@@ -44,7 +109,110 @@ impl TryFrom<Message> for Command {
             "/move_file" => Ok(Command::MoveFile),
             "/delete_dir" => Ok(Command::DeleteDir),
             "/delete_file" => Ok(Command::DeleteFile),
+            "/undo" => Ok(Command::Undo),
+            "/history" => Ok(Command::History),
+            "/forget_me" => Ok(Command::ForgetMe),
+            "/join" => Ok(Command::Join),
+            "/role" => Ok(Command::Role),
+            "/share" => Ok(Command::Share),
+            "/shares" => Ok(Command::Shares),
+            "/drive" => Ok(Command::Drive),
+            "/ls" => Ok(Command::Ls),
+            "/cd" => Ok(Command::Cd),
+            "/pwd" => Ok(Command::Pwd),
+            "/mv" => Ok(Command::Mv),
+            "/language" => Ok(Command::Language),
+            "/settings" => Ok(Command::Settings),
             _ => Err("Unknown command".to_string()),
         }
     }
 }
+
+/// Returns the text that follows the command at the start of the message, trimmed.
+pub fn command_argument(message: &Message) -> Option<&str> {
+    let text = message.text.as_deref()?;
+    let entity = message.entities.as_ref()?.first()?;
+    if entity.type_field != MessageEntityType::BotCommand || entity.offset != 0 {
+        return None;
+    }
+
+    text.get(entity.length as usize..)
+        .map(str::trim)
+        .filter(|argument| !argument.is_empty())
+}
+
+/// Returns the arguments that follow the command, see [split_arguments].
+pub fn command_arguments(message: &Message) -> Result<Vec<String>, String> {
+    command_argument(message).map_or(Ok(vec![]), split_arguments)
+}
+
+/// Splits the text on whitespace, keeping together the text between quotes,
+/// e.g. to pass paths with spaces.
+pub fn split_arguments(text: &str) -> Result<Vec<String>, String> {
+    let mut arguments = vec![];
+    let mut argument: Option<String> = None;
+    let mut closing_quote: Option<char> = None;
+    for c in text.chars() {
+        match closing_quote {
+            Some(quote) if c == quote => closing_quote = None,
+            Some(_) => argument.get_or_insert_with(String::new).push(c),
+            None => match c {
+                '"' | '\'' => {
+                    closing_quote = Some(c);
+                    argument.get_or_insert_with(String::new);
+                }
+                // the phone keyboards often replace the double quotes with the typographic ones
+                '“' => {
+                    closing_quote = Some('”');
+                    argument.get_or_insert_with(String::new);
+                }
+                c if c.is_whitespace() => arguments.extend(argument.take()),
+                c => argument.get_or_insert_with(String::new).push(c),
+            },
+        }
+    }
+    if closing_quote.is_some() {
+        return Err(unclosed_quote_error());
+    }
+    arguments.extend(argument);
+    Ok(arguments)
+}
+
+/// Returns the bot username that the command at the start of the message is addressed to,
+/// if the command has the `/command@botname` form.
+pub fn command_addressee(message: &Message) -> Option<&str> {
+    let text = message.text.as_deref()?;
+    let entity = message.entities.as_ref()?.first()?;
+    if entity.type_field != MessageEntityType::BotCommand || entity.offset != 0 {
+        return None;
+    }
+
+    text.get(..entity.length as usize)?
+        .split_once('@')
+        .map(|(_, username)| username)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    #[rstest]
+    #[case("", vec![])]
+    #[case("/Docs/2026/Q1", vec!["/Docs/2026/Q1"])]
+    #[case("  /a.pdf   /Archive/ ", vec!["/a.pdf", "/Archive/"])]
+    #[case("\"/My Docs/a b.pdf\" Archive", vec!["/My Docs/a b.pdf", "Archive"])]
+    #[case("'/My Docs'/x \"\"", vec!["/My Docs/x", ""])]
+    #[case("“/My Docs” '\"quoted\"'", vec!["/My Docs", "\"quoted\""])]
+    fn split_arguments_cases(#[case] text: &str, #[case] expected: Vec<&str>) {
+        assert_eq!(
+            split_arguments(text),
+            Ok(expected.into_iter().map(String::from).collect())
+        );
+    }
+
+    #[rstest]
+    fn split_arguments_unclosed_quote() {
+        assert_eq!(split_arguments("\"/My Docs"), Err(unclosed_quote_error()));
+    }
+}
@@ -0,0 +1,153 @@
+use std::collections::BTreeSet;
+
+use candid::{CandidType, Deserialize};
+
+use crate::utils::{
+    DEFAULT_DIRECTORIES, DEFAULT_MAX_JOURNAL_ENTRIES, DEFAULT_MAX_PROCESSED_UPDATES,
+    DEFAULT_RATE_LIMIT_CONFIG, DEFAULT_WEBHOOK_SECRET_ROTATION_PERIOD_SECONDS,
+};
+
+use super::{Migration, RateLimitConfig, SchemaVersion, VersionedStorable};
+
+#[derive(Debug, CandidType, Deserialize, Clone, PartialEq, Eq)]
+pub struct ConfigLimits {
+    /// The maximum number of operations kept in each chat's journal.
+    pub max_journal_entries: u32,
+    /// The maximum number of processed updates remembered to ignore the webhook deliveries retried by Telegram.
+    pub max_processed_updates: u64,
+    pub rate_limits: RateLimitConfig,
+    /// How long the previous webhook secret is still accepted after a new one is set, when not specified.
+    pub webhook_secret_rotation_period_seconds: u64,
+}
+
+#[derive(Debug, CandidType, Deserialize, Clone, PartialEq, Eq)]
+pub struct ConfigFeatures {
+    /// Enables the `/undo` and `/history` commands.
+    pub undo: bool,
+    pub rate_limiting: bool,
+}
+
+/// The settings of the bot, passed when the canister is installed or upgraded
+/// and updatable by the controllers.
+///
+/// The webhook secret is set next to it, as it must never be returned.
+#[derive(Debug, CandidType, Deserialize, Clone, PartialEq, Eq)]
+pub struct Config {
+    /// The username of the bot, without the `@`.
+    pub bot_username: Option<String>,
+    /// The directories created in the root of every new filesystem.
+    pub default_directories: Vec<String>,
+    pub limits: ConfigLimits,
+    pub features: ConfigFeatures,
+}
+
+impl Config {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(bot_username) = &self.bot_username {
+            if bot_username.is_empty()
+                || !bot_username
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_')
+            {
+                return Err(format!("Invalid bot username {bot_username:?}"));
+            }
+        }
+
+        let mut dir_names = BTreeSet::new();
+        for dir_name in &self.default_directories {
+            if dir_name.is_empty() || dir_name.contains('/') || dir_name == "." || dir_name == ".."
+            {
+                return Err(format!("Invalid default directory name {dir_name:?}"));
+            }
+            if !dir_names.insert(dir_name) {
+                return Err(format!("Duplicate default directory name {dir_name:?}"));
+            }
+        }
+
+        if self.limits.max_journal_entries == 0 {
+            return Err("The maximum number of journal entries must be positive".to_string());
+        }
+        if self.limits.max_processed_updates == 0 {
+            return Err("The maximum number of processed updates must be positive".to_string());
+        }
+        self.limits.rate_limits.validate()
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            bot_username: None,
+            default_directories: DEFAULT_DIRECTORIES.map(String::from).to_vec(),
+            limits: ConfigLimits {
+                max_journal_entries: DEFAULT_MAX_JOURNAL_ENTRIES,
+                max_processed_updates: DEFAULT_MAX_PROCESSED_UPDATES,
+                rate_limits: DEFAULT_RATE_LIMIT_CONFIG,
+                webhook_secret_rotation_period_seconds:
+                    DEFAULT_WEBHOOK_SECRET_ROTATION_PERIOD_SECONDS,
+            },
+            features: ConfigFeatures {
+                undo: true,
+                rate_limiting: true,
+            },
+        }
+    }
+}
+
+impl VersionedStorable for Config {
+    const SCHEMA_VERSION: SchemaVersion = 1;
+    const MIGRATIONS: &'static [Migration] = &[];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::{decode_versioned, encode_versioned};
+    use rstest::*;
+
+    #[rstest]
+    fn storable_impl() {
+        let config = Config::default();
+
+        let serialized_config = encode_versioned(&config);
+        let deserialized_config = decode_versioned(&serialized_config);
+
+        assert_eq!(deserialized_config, Ok(config));
+    }
+
+    #[rstest]
+    fn validate() {
+        assert_eq!(Config::default().validate(), Ok(()));
+
+        let config = Config {
+            bot_username: Some("infinitecloud_bot".to_string()),
+            ..Config::default()
+        };
+        assert_eq!(config.validate(), Ok(()));
+
+        let config = Config {
+            bot_username: Some("@infinitecloud_bot".to_string()),
+            ..Config::default()
+        };
+        assert_eq!(
+            config.validate(),
+            Err("Invalid bot username \"@infinitecloud_bot\"".to_string())
+        );
+
+        let config = Config {
+            default_directories: vec!["Documents/Work".to_string()],
+            ..Config::default()
+        };
+        assert_eq!(
+            config.validate(),
+            Err("Invalid default directory name \"Documents/Work\"".to_string())
+        );
+
+        let mut config = Config::default();
+        config.limits.max_journal_entries = 0;
+        assert_eq!(
+            config.validate(),
+            Err("The maximum number of journal entries must be positive".to_string())
+        );
+    }
+}
@@ -0,0 +1,135 @@
+use std::borrow::Cow;
+
+use candid::{CandidType, Deserialize};
+use ic_stable_structures::{storable::Bound, Storable};
+
+use super::{ChatId, Migration, NodeId, SchemaVersion, VersionedStorable};
+
+pub type DriveId = u64;
+
+/// The name of the drive that every chat has, stored as the chat's filesystem.
+pub const MAIN_DRIVE_NAME: &str = "Main";
+
+/// A named filesystem of a chat, next to its main drive.
+#[derive(Debug, CandidType, Deserialize, Clone, PartialEq, Eq)]
+pub struct Drive {
+    pub name: String,
+    pub created_at: u64,
+}
+
+impl VersionedStorable for Drive {
+    const SCHEMA_VERSION: SchemaVersion = 1;
+    const MIGRATIONS: &'static [Migration] = &[];
+}
+
+/// The key of a [Drive] in stable memory.
+/// Keys are ordered by chat first, so that the drives of a chat are contiguous.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DriveKey {
+    pub chat_id: ChatId,
+    pub drive_id: DriveId,
+}
+
+impl DriveKey {
+    pub fn new(chat_id: ChatId, drive_id: DriveId) -> Self {
+        Self { chat_id, drive_id }
+    }
+
+    /// The lowest and the highest keys of the chat, to iterate over its drives.
+    pub fn chat_bounds(chat_id: &ChatId) -> (Self, Self) {
+        (
+            Self::new(chat_id.clone(), DriveId::MIN),
+            Self::new(chat_id.clone(), DriveId::MAX),
+        )
+    }
+}
+
+impl Storable for DriveKey {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        let mut bytes = self.chat_id.to_bytes().into_owned();
+        bytes.extend_from_slice(&self.drive_id.to_bytes());
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        let (chat_id_bytes, drive_id_bytes) = bytes.split_at(8);
+        Self {
+            chat_id: ChatId::from_bytes(Cow::Borrowed(chat_id_bytes)),
+            drive_id: DriveId::from_bytes(Cow::Borrowed(drive_id_bytes)),
+        }
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: 16,
+        is_fixed_size: true,
+    };
+}
+
+/// The key of a node of a drive's filesystem in stable memory.
+/// Keys are ordered by chat and then by drive, so that the nodes of a drive are contiguous.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DriveNodeKey {
+    pub chat_id: ChatId,
+    pub drive_id: DriveId,
+    pub node_id: NodeId,
+}
+
+impl DriveNodeKey {
+    pub fn new(chat_id: ChatId, drive_id: DriveId, node_id: NodeId) -> Self {
+        Self {
+            chat_id,
+            drive_id,
+            node_id,
+        }
+    }
+}
+
+impl Storable for DriveNodeKey {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        let mut bytes = self.chat_id.to_bytes().into_owned();
+        bytes.extend_from_slice(&self.drive_id.to_bytes());
+        bytes.extend_from_slice(&self.node_id.to_bytes());
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        let (chat_id_bytes, ids_bytes) = bytes.split_at(8);
+        let (drive_id_bytes, node_id_bytes) = ids_bytes.split_at(8);
+        Self {
+            chat_id: ChatId::from_bytes(Cow::Borrowed(chat_id_bytes)),
+            drive_id: DriveId::from_bytes(Cow::Borrowed(drive_id_bytes)),
+            node_id: NodeId::from_bytes(Cow::Borrowed(node_id_bytes)),
+        }
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: 24,
+        is_fixed_size: true,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    #[rstest]
+    fn drive_key_storable_impl() {
+        let drive_key = DriveKey::new(ChatId::from(-123i64), 456);
+
+        let serialized_drive_key = drive_key.to_bytes();
+        let deserialized_drive_key = DriveKey::from_bytes(serialized_drive_key);
+
+        assert_eq!(deserialized_drive_key, drive_key);
+    }
+
+    #[rstest]
+    fn drive_node_key_storable_impl() {
+        let drive_node_key = DriveNodeKey::new(ChatId::from(-123i64), 456, 789);
+
+        let serialized_drive_node_key = drive_node_key.to_bytes();
+        let deserialized_drive_node_key = DriveNodeKey::from_bytes(serialized_drive_node_key);
+
+        assert_eq!(deserialized_drive_node_key, drive_node_key);
+    }
+}
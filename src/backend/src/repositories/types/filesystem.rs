@@ -1,23 +1,53 @@
 use std::{
     borrow::Cow,
-    collections::BTreeMap,
+    cell::{Cell, RefCell},
+    cmp::Reverse,
+    collections::{btree_map::Entry, BTreeMap, BTreeSet},
     path::{Path, PathBuf},
 };
 
-use candid::{CandidType, Decode, Deserialize, Encode};
+use candid::{decode_one, encode_one, CandidType, Deserialize};
 use frankenstein::types::{InlineKeyboardButton, InlineKeyboardMarkup};
 use ic_stable_structures::{storable::Bound, Storable};
 use mime2ext::mime2ext;
 
-use crate::utils::{
-    filesystem::root_path,
-    get_current_time, is_absolute,
-    messages::{current_dir_inline_button, delete_dir_inline_button, parent_dir_inline_button},
-    path_button, TG_FILE_EXTENSION_PREFIX, TG_FILE_MIME_TYPE_PREFIX,
+use crate::{
+    custom_print,
+    utils::{
+        filesystem::{root_path, shared_path},
+        get_current_time, is_absolute,
+        messages::{current_dir_inline_button, delete_dir_inline_button, parent_dir_inline_button},
+        path_button, DEFAULT_DIRECTORIES, TG_FILE_EXTENSION_PREFIX, TG_FILE_MIME_TYPE_PREFIX,
+    },
+};
+
+use super::{
+    ChatId, ConflictPolicy, DriveId, Migration, SchemaVersion, SortOrder, VersionedStorable,
 };
 
 pub type MessageId = i32;
 
+pub type NodeId = u64;
+
+/// The id of the root directory of each chat's filesystem.
+pub const ROOT_NODE_ID: NodeId = 0;
+
+/// The kind of a file sent to the bot, which tells how its file id can be sent again.
+#[derive(Debug, CandidType, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum TelegramFileKind {
+    Document,
+    Photo,
+    Video,
+    Audio,
+}
+
+/// The Telegram file of a saved message, used to send the file in other chats.
+#[derive(Debug, CandidType, Deserialize, Clone, PartialEq, Eq)]
+pub struct TelegramFile {
+    pub file_id: String,
+    pub kind: TelegramFileKind,
+}
+
 #[derive(Debug, CandidType, Deserialize, Clone, PartialEq, Eq)]
 pub enum FileSystemNode {
     File {
@@ -25,6 +55,8 @@ pub enum FileSystemNode {
         created_at: u64,
         size: u64,
         mime_type: Option<String>,
+        telegram_file: Option<TelegramFile>,
+        tags: Option<BTreeSet<String>>,
     },
     Directory {
         created_at: u64,
@@ -41,9 +73,19 @@ impl FileSystemNode {
             created_at: get_current_time(),
             size,
             mime_type,
+            telegram_file: None,
+            tags: None,
+        }
+    }
+
+    pub fn with_telegram_file(mut self, file: Option<TelegramFile>) -> Self {
+        if let Self::File { telegram_file, .. } = &mut self {
+            *telegram_file = file;
         }
+        self
     }
 
+    #[cfg(test)]
     fn new_directory() -> Self {
         Self::Directory {
             created_at: get_current_time(),
@@ -77,44 +119,171 @@ impl FileSystemNode {
         }
     }
 
-    #[cfg(test)]
-    fn ls(&self) -> Result<Vec<PathBuf>, String> {
-        match self {
-            Self::Directory { nodes, .. } => Ok(nodes.keys().cloned().collect()),
-            Self::File { .. } => Err("Not a directory".to_string()),
+    pub fn file_message_id(&self) -> Option<MessageId> {
+        if let Self::File { message_id, .. } = self {
+            Some(*message_id)
+        } else {
+            None
         }
     }
 
-    fn ls_directories(&self) -> Result<Vec<PathBuf>, String> {
-        match self {
-            Self::Directory { nodes, .. } => {
-                let mut result = Vec::new();
-                for (path, node) in nodes {
-                    if node.is_directory() {
-                        result.push(path.clone());
-                    }
-                }
-                Ok(result)
+    pub fn file_mime_type(&self) -> Option<String> {
+        if let Self::File { mime_type, .. } = self {
+            mime_type.clone()
+        } else {
+            None
+        }
+    }
+}
+
+/// A reference from a directory to one of its children.
+/// The node kind is duplicated here so that a directory
+/// can be listed without loading its children.
+#[derive(Debug, CandidType, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct FileSystemNodeRef {
+    id: NodeId,
+    is_directory: bool,
+}
+
+pub type FileSystemNodeRefs = BTreeMap<PathBuf, FileSystemNodeRef>;
+
+/// A filesystem node as it is kept in stable memory.
+///
+/// Unlike [FileSystemNode], a directory only references its children by id,
+/// so that each node can be loaded and stored without touching the rest of the tree.
+#[derive(Debug, CandidType, Deserialize, Clone, PartialEq, Eq)]
+pub enum StoredFileSystemNode {
+    File {
+        message_id: MessageId,
+        created_at: u64,
+        size: u64,
+        mime_type: Option<String>,
+        /// The chat of the message, when it's not the chat that owns the filesystem,
+        /// as for the files saved in a shared folder by the recipient.
+        source_chat_id: Option<ChatId>,
+        /// Missing for the files saved before the file ids were stored, and for text messages.
+        telegram_file: Option<TelegramFile>,
+        tags: BTreeSet<String>,
+    },
+    Directory {
+        created_at: u64,
+        nodes: FileSystemNodeRefs,
+    },
+}
+
+impl StoredFileSystemNode {
+    fn new_directory() -> Self {
+        Self::Directory {
+            created_at: get_current_time(),
+            nodes: FileSystemNodeRefs::new(),
+        }
+    }
+
+    /// Adds the given directories to the listing of the node, without storing them.
+    fn with_virtual_directories(mut self, names: impl IntoIterator<Item = PathBuf>) -> Self {
+        if let Self::Directory { nodes, .. } = &mut self {
+            for name in names {
+                nodes.entry(name).or_insert(FileSystemNodeRef {
+                    id: ROOT_NODE_ID,
+                    is_directory: true,
+                });
             }
-            Self::File { .. } => Err("Not a directory".to_string()),
         }
+        self
+    }
+
+    fn from_file_node(file_node: FileSystemNode) -> Result<Self, String> {
+        match file_node {
+            FileSystemNode::File {
+                message_id,
+                created_at,
+                size,
+                mime_type,
+                telegram_file,
+                tags,
+            } => Ok(Self::File {
+                message_id,
+                created_at,
+                size,
+                mime_type,
+                source_chat_id: None,
+                telegram_file,
+                tags: tags.unwrap_or_default(),
+            }),
+            FileSystemNode::Directory { .. } => Err("Not a file".to_string()),
+        }
+    }
+
+    pub fn is_directory(&self) -> bool {
+        matches!(self, Self::Directory { .. })
+    }
+
+    #[cfg(test)]
+    pub fn is_file(&self) -> bool {
+        matches!(self, Self::File { .. })
     }
 
-    fn ls_files(&self) -> Result<Vec<PathBuf>, String> {
+    pub fn is_empty_directory(&self) -> bool {
+        matches!(self, Self::Directory { nodes, .. } if nodes.is_empty())
+    }
+
+    /// The chat, when it's not the chat that owns the filesystem, and the message of a file saved from a text message.
+    pub fn text_message(&self) -> Option<(Option<&ChatId>, MessageId)> {
         match self {
-            Self::Directory { nodes, .. } => {
-                let mut result = Vec::new();
-                for (path, node) in nodes {
-                    if node.is_file() {
-                        result.push(path.clone());
-                    }
-                }
-                Ok(result)
+            Self::File {
+                message_id,
+                mime_type: Some(mime_type),
+                source_chat_id,
+                ..
+            } if mime_type.strip_prefix(TG_FILE_MIME_TYPE_PREFIX) == Some("text") => {
+                Some((source_chat_id.as_ref(), *message_id))
             }
+            _ => None,
+        }
+    }
+
+    fn node_ref(&self, id: NodeId) -> FileSystemNodeRef {
+        FileSystemNodeRef {
+            id,
+            is_directory: self.is_directory(),
+        }
+    }
+
+    #[cfg(test)]
+    fn ls(&self) -> Result<Vec<PathBuf>, String> {
+        match self {
+            Self::Directory { nodes, .. } => Ok(nodes.keys().cloned().collect()),
             Self::File { .. } => Err("Not a directory".to_string()),
         }
     }
 
+    /// Lists the children of the kind, in the given order.
+    /// The node ids are allocated in creation order, so they sort the children by age.
+    fn ls_sorted(&self, is_directory: bool, sort_order: SortOrder) -> Result<Vec<PathBuf>, String> {
+        let Self::Directory { nodes, .. } = self else {
+            return Err("Not a directory".to_string());
+        };
+        let mut children = nodes
+            .iter()
+            .filter(|(_, node_ref)| node_ref.is_directory == is_directory)
+            .collect::<Vec<_>>();
+        match sort_order {
+            SortOrder::Name => {}
+            SortOrder::NameDescending => children.reverse(),
+            SortOrder::Newest => children.sort_by_key(|(_, node_ref)| Reverse(node_ref.id)),
+            SortOrder::Oldest => children.sort_by_key(|(_, node_ref)| node_ref.id),
+        }
+        Ok(children.into_iter().map(|(path, _)| path.clone()).collect())
+    }
+
+    pub fn ls_directories(&self, sort_order: SortOrder) -> Result<Vec<PathBuf>, String> {
+        self.ls_sorted(true, sort_order)
+    }
+
+    pub fn ls_files(&self, sort_order: SortOrder) -> Result<Vec<PathBuf>, String> {
+        self.ls_sorted(false, sort_order)
+    }
+
     pub fn file_message_id(&self) -> Option<MessageId> {
         if let Self::File { message_id, .. } = self {
             Some(*message_id)
@@ -123,143 +292,1069 @@ impl FileSystemNode {
         }
     }
 
-    pub fn file_mime_type(&self) -> Option<String> {
-        if let Self::File { mime_type, .. } = self {
-            mime_type.clone()
-        } else {
-            None
+    pub fn file_tags(&self) -> BTreeSet<String> {
+        match self {
+            Self::File { tags, .. } => tags.clone(),
+            Self::Directory { .. } => BTreeSet::new(),
         }
     }
 }
 
-#[derive(Debug, CandidType, Deserialize, Clone, PartialEq, Eq)]
-pub struct FileSystem {
-    root: FileSystemNode,
+impl VersionedStorable for StoredFileSystemNode {
+    const SCHEMA_VERSION: SchemaVersion = 2;
+    const MIGRATIONS: &'static [Migration] = &[migrate_stored_filesystem_node_v1_to_v2];
 }
 
-impl Default for FileSystem {
-    fn default() -> Self {
-        let mut root = FileSystemNode::new_directory();
-        if let FileSystemNode::Directory { ref mut nodes, .. } = root {
-            nodes.insert(PathBuf::from("Documents"), FileSystemNode::new_directory());
-            nodes.insert(PathBuf::from("Images"), FileSystemNode::new_directory());
-            nodes.insert(PathBuf::from("Videos"), FileSystemNode::new_directory());
-            nodes.insert(PathBuf::from("Trash"), FileSystemNode::new_directory());
+/// A [StoredFileSystemNode] as it was stored by the first schema version,
+/// when the files without tags had no tags set.
+#[derive(CandidType, Deserialize)]
+enum StoredFileSystemNodeV1 {
+    File {
+        message_id: MessageId,
+        created_at: u64,
+        size: u64,
+        mime_type: Option<String>,
+        source_chat_id: Option<ChatId>,
+        telegram_file: Option<TelegramFile>,
+        tags: Option<BTreeSet<String>>,
+    },
+    Directory {
+        created_at: u64,
+        nodes: FileSystemNodeRefs,
+    },
+}
+
+fn migrate_stored_filesystem_node_v1_to_v2(bytes: &[u8]) -> Result<Vec<u8>, String> {
+    let node = match decode_one(bytes).map_err(|err| err.to_string())? {
+        StoredFileSystemNodeV1::File {
+            message_id,
+            created_at,
+            size,
+            mime_type,
+            source_chat_id,
+            telegram_file,
+            tags,
+        } => StoredFileSystemNode::File {
+            message_id,
+            created_at,
+            size,
+            mime_type,
+            source_chat_id,
+            telegram_file,
+            tags: tags.unwrap_or_default(),
+        },
+        StoredFileSystemNodeV1::Directory { created_at, nodes } => {
+            StoredFileSystemNode::Directory { created_at, nodes }
         }
-        Self { root }
+    };
+    encode_one(node).map_err(|err| err.to_string())
+}
+
+/// The key of a [StoredFileSystemNode] in stable memory.
+/// Keys are ordered by chat first, so that all the nodes of a chat are contiguous.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct FileSystemNodeKey {
+    chat_id: ChatId,
+    node_id: NodeId,
+}
+
+impl FileSystemNodeKey {
+    pub fn new(chat_id: ChatId, node_id: NodeId) -> Self {
+        Self { chat_id, node_id }
+    }
+
+    pub fn chat_id(&self) -> &ChatId {
+        &self.chat_id
+    }
+
+    pub fn node_id(&self) -> NodeId {
+        self.node_id
     }
 }
 
-impl FileSystem {
-    #[cfg(test)]
-    fn new() -> Self {
+impl Storable for FileSystemNodeKey {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        let mut bytes = Vec::with_capacity(16);
+        bytes.extend_from_slice(&self.chat_id.0.to_be_bytes());
+        bytes.extend_from_slice(&self.node_id.to_be_bytes());
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        let (chat_id, node_id) = bytes.split_at(8);
         Self {
-            root: FileSystemNode::new_directory(),
+            chat_id: ChatId(u64::from_be_bytes(chat_id.try_into().unwrap())),
+            node_id: u64::from_be_bytes(node_id.try_into().unwrap()),
         }
     }
 
-    pub fn get_node(&self, path: &Path) -> Result<&FileSystemNode, String> {
-        if !is_absolute(path) {
-            return Err("Path must be absolute".to_string());
+    const BOUND: Bound = Bound::Bounded {
+        max_size: 16,
+        is_fixed_size: true,
+    };
+}
+
+/// The key of a text file in the index of the text files by message, so that the files
+/// of an edited message are found without walking the trees.
+/// Keys are ordered by the owner's filesystem first, so that the files of a filesystem are contiguous.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TextFileKey {
+    pub owner_chat_id: ChatId,
+    /// The main drive takes the id 0, which the drives never get.
+    pub drive_id: DriveId,
+    /// The chat of the message.
+    pub chat_id: ChatId,
+    pub message_id: MessageId,
+    pub node_id: NodeId,
+}
+
+impl TextFileKey {
+    pub fn new(
+        owner_chat_id: ChatId,
+        drive_id: DriveId,
+        chat_id: ChatId,
+        message_id: MessageId,
+        node_id: NodeId,
+    ) -> Self {
+        Self {
+            owner_chat_id,
+            drive_id,
+            chat_id,
+            message_id,
+            node_id,
         }
+    }
+}
 
-        let mut current = &self.root;
-        for component in path.components().skip(1) {
-            // Skip root
-            if let FileSystemNode::Directory { nodes, .. } = current {
-                current = nodes
-                    .get::<Path>(component.as_ref())
-                    .ok_or("Path not found")?;
-            } else {
-                return Ok(current);
-            }
+impl Storable for TextFileKey {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        let mut bytes = Vec::with_capacity(36);
+        bytes.extend_from_slice(&self.owner_chat_id.0.to_be_bytes());
+        bytes.extend_from_slice(&self.drive_id.to_be_bytes());
+        bytes.extend_from_slice(&self.chat_id.0.to_be_bytes());
+        // the sign bit is flipped, so that the bytes are ordered like the ids
+        bytes.extend_from_slice(&(self.message_id ^ i32::MIN).to_be_bytes());
+        bytes.extend_from_slice(&self.node_id.to_be_bytes());
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        let (owner_chat_id, bytes) = bytes.split_at(8);
+        let (drive_id, bytes) = bytes.split_at(8);
+        let (chat_id, bytes) = bytes.split_at(8);
+        let (message_id, node_id) = bytes.split_at(4);
+        Self {
+            owner_chat_id: ChatId(u64::from_be_bytes(owner_chat_id.try_into().unwrap())),
+            drive_id: u64::from_be_bytes(drive_id.try_into().unwrap()),
+            chat_id: ChatId(u64::from_be_bytes(chat_id.try_into().unwrap())),
+            message_id: i32::from_be_bytes(message_id.try_into().unwrap()) ^ i32::MIN,
+            node_id: u64::from_be_bytes(node_id.try_into().unwrap()),
         }
-        Ok(current)
     }
 
-    fn insert_node(&mut self, path: &Path, node: FileSystemNode) -> Result<(), String> {
-        let parent = path.parent().ok_or("Invalid path")?;
-        let mut current = &mut self.root;
-        for component in parent.components().skip(1) {
-            // Skip root path
-            if let FileSystemNode::Directory { nodes, .. } = current {
-                current = nodes
-                    .entry(component.as_os_str().into())
-                    .or_insert_with(FileSystemNode::new_directory);
-            } else {
-                return Err("Parent is not a directory".to_string());
-            }
+    const BOUND: Bound = Bound::Bounded {
+        max_size: 36,
+        is_fixed_size: true,
+    };
+}
+
+/// How far the text files stored before the index was kept have been indexed, chat by chat.
+#[derive(Debug, CandidType, Deserialize, Clone, PartialEq, Eq, Default)]
+pub enum TextFileIndexProgress {
+    #[default]
+    NotStarted,
+    IndexedUpTo(ChatId),
+    Done,
+}
+
+impl VersionedStorable for TextFileIndexProgress {
+    const SCHEMA_VERSION: SchemaVersion = 1;
+    const MIGRATIONS: &'static [Migration] = &[];
+}
+
+/// The key of a quarantined [StoredFileSystemNode] in stable memory.
+/// Each quarantine of a chat has its own generation, so that a later quarantine doesn't overwrite an earlier one.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct QuarantinedNodeKey {
+    pub chat_id: ChatId,
+    pub generation: u64,
+    pub node_id: NodeId,
+}
+
+impl QuarantinedNodeKey {
+    pub fn new(chat_id: ChatId, generation: u64, node_id: NodeId) -> Self {
+        Self {
+            chat_id,
+            generation,
+            node_id,
         }
-        if let FileSystemNode::Directory { nodes, .. } = current {
-            let new_node_key = path.file_name().ok_or("Invalid file name")?.into();
-            nodes.insert(new_node_key, node);
-            Ok(())
-        } else {
-            Err("Parent is not a directory".to_string())
+    }
+}
+
+impl Storable for QuarantinedNodeKey {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        let mut bytes = self.chat_id.to_bytes().into_owned();
+        bytes.extend_from_slice(&self.generation.to_bytes());
+        bytes.extend_from_slice(&self.node_id.to_bytes());
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        let (chat_id_bytes, ids_bytes) = bytes.split_at(8);
+        let (generation_bytes, node_id_bytes) = ids_bytes.split_at(8);
+        Self {
+            chat_id: ChatId::from_bytes(Cow::Borrowed(chat_id_bytes)),
+            generation: u64::from_bytes(Cow::Borrowed(generation_bytes)),
+            node_id: NodeId::from_bytes(Cow::Borrowed(node_id_bytes)),
         }
     }
 
-    fn remove_node(&mut self, path: &Path) -> Result<FileSystemNode, String> {
-        let parent = path.parent().ok_or("Invalid path")?;
-        let mut current = &mut self.root;
-        for component in parent.components().skip(1) {
-            // Skip root path
-            if let FileSystemNode::Directory { nodes, .. } = current {
-                current = nodes
-                    .entry(component.as_os_str().into())
-                    .or_insert_with(FileSystemNode::new_directory);
-            } else {
-                return Err("Parent is not a directory".to_string());
-            }
+    const BOUND: Bound = Bound::Bounded {
+        max_size: 24,
+        is_fixed_size: true,
+    };
+}
+
+/// The layout in which the whole filesystem tree of a chat was stored as a single value.
+/// Only kept to migrate the existing data to the per-node layout.
+#[derive(Debug, CandidType, Deserialize, Clone, PartialEq, Eq)]
+pub struct LegacyFileSystem {
+    root: FileSystemNode,
+}
+
+#[cfg(test)]
+impl LegacyFileSystem {
+    pub fn new(root: FileSystemNode) -> Self {
+        Self { root }
+    }
+}
+
+// the legacy values are plain Candid, which is read as the first schema version
+impl VersionedStorable for LegacyFileSystem {
+    const SCHEMA_VERSION: SchemaVersion = 1;
+    const MIGRATIONS: &'static [Migration] = &[];
+}
+
+/// Loads the stored nodes of a filesystem on demand.
+pub trait FileSystemNodeLoader {
+    /// Returns an error if the stored node can't be decoded.
+    fn load_node(&self, node_id: NodeId) -> Result<Option<StoredFileSystemNode>, String>;
+
+    /// Returns the ids of the stored text files saved from the message, according to the index.
+    /// The chat is `None` for the messages of the chat that owns the filesystem.
+    /// The ids of the files removed since they were indexed may be returned as well.
+    fn load_text_file_node_ids(
+        &self,
+        chat_id: Option<&ChatId>,
+        message_id: MessageId,
+    ) -> Vec<NodeId>;
+}
+
+/// A folder of another chat's filesystem, mounted under `/Shared/<name>`.
+pub struct FileSystemMount {
+    owner_chat_id: ChatId,
+    owner_drive_id: Option<DriveId>,
+    /// The chat that the folder is mounted in.
+    chat_id: ChatId,
+    path: PathBuf,
+    is_read_only: bool,
+}
+
+impl FileSystemMount {
+    pub fn new(
+        owner_chat_id: ChatId,
+        owner_drive_id: Option<DriveId>,
+        chat_id: ChatId,
+        path: PathBuf,
+        is_read_only: bool,
+    ) -> Self {
+        Self {
+            owner_chat_id,
+            owner_drive_id,
+            chat_id,
+            path,
+            is_read_only,
         }
-        if let FileSystemNode::Directory { nodes, .. } = current {
-            let node_key: PathBuf = path.file_name().ok_or("Invalid file name")?.into();
-            nodes
-                .remove(&node_key)
-                .ok_or_else(|| "Node not found".to_string())
-        } else {
-            Err("Parent is not a directory".to_string())
+    }
+
+    fn owner_filesystem_key(&self) -> (ChatId, Option<DriveId>) {
+        (self.owner_chat_id.clone(), self.owner_drive_id)
+    }
+}
+
+/// A view over the filesystem of a chat.
+///
+/// Nodes are loaded lazily while resolving paths, so that an operation
+/// only reads the nodes along the paths it involves.
+pub struct FileSystem {
+    /// The nodes loaded so far, including the ones created or modified.
+    nodes: RefCell<BTreeMap<NodeId, StoredFileSystemNode>>,
+    /// The nodes created or modified since the view was created.
+    dirty_node_ids: BTreeSet<NodeId>,
+    removed_node_ids: BTreeSet<NodeId>,
+    next_node_id: NodeId,
+    loader: Option<Box<dyn FileSystemNodeLoader>>,
+    /// Set when a stored node could not be decoded.
+    corrupted: Cell<bool>,
+    /// The folders shared by other chats, by name.
+    mounts: BTreeMap<String, FileSystemMount>,
+    /// The views over the filesystems of the mounts, by owner chat and drive.
+    /// The folders shared from the same filesystem share its view, so that their changes don't overlap.
+    mounted_filesystems: BTreeMap<(ChatId, Option<DriveId>), FileSystem>,
+    /// How the directories are listed in the keyboards. Not stored, set from the chat settings.
+    sort_order: SortOrder,
+}
+
+/// The changes of a view, and of the views over its mounts, taken before a transaction.
+struct FileSystemSnapshot {
+    nodes: BTreeMap<NodeId, StoredFileSystemNode>,
+    dirty_node_ids: BTreeSet<NodeId>,
+    removed_node_ids: BTreeSet<NodeId>,
+    next_node_id: NodeId,
+    mounted_filesystems: BTreeMap<(ChatId, Option<DriveId>), FileSystemSnapshot>,
+}
+
+impl Default for FileSystem {
+    fn default() -> Self {
+        Self::new_with_directories(&DEFAULT_DIRECTORIES).expect("default directories must be valid")
+    }
+}
+
+impl From<LegacyFileSystem> for FileSystem {
+    fn from(legacy: LegacyFileSystem) -> Self {
+        let mut filesystem = Self {
+            nodes: RefCell::new(BTreeMap::new()),
+            dirty_node_ids: BTreeSet::new(),
+            removed_node_ids: BTreeSet::new(),
+            next_node_id: ROOT_NODE_ID,
+            loader: None,
+            corrupted: Cell::new(false),
+            mounts: BTreeMap::new(),
+            mounted_filesystems: BTreeMap::new(),
+            sort_order: SortOrder::default(),
+        };
+        filesystem.add_legacy_node(legacy.root);
+        filesystem
+    }
+}
+
+impl FileSystem {
+    fn empty() -> Self {
+        Self {
+            nodes: RefCell::new(BTreeMap::from([(
+                ROOT_NODE_ID,
+                StoredFileSystemNode::new_directory(),
+            )])),
+            dirty_node_ids: BTreeSet::from([ROOT_NODE_ID]),
+            removed_node_ids: BTreeSet::new(),
+            next_node_id: ROOT_NODE_ID + 1,
+            loader: None,
+            corrupted: Cell::new(false),
+            mounts: BTreeMap::new(),
+            mounted_filesystems: BTreeMap::new(),
+            sort_order: SortOrder::default(),
         }
     }
 
-    #[cfg(test)]
-    fn ls(&self, path: &Path) -> Result<Vec<PathBuf>, String> {
-        let node = self.get_node(path)?;
-        if node.is_directory() {
-            node.ls()
-        } else {
-            Err("Not a directory".to_string())
+    /// Creates a filesystem with the given directories in its root.
+    pub fn new_with_directories(dir_names: &[impl AsRef<str>]) -> Result<Self, String> {
+        let mut filesystem = Self::empty();
+        for dir_name in dir_names {
+            filesystem.mkdir(&root_path().join(dir_name.as_ref()))?;
         }
+        Ok(filesystem)
     }
 
-    pub fn mkdir(&mut self, path: &Path) -> Result<(), String> {
-        self.insert_node(path, FileSystemNode::new_directory())
+    #[cfg(test)]
+    fn new() -> Self {
+        Self::empty()
     }
 
-    pub fn create_file_from_node(
-        &mut self,
-        path: &Path,
-        file_node: FileSystemNode,
-    ) -> Result<PathBuf, String> {
-        let mut path = path.to_path_buf();
-
-        if path.extension().is_none() {
-            if let Some(mut ext) = file_node.file_mime_type() {
-                if ext.starts_with(TG_FILE_MIME_TYPE_PREFIX) {
-                    ext = ext.replace(TG_FILE_MIME_TYPE_PREFIX, TG_FILE_EXTENSION_PREFIX);
-                } else {
-                    ext = mime2ext(&ext).unwrap_or_default().to_string();
-                }
-                path = path.with_extension(ext);
-            }
+    /// Creates a view over an existing filesystem, whose nodes are read through the `loader`.
+    pub fn with_loader(next_node_id: NodeId, loader: Box<dyn FileSystemNodeLoader>) -> Self {
+        Self {
+            nodes: RefCell::new(BTreeMap::new()),
+            dirty_node_ids: BTreeSet::new(),
+            removed_node_ids: BTreeSet::new(),
+            next_node_id,
+            loader: Some(loader),
+            corrupted: Cell::new(false),
+            mounts: BTreeMap::new(),
+            mounted_filesystems: BTreeMap::new(),
+            sort_order: SortOrder::default(),
         }
+    }
 
-        self.insert_node(&path, file_node)?;
-        Ok(path)
+    /// Whether a stored node could not be decoded while resolving paths.
+    /// A corrupted filesystem must not be written back.
+    pub fn is_corrupted(&self) -> bool {
+        self.corrupted.get()
     }
 
-    #[cfg(test)]
-    fn create_file(
+    pub fn sort_order(&self) -> SortOrder {
+        self.sort_order
+    }
+
+    pub fn set_sort_order(&mut self, sort_order: SortOrder) {
+        self.sort_order = sort_order;
+    }
+
+    /// Mounts a folder shared by another chat under `/Shared/<name>`.
+    /// The owner's filesystem is only loaded if no other folder of it is mounted yet.
+    pub fn mount(
+        &mut self,
+        name: String,
+        mount: FileSystemMount,
+        load_owner_filesystem: impl FnOnce() -> FileSystem,
+    ) {
+        self.mounted_filesystems
+            .entry(mount.owner_filesystem_key())
+            .or_insert_with(load_owner_filesystem);
+        self.mounts.insert(name, mount);
+    }
+
+    /// Removes the mounted filesystems that have been modified, to write them back to their owners.
+    pub fn take_dirty_mounts(&mut self) -> Vec<(ChatId, Option<DriveId>, FileSystem)> {
+        self.mounts.clear();
+        std::mem::take(&mut self.mounted_filesystems)
+            .into_iter()
+            .filter(|((owner_chat_id, _), filesystem)| {
+                if filesystem.is_corrupted() {
+                    custom_print!(
+                        "Discarding the changes to the corrupted filesystem of chat {}",
+                        owner_chat_id
+                    );
+                    return false;
+                }
+                filesystem.is_dirty()
+            })
+            .map(|((owner_chat_id, owner_drive_id), filesystem)| {
+                (owner_chat_id, owner_drive_id, filesystem)
+            })
+            .collect()
+    }
+
+    /// Returns the name of the mount that contains `path` and the path in the mounted filesystem.
+    fn find_mount(&self, path: &Path) -> Option<(String, PathBuf)> {
+        let mut components = path.strip_prefix(shared_path()).ok()?.components();
+        let name = components.next()?.as_os_str().to_string_lossy().to_string();
+        let mount = self.mounts.get(&name)?;
+        let mounted_path = match components.as_path() {
+            relative_path if relative_path.as_os_str().is_empty() => mount.path.clone(),
+            relative_path => mount.path.join(relative_path),
+        };
+        Some((name, mounted_path))
+    }
+
+    /// Whether `path` is in a folder shared by another chat.
+    pub fn is_mounted(&self, path: &Path) -> bool {
+        self.find_mount(path).is_some()
+    }
+
+    /// Like [FileSystem::find_mount], but fails if the mount can't be written
+    /// or if `path` is the mounted folder itself.
+    fn find_writable_mount(&self, path: &Path) -> Result<Option<(String, PathBuf)>, String> {
+        let Some((name, mounted_path)) = self.find_mount(path) else {
+            return Ok(None);
+        };
+        let mount = self.get_mount(&name)?;
+        if mount.is_read_only {
+            return Err(format!("The shared folder {name} is read-only"));
+        }
+        if mounted_path == mount.path {
+            return Err(format!("The shared folder {name} can't be changed"));
+        }
+        Ok(Some((name, mounted_path)))
+    }
+
+    fn get_mount(&self, name: &str) -> Result<&FileSystemMount, String> {
+        self.mounts
+            .get(name)
+            .ok_or_else(|| "Shared folder not found".to_string())
+    }
+
+    fn get_mounted_filesystem(
+        &self,
+        name: &str,
+    ) -> Result<(&FileSystemMount, &FileSystem), String> {
+        let mount = self.get_mount(name)?;
+        let filesystem = self
+            .mounted_filesystems
+            .get(&mount.owner_filesystem_key())
+            .ok_or_else(|| "Shared folder not found".to_string())?;
+        Ok((mount, filesystem))
+    }
+
+    fn get_mounted_filesystem_mut(
+        &mut self,
+        name: &str,
+    ) -> Result<(&FileSystemMount, &mut FileSystem), String> {
+        let mount = self
+            .mounts
+            .get(name)
+            .ok_or_else(|| "Shared folder not found".to_string())?;
+        let filesystem = self
+            .mounted_filesystems
+            .get_mut(&mount.owner_filesystem_key())
+            .ok_or_else(|| "Shared folder not found".to_string())?;
+        Ok((mount, filesystem))
+    }
+
+    /// Whether any node has been created, modified or removed since the view was created.
+    pub fn is_dirty(&self) -> bool {
+        !self.dirty_node_ids.is_empty() || !self.removed_node_ids.is_empty()
+    }
+
+    /// The id the next created node gets, to be stored with the nodes.
+    pub fn next_node_id(&self) -> NodeId {
+        self.next_node_id
+    }
+
+    /// Consumes the view and returns the nodes to write and the ids of the nodes to remove.
+    /// Nodes that have only been read are not returned.
+    pub fn into_changes(self) -> (BTreeMap<NodeId, StoredFileSystemNode>, BTreeSet<NodeId>) {
+        let dirty_node_ids = self.dirty_node_ids;
+        let mut nodes = self.nodes.into_inner();
+        nodes.retain(|id, _| dirty_node_ids.contains(id));
+        (nodes, self.removed_node_ids)
+    }
+
+    /// Runs `f` on the view, and rolls back all its changes if it fails,
+    /// including the changes to the mounted folders.
+    pub fn transaction<T, E>(&mut self, f: impl FnOnce(&mut Self) -> Result<T, E>) -> Result<T, E> {
+        let snapshot = self.snapshot();
+        let result = f(self);
+        if result.is_err() {
+            self.restore(snapshot);
+        }
+        result
+    }
+
+    fn snapshot(&self) -> FileSystemSnapshot {
+        FileSystemSnapshot {
+            nodes: self.nodes.borrow().clone(),
+            dirty_node_ids: self.dirty_node_ids.clone(),
+            removed_node_ids: self.removed_node_ids.clone(),
+            next_node_id: self.next_node_id,
+            mounted_filesystems: self
+                .mounted_filesystems
+                .iter()
+                .map(|(key, filesystem)| (key.clone(), filesystem.snapshot()))
+                .collect(),
+        }
+    }
+
+    fn restore(&mut self, snapshot: FileSystemSnapshot) {
+        self.nodes.replace(snapshot.nodes);
+        self.dirty_node_ids = snapshot.dirty_node_ids;
+        self.removed_node_ids = snapshot.removed_node_ids;
+        self.next_node_id = snapshot.next_node_id;
+        for (key, snapshot) in snapshot.mounted_filesystems {
+            if let Some(filesystem) = self.mounted_filesystems.get_mut(&key) {
+                filesystem.restore(snapshot);
+            }
+        }
+    }
+
+    fn add_legacy_node(&mut self, legacy_node: FileSystemNode) -> FileSystemNodeRef {
+        // the id is reserved before adding the children, so that the root gets the first id
+        let id = self.next_node_id;
+        self.next_node_id += 1;
+        let node = match legacy_node {
+            FileSystemNode::File {
+                message_id,
+                created_at,
+                size,
+                mime_type,
+                telegram_file,
+                tags,
+            } => StoredFileSystemNode::File {
+                message_id,
+                created_at,
+                size,
+                mime_type,
+                source_chat_id: None,
+                telegram_file,
+                tags: tags.unwrap_or_default(),
+            },
+            FileSystemNode::Directory { created_at, nodes } => StoredFileSystemNode::Directory {
+                created_at,
+                nodes: nodes
+                    .into_iter()
+                    .map(|(path, child)| (path, self.add_legacy_node(child)))
+                    .collect(),
+            },
+        };
+        let node_ref = node.node_ref(id);
+        self.nodes.get_mut().insert(id, node);
+        self.dirty_node_ids.insert(id);
+        node_ref
+    }
+
+    fn add_node(&mut self, node: StoredFileSystemNode) -> FileSystemNodeRef {
+        let id = self.next_node_id;
+        self.next_node_id += 1;
+        let node_ref = node.node_ref(id);
+        self.nodes.get_mut().insert(id, node);
+        self.dirty_node_ids.insert(id);
+        node_ref
+    }
+
+    fn load_node(&self, id: NodeId) -> Result<StoredFileSystemNode, String> {
+        if let Some(node) = self.nodes.borrow().get(&id) {
+            return Ok(node.clone());
+        }
+
+        let node = match &self.loader {
+            Some(loader) => loader
+                .load_node(id)
+                .inspect_err(|_| self.corrupted.set(true)),
+            None => Ok(None),
+        }
+        .map_err(|err| format!("Filesystem node {id} is corrupted: {err}"))?
+        .ok_or_else(|| "Node not found".to_string())?;
+        self.nodes.borrow_mut().insert(id, node.clone());
+        Ok(node)
+    }
+
+    /// Loads the node and marks it as modified.
+    fn load_node_mut(&mut self, id: NodeId) -> Result<&mut StoredFileSystemNode, String> {
+        self.load_node(id)?;
+        self.dirty_node_ids.insert(id);
+        self.nodes
+            .get_mut()
+            .get_mut(&id)
+            .ok_or_else(|| "Node not found".to_string())
+    }
+
+    fn resolve(&self, path: &Path) -> Result<NodeId, String> {
+        if !is_absolute(path) {
+            return Err("Path must be absolute".to_string());
+        }
+
+        let mut current_id = ROOT_NODE_ID;
+        for component in path.components().skip(1) {
+            // Skip root
+            if let StoredFileSystemNode::Directory { nodes, .. } = self.load_node(current_id)? {
+                current_id = nodes
+                    .get::<Path>(component.as_ref())
+                    .ok_or("Path not found")?
+                    .id;
+            } else {
+                return Ok(current_id);
+            }
+        }
+        Ok(current_id)
+    }
+
+    pub fn get_node(&self, path: &Path) -> Result<StoredFileSystemNode, String> {
+        if let Some((name, mounted_path)) = self.find_mount(path) {
+            return self
+                .get_mounted_filesystem(&name)?
+                .1
+                .get_node(&mounted_path);
+        }
+
+        let node = self.resolve(path).and_then(|id| self.load_node(id));
+        if self.mounts.is_empty() {
+            return node;
+        }
+        // the shared directory is listed as long as some folders are mounted in it
+        if path == root_path() {
+            let shared_dir_name = PathBuf::from(shared_path().file_name().unwrap_or_default());
+            return node.map(|node| node.with_virtual_directories([shared_dir_name]));
+        }
+        if path == shared_path() {
+            return Ok(node
+                .unwrap_or_else(|_| StoredFileSystemNode::new_directory())
+                .with_virtual_directories(self.mounts.keys().map(PathBuf::from)));
+        }
+        node
+    }
+
+    /// Returns the chat and the id of the message of the file at `path`.
+    /// The chat is only returned when it's not the chat of this filesystem.
+    pub fn get_file_message(&self, path: &Path) -> Result<(Option<ChatId>, MessageId), String> {
+        let (source_chat_id, message_id) = match self.find_mount(path) {
+            Some((name, mounted_path)) => {
+                let (mount, filesystem) = self.get_mounted_filesystem(&name)?;
+                let (source_chat_id, message_id) = filesystem.get_file_message(&mounted_path)?;
+                let source_chat_id = source_chat_id.unwrap_or(mount.owner_chat_id.clone());
+                (
+                    Some(source_chat_id).filter(|chat_id| chat_id != &mount.chat_id),
+                    message_id,
+                )
+            }
+            None => match self.get_node(path)? {
+                StoredFileSystemNode::File {
+                    message_id,
+                    source_chat_id,
+                    ..
+                } => (source_chat_id, message_id),
+                StoredFileSystemNode::Directory { .. } => return Err("Not a file".to_string()),
+            },
+        };
+        Ok((source_chat_id, message_id))
+    }
+
+    /// Loads the whole tree, with each directory embedding its children.
+    pub fn export(&self) -> Result<FileSystemNode, String> {
+        self.export_node(ROOT_NODE_ID)
+    }
+
+    fn export_node(&self, id: NodeId) -> Result<FileSystemNode, String> {
+        match self.load_node(id)? {
+            StoredFileSystemNode::File {
+                message_id,
+                created_at,
+                size,
+                mime_type,
+                telegram_file,
+                tags,
+                ..
+            } => Ok(FileSystemNode::File {
+                message_id,
+                created_at,
+                size,
+                mime_type,
+                telegram_file,
+                tags: (!tags.is_empty()).then_some(tags),
+            }),
+            StoredFileSystemNode::Directory { created_at, nodes } => {
+                Ok(FileSystemNode::Directory {
+                    created_at,
+                    nodes: nodes
+                        .into_iter()
+                        .map(|(path, node_ref)| Ok((path, self.export_node(node_ref.id)?)))
+                        .collect::<Result<_, String>>()?,
+                })
+            }
+        }
+    }
+
+    /// Visits the files of the tree in path order, until `visit` returns `false`.
+    /// The shared folders are not visited.
+    fn visit_files(
+        &self,
+        mut visit: impl FnMut(PathBuf, NodeId, StoredFileSystemNode) -> bool,
+    ) -> Result<(), String> {
+        let mut pending = vec![(root_path(), ROOT_NODE_ID)];
+        while let Some((path, id)) = pending.pop() {
+            match self.load_node(id)? {
+                StoredFileSystemNode::Directory { nodes, .. } => {
+                    // reversed, so that the children are popped in name order
+                    pending.extend(
+                        nodes
+                            .into_iter()
+                            .rev()
+                            .map(|(name, node_ref)| (path.join(name), node_ref.id)),
+                    );
+                }
+                node => {
+                    if !visit(path, id, node) {
+                        break;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the files that have a Telegram file and whose name contains `query`,
+    /// ignoring the case, in path order. The shared folders are not searched.
+    pub fn search_telegram_files(
+        &self,
+        query: &str,
+        limit: usize,
+    ) -> Result<Vec<(PathBuf, TelegramFile)>, String> {
+        let query = query.to_lowercase();
+        let mut files = vec![];
+        self.visit_files(|path, _, node| {
+            if let StoredFileSystemNode::File {
+                telegram_file: Some(telegram_file),
+                ..
+            } = node
+            {
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                if name.to_lowercase().contains(&query) {
+                    files.push((path, telegram_file));
+                }
+            }
+            files.len() < limit
+        })?;
+        Ok(files)
+    }
+
+    /// Sets the size of the text files saved from the message `message_id` of this filesystem's chat,
+    /// after the message has been edited. The files saved by the chat in the writable shared folders
+    /// are updated as well. Returns the number of updated files.
+    pub fn update_text_files(&mut self, message_id: MessageId, size: u64) -> Result<usize, String> {
+        let mut updated_count = self.update_text_files_from(None, message_id, size)?;
+        let writable_mounts: BTreeMap<_, _> = self
+            .mounts
+            .values()
+            .filter(|mount| !mount.is_read_only)
+            .map(|mount| (mount.owner_filesystem_key(), mount.chat_id.clone()))
+            .collect();
+        for (owner_filesystem_key, chat_id) in writable_mounts {
+            if let Some(filesystem) = self.mounted_filesystems.get_mut(&owner_filesystem_key) {
+                updated_count +=
+                    filesystem.update_text_files_from(Some(&chat_id), message_id, size)?;
+            }
+        }
+        Ok(updated_count)
+    }
+
+    fn update_text_files_from(
+        &mut self,
+        chat_id: Option<&ChatId>,
+        message_id: MessageId,
+        size: u64,
+    ) -> Result<usize, String> {
+        // the stored files are found through the index, the ones not stored yet are loaded already
+        let mut node_ids: BTreeSet<NodeId> = self
+            .loader
+            .as_ref()
+            .map(|loader| loader.load_text_file_node_ids(chat_id, message_id))
+            .unwrap_or_default()
+            .into_iter()
+            .collect();
+        node_ids.extend(
+            self.nodes
+                .get_mut()
+                .iter()
+                .filter(|(_, node)| node.text_message() == Some((chat_id, message_id)))
+                .map(|(id, _)| *id),
+        );
+
+        let mut updated_count = 0;
+        for id in node_ids {
+            if self.removed_node_ids.contains(&id) {
+                continue;
+            }
+            // the index isn't updated until the filesystem is stored, the files may have been removed since
+            let node = match self.load_node(id) {
+                Ok(node) => node,
+                Err(_) if !self.is_corrupted() => continue,
+                Err(err) => return Err(err),
+            };
+            if node.text_message() != Some((chat_id, message_id)) {
+                continue;
+            }
+            if let StoredFileSystemNode::File {
+                size: file_size, ..
+            } = self.load_node_mut(id)?
+            {
+                *file_size = size;
+            }
+            updated_count += 1;
+        }
+        Ok(updated_count)
+    }
+
+    /// Walks the whole tree from the root and returns the ids of the reachable nodes,
+    /// along with the issues found on the way.
+    pub fn check_consistency(&self) -> (BTreeSet<NodeId>, Vec<String>) {
+        let mut reachable_node_ids = BTreeSet::new();
+        let mut issues = vec![];
+        let mut pending = vec![(
+            root_path(),
+            FileSystemNodeRef {
+                id: ROOT_NODE_ID,
+                is_directory: true,
+            },
+        )];
+        while let Some((path, node_ref)) = pending.pop() {
+            let id = node_ref.id;
+            if !reachable_node_ids.insert(id) {
+                issues.push(format!(
+                    "{}: node {id} is referenced more than once",
+                    path.display()
+                ));
+                continue;
+            }
+
+            match self.load_node(id) {
+                Ok(node) => {
+                    if node.is_directory() != node_ref.is_directory {
+                        issues.push(format!(
+                            "{}: node {id} doesn't match the kind of its reference",
+                            path.display()
+                        ));
+                    }
+                    if let StoredFileSystemNode::Directory { nodes, .. } = node {
+                        pending.extend(
+                            nodes
+                                .into_iter()
+                                .map(|(name, child_ref)| (path.join(name), child_ref)),
+                        );
+                    }
+                }
+                Err(err) => issues.push(format!("{}: {err}", path.display())),
+            }
+        }
+        (reachable_node_ids, issues)
+    }
+
+    /// Returns the id of the directory at `path`, creating the missing directories along it.
+    fn get_or_create_directory(&mut self, path: &Path) -> Result<NodeId, String> {
+        let mut current_id = ROOT_NODE_ID;
+        for component in path.components().skip(1) {
+            // Skip root path
+            let child = match self.load_node(current_id)? {
+                StoredFileSystemNode::Directory { nodes, .. } => {
+                    nodes.get::<Path>(component.as_ref()).copied()
+                }
+                StoredFileSystemNode::File { .. } => {
+                    return Err("Parent is not a directory".to_string())
+                }
+            };
+            current_id = match child {
+                Some(child) => child.id,
+                None => {
+                    let child = self.add_node(StoredFileSystemNode::new_directory());
+                    if let StoredFileSystemNode::Directory { nodes, .. } =
+                        self.load_node_mut(current_id)?
+                    {
+                        nodes.insert(component.as_os_str().into(), child);
+                    }
+                    child.id
+                }
+            };
+        }
+        Ok(current_id)
+    }
+
+    /// Inserts the node at `path`, which must be free: the existing nodes are never replaced.
+    fn insert_node(&mut self, path: &Path, node_ref: FileSystemNodeRef) -> Result<(), String> {
+        let parent = path.parent().ok_or("Invalid path")?;
+        let parent_id = self.get_or_create_directory(parent)?;
+        let new_node_key: PathBuf = path.file_name().ok_or("Invalid file name")?.into();
+        match self.load_node_mut(parent_id)? {
+            StoredFileSystemNode::Directory { nodes, .. } => match nodes.entry(new_node_key) {
+                Entry::Occupied(_) => Err("Path already exists".to_string()),
+                Entry::Vacant(entry) => {
+                    entry.insert(node_ref);
+                    Ok(())
+                }
+            },
+            StoredFileSystemNode::File { .. } => Err("Parent is not a directory".to_string()),
+        }
+    }
+
+    fn remove_node(&mut self, path: &Path) -> Result<FileSystemNodeRef, String> {
+        let parent = path.parent().ok_or("Invalid path")?;
+        let parent_id = self.resolve(parent)?;
+        let node_key: PathBuf = path.file_name().ok_or("Invalid file name")?.into();
+        match self.load_node_mut(parent_id)? {
+            StoredFileSystemNode::Directory { nodes, .. } => nodes
+                .remove(&node_key)
+                .ok_or_else(|| "Node not found".to_string()),
+            StoredFileSystemNode::File { .. } => Err("Parent is not a directory".to_string()),
+        }
+    }
+
+    fn remove_subtree(&mut self, id: NodeId) -> Result<(), String> {
+        if let StoredFileSystemNode::Directory { nodes, .. } = self.load_node(id)? {
+            for child in nodes.values() {
+                self.remove_subtree(child.id)?;
+            }
+        }
+        self.nodes.get_mut().remove(&id);
+        self.dirty_node_ids.remove(&id);
+        self.removed_node_ids.insert(id);
+        Ok(())
+    }
+
+    #[cfg(test)]
+    fn ls(&self, path: &Path) -> Result<Vec<PathBuf>, String> {
+        let node = self.get_node(path)?;
+        if node.is_directory() {
+            node.ls()
+        } else {
+            Err("Not a directory".to_string())
+        }
+    }
+
+    /// Adds the node and inserts it at `path`, leaving the filesystem untouched on errors.
+    fn insert_new_node(&mut self, path: &Path, node: StoredFileSystemNode) -> Result<(), String> {
+        let node_ref = self.add_node(node);
+        self.insert_node(path, node_ref).inspect_err(|_| {
+            self.nodes.get_mut().remove(&node_ref.id);
+            self.dirty_node_ids.remove(&node_ref.id);
+        })
+    }
+
+    pub fn mkdir(&mut self, path: &Path) -> Result<(), String> {
+        match self.find_writable_mount(path)? {
+            Some((name, mounted_path)) => self
+                .get_mounted_filesystem_mut(&name)?
+                .1
+                .mkdir(&mounted_path),
+            None => self.insert_new_node(path, StoredFileSystemNode::new_directory()),
+        }
+    }
+
+    pub fn create_file_from_node(
+        &mut self,
+        path: &Path,
+        file_node: FileSystemNode,
+    ) -> Result<PathBuf, String> {
+        let path = file_path_with_extension(path, &file_node);
+
+        let mut node = StoredFileSystemNode::from_file_node(file_node)?;
+        match self.find_writable_mount(&path)? {
+            Some((name, mounted_path)) => {
+                let (mount, filesystem) = self.get_mounted_filesystem_mut(&name)?;
+                // the message of the file is in this chat, not in the chat of the mounted filesystem
+                if let StoredFileSystemNode::File { source_chat_id, .. } = &mut node {
+                    *source_chat_id = Some(mount.chat_id.clone());
+                }
+                filesystem.insert_new_node(&mounted_path, node)?;
+            }
+            None => self.insert_new_node(&path, node)?,
+        }
+        Ok(path)
+    }
+
+    /// Makes room for a new file at `path`, returning the path to create it at
+    /// and the file removed to make room, if any.
+    /// Rejecting the conflict fails when `path` is taken, leaving the existing node as it is.
+    pub fn resolve_conflict(
+        &mut self,
+        path: &Path,
+        conflict_policy: ConflictPolicy,
+    ) -> Result<(PathBuf, Option<StoredFileSystemNode>), String> {
+        let Ok(existing_node) = self.get_node(path) else {
+            return Ok((path.to_path_buf(), None));
+        };
+
+        match conflict_policy {
+            ConflictPolicy::Reject => Err("A file with the same name already exists".to_string()),
+            ConflictPolicy::Rename => {
+                let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+                let extension = path
+                    .extension()
+                    .map(|extension| format!(".{}", extension.to_string_lossy()))
+                    .unwrap_or_default();
+                let free_path = (1..)
+                    .map(|number| path.with_file_name(format!("{stem} ({number}){extension}")))
+                    .find(|candidate| self.get_node(candidate).is_err())
+                    .ok_or("No free name")?;
+                Ok((free_path, None))
+            }
+            ConflictPolicy::Overwrite if existing_node.is_directory() => {
+                Err("A directory has the same name".to_string())
+            }
+            ConflictPolicy::Overwrite => {
+                self.rm(path)?;
+                Ok((path.to_path_buf(), Some(existing_node)))
+            }
+        }
+    }
+
+    #[cfg(test)]
+    fn create_file(
         &mut self,
         path: &Path,
         message_id: MessageId,
@@ -270,99 +1365,625 @@ impl FileSystem {
         self.create_file_from_node(path, file_node)
     }
 
-    pub fn mv(&mut self, from: &Path, to: &Path) -> Result<(), String> {
-        let node = self.remove_node(from)?;
-        self.insert_node(to, node)
-    }
-}
+    pub fn mv(&mut self, from: &Path, to: &Path) -> Result<(), String> {
+        if to != from && to.starts_with(from) {
+            return Err("A directory can't be moved into itself".to_string());
+        }
+        match (
+            self.find_writable_mount(from)?,
+            self.find_writable_mount(to)?,
+        ) {
+            (None, None) => {
+                // the node at the destination would be replaced and lost
+                if to != from && self.get_node(to).is_ok() {
+                    return Err("Destination is already taken".to_string());
+                }
+                let node_ref = self.remove_node(from)?;
+                self.insert_node(to, node_ref)
+            }
+            (Some((from_name, mounted_from)), Some((to_name, mounted_to)))
+                if from_name == to_name =>
+            {
+                self.get_mounted_filesystem_mut(&from_name)?
+                    .1
+                    .mv(&mounted_from, &mounted_to)
+            }
+            _ => Err("Files can't be moved in or out of a shared folder".to_string()),
+        }
+    }
+
+    /// Copies the file at `from` to `to`. The copy refers to the same message.
+    pub fn cp(&mut self, from: &Path, to: &Path) -> Result<(), String> {
+        match (
+            self.find_writable_mount(from)?,
+            self.find_writable_mount(to)?,
+        ) {
+            (None, None) => {
+                let node = self.get_node(from)?;
+                if node.is_directory() {
+                    return Err("Directories can't be copied".to_string());
+                }
+                self.insert_new_node(to, node)
+            }
+            (Some((from_name, mounted_from)), Some((to_name, mounted_to)))
+                if from_name == to_name =>
+            {
+                self.get_mounted_filesystem_mut(&from_name)?
+                    .1
+                    .cp(&mounted_from, &mounted_to)
+            }
+            _ => Err("Files can't be copied in or out of a shared folder".to_string()),
+        }
+    }
+
+    /// Inserts a file node as it is, e.g. to restore a removed file.
+    pub fn insert_file(&mut self, path: &Path, node: StoredFileSystemNode) -> Result<(), String> {
+        if node.is_directory() {
+            return Err("Not a file".to_string());
+        }
+        match self.find_writable_mount(path)? {
+            Some((name, mounted_path)) => self
+                .get_mounted_filesystem_mut(&name)?
+                .1
+                .insert_file(&mounted_path, node),
+            None => self.insert_new_node(path, node),
+        }
+    }
+
+    /// Adds the tag to the file at `path`. Returns `false` if the file already has the tag.
+    pub fn tag_file(&mut self, path: &Path, tag: &str) -> Result<bool, String> {
+        if let Some((name, mounted_path)) = self.find_writable_mount(path)? {
+            return self
+                .get_mounted_filesystem_mut(&name)?
+                .1
+                .tag_file(&mounted_path, tag);
+        }
+
+        let id = self.resolve(path)?;
+        match self.load_node_mut(id)? {
+            StoredFileSystemNode::File { tags, .. } => Ok(tags.insert(tag.to_string())),
+            StoredFileSystemNode::Directory { .. } => Err("Not a file".to_string()),
+        }
+    }
+
+    /// Removes the tag from the file at `path`. Returns `false` if the file doesn't have the tag.
+    pub fn untag_file(&mut self, path: &Path, tag: &str) -> Result<bool, String> {
+        if let Some((name, mounted_path)) = self.find_writable_mount(path)? {
+            return self
+                .get_mounted_filesystem_mut(&name)?
+                .1
+                .untag_file(&mounted_path, tag);
+        }
+
+        let id = self.resolve(path)?;
+        match self.load_node_mut(id)? {
+            StoredFileSystemNode::File { tags, .. } => Ok(tags.remove(tag)),
+            StoredFileSystemNode::Directory { .. } => Err("Not a file".to_string()),
+        }
+    }
+
+    pub fn rm(&mut self, path: &Path) -> Result<(), String> {
+        if let Some((name, mounted_path)) = self.find_writable_mount(path)? {
+            return self.get_mounted_filesystem_mut(&name)?.1.rm(&mounted_path);
+        }
+
+        let node_ref = self.remove_node(path)?;
+        self.remove_subtree(node_ref.id)
+    }
+}
+
+/// Adds the extension of the file's mime type to `path`, if it has no extension.
+pub fn file_path_with_extension(path: &Path, file_node: &FileSystemNode) -> PathBuf {
+    if path.extension().is_some() {
+        return path.to_path_buf();
+    }
+    match file_node.file_mime_type() {
+        Some(mime_type) => {
+            let ext = if mime_type.starts_with(TG_FILE_MIME_TYPE_PREFIX) {
+                mime_type.replace(TG_FILE_MIME_TYPE_PREFIX, TG_FILE_EXTENSION_PREFIX)
+            } else {
+                mime2ext(&mime_type).unwrap_or_default().to_string()
+            };
+            path.with_extension(ext)
+        }
+        None => path.to_path_buf(),
+    }
+}
+
+pub struct KeyboardDirectoryBuilder<'a> {
+    inline_keyboard: Vec<InlineKeyboardButton>,
+    bottom_rows: Vec<Vec<InlineKeyboardButton>>,
+    current_node: StoredFileSystemNode,
+    current_path: &'a Path,
+    sort_order: SortOrder,
+}
+
+impl<'a> KeyboardDirectoryBuilder<'a> {
+    pub fn new(filesystem: &FileSystem, current_path: &'a Path) -> Result<Self, String> {
+        let current_node = filesystem.get_node(current_path)?;
+
+        let mut inline_keyboard = if current_path != root_path() {
+            vec![parent_dir_inline_button()]
+        } else {
+            vec![]
+        };
+
+        let sort_order = filesystem.sort_order();
+        for path in current_node.ls_directories(sort_order)? {
+            inline_keyboard.push(path_button(&current_path.join(path), true));
+        }
+
+        Ok(Self {
+            inline_keyboard,
+            bottom_rows: vec![],
+            current_node,
+            current_path,
+            sort_order,
+        })
+    }
+
+    /// Prepends the delete dir button to the keyboard
+    #[allow(dead_code)] // TODO: remove once used
+    pub fn with_delete_dir_button(&mut self) -> &mut Self {
+        self.inline_keyboard.insert(0, delete_dir_inline_button());
+        self
+    }
+
+    /// Prepends the current dir button to the keyboard
+    pub fn with_current_dir_button(&mut self) -> &mut Self {
+        self.inline_keyboard.insert(0, current_dir_inline_button());
+        self
+    }
+
+    /// Appends the files of the current directory to the keyboard
+    pub fn with_files(&mut self) -> Result<&mut Self, String> {
+        let paths = self.current_node.ls_files(self.sort_order)?;
+        for path in paths {
+            self.inline_keyboard
+                .push(path_button(&self.current_path.join(path), false));
+        }
+        Ok(self)
+    }
+
+    /// Appends the files of the current directory to the keyboard,
+    /// marking the ones in `selection`
+    pub fn with_selectable_files(
+        &mut self,
+        selection: &BTreeSet<PathBuf>,
+    ) -> Result<&mut Self, String> {
+        let paths = self.current_node.ls_files(self.sort_order)?;
+        for path in paths {
+            let path = self.current_path.join(path);
+            let mut button = path_button(&path, false);
+            if selection.contains(&path) {
+                button.text = format!("✅ {}", button.text);
+            }
+            self.inline_keyboard.push(button);
+        }
+        Ok(self)
+    }
+
+    /// Appends the given rows below the directories and files
+    pub fn with_bottom_rows(&mut self, rows: Vec<Vec<InlineKeyboardButton>>) -> &mut Self {
+        self.bottom_rows.extend(rows);
+        self
+    }
+
+    pub fn build(&self) -> InlineKeyboardMarkup {
+        InlineKeyboardMarkup {
+            // to display one button per row, each button should have its own row
+            inline_keyboard: self
+                .inline_keyboard
+                .iter()
+                .map(|el| vec![el.clone()])
+                .chain(self.bottom_rows.iter().cloned())
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        repositories::{decode_versioned, encode_versioned, VersionedValue},
+        utils::messages::{back_inline_button, cancel_inline_button},
+    };
+    use rstest::*;
+
+    /// A [LegacyFileSystem] encoded by the release that stored the whole tree as a single value:
+    /// `/Documents/report.pdf` (message id 42) and an empty `/Trash` directory.
+    const LEGACY_FILESYSTEM_FIXTURE: &str = "4449444c076c01a2dcbbdd04016b029cf5d3f40202cda4df900b046c04c1c1cee20478e5a88eea0503aaacd9d0067893a5bf950d756e716c02aaacd9d00678b1f0fafd09056d066c020071010101000100002a36fe9c97170209446f63756d656e74730100002a36fe9c9717010a7265706f72742e706466000004000000000000010f6170706c69636174696f6e2f70646600002a36fe9c97172a0000000554726173680100002a36fe9c971700";
+
+    /// [StoredFileSystemNode]s encoded by the first schema version, before the tags became mandatory:
+    /// a file tagged `work`, a file without tags and a directory that contains `report.pdf` (node 1).
+    const STORED_FILE_V1_FIXTURE: &str = "c0014449444c0d6b029cf5d3f40201cda4df900b096c07c1c1cee20478d9e9dae70402e5a88eea0504aaacd9d00678baef8bdf0605fe8ef3920c0893a5bf950d756e036d716e716e066c02d4c2a7b80407feb1dffa0f716b0492f184a9057fbbd692a6077fb6d1b7d50b7fdb88a6d10c7f6e786c02aaacd9d00678b1f0fafd090a6d0b6c020071010c6c02dbb7017898a5d6ba047e0100000004000000000000010104776f726b010f6170706c69636174696f6e2f70646600002a36fe9c97170001c8010000000000002a000000";
+    const STORED_UNTAGGED_FILE_V1_FIXTURE: &str = "c0014449444c0d6b029cf5d3f40201cda4df900b096c07c1c1cee20478d9e9dae70402e5a88eea0504aaacd9d00678baef8bdf0605fe8ef3920c0893a5bf950d756e036d716e716e066c02d4c2a7b80407feb1dffa0f716b0492f184a9057fbbd692a6077fb6d1b7d50b7fdb88a6d10c7f6e786c02aaacd9d00678b1f0fafd090a6d0b6c020071010c6c02dbb7017898a5d6ba047e0100000a00000000000000000000002a36fe9c971700002b000000";
+    const STORED_DIRECTORY_V1_FIXTURE: &str = "c0014449444c0d6b029cf5d3f40201cda4df900b096c07c1c1cee20478d9e9dae70402e5a88eea0504aaacd9d00678baef8bdf0605fe8ef3920c0893a5bf950d756e036d716e716e066c02d4c2a7b80407feb1dffa0f716b0492f184a9057fbbd692a6077fb6d1b7d50b7fdb88a6d10c7f6e786c02aaacd9d00678b1f0fafd090a6d0b6c020071010c6c02dbb7017898a5d6ba047e01000100002a36fe9c9717010a7265706f72742e706466010000000000000000";
+
+    fn decode_hex(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    /// An in-memory loader that records which nodes have been loaded.
+    struct TestNodeLoader {
+        nodes: BTreeMap<NodeId, StoredFileSystemNode>,
+        loaded_node_ids: std::rc::Rc<RefCell<Vec<NodeId>>>,
+    }
+
+    impl FileSystemNodeLoader for TestNodeLoader {
+        fn load_node(&self, node_id: NodeId) -> Result<Option<StoredFileSystemNode>, String> {
+            self.loaded_node_ids.borrow_mut().push(node_id);
+            Ok(self.nodes.get(&node_id).cloned())
+        }
+
+        fn load_text_file_node_ids(
+            &self,
+            chat_id: Option<&ChatId>,
+            message_id: MessageId,
+        ) -> Vec<NodeId> {
+            self.nodes
+                .iter()
+                .filter(|(_, node)| node.text_message() == Some((chat_id, message_id)))
+                .map(|(id, _)| *id)
+                .collect()
+        }
+    }
+
+    /// A loader whose stored nodes can't be decoded.
+    struct CorruptedNodeLoader;
+
+    impl FileSystemNodeLoader for CorruptedNodeLoader {
+        fn load_node(&self, _node_id: NodeId) -> Result<Option<StoredFileSystemNode>, String> {
+            Err("Cannot decode value".to_string())
+        }
+
+        fn load_text_file_node_ids(
+            &self,
+            _chat_id: Option<&ChatId>,
+            _message_id: MessageId,
+        ) -> Vec<NodeId> {
+            vec![]
+        }
+    }
+
+    fn with_test_loader(filesystem: FileSystem) -> (FileSystem, std::rc::Rc<RefCell<Vec<NodeId>>>) {
+        let next_node_id = filesystem.next_node_id;
+        let (nodes, _) = filesystem.into_changes();
+        with_test_loader_nodes(next_node_id, nodes)
+    }
+
+    fn with_test_loader_nodes(
+        next_node_id: NodeId,
+        nodes: BTreeMap<NodeId, StoredFileSystemNode>,
+    ) -> (FileSystem, std::rc::Rc<RefCell<Vec<NodeId>>>) {
+        let loaded_node_ids = std::rc::Rc::new(RefCell::new(vec![]));
+        let loader = TestNodeLoader {
+            nodes,
+            loaded_node_ids: loaded_node_ids.clone(),
+        };
+        (
+            FileSystem::with_loader(next_node_id, Box::new(loader)),
+            loaded_node_ids,
+        )
+    }
+
+    #[rstest]
+    fn stored_filesystem_node_storable_impl() {
+        let filesystem = FileSystem::default();
+        let node = filesystem.get_node(&root_path()).unwrap();
+
+        let serialized_node = encode_versioned(&node);
+        let deserialized_node = decode_versioned(&serialized_node);
+
+        assert_eq!(deserialized_node, Ok(node));
+    }
+
+    #[rstest]
+    fn stored_filesystem_node_migrates_v1_fixtures() {
+        assert_eq!(
+            decode_versioned(&decode_hex(STORED_FILE_V1_FIXTURE)),
+            Ok(StoredFileSystemNode::File {
+                message_id: 42,
+                created_at: 1_700_000_000_000_000_000,
+                size: 1024,
+                mime_type: Some("application/pdf".to_string()),
+                source_chat_id: Some(ChatId(456)),
+                telegram_file: None,
+                tags: BTreeSet::from(["work".to_string()]),
+            })
+        );
+        assert_eq!(
+            decode_versioned(&decode_hex(STORED_UNTAGGED_FILE_V1_FIXTURE)),
+            Ok(StoredFileSystemNode::File {
+                message_id: 43,
+                created_at: 1_700_000_000_000_000_000,
+                size: 10,
+                mime_type: None,
+                source_chat_id: None,
+                telegram_file: None,
+                tags: BTreeSet::new(),
+            })
+        );
+        assert_eq!(
+            decode_versioned(&decode_hex(STORED_DIRECTORY_V1_FIXTURE)),
+            Ok(StoredFileSystemNode::Directory {
+                created_at: 1_700_000_000_000_000_000,
+                nodes: FileSystemNodeRefs::from([(
+                    PathBuf::from("report.pdf"),
+                    FileSystemNodeRef {
+                        id: 1,
+                        is_directory: false,
+                    },
+                )]),
+            })
+        );
+        // the migrated nodes are written with the current version
+        assert_eq!(
+            &encode_versioned(&StoredFileSystemNode::new_directory())[..2],
+            &[0xc0, 2]
+        );
+    }
+
+    #[rstest]
+    fn filesystem_node_key_storable_impl() {
+        let key = FileSystemNodeKey::new(ChatId(123), 456);
+
+        let serialized_key = key.to_bytes();
+        assert_eq!(serialized_key.len(), 16);
+        let deserialized_key = FileSystemNodeKey::from_bytes(serialized_key);
+
+        assert_eq!(deserialized_key, key);
+        assert!(FileSystemNodeKey::new(ChatId(1), u64::MAX) < FileSystemNodeKey::new(ChatId(2), 0));
+    }
+
+    #[rstest]
+    fn text_file_key_storable_impl() {
+        let key = TextFileKey::new(ChatId::from(-123i64), 1, ChatId(456), -789, 10);
+
+        let serialized_key = key.to_bytes();
+        assert_eq!(serialized_key.len(), 36);
+        let deserialized_key = TextFileKey::from_bytes(serialized_key);
+
+        assert_eq!(deserialized_key, key);
+        assert!(
+            TextFileKey::new(ChatId(1), 0, ChatId(1), MessageId::MIN, 0).to_bytes()
+                < TextFileKey::new(ChatId(1), 0, ChatId(1), MessageId::MAX, 0).to_bytes()
+        );
+    }
+
+    #[rstest]
+    fn quarantined_node_key_storable_impl() {
+        let key = QuarantinedNodeKey::new(ChatId::from(-123i64), 2, 456);
+
+        let serialized_key = key.to_bytes();
+        assert_eq!(serialized_key.len(), 24);
+        let deserialized_key = QuarantinedNodeKey::from_bytes(serialized_key);
+
+        assert_eq!(deserialized_key, key);
+    }
+
+    #[rstest]
+    fn filesystem_from_legacy() {
+        let legacy = VersionedValue::<LegacyFileSystem>::from_bytes(Cow::Owned(decode_hex(
+            LEGACY_FILESYSTEM_FIXTURE,
+        )))
+        .decode()
+        .unwrap();
+
+        let filesystem = FileSystem::from(legacy);
+
+        assert_eq!(
+            filesystem.ls(&root_path()),
+            Ok(vec![PathBuf::from("Documents"), PathBuf::from("Trash")])
+        );
+        let file = filesystem
+            .get_node(&PathBuf::from("/Documents/report.pdf"))
+            .unwrap();
+        assert_eq!(
+            file,
+            StoredFileSystemNode::File {
+                message_id: 42,
+                created_at: 1_700_000_000_000_000_000,
+                size: 1024,
+                mime_type: Some("application/pdf".to_string()),
+                source_chat_id: None,
+                telegram_file: None,
+                tags: BTreeSet::new(),
+            }
+        );
+        assert!(filesystem
+            .get_node(&PathBuf::from("/Trash"))
+            .unwrap()
+            .is_empty_directory());
+        let (nodes, removed_node_ids) = filesystem.into_changes();
+        assert_eq!(nodes.len(), 4);
+        assert!(nodes.contains_key(&ROOT_NODE_ID));
+        assert!(removed_node_ids.is_empty());
+    }
+
+    #[rstest]
+    fn legacy_filesystem_decode_corrupted() {
+        let mut bytes = decode_hex(LEGACY_FILESYSTEM_FIXTURE);
+        bytes.truncate(bytes.len() / 2);
+
+        assert!(
+            VersionedValue::<LegacyFileSystem>::from_bytes(Cow::Owned(bytes))
+                .decode()
+                .is_err()
+        );
+    }
+
+    #[rstest]
+    fn filesystem_with_loader_loads_only_involved_nodes() {
+        let mut filesystem = FileSystem::default();
+        filesystem
+            .create_file(
+                &PathBuf::from("/Documents/dir-a/file-a"),
+                0,
+                0,
+                Some("text/plain".to_string()),
+            )
+            .unwrap();
+        let (filesystem, loaded_node_ids) = with_test_loader(filesystem);
+
+        assert!(filesystem
+            .get_node(&PathBuf::from("/Documents/dir-a"))
+            .unwrap()
+            .is_directory());
+        assert_eq!(loaded_node_ids.borrow().len(), 3);
+
+        // already loaded nodes are not loaded again
+        filesystem.get_node(&PathBuf::from("/Documents")).unwrap();
+        assert_eq!(loaded_node_ids.borrow().len(), 3);
 
-impl Storable for FileSystem {
-    fn to_bytes(&self) -> Cow<[u8]> {
-        Cow::Owned(Encode!(self).unwrap())
+        // nodes that have only been read are not written back
+        assert!(!filesystem.is_dirty());
+        let (nodes, removed_node_ids) = filesystem.into_changes();
+        assert!(nodes.is_empty());
+        assert!(removed_node_ids.is_empty());
     }
 
-    fn from_bytes(bytes: Cow<[u8]>) -> Self {
-        Decode!(bytes.as_ref(), Self).unwrap()
+    #[rstest]
+    fn filesystem_with_loader_mv() {
+        let mut filesystem = FileSystem::default();
+        filesystem.mkdir(&PathBuf::from("/dir-a/subdir-a")).unwrap();
+        let (mut filesystem, _) = with_test_loader(filesystem);
+
+        filesystem
+            .mv(&PathBuf::from("/dir-a"), &PathBuf::from("/Documents/dir-a"))
+            .unwrap();
+
+        assert!(filesystem
+            .get_node(&PathBuf::from("/Documents/dir-a/subdir-a"))
+            .unwrap()
+            .is_directory());
+        // the moved subtree keeps its node ids, only the parents are written
+        assert!(filesystem.is_dirty());
+        let (nodes, removed_node_ids) = filesystem.into_changes();
+        assert_eq!(nodes.keys().collect::<Vec<_>>(), vec![&ROOT_NODE_ID, &1]);
+        assert!(removed_node_ids.is_empty());
     }
 
-    const BOUND: Bound = Bound::Unbounded;
-}
+    /// Compares the bytes encoded and written to stable memory to save a file among 1000 others,
+    /// with the per-node layout and with the legacy layout that rewrote the whole tree on every update.
+    /// The instructions spent encoding and writing grow with the written bytes.
+    #[rstest]
+    fn filesystem_with_loader_writes_less_than_whole_tree() {
+        let mut filesystem = FileSystem::default();
+        for dir_index in 0..10 {
+            for file_index in 0..100 {
+                filesystem
+                    .create_file(
+                        &PathBuf::from(format!("/dir-{dir_index}/file-{file_index}")),
+                        file_index,
+                        1024,
+                        Some("application/pdf".to_string()),
+                    )
+                    .unwrap();
+            }
+        }
+        let whole_tree_bytes =
+            candid::encode_one(LegacyFileSystem::new(filesystem.export().unwrap()))
+                .unwrap()
+                .len();
+        let (mut filesystem, loaded_node_ids) = with_test_loader(filesystem);
 
-pub struct KeyboardDirectoryBuilder<'a> {
-    inline_keyboard: Vec<InlineKeyboardButton>,
-    current_node: &'a FileSystemNode,
-    current_path: &'a Path,
-}
+        filesystem
+            .create_file(
+                &PathBuf::from("/dir-3/new-file"),
+                1_000,
+                1024,
+                Some("application/pdf".to_string()),
+            )
+            .unwrap();
 
-impl<'a> KeyboardDirectoryBuilder<'a> {
-    pub fn new(filesystem: &'a FileSystem, current_path: &'a Path) -> Result<Self, String> {
-        let current_node = filesystem.get_node(current_path)?;
+        let (nodes, _) = filesystem.into_changes();
+        let per_node_bytes: usize = nodes
+            .values()
+            .map(|node| encode_versioned(node).len())
+            .sum();
+        // only the parent directory and the new file are decoded and written:
+        // about 2.5 kB, against the about 52 kB of the whole tree
+        assert_eq!(loaded_node_ids.borrow().len(), 2);
+        assert_eq!(nodes.len(), 2);
+        assert!(per_node_bytes * 20 < whole_tree_bytes);
+    }
 
-        let mut inline_keyboard = if current_path != root_path() {
-            vec![parent_dir_inline_button()]
-        } else {
-            vec![]
-        };
+    #[rstest]
+    fn filesystem_with_loader_rm() {
+        let mut filesystem = FileSystem::default();
+        filesystem.mkdir(&PathBuf::from("/dir-a/subdir-a")).unwrap();
+        let (mut filesystem, _) = with_test_loader(filesystem);
 
-        for path in current_node.ls_directories()? {
-            inline_keyboard.push(path_button(&current_path.join(path), true));
-        }
+        filesystem.rm(&PathBuf::from("/dir-a")).unwrap();
 
-        Ok(Self {
-            inline_keyboard,
-            current_node,
-            current_path,
-        })
+        let (nodes, removed_node_ids) = filesystem.into_changes();
+        assert_eq!(nodes.keys().collect::<Vec<_>>(), vec![&ROOT_NODE_ID]);
+        assert_eq!(removed_node_ids.len(), 2);
     }
 
-    /// Prepends the delete dir button to the keyboard
-    #[allow(dead_code)] // TODO: remove once used
-    pub fn with_delete_dir_button(&mut self) -> &mut Self {
-        self.inline_keyboard.insert(0, delete_dir_inline_button());
-        self
-    }
+    #[rstest]
+    fn filesystem_export() {
+        let mut filesystem = FileSystem::new();
+        filesystem
+            .create_file(
+                &PathBuf::from("/Documents/file-a.txt"),
+                1,
+                0,
+                Some("text/plain".to_string()),
+            )
+            .unwrap();
+        let (filesystem, _) = with_test_loader(filesystem);
 
-    /// Prepends the current dir button to the keyboard
-    pub fn with_current_dir_button(&mut self) -> &mut Self {
-        self.inline_keyboard.insert(0, current_dir_inline_button());
-        self
-    }
+        let root = filesystem.export().unwrap();
 
-    /// Appends the files of the current directory to the keyboard
-    pub fn with_files(&mut self) -> Result<&mut Self, String> {
-        let paths = self.current_node.ls_files()?;
-        for path in paths {
-            self.inline_keyboard
-                .push(path_button(&self.current_path.join(path), false));
-        }
-        Ok(self)
+        let documents = root.get_nodes().get(&PathBuf::from("Documents")).unwrap();
+        assert!(documents.is_directory());
+        let file = documents
+            .get_nodes()
+            .get(&PathBuf::from("file-a.txt"))
+            .unwrap();
+        assert!(file.is_file());
     }
 
-    pub fn build(&self) -> InlineKeyboardMarkup {
-        InlineKeyboardMarkup {
-            // to display one button per row, each button should have its own row
-            inline_keyboard: self
-                .inline_keyboard
-                .iter()
-                .map(|el| vec![el.clone()])
-                .collect(),
-        }
+    #[rstest]
+    fn filesystem_check_consistency() {
+        let mut filesystem = FileSystem::default();
+        filesystem.mkdir(&PathBuf::from("/dir-a/subdir-a")).unwrap();
+        let next_node_id = filesystem.next_node_id;
+        let (mut nodes, _) = filesystem.into_changes();
+        assert_eq!(nodes.len(), 7);
+
+        let (filesystem, _) = with_test_loader_nodes(next_node_id, nodes.clone());
+        let (reachable_node_ids, issues) = filesystem.check_consistency();
+        assert_eq!(reachable_node_ids.len(), 7);
+        assert!(issues.is_empty());
+
+        // /dir-a is created after /dir-a/subdir-a, so it's the last created node
+        let dir_a_id = next_node_id - 1;
+        let subdir_a_id = next_node_id - 2;
+        nodes.remove(&dir_a_id);
+        let (filesystem, _) = with_test_loader_nodes(next_node_id, nodes);
+        let (reachable_node_ids, issues) = filesystem.check_consistency();
+        assert_eq!(reachable_node_ids.len(), 6);
+        assert!(!reachable_node_ids.contains(&subdir_a_id));
+        assert_eq!(issues, vec!["/dir-a: Node not found".to_string()]);
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use rstest::*;
 
     #[rstest]
-    fn filesystem_storable_impl() {
-        let filesystem = FileSystem::default();
-
-        let serialized_filesystem = filesystem.to_bytes();
-        let deserialized_filesystem = FileSystem::from_bytes(serialized_filesystem);
+    fn filesystem_with_corrupted_loader() {
+        let filesystem = FileSystem::with_loader(1, Box::new(CorruptedNodeLoader));
+        assert!(!filesystem.is_corrupted());
 
-        assert_eq!(deserialized_filesystem, filesystem);
+        assert_eq!(
+            filesystem.get_node(&root_path()),
+            Err("Filesystem node 0 is corrupted: Cannot decode value".to_string())
+        );
+        assert!(filesystem.is_corrupted());
     }
 
     #[rstest]
@@ -557,6 +2178,43 @@ mod tests {
         assert!(filesystem.get_node(&expected_path).unwrap().is_file());
     }
 
+    #[rstest]
+    fn filesystem_resolve_conflict() {
+        let mut filesystem = FileSystem::new();
+        let path = PathBuf::from("/dir-a/report.pdf");
+        let free_path = PathBuf::from("/dir-a/notes.pdf");
+        filesystem.create_file(&path, 1, 0, None).unwrap();
+        filesystem
+            .create_file(&PathBuf::from("/dir-a/report (1).pdf"), 2, 0, None)
+            .unwrap();
+
+        for conflict_policy in ConflictPolicy::ALL {
+            assert_eq!(
+                filesystem.resolve_conflict(&free_path, conflict_policy),
+                Ok((free_path.clone(), None))
+            );
+        }
+        assert_eq!(
+            filesystem.resolve_conflict(&path, ConflictPolicy::Reject),
+            Err("A file with the same name already exists".to_string())
+        );
+        assert_eq!(
+            filesystem.resolve_conflict(&path, ConflictPolicy::Rename),
+            Ok((PathBuf::from("/dir-a/report (2).pdf"), None))
+        );
+        assert_eq!(
+            filesystem.resolve_conflict(&PathBuf::from("/dir-a"), ConflictPolicy::Overwrite),
+            Err("A directory has the same name".to_string())
+        );
+
+        let (overwritten_path, removed_node) = filesystem
+            .resolve_conflict(&path, ConflictPolicy::Overwrite)
+            .unwrap();
+        assert_eq!(overwritten_path, path);
+        assert_eq!(removed_node.unwrap().file_message_id(), Some(1));
+        assert!(filesystem.get_node(&path).is_err());
+    }
+
     #[rstest]
     fn filesystem_mv_directory() {
         let mut filesystem = FileSystem::new();
@@ -585,6 +2243,24 @@ mod tests {
             .is_directory());
     }
 
+    #[rstest]
+    fn filesystem_mv_directory_into_itself() {
+        let mut filesystem = FileSystem::new();
+        filesystem.mkdir(&PathBuf::from("/dir-a/subdir-a")).unwrap();
+
+        assert_eq!(
+            filesystem.mv(
+                &PathBuf::from("/dir-a"),
+                &PathBuf::from("/dir-a/subdir-a/dir-a"),
+            ),
+            Err("A directory can't be moved into itself".to_string())
+        );
+        assert!(filesystem
+            .get_node(&PathBuf::from("/dir-a/subdir-a"))
+            .unwrap()
+            .is_directory());
+    }
+
     #[rstest]
     fn filesystem_mv_file() {
         let mut filesystem = FileSystem::new();
@@ -627,6 +2303,160 @@ mod tests {
             .is_file());
     }
 
+    #[rstest]
+    fn filesystem_mv_to_taken_destination() {
+        let mut filesystem = FileSystem::new();
+        filesystem
+            .create_file(&PathBuf::from("/dir-a/file-a.txt"), 1, 0, None)
+            .unwrap();
+        filesystem
+            .create_file(&PathBuf::from("/dir-b/file-a.txt"), 2, 0, None)
+            .unwrap();
+        filesystem.mkdir(&PathBuf::from("/dir-c/subdir-c")).unwrap();
+
+        assert_eq!(
+            filesystem.mv(
+                &PathBuf::from("/dir-a/file-a.txt"),
+                &PathBuf::from("/dir-b/file-a.txt"),
+            ),
+            Err("Destination is already taken".to_string())
+        );
+        assert_eq!(
+            filesystem.mv(
+                &PathBuf::from("/dir-a/file-a.txt"),
+                &PathBuf::from("/dir-c")
+            ),
+            Err("Destination is already taken".to_string())
+        );
+
+        // nothing is moved or removed
+        assert_eq!(
+            filesystem
+                .get_node(&PathBuf::from("/dir-a/file-a.txt"))
+                .unwrap()
+                .file_message_id(),
+            Some(1)
+        );
+        assert_eq!(
+            filesystem
+                .get_node(&PathBuf::from("/dir-b/file-a.txt"))
+                .unwrap()
+                .file_message_id(),
+            Some(2)
+        );
+        assert!(filesystem
+            .get_node(&PathBuf::from("/dir-c/subdir-c"))
+            .unwrap()
+            .is_directory());
+    }
+
+    #[rstest]
+    fn filesystem_cp_file() {
+        let mut filesystem = FileSystem::new();
+        filesystem
+            .create_file(
+                &PathBuf::from("/dir-a/file-a"),
+                1,
+                10,
+                Some("text/plain".to_string()),
+            )
+            .unwrap();
+        filesystem.mkdir(&PathBuf::from("/dir-b")).unwrap();
+
+        filesystem
+            .cp(
+                &PathBuf::from("/dir-a/file-a.txt"),
+                &PathBuf::from("/dir-b/file-a.txt"),
+            )
+            .unwrap();
+
+        let original = filesystem
+            .get_node(&PathBuf::from("/dir-a/file-a.txt"))
+            .unwrap();
+        let copy = filesystem
+            .get_node(&PathBuf::from("/dir-b/file-a.txt"))
+            .unwrap();
+        assert_eq!(original, copy);
+        assert_eq!(
+            filesystem.cp(&PathBuf::from("/dir-a"), &PathBuf::from("/dir-c")),
+            Err("Directories can't be copied".to_string())
+        );
+    }
+
+    #[rstest]
+    fn filesystem_tag_file() {
+        let mut filesystem = FileSystem::new();
+        let path = filesystem
+            .create_file(
+                &PathBuf::from("/file-a"),
+                1,
+                10,
+                Some("text/plain".to_string()),
+            )
+            .unwrap();
+
+        assert_eq!(filesystem.tag_file(&path, "work"), Ok(true));
+        assert_eq!(filesystem.tag_file(&path, "work"), Ok(false));
+        assert_eq!(filesystem.tag_file(&path, "2024"), Ok(true));
+        assert_eq!(
+            filesystem.get_node(&path).unwrap().file_tags(),
+            BTreeSet::from(["2024".to_string(), "work".to_string()])
+        );
+        assert_eq!(
+            filesystem.tag_file(&root_path(), "work"),
+            Err("Not a file".to_string())
+        );
+
+        assert_eq!(filesystem.untag_file(&path, "work"), Ok(true));
+        assert_eq!(filesystem.untag_file(&path, "work"), Ok(false));
+        assert_eq!(
+            filesystem.get_node(&path).unwrap().file_tags(),
+            BTreeSet::from(["2024".to_string()])
+        );
+    }
+
+    #[rstest]
+    fn filesystem_transaction() {
+        let mut filesystem = FileSystem::new();
+        filesystem.mkdir(&PathBuf::from("/dir-a")).unwrap();
+        let next_node_id = filesystem.next_node_id();
+
+        let result = filesystem.transaction(|filesystem| {
+            filesystem.mkdir(&PathBuf::from("/dir-b"))?;
+            filesystem.rm(&PathBuf::from("/dir-a"))?;
+            filesystem.rm(&PathBuf::from("/dir-c"))
+        });
+
+        assert_eq!(result, Err("Node not found".to_string()));
+        assert_eq!(
+            filesystem.ls(&root_path()),
+            Ok(vec![PathBuf::from("dir-a")])
+        );
+        assert_eq!(filesystem.next_node_id(), next_node_id);
+        assert_eq!(
+            filesystem.transaction(|filesystem| filesystem.mkdir(&PathBuf::from("/dir-b"))),
+            Ok(())
+        );
+        assert!(filesystem.get_node(&PathBuf::from("/dir-b")).is_ok());
+    }
+
+    #[rstest]
+    fn filesystem_rm() {
+        let mut filesystem = FileSystem::new();
+        filesystem.mkdir(&PathBuf::from("/dir-a/subdir-a")).unwrap();
+        filesystem.rm(&PathBuf::from("/dir-a/subdir-a")).unwrap();
+
+        assert_eq!(filesystem.ls(&PathBuf::from("/dir-a")), Ok(vec![]));
+        assert_eq!(
+            filesystem.rm(&PathBuf::from("/dir-b/subdir-b")),
+            Err("Path not found".to_string())
+        );
+        assert_eq!(
+            filesystem.get_node(&PathBuf::from("/dir-b")),
+            Err("Path not found".to_string())
+        );
+    }
+
     #[rstest]
     fn filesystem_node_get_nodes() {
         let mut node = FileSystemNode::new_directory();
@@ -648,33 +2478,60 @@ mod tests {
     }
 
     #[rstest]
-    fn filesystem_node_ls_directories() {
-        let mut node = FileSystemNode::new_directory();
-        node.get_nodes_mut()
-            .insert(PathBuf::from("dir-a"), FileSystemNode::new_directory());
-        node.get_nodes_mut().insert(
-            PathBuf::from("file-a"),
-            FileSystemNode::new_file(0, 0, None),
-        );
+    fn stored_filesystem_node_ls_directories() {
+        let mut filesystem = FileSystem::new();
+        filesystem.mkdir(&PathBuf::from("/dir-a")).unwrap();
+        filesystem
+            .create_file(&PathBuf::from("/file-a"), 0, 0, None)
+            .unwrap();
+        let node = filesystem.get_node(&root_path()).unwrap();
 
-        let directories = node.ls_directories().unwrap();
+        let directories = node.ls_directories(SortOrder::Name).unwrap();
 
         assert_eq!(directories.len(), 1);
         assert_eq!(directories[0], PathBuf::from("dir-a"));
     }
 
     #[rstest]
-    fn filesystem_node_ls_files() {
-        let mut node = FileSystemNode::new_directory();
-        node.get_nodes_mut().insert(
-            PathBuf::from("file-a"),
-            FileSystemNode::new_file(0, 0, Some("text/plain".to_string())),
-        );
+    fn stored_filesystem_node_ls_files() {
+        let mut filesystem = FileSystem::new();
+        filesystem
+            .create_file(
+                &PathBuf::from("/file-a"),
+                0,
+                0,
+                Some("text/plain".to_string()),
+            )
+            .unwrap();
+        let node = filesystem.get_node(&root_path()).unwrap();
 
-        let files = node.ls_files().unwrap();
+        let files = node.ls_files(SortOrder::Name).unwrap();
 
         assert_eq!(files.len(), 1);
-        assert_eq!(files[0], PathBuf::from("file-a"));
+        assert_eq!(files[0], PathBuf::from("file-a.txt"));
+    }
+
+    #[rstest]
+    #[case(SortOrder::Name, ["file-a", "file-b", "file-c"])]
+    #[case(SortOrder::NameDescending, ["file-c", "file-b", "file-a"])]
+    #[case(SortOrder::Newest, ["file-a", "file-c", "file-b"])]
+    #[case(SortOrder::Oldest, ["file-b", "file-c", "file-a"])]
+    fn stored_filesystem_node_ls_files_sorted(
+        #[case] sort_order: SortOrder,
+        #[case] expected: [&str; 3],
+    ) {
+        let mut filesystem = FileSystem::new();
+        for name in ["file-b", "file-c", "file-a"] {
+            filesystem
+                .create_file(&root_path().join(name), 0, 0, None)
+                .unwrap();
+        }
+        let node = filesystem.get_node(&root_path()).unwrap();
+
+        assert_eq!(
+            node.ls_files(sort_order).unwrap(),
+            expected.map(PathBuf::from)
+        );
     }
 
     #[rstest]
@@ -721,7 +2578,7 @@ mod tests {
         let contents = filesystem
             .get_node(&path)
             .unwrap()
-            .ls_directories()
+            .ls_directories(SortOrder::Name)
             .unwrap();
         assert_eq!(builder.inline_keyboard.len(), contents.len() + 1);
         assert_eq!(builder.inline_keyboard[0], parent_dir_inline_button());
@@ -771,4 +2628,28 @@ mod tests {
         let file_paths = filesystem.ls(&path).unwrap();
         assert_eq!(keyboard.inline_keyboard.len(), file_paths.len());
     }
+
+    #[rstest]
+    fn test_keyboard_directory_builder_with_selectable_files() {
+        let mut filesystem = FileSystem::default();
+        let selected = filesystem
+            .create_file(&PathBuf::from("/file-a"), 1, 100, None)
+            .unwrap();
+        filesystem
+            .create_file(&PathBuf::from("/file-b"), 2, 100, None)
+            .unwrap();
+        let path = PathBuf::from("/");
+        let mut builder = KeyboardDirectoryBuilder::new(&filesystem, &path).unwrap();
+        let keyboard = builder
+            .with_selectable_files(&BTreeSet::from([selected]))
+            .unwrap()
+            .with_bottom_rows(vec![vec![back_inline_button(), cancel_inline_button()]])
+            .build();
+
+        let rows = keyboard.inline_keyboard;
+        let file_rows = &rows[rows.len() - 3..];
+        assert_eq!(file_rows[0][0].text, "✅ file-a");
+        assert_eq!(file_rows[1][0].text, "file-b");
+        assert_eq!(file_rows[2].len(), 2);
+    }
 }
@@ -0,0 +1,653 @@
+use std::{collections::VecDeque, path::PathBuf};
+
+use candid::{CandidType, Deserialize};
+
+use crate::utils::get_current_time;
+
+use super::{
+    BulkOperation, DriveId, FileSystem, MessageId, Migration, SchemaVersion, StoredFileSystemNode,
+    VersionedStorable,
+};
+
+/// A mutating operation that has been applied to a chat's filesystem.
+#[derive(Debug, CandidType, Deserialize, Clone, PartialEq, Eq)]
+pub enum JournalOperation {
+    MkDir {
+        path: PathBuf,
+    },
+    SaveFile {
+        path: PathBuf,
+        message_id: MessageId,
+    },
+    MoveFile {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    RenameFile {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    CopyFile {
+        from: PathBuf,
+        to: PathBuf,
+        message_id: MessageId,
+    },
+    /// Keeps the removed node, so that it can be restored as it was.
+    DeleteFile {
+        path: PathBuf,
+        node: StoredFileSystemNode,
+    },
+    /// Only recorded when the file didn't have the tag yet.
+    TagFile {
+        path: PathBuf,
+        tag: String,
+    },
+    /// The operations of a bulk action on the selected files, reverted together.
+    Bulk {
+        operation: BulkOperation,
+        operations: Vec<JournalOperation>,
+    },
+}
+
+impl JournalOperation {
+    pub fn inverse(&self) -> JournalInverseOperation {
+        match self {
+            Self::MkDir { path } => JournalInverseOperation::RemoveDirectory { path: path.clone() },
+            Self::SaveFile { path, message_id } => JournalInverseOperation::RemoveFile {
+                path: path.clone(),
+                message_id: *message_id,
+            },
+            Self::MoveFile { from, to } | Self::RenameFile { from, to } => {
+                JournalInverseOperation::Move {
+                    from: to.clone(),
+                    to: from.clone(),
+                }
+            }
+            Self::CopyFile { to, message_id, .. } => JournalInverseOperation::RemoveFile {
+                path: to.clone(),
+                message_id: *message_id,
+            },
+            Self::DeleteFile { path, node } => JournalInverseOperation::RestoreFile {
+                path: path.clone(),
+                node: node.clone(),
+            },
+            Self::TagFile { path, tag } => JournalInverseOperation::UntagFile {
+                path: path.clone(),
+                tag: tag.clone(),
+            },
+            Self::Bulk { operations, .. } => JournalInverseOperation::Bulk {
+                operations: operations
+                    .iter()
+                    .rev()
+                    .map(|operation| operation.inverse())
+                    .collect(),
+            },
+        }
+    }
+}
+
+/// The operation that reverts a [JournalOperation].
+///
+/// Each variant checks that the filesystem is still in the state
+/// left by the original operation before applying it.
+#[derive(Debug, CandidType, Deserialize, Clone, PartialEq, Eq)]
+pub enum JournalInverseOperation {
+    RemoveDirectory {
+        path: PathBuf,
+    },
+    RemoveFile {
+        path: PathBuf,
+        message_id: MessageId,
+    },
+    Move {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    RestoreFile {
+        path: PathBuf,
+        node: StoredFileSystemNode,
+    },
+    UntagFile {
+        path: PathBuf,
+        tag: String,
+    },
+    /// Applied in order, either all of them or none.
+    Bulk {
+        operations: Vec<JournalInverseOperation>,
+    },
+}
+
+impl JournalInverseOperation {
+    pub fn apply(&self, fs: &mut FileSystem) -> Result<(), String> {
+        match self {
+            Self::RemoveDirectory { path } => {
+                match fs.get_node(path) {
+                    Ok(node) if node.is_empty_directory() => {}
+                    Ok(node) if node.is_directory() => {
+                        return Err("Directory is not empty anymore".to_string())
+                    }
+                    _ => return Err("Directory does not exist anymore".to_string()),
+                }
+                fs.rm(path)
+            }
+            Self::RemoveFile { path, message_id } => {
+                match fs.get_node(path) {
+                    Ok(node) if node.file_message_id() == Some(*message_id) => {}
+                    _ => return Err("File does not exist anymore".to_string()),
+                }
+                fs.rm(path)
+            }
+            Self::Move { from, to } => {
+                if fs.get_node(from).is_err() {
+                    return Err("File does not exist anymore".to_string());
+                }
+                if fs.get_node(to).is_ok() {
+                    return Err("Destination is already taken".to_string());
+                }
+                let to_parent = to.parent().ok_or("Invalid path")?;
+                if !fs.get_node(to_parent).is_ok_and(|node| node.is_directory()) {
+                    return Err("Destination directory does not exist anymore".to_string());
+                }
+                fs.mv(from, to)
+            }
+            Self::RestoreFile { path, node } => {
+                if fs.get_node(path).is_ok() {
+                    return Err("Path is already taken".to_string());
+                }
+                let parent = path.parent().ok_or("Invalid path")?;
+                if !fs.get_node(parent).is_ok_and(|node| node.is_directory()) {
+                    return Err("Directory does not exist anymore".to_string());
+                }
+                fs.insert_file(path, node.clone())
+            }
+            Self::UntagFile { path, tag } => {
+                match fs.get_node(path) {
+                    Ok(node) if node.file_tags().contains(tag) => {}
+                    Ok(node) if !node.is_directory() => {
+                        return Err("File is not tagged anymore".to_string())
+                    }
+                    _ => return Err("File does not exist anymore".to_string()),
+                }
+                fs.untag_file(path, tag).map(|_| ())
+            }
+            Self::Bulk { operations } => fs.transaction(|fs| {
+                operations
+                    .iter()
+                    .try_for_each(|operation| operation.apply(fs))
+            }),
+        }
+    }
+}
+
+#[derive(Debug, CandidType, Deserialize, Clone, PartialEq, Eq)]
+pub struct JournalEntry {
+    timestamp: u64,
+    operation: JournalOperation,
+    inverse: JournalInverseOperation,
+    /// The drive the operation was applied to, the main drive when `None`.
+    drive_id: Option<DriveId>,
+}
+
+impl JournalEntry {
+    pub fn new(operation: JournalOperation) -> Self {
+        Self {
+            timestamp: get_current_time(),
+            inverse: operation.inverse(),
+            operation,
+            drive_id: None,
+        }
+    }
+
+    pub fn with_drive_id(mut self, drive_id: Option<DriveId>) -> Self {
+        self.drive_id = drive_id;
+        self
+    }
+
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
+    pub fn operation(&self) -> &JournalOperation {
+        &self.operation
+    }
+
+    pub fn inverse(&self) -> &JournalInverseOperation {
+        &self.inverse
+    }
+
+    #[cfg(test)]
+    pub fn drive_id(&self) -> Option<DriveId> {
+        self.drive_id
+    }
+}
+
+/// The bounded list of the latest operations applied to a chat's filesystem.
+/// Oldest entries are dropped when the journal is full.
+#[derive(Debug, CandidType, Deserialize, Clone, PartialEq, Eq, Default)]
+pub struct Journal {
+    entries: VecDeque<JournalEntry>,
+}
+
+impl Journal {
+    /// Appends the entry, dropping the oldest entries to keep at most `max_entries`.
+    pub fn push(&mut self, entry: JournalEntry, max_entries: usize) {
+        while self.entries.len() >= max_entries.max(1) {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    /// Returns the latest entry of the drive, the main drive when `None`.
+    pub fn latest_drive_entry(&self, drive_id: Option<DriveId>) -> Option<&JournalEntry> {
+        self.entries().find(|entry| entry.drive_id == drive_id)
+    }
+
+    /// Removes the latest entry of the drive, leaving the entries of the other drives in place.
+    pub fn pop_drive_entry(&mut self, drive_id: Option<DriveId>) -> Option<JournalEntry> {
+        let index = self
+            .entries
+            .iter()
+            .rposition(|entry| entry.drive_id == drive_id)?;
+        self.entries.remove(index)
+    }
+
+    /// Removes the entries of the drive, the main drive when `None`.
+    pub fn remove_drive_entries(&mut self, drive_id: Option<DriveId>) {
+        self.entries.retain(|entry| entry.drive_id != drive_id);
+    }
+
+    /// Returns the entries from the most recent to the oldest.
+    pub fn entries(&self) -> impl Iterator<Item = &JournalEntry> {
+        self.entries.iter().rev()
+    }
+
+    #[cfg(test)]
+    fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl VersionedStorable for Journal {
+    const SCHEMA_VERSION: SchemaVersion = 1;
+    const MIGRATIONS: &'static [Migration] = &[];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::{decode_versioned, encode_versioned, FileSystemNode};
+    use rstest::*;
+
+    const MAX_JOURNAL_ENTRIES: usize = 20;
+
+    #[rstest]
+    fn journal_storable_impl() {
+        let mut journal = Journal::default();
+        journal.push(
+            JournalEntry::new(JournalOperation::MkDir {
+                path: PathBuf::from("/dir-a"),
+            }),
+            MAX_JOURNAL_ENTRIES,
+        );
+
+        let serialized_journal = encode_versioned(&journal);
+        let deserialized_journal = decode_versioned(&serialized_journal);
+
+        assert_eq!(deserialized_journal, Ok(journal));
+    }
+
+    #[rstest]
+    fn journal_push_bounded() {
+        let mut journal = Journal::default();
+        for i in 0..MAX_JOURNAL_ENTRIES + 5 {
+            journal.push(
+                JournalEntry::new(JournalOperation::MkDir {
+                    path: PathBuf::from(format!("/dir-{i}")),
+                }),
+                MAX_JOURNAL_ENTRIES,
+            );
+        }
+
+        assert_eq!(journal.entries().count(), MAX_JOURNAL_ENTRIES);
+        assert_eq!(
+            journal.entries().next().unwrap().operation(),
+            &JournalOperation::MkDir {
+                path: PathBuf::from(format!("/dir-{}", MAX_JOURNAL_ENTRIES + 4)),
+            }
+        );
+        assert_eq!(
+            journal.entries().last().unwrap().operation(),
+            &JournalOperation::MkDir {
+                path: PathBuf::from("/dir-5"),
+            }
+        );
+    }
+
+    #[rstest]
+    fn journal_remove_drive_entries() {
+        let mut journal = Journal::default();
+        for drive_id in [None, Some(1), None] {
+            journal.push(
+                JournalEntry::new(JournalOperation::MkDir {
+                    path: PathBuf::from("/dir-a"),
+                })
+                .with_drive_id(drive_id),
+                MAX_JOURNAL_ENTRIES,
+            );
+        }
+
+        journal.remove_drive_entries(None);
+
+        assert_eq!(
+            journal
+                .entries()
+                .map(|entry| entry.drive_id())
+                .collect::<Vec<_>>(),
+            vec![Some(1)]
+        );
+    }
+
+    #[rstest]
+    fn journal_pop_drive_entry() {
+        let mut journal = Journal::default();
+        for (path, drive_id) in [("/dir-a", None), ("/dir-b", None), ("/dir-c", Some(1))] {
+            journal.push(
+                JournalEntry::new(JournalOperation::MkDir {
+                    path: PathBuf::from(path),
+                })
+                .with_drive_id(drive_id),
+                MAX_JOURNAL_ENTRIES,
+            );
+        }
+
+        let latest_entry = journal.latest_drive_entry(None).cloned();
+        assert_eq!(
+            latest_entry.as_ref().map(|entry| entry.operation()),
+            Some(&JournalOperation::MkDir {
+                path: PathBuf::from("/dir-b"),
+            })
+        );
+        assert_eq!(journal.pop_drive_entry(None), latest_entry);
+        assert_eq!(
+            journal
+                .entries()
+                .map(|entry| entry.drive_id())
+                .collect::<Vec<_>>(),
+            vec![Some(1), None]
+        );
+        journal.pop_drive_entry(None);
+        assert_eq!(journal.pop_drive_entry(None), None);
+        assert!(!journal.is_empty());
+        journal.pop_drive_entry(Some(1));
+        assert!(journal.is_empty());
+    }
+
+    #[rstest]
+    fn undo_mkdir() {
+        let mut fs = FileSystem::default();
+        let path = PathBuf::from("/dir-a");
+        fs.mkdir(&path).unwrap();
+        let entry = JournalEntry::new(JournalOperation::MkDir { path: path.clone() });
+
+        entry.inverse().apply(&mut fs).unwrap();
+
+        assert_eq!(fs.get_node(&path), Err("Path not found".to_string()));
+    }
+
+    #[rstest]
+    fn undo_mkdir_not_empty() {
+        let mut fs = FileSystem::default();
+        let path = PathBuf::from("/dir-a");
+        fs.mkdir(&path).unwrap();
+        let entry = JournalEntry::new(JournalOperation::MkDir { path: path.clone() });
+        fs.mkdir(&path.join("dir-b")).unwrap();
+
+        assert_eq!(
+            entry.inverse().apply(&mut fs),
+            Err("Directory is not empty anymore".to_string())
+        );
+        assert!(fs.get_node(&path).unwrap().is_directory());
+    }
+
+    #[rstest]
+    fn undo_save_file() {
+        let mut fs = FileSystem::default();
+        let path = fs
+            .create_file_from_node(
+                &PathBuf::from("/Documents/file-a"),
+                FileSystemNode::new_file(1, 0, Some("text/plain".to_string())),
+            )
+            .unwrap();
+        let entry = JournalEntry::new(JournalOperation::SaveFile {
+            path: path.clone(),
+            message_id: 1,
+        });
+
+        entry.inverse().apply(&mut fs).unwrap();
+
+        assert_eq!(fs.get_node(&path), Err("Path not found".to_string()));
+    }
+
+    #[rstest]
+    fn undo_save_file_replaced() {
+        let mut fs = FileSystem::default();
+        let path = PathBuf::from("/Documents/file-a.txt");
+        fs.create_file_from_node(
+            &path,
+            FileSystemNode::new_file(2, 0, Some("text/plain".to_string())),
+        )
+        .unwrap();
+        let entry = JournalEntry::new(JournalOperation::SaveFile {
+            path: path.clone(),
+            message_id: 1,
+        });
+
+        assert_eq!(
+            entry.inverse().apply(&mut fs),
+            Err("File does not exist anymore".to_string())
+        );
+        assert!(fs.get_node(&path).unwrap().is_file());
+    }
+
+    #[rstest]
+    fn undo_move_file() {
+        let mut fs = FileSystem::default();
+        let from = PathBuf::from("/Documents/file-a.txt");
+        let to = PathBuf::from("/Images/file-a.txt");
+        fs.create_file_from_node(
+            &from,
+            FileSystemNode::new_file(1, 0, Some("text/plain".to_string())),
+        )
+        .unwrap();
+        fs.mv(&from, &to).unwrap();
+        let entry = JournalEntry::new(JournalOperation::MoveFile {
+            from: from.clone(),
+            to: to.clone(),
+        });
+
+        entry.inverse().apply(&mut fs).unwrap();
+
+        assert!(fs.get_node(&from).unwrap().is_file());
+        assert_eq!(fs.get_node(&to), Err("Path not found".to_string()));
+    }
+
+    #[rstest]
+    fn undo_rename_file_destination_taken() {
+        let mut fs = FileSystem::default();
+        let from = PathBuf::from("/Documents/file-a.txt");
+        let to = PathBuf::from("/Documents/file-b.txt");
+        fs.create_file_from_node(
+            &from,
+            FileSystemNode::new_file(1, 0, Some("text/plain".to_string())),
+        )
+        .unwrap();
+        fs.mv(&from, &to).unwrap();
+        let entry = JournalEntry::new(JournalOperation::RenameFile {
+            from: from.clone(),
+            to: to.clone(),
+        });
+        fs.create_file_from_node(
+            &from,
+            FileSystemNode::new_file(2, 0, Some("text/plain".to_string())),
+        )
+        .unwrap();
+
+        assert_eq!(
+            entry.inverse().apply(&mut fs),
+            Err("Destination is already taken".to_string())
+        );
+        assert!(fs.get_node(&to).unwrap().is_file());
+    }
+
+    #[rstest]
+    fn undo_copy_file() {
+        let mut fs = FileSystem::default();
+        let from = PathBuf::from("/Documents/file-a.txt");
+        let to = PathBuf::from("/Images/file-a.txt");
+        fs.create_file_from_node(
+            &from,
+            FileSystemNode::new_file(1, 0, Some("text/plain".to_string())),
+        )
+        .unwrap();
+        fs.cp(&from, &to).unwrap();
+        let entry = JournalEntry::new(JournalOperation::CopyFile {
+            from: from.clone(),
+            to: to.clone(),
+            message_id: 1,
+        });
+
+        entry.inverse().apply(&mut fs).unwrap();
+
+        assert!(fs.get_node(&from).unwrap().is_file());
+        assert_eq!(fs.get_node(&to), Err("Path not found".to_string()));
+    }
+
+    #[rstest]
+    fn undo_delete_file() {
+        let mut fs = FileSystem::default();
+        let path = PathBuf::from("/Documents/file-a.txt");
+        fs.create_file_from_node(
+            &path,
+            FileSystemNode::new_file(1, 0, Some("text/plain".to_string())),
+        )
+        .unwrap();
+        fs.tag_file(&path, "work").unwrap();
+        let node = fs.get_node(&path).unwrap();
+        fs.rm(&path).unwrap();
+        let entry = JournalEntry::new(JournalOperation::DeleteFile {
+            path: path.clone(),
+            node: node.clone(),
+        });
+
+        entry.inverse().apply(&mut fs).unwrap();
+
+        assert_eq!(fs.get_node(&path), Ok(node));
+        assert_eq!(
+            entry.inverse().apply(&mut fs),
+            Err("Path is already taken".to_string())
+        );
+    }
+
+    #[rstest]
+    fn undo_tag_file() {
+        let mut fs = FileSystem::default();
+        let path = PathBuf::from("/Documents/file-a.txt");
+        fs.create_file_from_node(
+            &path,
+            FileSystemNode::new_file(1, 0, Some("text/plain".to_string())),
+        )
+        .unwrap();
+        fs.tag_file(&path, "work").unwrap();
+        let entry = JournalEntry::new(JournalOperation::TagFile {
+            path: path.clone(),
+            tag: "work".to_string(),
+        });
+
+        entry.inverse().apply(&mut fs).unwrap();
+
+        assert!(fs.get_node(&path).unwrap().file_tags().is_empty());
+        assert_eq!(
+            entry.inverse().apply(&mut fs),
+            Err("File is not tagged anymore".to_string())
+        );
+    }
+
+    #[rstest]
+    fn undo_bulk() {
+        let mut fs = FileSystem::default();
+        let paths = [
+            PathBuf::from("/Documents/file-a.txt"),
+            PathBuf::from("/Documents/file-b.txt"),
+        ];
+        let mut operations = vec![];
+        for (message_id, path) in paths.iter().enumerate() {
+            fs.create_file_from_node(
+                path,
+                FileSystemNode::new_file(
+                    message_id as MessageId,
+                    0,
+                    Some("text/plain".to_string()),
+                ),
+            )
+            .unwrap();
+            let to = PathBuf::from("/Images").join(path.file_name().unwrap());
+            fs.mv(path, &to).unwrap();
+            operations.push(JournalOperation::MoveFile {
+                from: path.clone(),
+                to,
+            });
+        }
+        let entry = JournalEntry::new(JournalOperation::Bulk {
+            operation: BulkOperation::Move,
+            operations,
+        });
+
+        entry.inverse().apply(&mut fs).unwrap();
+
+        for path in &paths {
+            assert!(fs.get_node(path).unwrap().is_file());
+        }
+    }
+
+    #[rstest]
+    fn undo_bulk_conflict() {
+        let mut fs = FileSystem::default();
+        let from = PathBuf::from("/Documents/file-a.txt");
+        let to = PathBuf::from("/Images/file-a.txt");
+        fs.create_file_from_node(
+            &from,
+            FileSystemNode::new_file(1, 0, Some("text/plain".to_string())),
+        )
+        .unwrap();
+        fs.mv(&from, &to).unwrap();
+        let entry = JournalEntry::new(JournalOperation::Bulk {
+            operation: BulkOperation::Move,
+            operations: vec![
+                JournalOperation::MoveFile {
+                    from: from.clone(),
+                    to: to.clone(),
+                },
+                JournalOperation::MkDir {
+                    path: PathBuf::from("/Projects"),
+                },
+            ],
+        });
+
+        // the directory is removed before the file is moved back, and restored when the move fails
+        fs.mkdir(&PathBuf::from("/Projects")).unwrap();
+        fs.create_file_from_node(
+            &from,
+            FileSystemNode::new_file(2, 0, Some("text/plain".to_string())),
+        )
+        .unwrap();
+        assert_eq!(
+            entry.inverse().apply(&mut fs),
+            Err("Destination is already taken".to_string())
+        );
+        assert!(fs
+            .get_node(&PathBuf::from("/Projects"))
+            .unwrap()
+            .is_directory());
+        assert!(fs.get_node(&to).unwrap().is_file());
+    }
+}
@@ -1,11 +1,39 @@
+mod access_policy;
+mod admin;
+mod backup;
 mod chat_id;
+mod chat_member;
 mod chat_session;
+mod chat_settings;
 mod command;
+mod config;
+mod drive;
 mod filesystem;
 mod http;
+mod journal;
+mod processed_update;
+mod rate_limit;
+mod share;
+mod user_id;
+mod versioned;
+mod webhook_secret;
 
+pub use access_policy::*;
+pub use admin::*;
+pub use backup::*;
 pub use chat_id::*;
+pub use chat_member::*;
 pub use chat_session::*;
+pub use chat_settings::*;
 pub use command::*;
+pub use config::*;
+pub use drive::*;
 pub use filesystem::*;
 pub use http::*;
+pub use journal::*;
+pub use processed_update::*;
+pub use rate_limit::*;
+pub use share::*;
+pub use user_id::*;
+pub use versioned::*;
+pub use webhook_secret::*;
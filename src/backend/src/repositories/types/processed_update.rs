@@ -0,0 +1,43 @@
+use candid::{CandidType, Deserialize};
+
+use super::{ChatId, Migration, SchemaVersion, VersionedStorable};
+
+/// The `update_id` of a Telegram update.
+pub type UpdateId = u32;
+
+/// An update that has already been processed, kept to answer the webhook
+/// deliveries that Telegram retries without processing the update again.
+#[derive(Debug, CandidType, Deserialize, Clone, PartialEq, Eq)]
+pub struct ProcessedUpdate {
+    /// The chat the update came from, so that the record can be removed with the chat data.
+    pub chat_id: Option<ChatId>,
+    pub processed_at: u64,
+    /// The JSON body returned to Telegram, if the update was answered with a method call.
+    pub response_body: Option<Vec<u8>>,
+}
+
+impl VersionedStorable for ProcessedUpdate {
+    const SCHEMA_VERSION: SchemaVersion = 1;
+    const MIGRATIONS: &'static [Migration] = &[];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::{decode_versioned, encode_versioned};
+    use rstest::*;
+
+    #[rstest]
+    fn storable_impl() {
+        let processed_update = ProcessedUpdate {
+            chat_id: Some(ChatId(123)),
+            processed_at: 1_700_000_000_000_000_000,
+            response_body: Some(b"{\"method\":\"sendMessage\"}".to_vec()),
+        };
+
+        let serialized_processed_update = encode_versioned(&processed_update);
+        let deserialized_processed_update = decode_versioned(&serialized_processed_update);
+
+        assert_eq!(deserialized_processed_update, Ok(processed_update));
+    }
+}
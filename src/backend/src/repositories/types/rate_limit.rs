@@ -0,0 +1,140 @@
+use candid::{CandidType, Deserialize};
+
+const NANOS_PER_SECOND: u64 = 1_000_000_000;
+
+/// Allows bursts of `capacity` updates, refilled at `refill_per_second` updates per second.
+#[derive(Debug, CandidType, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    pub capacity: u32,
+    pub refill_per_second: u32,
+}
+
+#[derive(Debug, CandidType, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitConfig {
+    pub per_chat: RateLimit,
+    pub global: RateLimit,
+}
+
+impl RateLimitConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        for rate_limit in [&self.per_chat, &self.global] {
+            if rate_limit.capacity == 0 || rate_limit.refill_per_second == 0 {
+                return Err("Rate limit capacity and refill rate must be positive".to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, CandidType, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct RateLimitCounters {
+    pub allowed_updates: u64,
+    pub throttled_chat_updates: u64,
+    pub throttled_global_updates: u64,
+    /// The number of chats whose bucket is currently tracked.
+    pub tracked_chats: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimitDecision {
+    Allowed,
+    ChatThrottled,
+    GlobalThrottled,
+}
+
+/// A token bucket, with the tokens counted in nanotokens to refill it without rounding.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenBucket {
+    nanotokens: u64,
+    refilled_at: u64,
+}
+
+impl TokenBucket {
+    pub fn new_full(rate_limit: &RateLimit, now: u64) -> Self {
+        Self {
+            nanotokens: Self::max_nanotokens(rate_limit),
+            refilled_at: now,
+        }
+    }
+
+    pub fn refill(&mut self, rate_limit: &RateLimit, now: u64) {
+        let elapsed = now.saturating_sub(self.refilled_at);
+        let refilled = elapsed.saturating_mul(u64::from(rate_limit.refill_per_second));
+        self.nanotokens = self
+            .nanotokens
+            .saturating_add(refilled)
+            .min(Self::max_nanotokens(rate_limit));
+        self.refilled_at = now;
+    }
+
+    pub fn has_token(&self) -> bool {
+        self.nanotokens >= NANOS_PER_SECOND
+    }
+
+    pub fn take_token(&mut self) {
+        self.nanotokens = self.nanotokens.saturating_sub(NANOS_PER_SECOND);
+    }
+
+    pub fn is_full(&self, rate_limit: &RateLimit) -> bool {
+        self.nanotokens >= Self::max_nanotokens(rate_limit)
+    }
+
+    fn max_nanotokens(rate_limit: &RateLimit) -> u64 {
+        u64::from(rate_limit.capacity) * NANOS_PER_SECOND
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    #[rstest]
+    fn token_bucket() {
+        let rate_limit = RateLimit {
+            capacity: 2,
+            refill_per_second: 1,
+        };
+        let mut bucket = TokenBucket::new_full(&rate_limit, 0);
+
+        bucket.take_token();
+        bucket.take_token();
+        assert!(!bucket.has_token());
+
+        bucket.refill(&rate_limit, NANOS_PER_SECOND / 2);
+        assert!(!bucket.has_token());
+        bucket.refill(&rate_limit, NANOS_PER_SECOND);
+        assert!(bucket.has_token());
+
+        bucket.refill(&rate_limit, 10 * NANOS_PER_SECOND);
+        assert!(bucket.is_full(&rate_limit));
+        bucket.take_token();
+        bucket.take_token();
+        assert!(!bucket.has_token());
+    }
+
+    #[rstest]
+    fn rate_limit_config_validate() {
+        let rate_limit = RateLimit {
+            capacity: 2,
+            refill_per_second: 1,
+        };
+        let config = RateLimitConfig {
+            per_chat: rate_limit,
+            global: rate_limit,
+        };
+        assert_eq!(config.validate(), Ok(()));
+
+        let config = RateLimitConfig {
+            per_chat: RateLimit {
+                capacity: 0,
+                refill_per_second: 1,
+            },
+            ..config
+        };
+        assert_eq!(
+            config.validate(),
+            Err("Rate limit capacity and refill rate must be positive".to_string())
+        );
+    }
+}
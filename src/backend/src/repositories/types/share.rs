@@ -0,0 +1,125 @@
+use std::{borrow::Cow, fmt, path::PathBuf};
+
+use candid::{CandidType, Deserialize};
+use ic_stable_structures::{storable::Bound, Storable};
+
+use super::{ChatId, DriveId, Migration, SchemaVersion, VersionedStorable};
+
+pub type ShareId = u64;
+
+#[derive(Debug, CandidType, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum SharePermission {
+    ReadOnly,
+    ReadWrite,
+}
+
+impl SharePermission {
+    pub fn is_read_only(&self) -> bool {
+        matches!(self, SharePermission::ReadOnly)
+    }
+}
+
+impl fmt::Display for SharePermission {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                SharePermission::ReadOnly => "read-only",
+                SharePermission::ReadWrite => "read-write",
+            }
+        )
+    }
+}
+
+/// The chat that accepted a share, and the name of the folder in its `/Shared` directory.
+#[derive(Debug, CandidType, Deserialize, Clone, PartialEq, Eq)]
+pub struct ShareRecipient {
+    pub chat_id: ChatId,
+    pub name: String,
+}
+
+/// A folder of a chat's filesystem, granted to another chat.
+#[derive(Debug, CandidType, Deserialize, Clone, PartialEq, Eq)]
+pub struct Share {
+    pub owner_chat_id: ChatId,
+    /// The drive of the shared directory, the owner's main drive when `None`.
+    pub drive_id: Option<DriveId>,
+    pub path: PathBuf,
+    pub permission: SharePermission,
+    /// The token of the deep link. It can only be used once.
+    pub token: String,
+    pub created_at: u64,
+    /// Set when the link is opened.
+    pub recipient: Option<ShareRecipient>,
+}
+
+impl Share {
+    pub fn is_accepted(&self) -> bool {
+        self.recipient.is_some()
+    }
+}
+
+impl VersionedStorable for Share {
+    const SCHEMA_VERSION: SchemaVersion = 1;
+    const MIGRATIONS: &'static [Migration] = &[];
+}
+
+/// Indexes a share by one of the chats involved, either the owner or the recipient.
+/// Keys are ordered by chat first, so that the shares of a chat are contiguous.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ChatShareKey {
+    pub chat_id: ChatId,
+    pub share_id: ShareId,
+}
+
+impl ChatShareKey {
+    pub fn new(chat_id: ChatId, share_id: ShareId) -> Self {
+        Self { chat_id, share_id }
+    }
+
+    /// The lowest and the highest keys of the chat, to iterate over its shares.
+    pub fn chat_bounds(chat_id: &ChatId) -> (Self, Self) {
+        (
+            Self::new(chat_id.clone(), ShareId::MIN),
+            Self::new(chat_id.clone(), ShareId::MAX),
+        )
+    }
+}
+
+impl Storable for ChatShareKey {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        let mut bytes = self.chat_id.to_bytes().into_owned();
+        bytes.extend_from_slice(&self.share_id.to_bytes());
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        let (chat_id_bytes, share_id_bytes) = bytes.split_at(8);
+        Self {
+            chat_id: ChatId::from_bytes(Cow::Borrowed(chat_id_bytes)),
+            share_id: ShareId::from_bytes(Cow::Borrowed(share_id_bytes)),
+        }
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: 16,
+        is_fixed_size: true,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    #[rstest]
+    fn chat_share_key_storable_impl() {
+        let chat_share_key = ChatShareKey::new(ChatId::from(-123i64), 456);
+
+        let serialized_chat_share_key = chat_share_key.to_bytes();
+        let deserialized_chat_share_key = ChatShareKey::from_bytes(serialized_chat_share_key);
+
+        assert_eq!(deserialized_chat_share_key, chat_share_key);
+    }
+}
@@ -0,0 +1,83 @@
+use std::{borrow::Cow, fmt::Display};
+
+use candid::{CandidType, Deserialize};
+use frankenstein::{types::User, updates::UpdateContent};
+use ic_stable_structures::{storable::Bound, Storable};
+
+/// The id of a Telegram user, which is different from the id of the chats the user is in,
+/// except for the private chat with the bot.
+#[derive(Debug, CandidType, Deserialize, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct UserId(pub u64);
+
+impl From<u64> for UserId {
+    fn from(value: u64) -> Self {
+        Self(value)
+    }
+}
+
+impl Display for UserId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Storable for UserId {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        self.0.to_bytes()
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Self(u64::from_bytes(bytes))
+    }
+
+    const BOUND: Bound = u64::BOUND;
+}
+
+impl TryFrom<&UpdateContent> for UserId {
+    type Error = String;
+
+    fn try_from(update_content: &UpdateContent) -> Result<Self, Self::Error> {
+        match update_content {
+            UpdateContent::Message(msg) | UpdateContent::EditedMessage(msg) => msg
+                .from
+                .as_ref()
+                .map(|user| UserId(user.id))
+                .ok_or_else(|| "User not found in message".to_string()),
+            UpdateContent::CallbackQuery(query) => Ok(UserId(query.from.id)),
+            UpdateContent::InlineQuery(query) => Ok(UserId(query.from.id)),
+            UpdateContent::MyChatMember(chat_member_updated)
+            | UpdateContent::ChatMember(chat_member_updated) => {
+                Ok(UserId(chat_member_updated.from.id))
+            }
+            _ => Err("Unsupported update content".to_string()),
+        }
+    }
+}
+
+/// The user that sent the update, if known.
+pub fn update_sender(update_content: &UpdateContent) -> Option<&User> {
+    match update_content {
+        UpdateContent::Message(msg) | UpdateContent::EditedMessage(msg) => msg.from.as_deref(),
+        UpdateContent::CallbackQuery(query) => Some(&query.from),
+        UpdateContent::InlineQuery(query) => Some(&query.from),
+        UpdateContent::MyChatMember(chat_member_updated)
+        | UpdateContent::ChatMember(chat_member_updated) => Some(&chat_member_updated.from),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    #[rstest]
+    fn user_id_storable_impl() {
+        let user_id = UserId(123);
+
+        let serialized_user_id = user_id.to_bytes();
+        let deserialized_user_id = UserId::from_bytes(serialized_user_id);
+
+        assert_eq!(deserialized_user_id, user_id);
+    }
+}
@@ -0,0 +1,254 @@
+use std::{borrow::Cow, fmt, marker::PhantomData};
+
+use candid::{decode_one, encode_one, CandidType};
+use ic_stable_structures::{storable::Bound, Storable};
+use serde::de::DeserializeOwned;
+
+pub type SchemaVersion = u8;
+
+/// Migrates a Candid encoded value from a schema version to the next one.
+pub type Migration = fn(&[u8]) -> Result<Vec<u8>, String>;
+
+/// The first byte of a versioned value, followed by the schema version and the Candid payload.
+/// Values stored before versioning was introduced start with the Candid magic bytes (`DIDL`) instead.
+const VERSIONED_VALUE_MAGIC: u8 = 0xc0;
+
+/// The schema version assigned to values stored before versioning was introduced.
+const UNVERSIONED_SCHEMA_VERSION: SchemaVersion = 1;
+
+/// A value stored in stable memory with its schema version,
+/// so that values written by older releases can be migrated when they are read.
+///
+/// Keys are not versioned, as they must keep a fixed size and ordering.
+pub trait VersionedStorable: CandidType + DeserializeOwned {
+    /// The schema version of the values encoded by the current release.
+    const SCHEMA_VERSION: SchemaVersion;
+
+    /// The registry of migrations from older versions:
+    /// `MIGRATIONS[i]` migrates a value from version `i + 1` to version `i + 2`,
+    /// so it must contain exactly `SCHEMA_VERSION - 1` migrations.
+    const MIGRATIONS: &'static [Migration];
+}
+
+pub fn encode_versioned<T: VersionedStorable>(value: &T) -> Vec<u8> {
+    let mut bytes = vec![VERSIONED_VALUE_MAGIC, T::SCHEMA_VERSION];
+    bytes.extend(encode_one(value).unwrap());
+    bytes
+}
+
+pub fn decode_versioned<T: VersionedStorable>(bytes: &[u8]) -> Result<T, String> {
+    let (version, payload) = match bytes {
+        [VERSIONED_VALUE_MAGIC, version, payload @ ..] => (*version, payload),
+        _ => (UNVERSIONED_SCHEMA_VERSION, bytes),
+    };
+
+    if version == 0 || version > T::SCHEMA_VERSION {
+        return Err(format!(
+            "Unsupported schema version {version}, the current one is {}",
+            T::SCHEMA_VERSION
+        ));
+    }
+
+    let migrations = T::MIGRATIONS
+        .get(usize::from(version - 1)..)
+        .ok_or_else(|| format!("Missing migrations from schema version {version}"))?;
+    let mut payload = Cow::Borrowed(payload);
+    for migration in migrations {
+        payload = Cow::Owned(migration(&payload)?);
+    }
+
+    decode_one(&payload).map_err(|err| err.to_string())
+}
+
+/// The raw bytes of a [VersionedStorable] value, as they are stored in stable memory.
+///
+/// Decoding is deferred to [VersionedValue::decode], so that a value that
+/// can't be decoded anymore doesn't trap the canister when it's read.
+pub struct VersionedValue<T> {
+    bytes: Vec<u8>,
+    _value: PhantomData<T>,
+}
+
+impl<T: VersionedStorable> VersionedValue<T> {
+    pub fn new(value: &T) -> Self {
+        Self {
+            bytes: encode_versioned(value),
+            _value: PhantomData,
+        }
+    }
+
+    pub fn decode(&self) -> Result<T, String> {
+        decode_versioned(&self.bytes)
+    }
+}
+
+impl<T> Clone for VersionedValue<T> {
+    fn clone(&self) -> Self {
+        Self {
+            bytes: self.bytes.clone(),
+            _value: PhantomData,
+        }
+    }
+}
+
+impl<T> fmt::Debug for VersionedValue<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "VersionedValue({} bytes)", self.bytes.len())
+    }
+}
+
+impl<T> Storable for VersionedValue<T> {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Borrowed(&self.bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Self {
+            bytes: bytes.into_owned(),
+            _value: PhantomData,
+        }
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::{ChatSession, Journal, StoredFileSystemNode};
+    use candid::Deserialize;
+    use rstest::*;
+
+    #[derive(Debug, CandidType, Deserialize, PartialEq, Eq)]
+    struct TestValueV1 {
+        name: String,
+    }
+
+    #[derive(Debug, CandidType, Deserialize, PartialEq, Eq)]
+    struct TestValueV2 {
+        first_name: String,
+    }
+
+    #[derive(Debug, CandidType, Deserialize, PartialEq, Eq)]
+    struct TestValue {
+        first_name: String,
+        last_name: Option<String>,
+    }
+
+    impl VersionedStorable for TestValueV1 {
+        const SCHEMA_VERSION: SchemaVersion = 1;
+        const MIGRATIONS: &'static [Migration] = &[];
+    }
+
+    impl VersionedStorable for TestValue {
+        const SCHEMA_VERSION: SchemaVersion = 3;
+        const MIGRATIONS: &'static [Migration] = &[migrate_v1_to_v2, migrate_v2_to_v3];
+    }
+
+    fn migrate_v1_to_v2(bytes: &[u8]) -> Result<Vec<u8>, String> {
+        let value: TestValueV1 = decode_one(bytes).map_err(|err| err.to_string())?;
+        encode_one(TestValueV2 {
+            first_name: value.name,
+        })
+        .map_err(|err| err.to_string())
+    }
+
+    fn migrate_v2_to_v3(bytes: &[u8]) -> Result<Vec<u8>, String> {
+        let value: TestValueV2 = decode_one(bytes).map_err(|err| err.to_string())?;
+        encode_one(TestValue {
+            first_name: value.first_name,
+            last_name: None,
+        })
+        .map_err(|err| err.to_string())
+    }
+
+    fn assert_migrations_registered<T: VersionedStorable>() {
+        assert_eq!(T::MIGRATIONS.len(), usize::from(T::SCHEMA_VERSION - 1));
+    }
+
+    #[rstest]
+    fn stored_types_register_all_migrations() {
+        assert_migrations_registered::<ChatSession>();
+        assert_migrations_registered::<Journal>();
+        assert_migrations_registered::<StoredFileSystemNode>();
+        assert_migrations_registered::<TestValue>();
+    }
+
+    #[rstest]
+    fn versioned_round_trip() {
+        let value = TestValue {
+            first_name: "John".to_string(),
+            last_name: Some("Doe".to_string()),
+        };
+
+        let bytes = encode_versioned(&value);
+
+        assert_eq!(&bytes[..2], &[VERSIONED_VALUE_MAGIC, 3]);
+        assert_eq!(decode_versioned::<TestValue>(&bytes), Ok(value));
+    }
+
+    #[rstest]
+    fn versioned_migrates_older_versions() {
+        let bytes = encode_versioned(&TestValueV1 {
+            name: "John".to_string(),
+        });
+
+        assert_eq!(
+            decode_versioned::<TestValue>(&bytes),
+            Ok(TestValue {
+                first_name: "John".to_string(),
+                last_name: None,
+            })
+        );
+    }
+
+    #[rstest]
+    fn versioned_migrates_unversioned_values() {
+        let bytes = encode_one(TestValueV1 {
+            name: "John".to_string(),
+        })
+        .unwrap();
+
+        assert_eq!(
+            decode_versioned::<TestValue>(&bytes),
+            Ok(TestValue {
+                first_name: "John".to_string(),
+                last_name: None,
+            })
+        );
+    }
+
+    #[rstest]
+    fn versioned_rejects_newer_versions() {
+        let bytes = encode_versioned(&TestValue {
+            first_name: "John".to_string(),
+            last_name: None,
+        });
+
+        assert_eq!(
+            decode_versioned::<TestValueV1>(&bytes),
+            Err("Unsupported schema version 3, the current one is 1".to_string())
+        );
+    }
+
+    #[rstest]
+    fn versioned_value_storable_impl() {
+        let value = TestValue {
+            first_name: "John".to_string(),
+            last_name: Some("Doe".to_string()),
+        };
+
+        let stored_value = VersionedValue::new(&value);
+        let deserialized_value = VersionedValue::<TestValue>::from_bytes(stored_value.to_bytes());
+
+        assert_eq!(deserialized_value.decode(), Ok(value));
+    }
+
+    #[rstest]
+    fn versioned_value_decode_corrupted() {
+        let stored_value =
+            VersionedValue::<TestValue>::from_bytes(Cow::Owned(vec![VERSIONED_VALUE_MAGIC, 3, 1]));
+
+        assert!(stored_value.decode().is_err());
+    }
+}
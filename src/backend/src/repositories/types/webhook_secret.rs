@@ -0,0 +1,94 @@
+use candid::{CandidType, Deserialize};
+
+use super::{Config, Migration, SchemaVersion, VersionedStorable};
+
+/// The maximum length of a webhook secret allowed by Telegram.
+const MAX_WEBHOOK_SECRET_LENGTH: usize = 256;
+
+/// A secret that is still accepted while Telegram switches to a new one.
+#[derive(Debug, CandidType, Deserialize, Clone, PartialEq, Eq)]
+pub struct PreviousWebhookSecret {
+    pub secret: String,
+    pub expires_at: u64,
+}
+
+/// The secrets that Telegram sends in the `X-Telegram-Bot-Api-Secret-Token` header of the webhook requests.
+#[derive(Debug, CandidType, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct WebhookSecrets {
+    pub current: Option<String>,
+    pub previous: Option<PreviousWebhookSecret>,
+}
+
+impl VersionedStorable for WebhookSecrets {
+    const SCHEMA_VERSION: SchemaVersion = 1;
+    const MIGRATIONS: &'static [Migration] = &[];
+}
+
+#[derive(Debug, CandidType, Deserialize, Clone, PartialEq, Eq)]
+pub struct SetWebhookSecretRequest {
+    pub secret: String,
+    /// How long the current secret is still accepted, the configured period is used when not specified.
+    pub rotation_period_seconds: Option<u64>,
+}
+
+/// The arguments passed when the canister is installed or upgraded.
+#[derive(Debug, CandidType, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct CanisterArgs {
+    pub webhook_secret: Option<String>,
+    /// Replaces the whole config. The current config is kept when not set.
+    pub config: Option<Config>,
+}
+
+/// Checks the format that Telegram requires for the webhook secret:
+/// 1-256 characters, only `A-Z`, `a-z`, `0-9`, `_` and `-` are allowed.
+pub fn validate_webhook_secret(secret: &str) -> Result<(), String> {
+    if secret.is_empty() || secret.len() > MAX_WEBHOOK_SECRET_LENGTH {
+        return Err(format!(
+            "Webhook secret must be between 1 and {MAX_WEBHOOK_SECRET_LENGTH} characters long"
+        ));
+    }
+    if !secret
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        return Err(
+            "Webhook secret can only contain the characters A-Z, a-z, 0-9, _ and -".to_string(),
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::{decode_versioned, encode_versioned};
+    use rstest::*;
+
+    #[rstest]
+    fn storable_impl() {
+        let webhook_secrets = WebhookSecrets {
+            current: Some("new-secret".to_string()),
+            previous: Some(PreviousWebhookSecret {
+                secret: "old-secret".to_string(),
+                expires_at: 1_700_000_000_000_000_000,
+            }),
+        };
+
+        let serialized_webhook_secrets = encode_versioned(&webhook_secrets);
+        let deserialized_webhook_secrets = decode_versioned(&serialized_webhook_secrets);
+
+        assert_eq!(deserialized_webhook_secrets, Ok(webhook_secrets));
+    }
+
+    #[rstest]
+    #[case("a", true)]
+    #[case("Secret_token-123", true)]
+    #[case(&"a".repeat(256), true)]
+    #[case("", false)]
+    #[case(&"a".repeat(257), false)]
+    #[case("secret token", false)]
+    #[case("secret/token", false)]
+    fn validate_webhook_secret_format(#[case] secret: &str, #[case] is_valid: bool) {
+        assert_eq!(validate_webhook_secret(secret).is_ok(), is_valid);
+    }
+}
@@ -0,0 +1,60 @@
+use std::cell::RefCell;
+
+use crate::custom_print;
+
+use super::{init_webhook_secret, VersionedValue, WebhookSecretMemory, WebhookSecrets};
+
+pub trait WebhookSecretRepository {
+    fn get_webhook_secrets(&self) -> WebhookSecrets;
+
+    fn set_webhook_secrets(&self, webhook_secrets: WebhookSecrets);
+}
+
+pub struct WebhookSecretRepositoryImpl {}
+
+impl Default for WebhookSecretRepositoryImpl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WebhookSecretRepository for WebhookSecretRepositoryImpl {
+    fn get_webhook_secrets(&self) -> WebhookSecrets {
+        STATE
+            .with_borrow(|s| s.webhook_secrets.get().decode())
+            .inspect_err(|err| {
+                custom_print!("Discarding undecodable webhook secrets: {err}");
+            })
+            .unwrap_or_default()
+    }
+
+    fn set_webhook_secrets(&self, webhook_secrets: WebhookSecrets) {
+        STATE.with_borrow_mut(|s| {
+            s.webhook_secrets
+                .set(VersionedValue::new(&webhook_secrets))
+                .unwrap()
+        });
+    }
+}
+
+impl WebhookSecretRepositoryImpl {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+struct WebhookSecretState {
+    webhook_secrets: WebhookSecretMemory,
+}
+
+impl Default for WebhookSecretState {
+    fn default() -> Self {
+        Self {
+            webhook_secrets: init_webhook_secret(),
+        }
+    }
+}
+
+thread_local! {
+    static STATE: RefCell<WebhookSecretState> = RefCell::new(WebhookSecretState::default());
+}
@@ -1,26 +1,45 @@
 use candid::Principal;
 use ic_cdk::{api::is_controller, trap};
+use sha2::{Digest, Sha256};
 
-use crate::repositories::HttpUpdateRequest;
+use crate::{
+    repositories::{
+        validate_webhook_secret, ConfigRepository, ConfigRepositoryImpl, HttpUpdateRequest,
+        PreviousWebhookSecret, SetWebhookSecretRequest, WebhookSecretRepository,
+        WebhookSecretRepositoryImpl,
+    },
+    utils::get_current_time,
+};
 
 const TELEGRAM_WEBHOOK_SECRET_TOKEN_HEADER: &str = "x-telegram-bot-api-secret-token";
-const TELEGRAM_WEBHOOK_SECRET_TOKEN: &str = env!("TELEGRAM_SECRET_TOKEN");
 
 pub trait AccessControlService {
     fn assert_caller_is_controller(&self, calling_principal: &Principal);
 
     fn assert_http_request_is_authorized(&self, req: &HttpUpdateRequest) -> bool;
+
+    /// Replaces the webhook secret, still accepting the current one for the rotation period,
+    /// so that the requests sent by Telegram before it switches to the new secret are not rejected.
+    fn set_webhook_secret(&self, req: SetWebhookSecretRequest) -> Result<(), String>;
 }
 
-pub struct AccessControlServiceImpl {}
+pub struct AccessControlServiceImpl<T: WebhookSecretRepository, C: ConfigRepository> {
+    webhook_secret_repository: T,
+    config_repository: C,
+}
 
-impl Default for AccessControlServiceImpl {
+impl Default for AccessControlServiceImpl<WebhookSecretRepositoryImpl, ConfigRepositoryImpl> {
     fn default() -> Self {
-        Self::new()
+        Self::new(
+            WebhookSecretRepositoryImpl::default(),
+            ConfigRepositoryImpl::default(),
+        )
     }
 }
 
-impl AccessControlService for AccessControlServiceImpl {
+impl<T: WebhookSecretRepository, C: ConfigRepository> AccessControlService
+    for AccessControlServiceImpl<T, C>
+{
     fn assert_caller_is_controller(&self, calling_principal: &Principal) {
         if !is_controller(calling_principal) {
             trap("caller is not a controller");
@@ -28,15 +47,168 @@ impl AccessControlService for AccessControlServiceImpl {
     }
 
     fn assert_http_request_is_authorized(&self, req: &HttpUpdateRequest) -> bool {
+        let webhook_secrets = self.webhook_secret_repository.get_webhook_secrets();
+        let now = get_current_time();
+        let accepted_secrets: Vec<&String> = webhook_secrets
+            .current
+            .iter()
+            .chain(
+                webhook_secrets
+                    .previous
+                    .iter()
+                    .filter(|previous| now < previous.expires_at)
+                    .map(|previous| &previous.secret),
+            )
+            .collect();
+
         req.headers.iter().any(|header| {
             header.0.to_lowercase() == TELEGRAM_WEBHOOK_SECRET_TOKEN_HEADER
-                && header.1 == TELEGRAM_WEBHOOK_SECRET_TOKEN
+                // every secret is compared, so that the timing doesn't reveal which one matched
+                && accepted_secrets
+                    .iter()
+                    .fold(false, |matched, secret| {
+                        constant_time_eq(&header.1, secret) | matched
+                    })
         })
     }
+
+    fn set_webhook_secret(&self, req: SetWebhookSecretRequest) -> Result<(), String> {
+        validate_webhook_secret(&req.secret)?;
+
+        let mut webhook_secrets = self.webhook_secret_repository.get_webhook_secrets();
+        if webhook_secrets.current.as_ref() == Some(&req.secret) {
+            return Ok(());
+        }
+
+        let rotation_period_seconds = req.rotation_period_seconds.unwrap_or_else(|| {
+            self.config_repository
+                .get_config()
+                .limits
+                .webhook_secret_rotation_period_seconds
+        });
+        webhook_secrets.previous = webhook_secrets
+            .current
+            .take()
+            .filter(|_| rotation_period_seconds > 0)
+            .map(|secret| PreviousWebhookSecret {
+                secret,
+                expires_at: get_current_time()
+                    .saturating_add(rotation_period_seconds.saturating_mul(1_000_000_000)),
+            });
+        webhook_secrets.current = Some(req.secret);
+
+        self.webhook_secret_repository
+            .set_webhook_secrets(webhook_secrets);
+        Ok(())
+    }
 }
 
-impl AccessControlServiceImpl {
-    fn new() -> Self {
-        Self {}
+impl<T: WebhookSecretRepository, C: ConfigRepository> AccessControlServiceImpl<T, C> {
+    fn new(webhook_secret_repository: T, config_repository: C) -> Self {
+        Self {
+            webhook_secret_repository,
+            config_repository,
+        }
+    }
+}
+
+/// Compares the digests of the values, so that the time taken
+/// doesn't depend on the position of the first difference, nor on the length of the secret.
+pub(crate) fn constant_time_eq(value: &str, secret: &str) -> bool {
+    let value_digest = Sha256::digest(value.as_bytes());
+    let secret_digest = Sha256::digest(secret.as_bytes());
+
+    value_digest
+        .iter()
+        .zip(secret_digest.iter())
+        .fold(0, |diff, (a, b)| diff | (a ^ b))
+        == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::{HeaderField, WebhookSecrets};
+    use rstest::*;
+
+    fn webhook_request(secret: &str) -> HttpUpdateRequest {
+        HttpUpdateRequest {
+            method: "POST".to_string(),
+            url: "/".to_string(),
+            headers: vec![HeaderField(
+                "X-Telegram-Bot-Api-Secret-Token".to_string(),
+                secret.to_string(),
+            )],
+            body: vec![],
+        }
+    }
+
+    fn set_webhook_secret(
+        service: &impl AccessControlService,
+        secret: &str,
+        rotation_period_seconds: Option<u64>,
+    ) {
+        service
+            .set_webhook_secret(SetWebhookSecretRequest {
+                secret: secret.to_string(),
+                rotation_period_seconds,
+            })
+            .unwrap();
+    }
+
+    #[rstest]
+    fn assert_http_request_is_authorized_without_secret() {
+        let service = AccessControlServiceImpl::default();
+
+        assert!(!service.assert_http_request_is_authorized(&webhook_request("")));
+        assert!(!service.assert_http_request_is_authorized(&webhook_request("secret")));
+    }
+
+    #[rstest]
+    fn set_webhook_secret_rotation() {
+        let service = AccessControlServiceImpl::default();
+        set_webhook_secret(&service, "old-secret", None);
+        assert!(service.assert_http_request_is_authorized(&webhook_request("old-secret")));
+        assert!(!service.assert_http_request_is_authorized(&webhook_request("old-secre")));
+
+        set_webhook_secret(&service, "new-secret", None);
+        assert!(service.assert_http_request_is_authorized(&webhook_request("old-secret")));
+        assert!(service.assert_http_request_is_authorized(&webhook_request("new-secret")));
+
+        // the old secret is rejected as soon as a secret is set without a rotation period
+        set_webhook_secret(&service, "newer-secret", Some(0));
+        assert!(!service.assert_http_request_is_authorized(&webhook_request("old-secret")));
+        assert!(!service.assert_http_request_is_authorized(&webhook_request("new-secret")));
+        assert!(service.assert_http_request_is_authorized(&webhook_request("newer-secret")));
+    }
+
+    #[rstest]
+    fn assert_http_request_is_authorized_expired_secret() {
+        WebhookSecretRepositoryImpl::default().set_webhook_secrets(WebhookSecrets {
+            current: Some("new-secret".to_string()),
+            previous: Some(PreviousWebhookSecret {
+                secret: "old-secret".to_string(),
+                expires_at: get_current_time() - 1,
+            }),
+        });
+        let service = AccessControlServiceImpl::default();
+
+        assert!(!service.assert_http_request_is_authorized(&webhook_request("old-secret")));
+        assert!(service.assert_http_request_is_authorized(&webhook_request("new-secret")));
+    }
+
+    #[rstest]
+    fn set_webhook_secret_invalid() {
+        let service = AccessControlServiceImpl::default();
+
+        assert_eq!(
+            service.set_webhook_secret(SetWebhookSecretRequest {
+                secret: "invalid secret".to_string(),
+                rotation_period_seconds: None,
+            }),
+            Err(
+                "Webhook secret can only contain the characters A-Z, a-z, 0-9, _ and -".to_string()
+            )
+        );
     }
 }
@@ -0,0 +1,239 @@
+use frankenstein::updates::UpdateContent;
+
+use crate::repositories::{
+    command_argument, validate_invite_code, AccessDecision, AccessPolicy, AccessPolicyEntries,
+    AccessPolicyMode, AccessPolicyRepository, AccessPolicyRepositoryImpl, ChatId, Command, UserId,
+};
+
+pub trait AccessPolicyService {
+    /// Checks whether the sender of the update can use the bot.
+    /// A rejected user that sends `/join` with the invite code is added to the allowlist.
+    fn check_update(&self, chat_id: &ChatId, update_content: &UpdateContent) -> AccessDecision;
+
+    fn get_access_policy(&self) -> AccessPolicy;
+
+    fn set_mode(&self, mode: AccessPolicyMode);
+
+    fn add_entries(&self, entries: AccessPolicyEntries);
+
+    fn remove_entries(&self, entries: AccessPolicyEntries);
+
+    /// Enables the `/join` command with the given code, or disables it.
+    fn set_invite_code(&self, invite_code: Option<String>) -> Result<(), String>;
+}
+
+pub struct AccessPolicyServiceImpl<T: AccessPolicyRepository> {
+    access_policy_repository: T,
+}
+
+impl Default for AccessPolicyServiceImpl<AccessPolicyRepositoryImpl> {
+    fn default() -> Self {
+        Self::new(AccessPolicyRepositoryImpl::default())
+    }
+}
+
+impl<T: AccessPolicyRepository> AccessPolicyService for AccessPolicyServiceImpl<T> {
+    fn check_update(&self, chat_id: &ChatId, update_content: &UpdateContent) -> AccessDecision {
+        let mut access_policy = self.access_policy_repository.get_access_policy();
+        let user_id = UserId::try_from(update_content).ok();
+        if access_policy.is_allowed(user_id.as_ref(), chat_id) {
+            return AccessDecision::Allowed;
+        }
+
+        // the invite code only lets users in an allowlist, denied users stay denied
+        let (AccessPolicyMode::Allowlist, Some(user_id), UpdateContent::Message(msg)) =
+            (access_policy.mode, user_id, update_content)
+        else {
+            return AccessDecision::Rejected;
+        };
+        let is_join_command = matches!(Command::try_from(msg.clone()), Ok(Command::Join));
+        if !is_join_command
+            || access_policy.invite_code.is_none()
+            || command_argument(msg) != access_policy.invite_code.as_deref()
+        {
+            return AccessDecision::Rejected;
+        }
+
+        access_policy.user_ids.insert(user_id);
+        self.access_policy_repository
+            .set_access_policy(access_policy);
+        AccessDecision::Enrolled
+    }
+
+    fn get_access_policy(&self) -> AccessPolicy {
+        self.access_policy_repository.get_access_policy()
+    }
+
+    fn set_mode(&self, mode: AccessPolicyMode) {
+        self.update_access_policy(|access_policy| access_policy.mode = mode);
+    }
+
+    fn add_entries(&self, entries: AccessPolicyEntries) {
+        self.update_access_policy(|access_policy| {
+            access_policy.user_ids.extend(entries.user_ids);
+            access_policy.chat_ids.extend(entries.chat_ids);
+        });
+    }
+
+    fn remove_entries(&self, entries: AccessPolicyEntries) {
+        self.update_access_policy(|access_policy| {
+            for user_id in &entries.user_ids {
+                access_policy.user_ids.remove(user_id);
+            }
+            for chat_id in &entries.chat_ids {
+                access_policy.chat_ids.remove(chat_id);
+            }
+        });
+    }
+
+    fn set_invite_code(&self, invite_code: Option<String>) -> Result<(), String> {
+        if let Some(invite_code) = &invite_code {
+            validate_invite_code(invite_code)?;
+        }
+
+        self.update_access_policy(|access_policy| access_policy.invite_code = invite_code);
+        Ok(())
+    }
+}
+
+impl<T: AccessPolicyRepository> AccessPolicyServiceImpl<T> {
+    fn new(access_policy_repository: T) -> Self {
+        Self {
+            access_policy_repository,
+        }
+    }
+
+    fn update_access_policy(&self, f: impl FnOnce(&mut AccessPolicy)) {
+        let mut access_policy = self.access_policy_repository.get_access_policy();
+        f(&mut access_policy);
+        self.access_policy_repository
+            .set_access_policy(access_policy);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use frankenstein::types::{Chat, ChatType, Message, MessageEntity, MessageEntityType, User};
+    use rstest::*;
+
+    const CHAT_ID: i64 = 123;
+    const USER_ID: u64 = 456;
+
+    fn message_update(text: &str) -> UpdateContent {
+        let entities = text.starts_with('/').then(|| {
+            vec![MessageEntity::builder()
+                .type_field(MessageEntityType::BotCommand)
+                .offset(0)
+                .length(text.split(' ').next().unwrap().len() as u16)
+                .build()]
+        });
+
+        UpdateContent::Message(
+            Message::builder()
+                .message_id(1)
+                .date(0)
+                .chat(Box::new(
+                    Chat::builder()
+                        .id(CHAT_ID)
+                        .type_field(ChatType::Private)
+                        .build(),
+                ))
+                .from(Box::new(
+                    User::builder()
+                        .id(USER_ID)
+                        .is_bot(false)
+                        .first_name("John")
+                        .build(),
+                ))
+                .text(text)
+                .maybe_entities(entities)
+                .build(),
+        )
+    }
+
+    #[rstest]
+    fn check_update_allowlist() {
+        let service = AccessPolicyServiceImpl::default();
+        let chat_id = ChatId::from(CHAT_ID);
+        assert_eq!(
+            service.check_update(&chat_id, &message_update("/start")),
+            AccessDecision::Allowed
+        );
+
+        service.set_mode(AccessPolicyMode::Allowlist);
+        assert_eq!(
+            service.check_update(&chat_id, &message_update("/start")),
+            AccessDecision::Rejected
+        );
+
+        service.add_entries(AccessPolicyEntries {
+            user_ids: vec![UserId(USER_ID)],
+            chat_ids: vec![],
+        });
+        assert_eq!(
+            service.check_update(&chat_id, &message_update("/start")),
+            AccessDecision::Allowed
+        );
+
+        service.remove_entries(AccessPolicyEntries {
+            user_ids: vec![UserId(USER_ID)],
+            chat_ids: vec![],
+        });
+        assert_eq!(
+            service.check_update(&chat_id, &message_update("/start")),
+            AccessDecision::Rejected
+        );
+    }
+
+    #[rstest]
+    fn check_update_invite_code() {
+        let service = AccessPolicyServiceImpl::default();
+        let chat_id = ChatId::from(CHAT_ID);
+        service.set_mode(AccessPolicyMode::Allowlist);
+        // without an invite code, nobody can join
+        assert_eq!(
+            service.check_update(&chat_id, &message_update("/join")),
+            AccessDecision::Rejected
+        );
+
+        service
+            .set_invite_code(Some("invite-code".to_string()))
+            .unwrap();
+        assert_eq!(
+            service.check_update(&chat_id, &message_update("/join wrong-code")),
+            AccessDecision::Rejected
+        );
+        assert_eq!(
+            service.check_update(&chat_id, &message_update("/join invite-code")),
+            AccessDecision::Enrolled
+        );
+        assert_eq!(
+            service.check_update(&chat_id, &message_update("/start")),
+            AccessDecision::Allowed
+        );
+        assert!(service
+            .get_access_policy()
+            .user_ids
+            .contains(&UserId(USER_ID)));
+    }
+
+    #[rstest]
+    fn check_update_denylist_ignores_invite_code() {
+        let service = AccessPolicyServiceImpl::default();
+        let chat_id = ChatId::from(CHAT_ID);
+        service.set_mode(AccessPolicyMode::Denylist);
+        service
+            .set_invite_code(Some("invite-code".to_string()))
+            .unwrap();
+        service.add_entries(AccessPolicyEntries {
+            user_ids: vec![],
+            chat_ids: vec![chat_id.clone()],
+        });
+
+        assert_eq!(
+            service.check_update(&chat_id, &message_update("/join invite-code")),
+            AccessDecision::Rejected
+        );
+    }
+}
@@ -0,0 +1,172 @@
+use crate::{
+    repositories::{
+        ChatConsistencyReport, ChatId, ChatMemberId, ChatMemberSession, ChatSession,
+        ChatSessionRepository, ChatSessionRepositoryImpl, CheckChatsConsistencyResponse,
+        FileSystemNode, FilesystemRepository, FilesystemRepositoryImpl, ListChatIdsRequest,
+        ListChatIdsResponse, QuarantinedFileSystemNode,
+    },
+    utils::{DEFAULT_CHAT_IDS_PAGE_SIZE, MAX_CHAT_IDS_PAGE_SIZE},
+};
+
+pub trait AdminService {
+    /// Lists the ids of the chats that have a chat session or a filesystem.
+    fn list_chat_ids(&self, req: ListChatIdsRequest) -> ListChatIdsResponse;
+
+    /// Returns the sessions of all the members of the chat.
+    fn get_chat_sessions(&self, chat_id: &ChatId) -> Vec<ChatMemberSession>;
+
+    fn get_filesystem(&self, chat_id: &ChatId) -> Result<Option<FileSystemNode>, String>;
+
+    fn get_quarantined_filesystem(&self, chat_id: &ChatId) -> Vec<QuarantinedFileSystemNode>;
+
+    /// Resets the sessions of all the members of the chat.
+    fn reset_chat_session(&self, chat_id: ChatId);
+
+    /// Returns the issues found in the chat's stored data.
+    fn check_chat_consistency(&self, chat_id: &ChatId) -> Vec<String>;
+
+    fn check_chats_consistency(&self, req: ListChatIdsRequest) -> CheckChatsConsistencyResponse;
+}
+
+pub struct AdminServiceImpl<T: ChatSessionRepository, F: FilesystemRepository> {
+    chat_session_repository: T,
+    filesystem_repository: F,
+}
+
+impl Default for AdminServiceImpl<ChatSessionRepositoryImpl, FilesystemRepositoryImpl> {
+    fn default() -> Self {
+        Self::new(
+            ChatSessionRepositoryImpl::default(),
+            FilesystemRepositoryImpl::default(),
+        )
+    }
+}
+
+impl<T: ChatSessionRepository, F: FilesystemRepository> AdminService for AdminServiceImpl<T, F> {
+    fn list_chat_ids(&self, req: ListChatIdsRequest) -> ListChatIdsResponse {
+        let limit = req
+            .limit
+            .unwrap_or(DEFAULT_CHAT_IDS_PAGE_SIZE)
+            .clamp(1, MAX_CHAT_IDS_PAGE_SIZE) as usize;
+        let start_after = req.start_after.as_ref();
+
+        // a chat may have a session without a filesystem and vice versa,
+        // so the first ids of both are merged to get the first ids of the union
+        let mut chat_ids = self
+            .chat_session_repository
+            .list_chat_ids(start_after, limit);
+        chat_ids.extend(self.filesystem_repository.list_chat_ids(start_after, limit));
+        chat_ids.sort();
+        chat_ids.dedup();
+
+        let next_start_after = if chat_ids.len() >= limit {
+            chat_ids.truncate(limit);
+            chat_ids.last().cloned()
+        } else {
+            None
+        };
+
+        ListChatIdsResponse {
+            chat_ids,
+            next_start_after,
+        }
+    }
+
+    fn get_chat_sessions(&self, chat_id: &ChatId) -> Vec<ChatMemberSession> {
+        self.chat_session_repository
+            .get_chat_sessions_by_chat_id(chat_id)
+    }
+
+    fn get_filesystem(&self, chat_id: &ChatId) -> Result<Option<FileSystemNode>, String> {
+        self.filesystem_repository
+            .get_filesystem_by_chat_id(chat_id)
+            .map(|filesystem| filesystem.export())
+            .transpose()
+    }
+
+    fn get_quarantined_filesystem(&self, chat_id: &ChatId) -> Vec<QuarantinedFileSystemNode> {
+        self.filesystem_repository
+            .get_quarantined_nodes_by_chat_id(chat_id)
+    }
+
+    fn reset_chat_session(&self, chat_id: ChatId) {
+        for chat_member_session in self.get_chat_sessions(&chat_id) {
+            self.chat_session_repository.set_chat_session(
+                ChatMemberId::new(chat_id.clone(), chat_member_session.user_id),
+                ChatSession::default(),
+            );
+        }
+    }
+
+    fn check_chat_consistency(&self, chat_id: &ChatId) -> Vec<String> {
+        let mut issues = vec![];
+
+        let filesystem = self
+            .filesystem_repository
+            .get_filesystem_by_chat_id(chat_id);
+        if let Some(filesystem) = &filesystem {
+            let (reachable_node_ids, filesystem_issues) = filesystem.check_consistency();
+            issues.extend(filesystem_issues);
+
+            let stored_node_ids = self.filesystem_repository.get_node_ids_by_chat_id(chat_id);
+            issues.extend(
+                stored_node_ids
+                    .difference(&reachable_node_ids)
+                    .map(|node_id| format!("Node {node_id} is not reachable from the root")),
+            );
+        }
+
+        for ChatMemberSession {
+            user_id,
+            chat_session,
+        } in self.get_chat_sessions(chat_id)
+        {
+            let current_path = chat_session.current_path();
+            let current_path_exists = match &filesystem {
+                Some(filesystem) => filesystem.get_node(current_path).is_ok(),
+                None => current_path.parent().is_none(),
+            };
+            if !current_path_exists {
+                issues.push(format!(
+                    "Current path {} of user {user_id} does not exist",
+                    current_path.display()
+                ));
+            }
+        }
+
+        let quarantined_nodes_count = self.get_quarantined_filesystem(chat_id).len();
+        if quarantined_nodes_count > 0 {
+            issues.push(format!(
+                "{quarantined_nodes_count} filesystem nodes are quarantined"
+            ));
+        }
+
+        issues
+    }
+
+    fn check_chats_consistency(&self, req: ListChatIdsRequest) -> CheckChatsConsistencyResponse {
+        let page = self.list_chat_ids(req);
+        let reports = page
+            .chat_ids
+            .into_iter()
+            .filter_map(|chat_id| {
+                let issues = self.check_chat_consistency(&chat_id);
+                (!issues.is_empty()).then_some(ChatConsistencyReport { chat_id, issues })
+            })
+            .collect();
+
+        CheckChatsConsistencyResponse {
+            reports,
+            next_start_after: page.next_start_after,
+        }
+    }
+}
+
+impl<T: ChatSessionRepository, F: FilesystemRepository> AdminServiceImpl<T, F> {
+    fn new(chat_session_repository: T, filesystem_repository: F) -> Self {
+        Self {
+            chat_session_repository,
+            filesystem_repository,
+        }
+    }
+}
@@ -0,0 +1,342 @@
+use ic_cdk::trap;
+
+use crate::{
+    repositories::{
+        BackupChunk, BackupChunkRequest, BackupManifest, BackupRepository, BackupRepositoryImpl,
+        MaintenanceMode,
+    },
+    utils::BACKUP_CHUNK_SIZE,
+};
+
+pub trait BackupService {
+    /// Stops processing updates and returns the regions to back up.
+    /// Calling it again while a backup is in progress resumes the same backup.
+    fn start_backup(&self) -> Result<BackupManifest, String>;
+
+    fn get_backup_chunk(&self, req: BackupChunkRequest) -> Result<BackupChunk, String>;
+
+    fn finish_backup(&self) -> Result<(), String>;
+
+    /// Stops processing updates and grows the regions to the sizes in the manifest.
+    /// The regions must not hold any data, so a restore can only target a fresh canister.
+    fn start_restore(&self, manifest: BackupManifest) -> Result<(), String>;
+
+    fn restore_chunk(&self, chunk: BackupChunk) -> Result<(), String>;
+
+    /// Keeps the canister in maintenance mode until it's upgraded, so that the restored
+    /// stable structures are loaded again in `post_upgrade`.
+    fn finish_restore(&self) -> Result<(), String>;
+
+    fn is_in_maintenance_mode(&self) -> bool;
+
+    /// Traps while a backup or a restore is in progress, to keep the stable memory unchanged.
+    fn assert_not_in_maintenance_mode(&self);
+}
+
+pub struct BackupServiceImpl<T: BackupRepository> {
+    backup_repository: T,
+}
+
+impl Default for BackupServiceImpl<BackupRepositoryImpl> {
+    fn default() -> Self {
+        Self::new(BackupRepositoryImpl::default())
+    }
+}
+
+impl<T: BackupRepository> BackupService for BackupServiceImpl<T> {
+    fn start_backup(&self) -> Result<BackupManifest, String> {
+        match self.backup_repository.get_maintenance_mode() {
+            Some(MaintenanceMode::Restore) => return Err("A restore is in progress".to_string()),
+            Some(MaintenanceMode::Restored) => return Err(upgrade_required_error()),
+            Some(MaintenanceMode::Backup) | None => {}
+        }
+        self.backup_repository
+            .set_maintenance_mode(Some(MaintenanceMode::Backup));
+
+        Ok(BackupManifest {
+            chunk_size: BACKUP_CHUNK_SIZE,
+            regions: self.backup_repository.get_memory_regions(),
+        })
+    }
+
+    fn get_backup_chunk(&self, req: BackupChunkRequest) -> Result<BackupChunk, String> {
+        self.assert_maintenance_mode(MaintenanceMode::Backup)?;
+
+        let region = self
+            .backup_repository
+            .get_memory_regions()
+            .into_iter()
+            .find(|region| region.memory_id == req.memory_id)
+            .ok_or_else(|| format!("Unknown memory region {}", req.memory_id))?;
+        if req.offset >= region.size {
+            return Err(format!(
+                "Offset {} out of bounds of memory region {}",
+                req.offset, req.memory_id
+            ));
+        }
+
+        let length = BACKUP_CHUNK_SIZE.min(region.size - req.offset);
+        let data = self
+            .backup_repository
+            .read_memory_region(req.memory_id, req.offset, length)?;
+        Ok(BackupChunk::new(req.memory_id, req.offset, data))
+    }
+
+    fn finish_backup(&self) -> Result<(), String> {
+        self.assert_maintenance_mode(MaintenanceMode::Backup)?;
+        self.backup_repository.set_maintenance_mode(None);
+        Ok(())
+    }
+
+    fn start_restore(&self, manifest: BackupManifest) -> Result<(), String> {
+        if self.backup_repository.get_maintenance_mode().is_some() {
+            return Err("A backup or a restore is already in progress".to_string());
+        }
+
+        let regions = self.backup_repository.get_memory_regions();
+        for region in &manifest.regions {
+            if !regions
+                .iter()
+                .any(|current_region| current_region.memory_id == region.memory_id)
+            {
+                return Err(format!("Unknown memory region {}", region.memory_id));
+            }
+            // the structures created by `init` and the timer of a fresh canister are overwritten
+            if !self
+                .backup_repository
+                .is_memory_region_empty(region.memory_id)
+            {
+                return Err(format!(
+                    "Memory region {} is not empty, restore into a fresh canister",
+                    region.memory_id
+                ));
+            }
+        }
+
+        for region in &manifest.regions {
+            self.backup_repository
+                .grow_memory_region(region.memory_id, region.size)?;
+        }
+        self.backup_repository
+            .set_maintenance_mode(Some(MaintenanceMode::Restore));
+        Ok(())
+    }
+
+    fn restore_chunk(&self, chunk: BackupChunk) -> Result<(), String> {
+        self.assert_maintenance_mode(MaintenanceMode::Restore)?;
+        chunk.verify_checksum()?;
+
+        self.backup_repository
+            .write_memory_region(chunk.memory_id, chunk.offset, &chunk.data)
+    }
+
+    fn finish_restore(&self) -> Result<(), String> {
+        self.assert_maintenance_mode(MaintenanceMode::Restore)?;
+        self.backup_repository
+            .set_maintenance_mode(Some(MaintenanceMode::Restored));
+        Ok(())
+    }
+
+    fn is_in_maintenance_mode(&self) -> bool {
+        self.backup_repository.get_maintenance_mode().is_some()
+    }
+
+    fn assert_not_in_maintenance_mode(&self) {
+        match self.backup_repository.get_maintenance_mode() {
+            Some(MaintenanceMode::Backup | MaintenanceMode::Restore) => {
+                trap("a backup or a restore is in progress")
+            }
+            Some(MaintenanceMode::Restored) => trap(upgrade_required_error()),
+            None => {}
+        }
+    }
+}
+
+impl<T: BackupRepository> BackupServiceImpl<T> {
+    fn new(backup_repository: T) -> Self {
+        Self { backup_repository }
+    }
+
+    fn assert_maintenance_mode(&self, maintenance_mode: MaintenanceMode) -> Result<(), String> {
+        if self.backup_repository.get_maintenance_mode() != Some(maintenance_mode) {
+            return Err(match maintenance_mode {
+                MaintenanceMode::Backup => "No backup in progress",
+                MaintenanceMode::Restore | MaintenanceMode::Restored => "No restore in progress",
+            }
+            .to_string());
+        }
+        Ok(())
+    }
+}
+
+fn upgrade_required_error() -> String {
+    "The memory has been restored, upgrade the canister to load it".to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::{
+        ChatId, ChatMemberId, ChatSession, ChatSessionRepository, ChatSessionRepositoryImpl,
+        ConfigRepository, ConfigRepositoryImpl, MemoryRegionInfo, UserId,
+    };
+    use crate::services::{
+        FilesystemService, FilesystemServiceImpl, ShareService, ShareServiceImpl,
+    };
+    use rstest::*;
+    use std::{path::PathBuf, thread};
+
+    fn backup(service: &impl BackupService) -> (BackupManifest, Vec<BackupChunk>) {
+        let manifest = service.start_backup().unwrap();
+        let mut chunks = vec![];
+        for region in &manifest.regions {
+            for offset in (0..region.size).step_by(manifest.chunk_size as usize) {
+                let chunk = service
+                    .get_backup_chunk(BackupChunkRequest {
+                        memory_id: region.memory_id,
+                        offset,
+                    })
+                    .unwrap();
+                chunk.verify_checksum().unwrap();
+                chunks.push(chunk);
+            }
+        }
+        service.finish_backup().unwrap();
+        (manifest, chunks)
+    }
+
+    #[rstest]
+    fn backup_and_restore() {
+        let chat_member_id = ChatMemberId::new(ChatId(123), UserId(123));
+        let mut chat_session = ChatSession::default();
+        chat_session.set_current_path(PathBuf::from("/Documents"));
+
+        // each thread has its own stable memory, so the restore targets a fresh one
+        let (manifest, chunks) = {
+            let chat_member_id = chat_member_id.clone();
+            let chat_session = chat_session.clone();
+            thread::spawn(move || {
+                ChatSessionRepositoryImpl::default().set_chat_session(chat_member_id, chat_session);
+                backup(&BackupServiceImpl::default())
+            })
+            .join()
+            .unwrap()
+        };
+        assert!(manifest.regions.iter().any(|region| region.size > 0));
+
+        let restored_chat_session = thread::spawn(move || {
+            let service = BackupServiceImpl::default();
+            service.start_restore(manifest).unwrap();
+            assert!(service.is_in_maintenance_mode());
+            for chunk in chunks {
+                service.restore_chunk(chunk).unwrap();
+            }
+            service.finish_restore().unwrap();
+            // until the canister is upgraded
+            assert!(service.is_in_maintenance_mode());
+            assert_eq!(service.start_backup(), Err(upgrade_required_error()));
+            assert_eq!(
+                service.finish_restore(),
+                Err("No restore in progress".to_string())
+            );
+
+            ChatSessionRepositoryImpl::default().get_chat_session(&chat_member_id)
+        })
+        .join()
+        .unwrap();
+
+        assert_eq!(restored_chat_session, Some(chat_session));
+    }
+
+    #[rstest]
+    fn restore_after_install_and_timer() {
+        let chat_member_id = ChatMemberId::new(ChatId(123), UserId(123));
+        let (manifest, chunks) = {
+            let chat_member_id = chat_member_id.clone();
+            thread::spawn(move || {
+                ChatSessionRepositoryImpl::default()
+                    .set_chat_session(chat_member_id, ChatSession::default());
+                ShareServiceImpl::default().set_token_salt(vec![1; 32]);
+                backup(&BackupServiceImpl::default())
+            })
+            .join()
+            .unwrap()
+        };
+
+        let restored_chat_session = thread::spawn(move || {
+            // what `init` and the timer do in a canister installed without arguments
+            let filesystem_service = FilesystemServiceImpl::default();
+            filesystem_service.migrate_legacy_filesystems(100);
+            assert!(!filesystem_service.has_legacy_filesystems());
+            ConfigRepositoryImpl::default().get_config();
+            ShareServiceImpl::default().set_token_salt(vec![2; 32]);
+
+            let service = BackupServiceImpl::default();
+            let backup_repository = BackupRepositoryImpl::default();
+            assert!(backup_repository
+                .get_memory_regions()
+                .iter()
+                .any(|region| region.size > 0));
+            service.start_restore(manifest).unwrap();
+            for chunk in chunks {
+                service.restore_chunk(chunk).unwrap();
+            }
+            service.finish_restore().unwrap();
+
+            ChatSessionRepositoryImpl::default().get_chat_session(&chat_member_id)
+        })
+        .join()
+        .unwrap();
+
+        assert_eq!(restored_chat_session, Some(ChatSession::default()));
+    }
+
+    #[rstest]
+    fn get_backup_chunk_without_backup() {
+        let service = BackupServiceImpl::default();
+
+        assert_eq!(
+            service.get_backup_chunk(BackupChunkRequest {
+                memory_id: 1,
+                offset: 0,
+            }),
+            Err("No backup in progress".to_string())
+        );
+    }
+
+    #[rstest]
+    fn start_restore_into_used_canister() {
+        ChatSessionRepositoryImpl::default().set_chat_session(
+            ChatMemberId::new(ChatId(123), UserId(123)),
+            ChatSession::default(),
+        );
+        let service = BackupServiceImpl::default();
+        let (manifest, _) = backup(&service);
+
+        assert_eq!(
+            service.start_restore(manifest),
+            Err("Memory region 9 is not empty, restore into a fresh canister".to_string())
+        );
+    }
+
+    #[rstest]
+    fn restore_chunk_checksum_mismatch() {
+        let service = BackupServiceImpl::default();
+        service
+            .start_restore(BackupManifest {
+                chunk_size: BACKUP_CHUNK_SIZE,
+                regions: vec![MemoryRegionInfo {
+                    memory_id: 1,
+                    size: 65536,
+                }],
+            })
+            .unwrap();
+        let mut chunk = BackupChunk::new(1, 0, vec![1, 2, 3]);
+        chunk.sha256[0] ^= 1;
+
+        assert_eq!(
+            service.restore_chunk(chunk),
+            Err("Checksum mismatch for chunk at offset 0 of memory region 1".to_string())
+        );
+    }
+}
@@ -0,0 +1,256 @@
+use frankenstein::types::{ChatMember, ChatMemberUpdated, User};
+
+use crate::{
+    custom_print,
+    repositories::{
+        ChatId, ChatMemberId, ChatRole, ChatRoleRepository, ChatRoleRepositoryImpl, UserId,
+    },
+    utils::DEFAULT_GROUP_MEMBER_ROLE,
+};
+
+pub trait ChatRoleService {
+    /// In private chats, the user is always the admin.
+    /// In groups, members without a stored role get the default role.
+    fn get_role(&self, chat_member_id: &ChatMemberId) -> ChatRole;
+
+    fn set_role(&self, chat_member_id: ChatMemberId, role: ChatRole) -> Result<(), String>;
+
+    /// Keeps the roles in sync with the Telegram status of the members:
+    /// the user that adds the bot to a group and the Telegram admins become admins,
+    /// the members that leave or lose their admin status fall back to the default role.
+    fn handle_chat_member_update(&self, chat_member_updated: &ChatMemberUpdated, is_bot: bool);
+
+    fn delete_chat_roles(&self, chat_id: &ChatId);
+}
+
+pub struct ChatRoleServiceImpl<T: ChatRoleRepository> {
+    chat_role_repository: T,
+}
+
+impl Default for ChatRoleServiceImpl<ChatRoleRepositoryImpl> {
+    fn default() -> Self {
+        Self::new(ChatRoleRepositoryImpl::default())
+    }
+}
+
+impl<T: ChatRoleRepository> ChatRoleService for ChatRoleServiceImpl<T> {
+    fn get_role(&self, chat_member_id: &ChatMemberId) -> ChatRole {
+        if chat_member_id.chat_id.is_private() {
+            return ChatRole::Admin;
+        }
+
+        self.chat_role_repository
+            .get_chat_role(chat_member_id)
+            .unwrap_or(DEFAULT_GROUP_MEMBER_ROLE)
+    }
+
+    fn set_role(&self, chat_member_id: ChatMemberId, role: ChatRole) -> Result<(), String> {
+        if chat_member_id.chat_id.is_private() {
+            return Err("Roles can only be set in groups".to_string());
+        }
+
+        self.chat_role_repository
+            .set_chat_role(chat_member_id, role);
+        Ok(())
+    }
+
+    fn handle_chat_member_update(&self, chat_member_updated: &ChatMemberUpdated, is_bot: bool) {
+        let chat_id = ChatId::from(chat_member_updated.chat.id);
+        if chat_id.is_private() {
+            return;
+        }
+
+        if is_bot {
+            if has_left(&chat_member_updated.old_chat_member)
+                && !has_left(&chat_member_updated.new_chat_member)
+            {
+                let chat_member_id =
+                    ChatMemberId::new(chat_id, UserId(chat_member_updated.from.id));
+                custom_print!(
+                    "Bot added to chat {} by user {}",
+                    chat_member_id.chat_id,
+                    chat_member_id.user_id
+                );
+                self.chat_role_repository
+                    .set_chat_role(chat_member_id, ChatRole::Admin);
+            }
+            return;
+        }
+
+        let old_chat_member = &chat_member_updated.old_chat_member;
+        let new_chat_member = &chat_member_updated.new_chat_member;
+        let chat_member_id =
+            ChatMemberId::new(chat_id, UserId(chat_member_user(new_chat_member).id));
+        if let Some(role) = ChatRole::from_chat_member(new_chat_member) {
+            self.chat_role_repository
+                .set_chat_role(chat_member_id, role);
+        } else if has_left(new_chat_member) || ChatRole::from_chat_member(old_chat_member).is_some()
+        {
+            self.chat_role_repository.remove_chat_role(&chat_member_id);
+        }
+    }
+
+    fn delete_chat_roles(&self, chat_id: &ChatId) {
+        self.chat_role_repository
+            .remove_chat_roles_by_chat_id(chat_id);
+    }
+}
+
+impl<T: ChatRoleRepository> ChatRoleServiceImpl<T> {
+    fn new(chat_role_repository: T) -> Self {
+        Self {
+            chat_role_repository,
+        }
+    }
+}
+
+fn chat_member_user(chat_member: &ChatMember) -> &User {
+    match chat_member {
+        ChatMember::Creator(member) => &member.user,
+        ChatMember::Administrator(member) => &member.user,
+        ChatMember::Member(member) => &member.user,
+        ChatMember::Restricted(member) => &member.user,
+        ChatMember::Left(member) => &member.user,
+        ChatMember::Kicked(member) => &member.user,
+    }
+}
+
+fn has_left(chat_member: &ChatMember) -> bool {
+    matches!(chat_member, ChatMember::Left(_) | ChatMember::Kicked(_))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use frankenstein::types::{Chat, ChatMemberLeft, ChatMemberMember, ChatMemberOwner, ChatType};
+    use rstest::*;
+
+    const GROUP_CHAT_ID: i64 = -123;
+    const ADMIN_USER_ID: u64 = 1;
+    const MEMBER_USER_ID: u64 = 2;
+    const BOT_USER_ID: u64 = 99;
+
+    fn user(id: u64) -> User {
+        User::builder()
+            .id(id)
+            .is_bot(false)
+            .first_name("John")
+            .build()
+    }
+
+    fn chat_member_updated(
+        from: u64,
+        old_chat_member: ChatMember,
+        new_chat_member: ChatMember,
+    ) -> ChatMemberUpdated {
+        ChatMemberUpdated::builder()
+            .chat(
+                Chat::builder()
+                    .id(GROUP_CHAT_ID)
+                    .type_field(ChatType::Group)
+                    .build(),
+            )
+            .from(user(from))
+            .date(0)
+            .old_chat_member(old_chat_member)
+            .new_chat_member(new_chat_member)
+            .build()
+    }
+
+    fn member(id: u64) -> ChatMember {
+        ChatMember::Member(ChatMemberMember::builder().user(user(id)).build())
+    }
+
+    fn left(id: u64) -> ChatMember {
+        ChatMember::Left(ChatMemberLeft::builder().user(user(id)).build())
+    }
+
+    fn creator(id: u64) -> ChatMember {
+        ChatMember::Creator(
+            ChatMemberOwner::builder()
+                .user(user(id))
+                .is_anonymous(false)
+                .build(),
+        )
+    }
+
+    fn chat_member_id(user_id: u64) -> ChatMemberId {
+        ChatMemberId::new(ChatId::from(GROUP_CHAT_ID), UserId(user_id))
+    }
+
+    #[rstest]
+    fn get_role_private_chat() {
+        let service = ChatRoleServiceImpl::default();
+        let chat_member_id = ChatMemberId::new(ChatId::from(123i64), UserId(123));
+
+        assert_eq!(service.get_role(&chat_member_id), ChatRole::Admin);
+        assert_eq!(
+            service.set_role(chat_member_id, ChatRole::Viewer),
+            Err("Roles can only be set in groups".to_string())
+        );
+    }
+
+    #[rstest]
+    fn handle_chat_member_update() {
+        let service = ChatRoleServiceImpl::default();
+        assert_eq!(
+            service.get_role(&chat_member_id(ADMIN_USER_ID)),
+            DEFAULT_GROUP_MEMBER_ROLE
+        );
+
+        // the bot is added to the group
+        service.handle_chat_member_update(
+            &chat_member_updated(ADMIN_USER_ID, left(BOT_USER_ID), member(BOT_USER_ID)),
+            true,
+        );
+        assert_eq!(
+            service.get_role(&chat_member_id(ADMIN_USER_ID)),
+            ChatRole::Admin
+        );
+
+        service
+            .set_role(chat_member_id(MEMBER_USER_ID), ChatRole::Viewer)
+            .unwrap();
+        // unrelated status changes keep the explicit role
+        service.handle_chat_member_update(
+            &chat_member_updated(ADMIN_USER_ID, left(MEMBER_USER_ID), member(MEMBER_USER_ID)),
+            false,
+        );
+        assert_eq!(
+            service.get_role(&chat_member_id(MEMBER_USER_ID)),
+            ChatRole::Viewer
+        );
+
+        service.handle_chat_member_update(
+            &chat_member_updated(
+                ADMIN_USER_ID,
+                member(MEMBER_USER_ID),
+                creator(MEMBER_USER_ID),
+            ),
+            false,
+        );
+        assert_eq!(
+            service.get_role(&chat_member_id(MEMBER_USER_ID)),
+            ChatRole::Admin
+        );
+
+        service.handle_chat_member_update(
+            &chat_member_updated(
+                ADMIN_USER_ID,
+                creator(MEMBER_USER_ID),
+                member(MEMBER_USER_ID),
+            ),
+            false,
+        );
+        assert_eq!(
+            service.get_role(&chat_member_id(MEMBER_USER_ID)),
+            DEFAULT_GROUP_MEMBER_ROLE
+        );
+
+        service.delete_chat_roles(&ChatId::from(GROUP_CHAT_ID));
+        assert_eq!(
+            service.get_role(&chat_member_id(ADMIN_USER_ID)),
+            DEFAULT_GROUP_MEMBER_ROLE
+        );
+    }
+}
@@ -1,103 +1,314 @@
-use frankenstein::types::{CallbackQuery, MaybeInaccessibleMessage, Message};
+use std::{
+    cell::RefCell,
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+};
+
+use frankenstein::{
+    inline_mode::{
+        InlineQuery, InlineQueryResult, InlineQueryResultCachedAudio,
+        InlineQueryResultCachedDocument, InlineQueryResultCachedPhoto,
+        InlineQueryResultCachedVideo,
+    },
+    types::{
+        CallbackQuery, ChatMemberUpdated, InlineKeyboardMarkup, MaybeInaccessibleMessage, Message,
+        User,
+    },
+};
 
 use crate::{
     custom_print,
     repositories::{
-        with_clear_action_on_error, ChatId, ChatSession, ChatSessionAction, ChatSessionRepository,
-        ChatSessionRepositoryImpl, ChatSessionWaitReply, Command, FileSystem, FileSystemNode,
-        FilesystemRepositoryImpl, KeyboardDirectoryBuilder, MessageId,
+        command_addressee, command_argument, command_arguments, file_path_with_extension,
+        with_clear_action_on_error, AutoNaming, BulkOperation, ChatId, ChatMemberId, ChatRole,
+        ChatRoleRepositoryImpl, ChatSession, ChatSessionAction, ChatSessionRepository,
+        ChatSessionRepositoryImpl, ChatSessionWaitReply, ChatSettings, ChatSettingsRepositoryImpl,
+        Command, ConfigRepository, ConfigRepositoryImpl, ConflictPolicy, DriveId,
+        DriveRepositoryImpl, FileSystem, FileSystemNode, FilesystemRepositoryImpl,
+        JournalOperation, JournalRepositoryImpl, KeyboardDirectoryBuilder, Language, MediaGroup,
+        MessageId, ProcessedUpdateRepositoryImpl, RateLimitRepositoryImpl, ReceivedFile, Setting,
+        ShareRepositoryImpl, TelegramFile, TelegramFileKind, UserId, UtcOffset,
     },
     utils::{
-        filesystem::root_path,
+        filesystem::{root_path, shared_path},
+        format_file_timestamp, format_timestamp, get_current_time, instruction_counter,
         messages::{
-            ask_directory_name_message, ask_file_name_message, ask_rename_file_message,
-            back_inline_keyboard, create_file_message, created_directory_success_message,
-            created_file_success_message, explorer_file_message, explorer_message, help_message,
-            info_message, mkdir_message, move_file_select_destination_message,
-            move_file_select_file_message, moved_file_success_message, rename_file_message,
-            renamed_file_success_message, start_message, COMING_SOON_TEXT,
+            already_enrolled_message, ask_directory_name_message, ask_drive_name_message,
+            ask_file_name_message, ask_media_group_file_names_message, ask_name_template_message,
+            ask_refine_file_name_message, ask_rename_file_message, ask_tag_message,
+            ask_time_zone_message, auto_naming_inline_keyboard, auto_naming_setting_message,
+            back_inline_button, back_inline_keyboard, bulk_delete_confirmation_message,
+            bulk_operation_failed_message, bulk_operation_message, bulk_select_destination_message,
+            coming_soon_message, confirm_inline_keyboard, conflict_policy_inline_keyboard,
+            conflict_policy_setting_message, corrupted_drive_message, corrupted_filesystem_message,
+            create_file_message, create_media_group_message, created_directories_message,
+            created_directory_success_message, created_file_success_message,
+            created_media_group_message, delete_drive_confirmation_message,
+            delete_drive_inline_keyboard, delete_drive_message, destination_not_found_error,
+            destination_taken_error, drive_created_message, drive_deleted_message,
+            drive_not_created_message, drive_switched_message, drives_inline_keyboard,
+            drives_message, empty_name_template_error, empty_selection_message, empty_tag_error,
+            explorer_file_message, explorer_message, file_name_slash_error,
+            foreign_keyboard_message, forget_me_cancelled_message,
+            forget_me_final_confirmation_message, forget_me_message, forgotten_message,
+            help_message, history_message, info_message, invalid_name_template_message,
+            invalid_tag_message, invalid_time_zone_message, keep_file_names_inline_keyboard,
+            language_message, language_set_message, language_setting_inline_keyboard,
+            language_unknown_message, languages_inline_keyboard, ls_message, mkdir_message,
+            move_file_select_destination_message, move_file_select_file_message,
+            moved_file_success_message, moved_path_message, name_template_slash_error,
+            name_template_too_long_error, not_a_directory_error, not_a_file_error,
+            nothing_to_undo_message, path_command_failed_message, path_exists_error,
+            permission_denied_message, refine_file_inline_keyboard, refine_file_message,
+            rename_file_message, renamed_file_success_message, role_not_in_group_message,
+            role_set_message, role_usage_message, save_dir_setting_message,
+            select_files_inline_button, select_files_message, selection_inline_rows,
+            settings_inline_keyboard, settings_message, share_accepted_message,
+            share_created_message, share_failed_message, share_message, share_not_accepted_message,
+            share_permission_inline_keyboard, share_permission_message, share_revoked_message,
+            shares_inline_keyboard, shares_message, sort_order_inline_keyboard,
+            sort_order_setting_message, start_message, tag_characters_error, tag_too_long_error,
+            undo_conflict_message, undo_disabled_message, undo_success_message,
+            working_dir_message, wrong_arguments_error,
         },
-        MessageParams, TG_FILE_MIME_TYPE_PREFIX,
+        resolve_path, set_language, MessageParams, DATE_PLACEHOLDER, FILE_NUMBER_PLACEHOLDER,
+        INLINE_QUERY_CACHE_TIME_SECONDS, MAX_AUTO_FILE_NAME_LENGTH, MAX_INLINE_QUERY_RESULTS,
+        MAX_TAG_LENGTH, TG_FILE_MIME_TYPE_PREFIX, TYPE_PLACEHOLDER,
     },
 };
 
-use super::{FilesystemService, FilesystemServiceImpl};
+use super::{
+    ChatRoleService, ChatRoleServiceImpl, ChatSettingsService, ChatSettingsServiceImpl,
+    DriveService, DriveServiceImpl, FilesystemService, FilesystemServiceImpl, JournalService,
+    JournalServiceImpl, ProcessedUpdateService, ProcessedUpdateServiceImpl, RateLimitService,
+    RateLimitServiceImpl, ShareService, ShareServiceImpl,
+};
 
 pub trait ChatSessionService {
-    fn get_or_create_chat_session(&self, chat_id: &ChatId) -> ChatSession;
+    fn get_or_create_chat_session(&self, chat_member_id: &ChatMemberId) -> ChatSession;
 
-    fn update_chat_session(&self, chat_id: ChatId, chat_session: ChatSession);
+    fn update_chat_session(&self, chat_member_id: ChatMemberId, chat_session: ChatSession);
 
     fn get_chat_sessions_count(&self) -> u32;
 
+    /// Moves the sessions stored per chat to the per-member layout.
+    /// In private chats the member is the user of the chat, while the sessions of the groups are dropped.
+    fn migrate_legacy_chat_sessions(&self) -> u64;
+
+    /// Removes everything stored about the chat: the sessions and the roles of its members,
+    /// the filesystem and the drives, the shares, the journal, the settings and the records of the processed updates.
+    fn delete_chat_data(&self, chat_id: &ChatId);
+
+    /// Commands addressed to other bots with the `/command@botname` form must be ignored.
+    fn is_addressed_to_bot(&self, msg: &Message) -> bool;
+
     fn handle_update_content_message(
         &self,
         chat_id: ChatId,
         msg: Message,
     ) -> Result<MessageParams, String>;
 
+    /// Adds the file of the message to the album that is being saved, without replying.
+    /// Returns `false` if the message isn't part of that album and must be handled as any other message.
+    fn add_media_group_file(&self, chat_id: &ChatId, msg: &Message) -> bool;
+
     fn handle_update_content_callback_query(
         &self,
         chat_id: ChatId,
         query: CallbackQuery,
     ) -> Result<MessageParams, String>;
+
+    /// Updates the text files saved from an edited message, on all the drives of the chat.
+    /// The edits of the messages that aren't saved are ignored.
+    fn handle_update_content_edited_message(&self, chat_id: ChatId, msg: Message);
+
+    /// Searches the files of the user's private chat, on its active drive,
+    /// and answers with the ones that can be sent in any chat.
+    fn handle_update_content_inline_query(
+        &self,
+        chat_id: ChatId,
+        query: InlineQuery,
+    ) -> Result<MessageParams, String>;
+
+    /// Handles the changes of the status of the bot (`is_bot`) or of the members in a chat.
+    fn handle_update_content_chat_member(
+        &self,
+        chat_member_updated: ChatMemberUpdated,
+        is_bot: bool,
+    );
 }
 
-pub struct ChatSessionServiceImpl<T: ChatSessionRepository, F: FilesystemService> {
+pub struct ChatSessionServiceImpl<
+    T: ChatSessionRepository,
+    F: FilesystemService,
+    J: JournalService,
+    U: ProcessedUpdateService,
+    R: ChatRoleService,
+    S: ShareService,
+    D: DriveService,
+    C: ConfigRepository,
+    L: ChatSettingsService,
+    M: RateLimitService,
+> {
     chat_session_repository: T,
     filesystem_service: F,
+    journal_service: J,
+    processed_update_service: U,
+    chat_role_service: R,
+    share_service: S,
+    drive_service: D,
+    config_repository: C,
+    chat_settings_service: L,
+    rate_limit_service: M,
+    /// The operations of the update being handled, recorded once the update has been saved.
+    pending_journal_operations: RefCell<Vec<(ChatId, Option<DriveId>, JournalOperation)>>,
 }
 
 impl Default
     for ChatSessionServiceImpl<
         ChatSessionRepositoryImpl,
-        FilesystemServiceImpl<FilesystemRepositoryImpl>,
+        FilesystemServiceImpl<FilesystemRepositoryImpl, ConfigRepositoryImpl>,
+        JournalServiceImpl<JournalRepositoryImpl, ConfigRepositoryImpl>,
+        ProcessedUpdateServiceImpl<ProcessedUpdateRepositoryImpl, ConfigRepositoryImpl>,
+        ChatRoleServiceImpl<ChatRoleRepositoryImpl>,
+        ShareServiceImpl<
+            ShareRepositoryImpl,
+            FilesystemServiceImpl<FilesystemRepositoryImpl, ConfigRepositoryImpl>,
+        >,
+        DriveServiceImpl<
+            DriveRepositoryImpl,
+            FilesystemServiceImpl<FilesystemRepositoryImpl, ConfigRepositoryImpl>,
+        >,
+        ConfigRepositoryImpl,
+        ChatSettingsServiceImpl<ChatSettingsRepositoryImpl>,
+        RateLimitServiceImpl<RateLimitRepositoryImpl, ConfigRepositoryImpl>,
     >
 {
     fn default() -> Self {
         Self::new(
             ChatSessionRepositoryImpl::default(),
             FilesystemServiceImpl::default(),
+            JournalServiceImpl::default(),
+            ProcessedUpdateServiceImpl::default(),
+            ChatRoleServiceImpl::default(),
+            ShareServiceImpl::default(),
+            DriveServiceImpl::default(),
+            ConfigRepositoryImpl::default(),
+            ChatSettingsServiceImpl::default(),
+            RateLimitServiceImpl::default(),
         )
     }
 }
 
-impl<T: ChatSessionRepository, F: FilesystemService> ChatSessionService
-    for ChatSessionServiceImpl<T, F>
+impl<
+        T: ChatSessionRepository,
+        F: FilesystemService,
+        J: JournalService,
+        U: ProcessedUpdateService,
+        R: ChatRoleService,
+        S: ShareService,
+        D: DriveService,
+        C: ConfigRepository,
+        L: ChatSettingsService,
+        M: RateLimitService,
+    > ChatSessionService for ChatSessionServiceImpl<T, F, J, U, R, S, D, C, L, M>
 {
-    fn get_or_create_chat_session(&self, chat_id: &ChatId) -> ChatSession {
+    fn get_or_create_chat_session(&self, chat_member_id: &ChatMemberId) -> ChatSession {
         match self
             .chat_session_repository
-            .get_chat_session_by_chat_id(chat_id)
+            .get_chat_session(chat_member_id)
         {
             Some(chat_session) => chat_session,
             None => {
                 let chat_session = ChatSession::default();
                 self.chat_session_repository
-                    .set_chat_session_by_chat_id(chat_id.clone(), chat_session.clone());
+                    .set_chat_session(chat_member_id.clone(), chat_session.clone());
                 chat_session
             }
         }
     }
 
-    fn update_chat_session(&self, chat_id: ChatId, chat_session: ChatSession) {
+    fn update_chat_session(&self, chat_member_id: ChatMemberId, chat_session: ChatSession) {
         self.chat_session_repository
-            .set_chat_session_by_chat_id(chat_id, chat_session);
+            .set_chat_session(chat_member_id, chat_session);
     }
 
     fn get_chat_sessions_count(&self) -> u32 {
         self.chat_session_repository.get_chat_session_count() as u32
     }
 
+    fn migrate_legacy_chat_sessions(&self) -> u64 {
+        let mut migrated_count = 0;
+        while let Some((chat_id, chat_session)) =
+            self.chat_session_repository.pop_legacy_chat_session()
+        {
+            if !chat_id.is_private() {
+                custom_print!("Dropping the legacy chat session of group {}", chat_id);
+                continue;
+            }
+
+            let user_id = UserId(chat_id.0);
+            self.chat_session_repository
+                .set_chat_session(ChatMemberId::new(chat_id, user_id), chat_session);
+            migrated_count += 1;
+        }
+        migrated_count
+    }
+
+    fn delete_chat_data(&self, chat_id: &ChatId) {
+        self.chat_session_repository
+            .remove_chat_sessions_by_chat_id(chat_id);
+        self.chat_role_service.delete_chat_roles(chat_id);
+        self.filesystem_service.delete_filesystem(chat_id);
+        self.drive_service.delete_chat_drives(chat_id);
+        self.share_service.delete_chat_shares(chat_id);
+        self.journal_service.delete_journal(chat_id);
+        self.chat_settings_service.delete_chat_settings(chat_id);
+        self.processed_update_service
+            .delete_processed_updates(chat_id);
+        self.rate_limit_service.delete_chat_bucket(chat_id);
+    }
+
+    fn is_addressed_to_bot(&self, msg: &Message) -> bool {
+        let Some(addressee) = command_addressee(msg) else {
+            return true;
+        };
+
+        // without the username, the bot can't tell whether the command is addressed to it
+        self.config_repository
+            .get_config()
+            .bot_username
+            .is_none_or(|bot_username| bot_username.eq_ignore_ascii_case(addressee))
+    }
+
     fn handle_update_content_message(
         &self,
         chat_id: ChatId,
         msg: Message,
     ) -> Result<MessageParams, String> {
-        let mut fs = self.filesystem_service.get_or_create_filesystem(&chat_id);
-        let mut chat_session = self.get_or_create_chat_session(&chat_id);
+        let chat_member_id = ChatMemberId::from_sender(chat_id.clone(), msg.from.as_deref());
+        let mut chat_session = self.get_or_create_chat_session(&chat_member_id);
+        let original_chat_session = chat_session.clone();
+        let drive_id = self.get_active_drive_id(&chat_id, &mut chat_session);
+        let mut fs = self
+            .filesystem_service
+            .get_or_create_filesystem(&chat_id, drive_id);
+        self.share_service.mount_shares(&chat_id, &mut fs);
+        let chat_settings = self.chat_settings_service.get_chat_settings(&chat_id);
+        fs.set_sort_order(chat_settings.sort_order());
+        // only the admins can send messages on behalf of the group
+        let role = if msg
+            .sender_chat
+            .as_ref()
+            .is_some_and(|sender_chat| ChatId::from(sender_chat.id) == chat_id)
+        {
+            ChatRole::Admin
+        } else {
+            self.chat_role_service.get_role(&chat_member_id)
+        };
 
         let from_user = msg.clone().from;
+        let message_id = msg.message_id;
 
         let res = with_clear_action_on_error(&mut chat_session, |cs| {
             let current_path = cs.current_path().clone();
@@ -107,6 +318,13 @@ impl<T: ChatSessionRepository, F: FilesystemService> ChatSessionService
                     );
 
             match Command::try_from(msg.clone()) {
+                Ok(command) if role < command.required_role() => {
+                    Ok(permission_denied_message_params(
+                        chat_id.clone(),
+                        role,
+                        command.required_role(),
+                    ))
+                }
                 Ok(command) => {
                     // when receiving a command, we want to reset the chat session
                     cs.reset();
@@ -114,12 +332,43 @@ impl<T: ChatSessionRepository, F: FilesystemService> ChatSessionService
                     let mut send_message_params = MessageParams::new_send(chat_id.clone());
 
                     match command {
-                        Command::Start => {
-                            send_message_params
-                                .set_text(start_message(from_user.map(|user| user.first_name)));
-                        }
+                        // the deep links of the shares open the chat with /start <token>
+                        Command::Start => match command_argument(&msg) {
+                            Some(_) if role < ChatRole::Editor => {
+                                send_message_params
+                                    .set_text(permission_denied_message(role, ChatRole::Editor));
+                            }
+                            Some(token) => {
+                                let text = match self.share_service.accept_share(chat_id.clone(), token)
+                                {
+                                    Ok(share) => share_accepted_message(
+                                        shared_path()
+                                            .join(share.recipient.map(|r| r.name).unwrap_or_default())
+                                            .to_string_lossy()
+                                            .to_string(),
+                                        share.permission,
+                                    ),
+                                    Err(err) => share_not_accepted_message(err),
+                                };
+                                send_message_params.set_text(text);
+                            }
+                            None => {
+                                send_message_params
+                                    .set_text(start_message(from_user.map(|user| user.first_name)));
+                            }
+                        },
                         Command::Help => send_message_params.set_text(help_message()),
                         Command::Info => send_message_params.set_text(info_message()),
+                        Command::MkDir if command_argument(&msg).is_some() => {
+                            send_message_params.set_text(self.run_path_command(
+                                &chat_id, drive_id, cs, &mut fs, command, &msg,
+                            ));
+                        }
+                        Command::Ls | Command::Cd | Command::Pwd | Command::Mv => {
+                            send_message_params.set_text(self.run_path_command(
+                                &chat_id, drive_id, cs, &mut fs, command, &msg,
+                            ));
+                        }
                         Command::MkDir => {
                             cs.set_action(ChatSessionAction::MkDir(None));
 
@@ -135,11 +384,10 @@ impl<T: ChatSessionRepository, F: FilesystemService> ChatSessionService
 
                             send_message_params
                                 .set_text(explorer_message(cs.current_path_string()));
-
-                            let keyboard = KeyboardDirectoryBuilder::new(&fs, cs.current_path())?
-                                .with_files()?
-                                .build();
-                            send_message_params.set_inline_keyboard_markup(keyboard);
+                            send_message_params.set_inline_keyboard_markup(explorer_keyboard(
+                                &fs,
+                                cs.current_path(),
+                            )?);
                         }
                         Command::RenameFile => {
                             cs.set_action(ChatSessionAction::RenameFile(None));
@@ -164,15 +412,165 @@ impl<T: ChatSessionRepository, F: FilesystemService> ChatSessionService
                             send_message_params.set_inline_keyboard_markup(keyboard);
                         }
                         Command::DeleteDir | Command::DeleteFile => {
-                            send_message_params.set_text(COMING_SOON_TEXT.to_string());
+                            send_message_params.set_text(coming_soon_message());
+                        }
+                        Command::Undo | Command::History if !self.journal_service.is_enabled() => {
+                            send_message_params.set_text(undo_disabled_message());
+                        }
+                        Command::Undo => {
+                            // the entries of the other drives are left for when they are active
+                            let text = match self
+                                .journal_service
+                                .get_latest_entry(&chat_id, drive_id)
+                            {
+                                // the entry is kept when it can't be undone, so that it can be
+                                // undone once the conflict is solved
+                                Some(entry) => match entry.inverse().apply(&mut fs) {
+                                    Ok(()) => {
+                                        self.journal_service
+                                            .pop_latest_entry(&chat_id, drive_id);
+                                        undo_success_message(entry.operation())
+                                    }
+                                    Err(err) => undo_conflict_message(entry.operation(), err),
+                                },
+                                None => nothing_to_undo_message(),
+                            };
+                            send_message_params.set_text(text);
+                        }
+                        Command::History => {
+                            let entries = self.journal_service.get_recent_entries(&chat_id);
+                            send_message_params.set_text(history_message(
+                                entries
+                                    .iter()
+                                    .map(|entry| {
+                                        (
+                                            format_timestamp(
+                                                entry.timestamp(),
+                                                &chat_settings.time_zone(),
+                                            ),
+                                            entry.operation(),
+                                        )
+                                    })
+                                    .collect(),
+                            ));
+                        }
+                        // only the users that can already use the bot get here
+                        Command::Join => {
+                            send_message_params.set_text(already_enrolled_message());
+                        }
+                        Command::ForgetMe => {
+                            cs.set_action(ChatSessionAction::ForgetMe(None));
+
+                            send_message_params.set_text(forget_me_message());
+                            send_message_params
+                                .set_inline_keyboard_markup(confirm_inline_keyboard());
+                        }
+                        Command::Share => {
+                            cs.set_action(ChatSessionAction::Share(None));
+
+                            send_message_params.set_text(share_message(cs.current_path_string()));
+
+                            let keyboard = KeyboardDirectoryBuilder::new(&fs, cs.current_path())?
+                                .with_current_dir_button()
+                                .build();
+                            send_message_params.set_inline_keyboard_markup(keyboard);
+                        }
+                        Command::Shares => {
+                            cs.set_action(ChatSessionAction::Shares);
+
+                            let shares = self.share_service.get_owned_shares(&chat_id);
+                            send_message_params.set_text(shares_message(&shares));
+                            send_message_params
+                                .set_inline_keyboard_markup(shares_inline_keyboard(&shares));
+                        }
+                        Command::Drive => {
+                            self.set_drives_menu(&chat_id, cs, &mut send_message_params);
+                        }
+                        Command::Language => match command_argument(&msg) {
+                            Some(argument) => {
+                                let language = if argument.eq_ignore_ascii_case("auto") {
+                                    Ok(None)
+                                } else {
+                                    argument.parse::<Language>().map(Some)
+                                };
+                                let text = match language {
+                                    Ok(language) => {
+                                        self.set_chat_language(
+                                            &chat_id,
+                                            language,
+                                            msg.from.as_deref(),
+                                        );
+                                        language_set_message(language)
+                                    }
+                                    Err(_) => language_unknown_message(argument.to_string()),
+                                };
+                                send_message_params.set_text(text);
+                            }
+                            None => {
+                                cs.set_action(ChatSessionAction::Languages);
+
+                                let language =
+                                    self.chat_settings_service.get_chat_settings(&chat_id).language;
+                                send_message_params.set_text(language_message(language));
+                                send_message_params
+                                    .set_inline_keyboard_markup(languages_inline_keyboard(language));
+                            }
+                        },
+                        Command::Settings => {
+                            self.set_settings_menu(&chat_id, cs, &mut send_message_params);
+                        }
+                        Command::Role if chat_id.is_private() => {
+                            send_message_params.set_text(role_not_in_group_message());
+                        }
+                        Command::Role => {
+                            let new_role = command_argument(&msg)
+                                .and_then(|argument| argument.parse::<ChatRole>().ok());
+                            let target_user = msg
+                                .reply_to_message
+                                .as_ref()
+                                .and_then(|reply_to_message| reply_to_message.from.as_deref());
+                            let text = match (new_role, target_user) {
+                                (Some(new_role), Some(target_user)) => {
+                                    self.chat_role_service.set_role(
+                                        ChatMemberId::new(chat_id.clone(), UserId(target_user.id)),
+                                        new_role,
+                                    )?;
+                                    role_set_message(target_user.first_name.clone(), new_role)
+                                }
+                                _ => role_usage_message(),
+                            };
+                            send_message_params.set_text(text);
                         }
                     }
 
                     Ok(send_message_params)
                 }
+                // any other message either replies to a pending action or starts saving a new file
+                Err(_) if role < ChatRole::Editor => Ok(permission_denied_message_params(
+                    chat_id.clone(),
+                    role,
+                    ChatRole::Editor,
+                )),
                 Err(_) => {
                     if let Some(text) = msg.text {
-                        return match cs.action() {
+                        // while a file saved in the inbox is being refined, a text is a new file
+                        let pending_action = cs.action().filter(|action| {
+                            !matches!(action, ChatSessionAction::RefineFile(_, None))
+                        });
+                        // the role may have changed since the action has been started
+                        if let Some(current_action) = pending_action
+                            .as_ref()
+                            .filter(|action| role < action.required_role())
+                        {
+                            let required_role = current_action.required_role();
+                            cs.clear_action();
+                            return Ok(permission_denied_message_params(
+                                chat_id.clone(),
+                                role,
+                                required_role,
+                            ));
+                        }
+                        return match pending_action {
                             Some(current_action) => match current_action {
                                 ChatSessionAction::MkDir(Some(
                                     ChatSessionWaitReply::DirectoryName,
@@ -180,6 +578,13 @@ impl<T: ChatSessionRepository, F: FilesystemService> ChatSessionService
                                     let dir_name = text;
                                     let dir_path = cs.current_path().join(&dir_name);
                                     fs.mkdir(&dir_path)?;
+                                    self.record_operation(
+                                        &chat_id,
+                                        drive_id,
+                                        JournalOperation::MkDir {
+                                            path: dir_path.clone(),
+                                        },
+                                    );
                                     cs.reset();
 
                                     let mut send_message_params =
@@ -198,9 +603,14 @@ impl<T: ChatSessionRepository, F: FilesystemService> ChatSessionService
                                 ) => {
                                     let file_name = text;
                                     let dir_path = cs.current_path();
-                                    let file_path = dir_path.join(file_name);
-                                    let final_file_path =
-                                        fs.create_file_from_node(&file_path, file_node)?;
+                                    let final_file_path = self.save_file(
+                                        &chat_id,
+                                        drive_id,
+                                        &mut fs,
+                                        &dir_path.join(file_name),
+                                        file_node,
+                                        chat_settings.conflict_policy(),
+                                    )?;
                                     let mut send_message_params =
                                         MessageParams::new_send(chat_id.clone());
                                     send_message_params.set_text(created_file_success_message(
@@ -213,6 +623,29 @@ impl<T: ChatSessionRepository, F: FilesystemService> ChatSessionService
                                     ));
                                     Ok(send_message_params)
                                 }
+                                ChatSessionAction::SaveMediaGroup(
+                                    Some(media_group),
+                                    Some(ChatSessionWaitReply::FileName),
+                                ) => {
+                                    let file_names =
+                                        media_group_file_names(&media_group, Some(text.trim()));
+                                    let dir_path = cs.current_path().clone();
+                                    let text = self.save_media_group_files(
+                                        &chat_id,
+                                        drive_id,
+                                        &mut fs,
+                                        &dir_path,
+                                        media_group,
+                                        file_names,
+                                        chat_settings.conflict_policy(),
+                                    );
+                                    cs.reset();
+
+                                    let mut send_message_params =
+                                        MessageParams::new_send(chat_id.clone());
+                                    send_message_params.set_text(text);
+                                    Ok(send_message_params)
+                                }
                                 ChatSessionAction::RenameFile(Some(
                                     ChatSessionWaitReply::FileName,
                                 )) => {
@@ -221,6 +654,14 @@ impl<T: ChatSessionRepository, F: FilesystemService> ChatSessionService
                                     let mut to_path = from_path.clone();
                                     to_path.set_file_name(&new_file_name);
                                     fs.mv(from_path, &to_path)?;
+                                    self.record_operation(
+                                        &chat_id,
+                                        drive_id,
+                                        JournalOperation::RenameFile {
+                                            from: from_path.clone(),
+                                            to: to_path.clone(),
+                                        },
+                                    );
                                     let mut send_message_params =
                                         MessageParams::new_send(chat_id.clone());
                                     send_message_params.set_text(renamed_file_success_message(
@@ -234,116 +675,242 @@ impl<T: ChatSessionRepository, F: FilesystemService> ChatSessionService
                                     ));
                                     Ok(send_message_params)
                                 }
+                                ChatSessionAction::Bulk(
+                                    BulkOperation::Tag,
+                                    Some(ChatSessionWaitReply::TagName),
+                                ) => {
+                                    let mut send_message_params =
+                                        MessageParams::new_send(chat_id.clone());
+                                    match parse_tag(&text) {
+                                        Ok(tag) => {
+                                            let text = self.apply_bulk_action(
+                                                &chat_id,
+                                                drive_id,
+                                                &mut fs,
+                                                BulkAction::Tag(&tag),
+                                                cs.selection(),
+                                            );
+                                            cs.reset();
+                                            send_message_params.set_text(text);
+                                        }
+                                        // keep waiting for a valid tag
+                                        Err(err) => {
+                                            send_message_params.set_text(invalid_tag_message(err))
+                                        }
+                                    }
+                                    Ok(send_message_params)
+                                }
+                                ChatSessionAction::CreateDrive(Some(
+                                    ChatSessionWaitReply::DriveName,
+                                )) => {
+                                    cs.reset();
+
+                                    let mut send_message_params =
+                                        MessageParams::new_send(chat_id.clone());
+                                    match self.drive_service.create_drive(chat_id.clone(), text) {
+                                        Ok(new_drive_id) => {
+                                            cs.set_drive_id(Some(new_drive_id));
+                                            send_message_params.set_text(drive_created_message(
+                                                self.drive_service
+                                                    .get_drive_name(&chat_id, Some(new_drive_id)),
+                                            ));
+                                        }
+                                        Err(err) => {
+                                            send_message_params
+                                                .set_text(drive_not_created_message(err));
+                                        }
+                                    }
+                                    Ok(send_message_params)
+                                }
+                                ChatSessionAction::EditSetting(Setting::TimeZone) => {
+                                    let mut send_message_params =
+                                        MessageParams::new_send(chat_id.clone());
+                                    match text.parse::<UtcOffset>() {
+                                        Ok(time_zone) => {
+                                            self.chat_settings_service.update_chat_settings(
+                                                chat_id.clone(),
+                                                |chat_settings| {
+                                                    chat_settings.time_zone = Some(time_zone)
+                                                },
+                                            );
+                                            self.set_settings_menu(
+                                                &chat_id,
+                                                cs,
+                                                &mut send_message_params,
+                                            );
+                                        }
+                                        // keep waiting for a valid time zone
+                                        Err(err) => send_message_params
+                                            .set_text(invalid_time_zone_message(err)),
+                                    }
+                                    Ok(send_message_params)
+                                }
+                                ChatSessionAction::EditSetting(Setting::NameTemplate) => {
+                                    let mut send_message_params =
+                                        MessageParams::new_send(chat_id.clone());
+                                    match parse_name_template(&text) {
+                                        Ok(name_template) => {
+                                            self.chat_settings_service.update_chat_settings(
+                                                chat_id.clone(),
+                                                |chat_settings| {
+                                                    chat_settings.name_template =
+                                                        Some(name_template)
+                                                },
+                                            );
+                                            self.set_settings_menu(
+                                                &chat_id,
+                                                cs,
+                                                &mut send_message_params,
+                                            );
+                                        }
+                                        // keep waiting for a valid template
+                                        Err(err) => send_message_params
+                                            .set_text(invalid_name_template_message(err)),
+                                    }
+                                    Ok(send_message_params)
+                                }
+                                ChatSessionAction::RefineFile(
+                                    Some(from_path),
+                                    Some(ChatSessionWaitReply::FileName),
+                                ) => {
+                                    // a name with a slash would move the file out of the chosen directory
+                                    if text.contains('/') {
+                                        return Err(file_name_slash_error());
+                                    }
+                                    let mut to_path = cs.current_path().join(&text);
+                                    if to_path.extension().is_none() {
+                                        if let Some(extension) = from_path.extension() {
+                                            to_path.set_extension(extension);
+                                        }
+                                    }
+                                    let text = self.refine_file(
+                                        &chat_id, drive_id, &mut fs, &from_path, &to_path,
+                                    )?;
+                                    cs.reset();
+
+                                    let mut send_message_params =
+                                        MessageParams::new_send(chat_id.clone());
+                                    send_message_params.set_text(text);
+                                    Ok(send_message_params)
+                                }
                                 _ => Ok(MessageParams::generic_error(chat_id.clone())),
                             },
-                            None => process_file_message(
-                                cs,
-                                &fs,
-                                chat_id.clone(),
-                                msg.message_id,
-                                Some(text.len().try_into().unwrap()),
-                                Some(format!("{TG_FILE_MIME_TYPE_PREFIX}text")),
-                            ),
+                            None => {
+                                let file = ReceivedFile {
+                                    node: FileSystemNode::new_file(
+                                        msg.message_id,
+                                        text.len().try_into().unwrap(),
+                                        Some(format!("{TG_FILE_MIME_TYPE_PREFIX}text")),
+                                    ),
+                                    file_name: None,
+                                };
+                                if chat_settings.inbox() {
+                                    self.save_to_inbox(
+                                        &chat_id,
+                                        drive_id,
+                                        &mut fs,
+                                        cs,
+                                        &chat_settings,
+                                        file,
+                                        None,
+                                    )
+                                } else {
+                                    process_file_message(
+                                        cs,
+                                        &fs,
+                                        &chat_settings,
+                                        chat_id.clone(),
+                                        None,
+                                        file,
+                                    )
+                                }
+                            }
                         };
                     };
 
-                    if let Some(document) = msg.document {
-                        return process_file_message(
-                            cs,
-                            &fs,
-                            chat_id.clone(),
-                            msg.message_id,
-                            document.file_size,
-                            document.mime_type,
-                        );
-                    }
-
-                    if let Some(photos) = msg.photo {
-                        let photo = photos.first().unwrap();
-                        return process_file_message(
+                    match received_file(&msg) {
+                        // the files of an album are saved one by one as they arrive
+                        Some(file) if chat_settings.inbox() => self.save_to_inbox(
+                            &chat_id,
+                            drive_id,
+                            &mut fs,
                             cs,
-                            &fs,
-                            chat_id.clone(),
-                            msg.message_id,
-                            photo.file_size,
-                            Some("image/jpeg".to_string()),
-                        );
-                    }
-
-                    if let Some(video) = msg.video {
-                        return process_file_message(
-                            cs,
-                            &fs,
-                            chat_id.clone(),
-                            msg.message_id,
-                            video.file_size,
-                            video.mime_type,
-                        );
-                    }
-
-                    if let Some(video_note) = msg.video_note {
-                        return process_file_message(
-                            cs,
-                            &fs,
-                            chat_id.clone(),
-                            msg.message_id,
-                            video_note.file_size,
-                            Some(format!("{TG_FILE_MIME_TYPE_PREFIX}video_note")),
-                        );
-                    }
-
-                    if let Some(audio) = msg.audio {
-                        return process_file_message(
+                            &chat_settings,
+                            file,
+                            msg.caption.as_deref(),
+                        ),
+                        Some(file) => process_file_message(
                             cs,
                             &fs,
+                            &chat_settings,
                             chat_id.clone(),
-                            msg.message_id,
-                            audio.file_size,
-                            audio.mime_type,
-                        );
+                            msg.media_group_id.clone(),
+                            file,
+                        ),
+                        None => Ok(MessageParams::generic_error(chat_id.clone())),
                     }
+                }
+            }
+        })
+        .map(|params| anchor_keyboard(&chat_id, &mut chat_session, params, message_id));
 
-                    if let Some(voice) = msg.voice {
-                        return process_file_message(
-                            cs,
-                            &fs,
-                            chat_id.clone(),
-                            msg.message_id,
-                            voice.file_size,
-                            voice.mime_type,
-                        );
-                    }
+        if fs.is_corrupted() {
+            return Ok(self.recover_corrupted_filesystem(chat_id, drive_id));
+        }
 
-                    if let Some(sticker) = msg.sticker {
-                        return process_file_message(
-                            cs,
-                            &fs,
-                            chat_id.clone(),
-                            msg.message_id,
-                            sticker.file_size,
-                            Some(format!("{TG_FILE_MIME_TYPE_PREFIX}sticker")),
-                        );
-                    }
+        if res.is_err() {
+            self.discard_failed_update(chat_member_id, &original_chat_session);
+            return res;
+        }
 
-                    if msg.contact.is_some() {
-                        return process_file_message(
-                            cs,
-                            &fs,
-                            chat_id.clone(),
-                            msg.message_id,
-                            None,
-                            Some(format!("{TG_FILE_MIME_TYPE_PREFIX}contact")),
-                        );
-                    }
+        self.save_chat_session_and_filesystem(
+            chat_member_id,
+            chat_session,
+            &original_chat_session,
+            drive_id,
+            fs,
+        );
 
-                    Ok(MessageParams::generic_error(chat_id.clone()))
-                }
-            }
-        });
+        res
+    }
 
-        self.save_chat_session_and_filesystem(chat_id, chat_session, fs);
+    fn add_media_group_file(&self, chat_id: &ChatId, msg: &Message) -> bool {
+        let Some(media_group_id) = &msg.media_group_id else {
+            return false;
+        };
+        let chat_member_id = ChatMemberId::from_sender(chat_id.clone(), msg.from.as_deref());
+        let Some(mut chat_session) = self
+            .chat_session_repository
+            .get_chat_session(&chat_member_id)
+        else {
+            return false;
+        };
+        let Some(ChatSessionAction::SaveMediaGroup(Some(mut media_group), wait_reply)) =
+            chat_session.action()
+        else {
+            return false;
+        };
+        if &media_group.media_group_id != media_group_id {
+            return false;
+        }
+        let Some(file) = received_file(msg) else {
+            return false;
+        };
 
-        res
+        media_group.files.push(file);
+        custom_print!(
+            "Added message {} to album {} in chat {}, {} files",
+            msg.message_id,
+            media_group_id,
+            chat_id,
+            media_group.files.len()
+        );
+        chat_session.set_action(ChatSessionAction::SaveMediaGroup(
+            Some(media_group),
+            wait_reply,
+        ));
+        self.update_chat_session(chat_member_id, chat_session);
+        true
     }
 
     fn handle_update_content_callback_query(
@@ -351,11 +918,49 @@ impl<T: ChatSessionRepository, F: FilesystemService> ChatSessionService
         chat_id: ChatId,
         query: CallbackQuery,
     ) -> Result<MessageParams, String> {
-        let mut fs = self.filesystem_service.get_or_create_filesystem(&chat_id);
-        let mut chat_session = self.get_or_create_chat_session(&chat_id);
+        let chat_member_id = ChatMemberId::new(chat_id.clone(), UserId(query.from.id));
+        let mut chat_session = self.get_or_create_chat_session(&chat_member_id);
+
+        let (keyboard_message_id, keyboard_anchor) = match &query.message {
+            Some(MaybeInaccessibleMessage::Message(msg)) => (
+                Some(msg.message_id),
+                msg.reply_to_message
+                    .as_ref()
+                    .map(|reply_to_message| reply_to_message.message_id),
+            ),
+            Some(MaybeInaccessibleMessage::InaccessibleMessage(msg)) => {
+                (Some(msg.message_id), None)
+            }
+            None => (None, None),
+        };
+        // in groups, the members can only press the buttons of the keyboards they opened
+        if !chat_id.is_private()
+            && (chat_session.action().is_none()
+                || chat_session.keyboard_anchor() != keyboard_anchor)
+        {
+            custom_print!(
+                "Rejecting a callback query of user {} on a keyboard of another member",
+                chat_member_id.user_id
+            );
+            return Ok(MessageParams::new_callback_query_alert(
+                query.id,
+                foreign_keyboard_message(),
+            ));
+        }
+
+        let original_chat_session = chat_session.clone();
+        let drive_id = self.get_active_drive_id(&chat_id, &mut chat_session);
+        let mut fs = self
+            .filesystem_service
+            .get_or_create_filesystem(&chat_id, drive_id);
+        self.share_service.mount_shares(&chat_id, &mut fs);
+        let chat_settings = self.chat_settings_service.get_chat_settings(&chat_id);
+        fs.set_sort_order(chat_settings.sort_order());
+        let role = self.chat_role_service.get_role(&chat_member_id);
+        let mut is_chat_data_deleted = false;
 
         let res = with_clear_action_on_error(&mut chat_session, |cs| {
-            let action = query
+            let action: ChatSessionAction = query
                 .data
                 .ok_or_else(|| "Data not found in callback query".to_string())?
                 .into();
@@ -379,6 +984,23 @@ impl<T: ChatSessionRepository, F: FilesystemService> ChatSessionService
             let current_action = cs.action().ok_or_else(|| {
                 "UpdateContent::CallbackQuery: No action in chat session".to_string()
             })?;
+            // the role may have changed since the action has been started
+            if role < current_action.required_role() {
+                cs.clear_action();
+                return Ok(permission_denied_message_params(
+                    chat_id.clone(),
+                    role,
+                    current_action.required_role(),
+                ));
+            }
+            // some buttons start actions that need a higher role than the current one
+            if role < action.required_role() {
+                return Ok(permission_denied_message_params(
+                    chat_id.clone(),
+                    role,
+                    action.required_role(),
+                ));
+            }
 
             match action {
                 ChatSessionAction::CurrentDir => match current_action {
@@ -393,13 +1015,73 @@ impl<T: ChatSessionRepository, F: FilesystemService> ChatSessionService
                         Ok(edit_message_params)
                     }
                     ChatSessionAction::SaveFile(Some(file_node), None) => {
-                        cs.set_action(ChatSessionAction::SaveFile(
-                            Some(file_node),
-                            Some(ChatSessionWaitReply::FileName),
-                        ));
-                        edit_message_params
-                            .set_text(ask_file_name_message(cs.current_path_string()));
-                        edit_message_params.set_inline_keyboard_markup(back_inline_keyboard());
+                        match auto_file_name(&chat_settings, cs.received_file_name()) {
+                            Some(file_name) => {
+                                let dir_path = cs.current_path().clone();
+                                let final_file_path = self.save_file(
+                                    &chat_id,
+                                    drive_id,
+                                    &mut fs,
+                                    &dir_path.join(file_name),
+                                    file_node,
+                                    chat_settings.conflict_policy(),
+                                )?;
+                                cs.reset();
+
+                                edit_message_params.set_text(created_file_success_message(
+                                    final_file_path
+                                        .file_name()
+                                        .unwrap_or_default()
+                                        .to_string_lossy()
+                                        .to_string(),
+                                    dir_path.to_string_lossy().to_string(),
+                                ));
+                            }
+                            None => {
+                                cs.set_action(ChatSessionAction::SaveFile(
+                                    Some(file_node),
+                                    Some(ChatSessionWaitReply::FileName),
+                                ));
+                                edit_message_params
+                                    .set_text(ask_file_name_message(cs.current_path_string()));
+                                edit_message_params
+                                    .set_inline_keyboard_markup(back_inline_keyboard());
+                            }
+                        }
+
+                        Ok(edit_message_params)
+                    }
+                    ChatSessionAction::SaveMediaGroup(Some(media_group), None) => {
+                        match auto_media_group_file_names(&chat_settings, &media_group) {
+                            Some(file_names) => {
+                                let dir_path = cs.current_path().clone();
+                                let text = self.save_media_group_files(
+                                    &chat_id,
+                                    drive_id,
+                                    &mut fs,
+                                    &dir_path,
+                                    media_group,
+                                    file_names,
+                                    chat_settings.conflict_policy(),
+                                );
+                                cs.reset();
+
+                                edit_message_params.set_text(text);
+                            }
+                            None => {
+                                edit_message_params.set_text(ask_media_group_file_names_message(
+                                    media_group.files.len(),
+                                    cs.current_path_string(),
+                                ));
+                                edit_message_params.set_inline_keyboard_markup(
+                                    keep_file_names_inline_keyboard(),
+                                );
+                                cs.set_action(ChatSessionAction::SaveMediaGroup(
+                                    Some(media_group),
+                                    Some(ChatSessionWaitReply::FileName),
+                                ));
+                            }
+                        }
 
                         Ok(edit_message_params)
                     }
@@ -408,6 +1090,14 @@ impl<T: ChatSessionRepository, F: FilesystemService> ChatSessionService
                             from_path.file_name().unwrap().to_string_lossy().to_string();
                         let to_path = cs.current_path().join(&file_name);
                         fs.mv(&from_path, &to_path)?;
+                        self.record_operation(
+                            &chat_id,
+                            drive_id,
+                            JournalOperation::MoveFile {
+                                from: from_path.clone(),
+                                to: to_path.clone(),
+                            },
+                        );
 
                         edit_message_params.set_text(moved_file_success_message(
                             file_name,
@@ -416,6 +1106,64 @@ impl<T: ChatSessionRepository, F: FilesystemService> ChatSessionService
                         ));
                         Ok(edit_message_params)
                     }
+                    ChatSessionAction::Bulk(
+                        operation @ (BulkOperation::Move | BulkOperation::Copy),
+                        None,
+                    ) => {
+                        let dir_path = cs.current_path().clone();
+                        let bulk_action = match operation {
+                            BulkOperation::Move => BulkAction::Move(&dir_path),
+                            _ => BulkAction::Copy(&dir_path),
+                        };
+                        let text = self.apply_bulk_action(
+                            &chat_id,
+                            drive_id,
+                            &mut fs,
+                            bulk_action,
+                            cs.selection(),
+                        );
+                        cs.reset();
+
+                        edit_message_params.set_text(text);
+                        Ok(edit_message_params)
+                    }
+                    ChatSessionAction::Share(None) => {
+                        cs.set_action(ChatSessionAction::Share(Some(cs.current_path().clone())));
+                        edit_message_params
+                            .set_text(share_permission_message(cs.current_path_string()));
+                        edit_message_params
+                            .set_inline_keyboard_markup(share_permission_inline_keyboard());
+
+                        Ok(edit_message_params)
+                    }
+                    ChatSessionAction::EditSetting(Setting::SaveDir) => {
+                        let save_dir = cs.current_path().clone();
+                        self.chat_settings_service
+                            .update_chat_settings(chat_id.clone(), |chat_settings| {
+                                chat_settings.save_dir = Some(save_dir)
+                            });
+                        self.set_settings_menu(&chat_id, cs, &mut edit_message_params);
+
+                        Ok(edit_message_params)
+                    }
+                    ChatSessionAction::RefineFile(Some(file_path), None) => {
+                        edit_message_params.set_text(ask_refine_file_name_message(
+                            file_path
+                                .file_name()
+                                .unwrap_or_default()
+                                .to_string_lossy()
+                                .to_string(),
+                            cs.current_path_string(),
+                        ));
+                        edit_message_params
+                            .set_inline_keyboard_markup(keep_file_names_inline_keyboard());
+                        cs.set_action(ChatSessionAction::RefineFile(
+                            Some(file_path),
+                            Some(ChatSessionWaitReply::FileName),
+                        ));
+
+                        Ok(edit_message_params)
+                    }
                     _ => action_not_supported_error(),
                 },
                 ChatSessionAction::ParentDir => {
@@ -431,11 +1179,8 @@ impl<T: ChatSessionRepository, F: FilesystemService> ChatSessionService
                                 cs.set_current_path(parent_path.to_path_buf());
                                 edit_message_params
                                     .set_text(explorer_message(cs.current_path_string()));
-
-                                let keyboard = KeyboardDirectoryBuilder::new(&fs, parent_path)?
-                                    .with_files()?
-                                    .build();
-                                edit_message_params.set_inline_keyboard_markup(keyboard);
+                                edit_message_params
+                                    .set_inline_keyboard_markup(explorer_keyboard(&fs, parent_path)?);
                             } else {
                                 // should never happen
                                 return Err("Parent is not a directory".to_string());
@@ -443,6 +1188,18 @@ impl<T: ChatSessionRepository, F: FilesystemService> ChatSessionService
 
                             Ok(edit_message_params)
                         }
+                        ChatSessionAction::SelectFiles => {
+                            cs.set_current_path(parent_path.to_path_buf());
+                            set_selection_view(cs, &fs, &mut edit_message_params)?;
+
+                            Ok(edit_message_params)
+                        }
+                        ChatSessionAction::Bulk(operation, None) => {
+                            cs.set_current_path(parent_path.to_path_buf());
+                            set_bulk_destination_view(cs, &fs, operation, &mut edit_message_params)?;
+
+                            Ok(edit_message_params)
+                        }
                         ChatSessionAction::MkDir(_) => {
                             cs.set_current_path(parent_path.to_path_buf());
                             edit_message_params.set_text(mkdir_message(cs.current_path_string()));
@@ -464,6 +1221,17 @@ impl<T: ChatSessionRepository, F: FilesystemService> ChatSessionService
                             edit_message_params.set_inline_keyboard_markup(keyboard);
                             Ok(edit_message_params)
                         }
+                        ChatSessionAction::SaveMediaGroup(Some(_), None) => {
+                            cs.set_current_path(parent_path.to_path_buf());
+                            edit_message_params
+                                .set_text(create_media_group_message(cs.current_path_string()));
+
+                            let keyboard = KeyboardDirectoryBuilder::new(&fs, parent_path)?
+                                .with_current_dir_button()
+                                .build();
+                            edit_message_params.set_inline_keyboard_markup(keyboard);
+                            Ok(edit_message_params)
+                        }
                         ChatSessionAction::RenameFile(_) => {
                             cs.set_current_path(parent_path.to_path_buf());
                             edit_message_params
@@ -503,27 +1271,54 @@ impl<T: ChatSessionRepository, F: FilesystemService> ChatSessionService
 
                             Ok(edit_message_params)
                         }
-                        _ => action_not_supported_error(),
-                    }
-                }
-                ChatSessionAction::FileOrDir(path) => match current_action {
-                    ChatSessionAction::Explorer => {
-                        let node = fs.get_node(&path)?;
-
-                        if node.is_directory() {
-                            cs.set_current_path(path.clone());
-                            edit_message_params
-                                .set_text(explorer_message(cs.current_path_string()));
+                        ChatSessionAction::Share(None) => {
+                            cs.set_current_path(parent_path.to_path_buf());
+                            edit_message_params.set_text(share_message(cs.current_path_string()));
 
-                            let keyboard = KeyboardDirectoryBuilder::new(&fs, &path)?
-                                .with_files()?
+                            let keyboard = KeyboardDirectoryBuilder::new(&fs, parent_path)?
+                                .with_current_dir_button()
                                 .build();
                             edit_message_params.set_inline_keyboard_markup(keyboard);
+                            Ok(edit_message_params)
+                        }
+                        ChatSessionAction::EditSetting(Setting::SaveDir) => {
+                            cs.set_current_path(parent_path.to_path_buf());
+                            set_save_dir_setting_view(cs, &fs, &mut edit_message_params)?;
+
+                            Ok(edit_message_params)
+                        }
+                        ChatSessionAction::RefineFile(Some(file_path), None) => {
+                            cs.set_current_path(parent_path.to_path_buf());
+                            set_refine_file_view(cs, &fs, &file_path, &mut edit_message_params)?;
+
+                            Ok(edit_message_params)
+                        }
+                        _ => action_not_supported_error(),
+                    }
+                }
+                ChatSessionAction::FileOrDir(path) => match current_action {
+                    ChatSessionAction::Explorer => {
+                        let node = fs.get_node(&path)?;
+
+                        if node.is_directory() {
+                            cs.set_current_path(path.clone());
+                            edit_message_params
+                                .set_text(explorer_message(cs.current_path_string()));
+                            edit_message_params
+                                .set_inline_keyboard_markup(explorer_keyboard(&fs, &path)?);
                         } else {
+                            let (source_chat_id, message_id) = fs.get_file_message(&path)?;
+                            // the files of the shared directories are in other chats,
+                            // so they can only be copied here
+                            if let Some(source_chat_id) = source_chat_id {
+                                return Ok(MessageParams::new_copy(
+                                    chat_id.clone(),
+                                    source_chat_id,
+                                    message_id,
+                                ));
+                            }
+
                             // reply to the file
-                            let message_id = node
-                                .file_message_id()
-                                .ok_or_else(|| "Message id not found".to_string())?;
                             let file_name = path
                                 .file_name()
                                 .ok_or_else(|| "File name not found".to_string())?
@@ -534,6 +1329,7 @@ impl<T: ChatSessionRepository, F: FilesystemService> ChatSessionService
                             send_message_params.set_text(explorer_file_message(
                                 file_name,
                                 cs.current_path_string(),
+                                &node.file_tags(),
                             ));
                             send_message_params.set_reply_to_message_id(message_id)?;
 
@@ -542,6 +1338,22 @@ impl<T: ChatSessionRepository, F: FilesystemService> ChatSessionService
 
                         Ok(edit_message_params)
                     }
+                    ChatSessionAction::SelectFiles => {
+                        if fs.get_node(&path)?.is_directory() {
+                            cs.set_current_path(path);
+                        } else {
+                            cs.toggle_selection(path);
+                        }
+                        set_selection_view(cs, &fs, &mut edit_message_params)?;
+
+                        Ok(edit_message_params)
+                    }
+                    ChatSessionAction::Bulk(operation, None) => {
+                        cs.set_current_path(path);
+                        set_bulk_destination_view(cs, &fs, operation, &mut edit_message_params)?;
+
+                        Ok(edit_message_params)
+                    }
                     ChatSessionAction::MkDir(_) => {
                         cs.set_current_path(path.clone());
                         edit_message_params.set_text(mkdir_message(cs.current_path_string()));
@@ -562,6 +1374,39 @@ impl<T: ChatSessionRepository, F: FilesystemService> ChatSessionService
                         edit_message_params.set_inline_keyboard_markup(keyboard);
                         Ok(edit_message_params)
                     }
+                    ChatSessionAction::SaveMediaGroup(Some(_), None) => {
+                        cs.set_current_path(path.clone());
+                        edit_message_params
+                            .set_text(create_media_group_message(cs.current_path_string()));
+
+                        let keyboard = KeyboardDirectoryBuilder::new(&fs, &path)?
+                            .with_current_dir_button()
+                            .build();
+                        edit_message_params.set_inline_keyboard_markup(keyboard);
+                        Ok(edit_message_params)
+                    }
+                    ChatSessionAction::Share(None) => {
+                        cs.set_current_path(path.clone());
+                        edit_message_params.set_text(share_message(cs.current_path_string()));
+
+                        let keyboard = KeyboardDirectoryBuilder::new(&fs, &path)?
+                            .with_current_dir_button()
+                            .build();
+                        edit_message_params.set_inline_keyboard_markup(keyboard);
+                        Ok(edit_message_params)
+                    }
+                    ChatSessionAction::EditSetting(Setting::SaveDir) => {
+                        cs.set_current_path(path);
+                        set_save_dir_setting_view(cs, &fs, &mut edit_message_params)?;
+
+                        Ok(edit_message_params)
+                    }
+                    ChatSessionAction::RefineFile(Some(file_path), None) => {
+                        cs.set_current_path(path);
+                        set_refine_file_view(cs, &fs, &file_path, &mut edit_message_params)?;
+
+                        Ok(edit_message_params)
+                    }
                     ChatSessionAction::RenameFile(None) => {
                         let node = fs.get_node(&path)?;
 
@@ -575,10 +1420,7 @@ impl<T: ChatSessionRepository, F: FilesystemService> ChatSessionService
                                 .build();
                             edit_message_params.set_inline_keyboard_markup(keyboard);
                         } else {
-                            // reply to the file
-                            let message_id = node
-                                .file_message_id()
-                                .ok_or_else(|| "Message id not found".to_string())?;
+                            let file_message = fs.get_file_message(&path)?;
                             let file_name = path
                                 .file_name()
                                 .ok_or_else(|| "File name not found".to_string())?
@@ -590,7 +1432,7 @@ impl<T: ChatSessionRepository, F: FilesystemService> ChatSessionService
                                 file_name,
                                 cs.current_path_string(),
                             ));
-                            send_message_params.set_reply_to_message_id(message_id)?;
+                            reply_to_file_message(&mut send_message_params, file_message)?;
 
                             cs.set_current_path(path);
                             cs.set_action(ChatSessionAction::RenameFile(Some(
@@ -632,10 +1474,7 @@ impl<T: ChatSessionRepository, F: FilesystemService> ChatSessionService
                             edit_message_params.set_text(message_text);
                             edit_message_params.set_inline_keyboard_markup(keyboard);
                         } else {
-                            // reply to the file
-                            let message_id = node
-                                .file_message_id()
-                                .ok_or_else(|| "Message id not found".to_string())?;
+                            let file_message = fs.get_file_message(&path)?;
                             let from_path = path.clone();
 
                             cs.set_current_path(root_path());
@@ -644,7 +1483,7 @@ impl<T: ChatSessionRepository, F: FilesystemService> ChatSessionService
                             send_message_params.set_text(move_file_select_destination_message(
                                 from_path.to_string_lossy().to_string(),
                             ));
-                            send_message_params.set_reply_to_message_id(message_id)?;
+                            reply_to_file_message(&mut send_message_params, file_message)?;
                             let keyboard = KeyboardDirectoryBuilder::new(&fs, cs.current_path())?
                                 .with_current_dir_button()
                                 .build();
@@ -660,6 +1499,28 @@ impl<T: ChatSessionRepository, F: FilesystemService> ChatSessionService
                     _ => action_not_supported_error(),
                 },
                 ChatSessionAction::Back => match current_action {
+                    ChatSessionAction::Bulk(_, _) => {
+                        cs.set_action(ChatSessionAction::SelectFiles);
+                        set_selection_view(cs, &fs, &mut edit_message_params)?;
+
+                        Ok(edit_message_params)
+                    }
+                    ChatSessionAction::CreateDrive(_) | ChatSessionAction::DeleteDrive(_) => {
+                        self.set_drives_menu(&chat_id, cs, &mut edit_message_params);
+
+                        Ok(edit_message_params)
+                    }
+                    ChatSessionAction::EditSetting(_) => {
+                        self.set_settings_menu(&chat_id, cs, &mut edit_message_params);
+
+                        Ok(edit_message_params)
+                    }
+                    ChatSessionAction::RefineFile(Some(file_path), Some(_)) => {
+                        set_refine_file_view(cs, &fs, &file_path, &mut edit_message_params)?;
+                        cs.set_action(ChatSessionAction::RefineFile(Some(file_path), None));
+
+                        Ok(edit_message_params)
+                    }
                     ChatSessionAction::MkDir(Some(_)) => {
                         cs.set_action(ChatSessionAction::MkDir(None));
 
@@ -684,60 +1545,1201 @@ impl<T: ChatSessionRepository, F: FilesystemService> ChatSessionService
 
                         Ok(edit_message_params)
                     }
+                    ChatSessionAction::SaveMediaGroup(Some(media_group), Some(_)) => {
+                        cs.set_action(ChatSessionAction::SaveMediaGroup(Some(media_group), None));
+
+                        edit_message_params
+                            .set_text(create_media_group_message(cs.current_path_string()));
+
+                        let keyboard = KeyboardDirectoryBuilder::new(&fs, cs.current_path())?
+                            .with_current_dir_button()
+                            .build();
+                        edit_message_params.set_inline_keyboard_markup(keyboard);
+
+                        Ok(edit_message_params)
+                    }
+                    ChatSessionAction::Share(Some(_)) => {
+                        cs.set_action(ChatSessionAction::Share(None));
+
+                        edit_message_params.set_text(share_message(cs.current_path_string()));
+
+                        let keyboard = KeyboardDirectoryBuilder::new(&fs, cs.current_path())?
+                            .with_current_dir_button()
+                            .build();
+                        edit_message_params.set_inline_keyboard_markup(keyboard);
+
+                        Ok(edit_message_params)
+                    }
+                    _ => action_not_supported_error(),
+                },
+                ChatSessionAction::Confirm => match current_action {
+                    ChatSessionAction::ForgetMe(None) => {
+                        cs.set_action(ChatSessionAction::ForgetMe(Some(
+                            ChatSessionWaitReply::FinalConfirmation,
+                        )));
+
+                        edit_message_params.set_text(forget_me_final_confirmation_message());
+                        edit_message_params.set_inline_keyboard_markup(confirm_inline_keyboard());
+
+                        Ok(edit_message_params)
+                    }
+                    ChatSessionAction::ForgetMe(Some(ChatSessionWaitReply::FinalConfirmation)) => {
+                        self.delete_chat_data(&chat_id);
+                        is_chat_data_deleted = true;
+
+                        edit_message_params.set_text(forgotten_message());
+
+                        Ok(edit_message_params)
+                    }
+                    ChatSessionAction::Bulk(BulkOperation::Delete, None) => {
+                        let text = self.apply_bulk_action(
+                            &chat_id,
+                            drive_id,
+                            &mut fs,
+                            BulkAction::Delete,
+                            cs.selection(),
+                        );
+                        cs.reset();
+
+                        edit_message_params.set_text(text);
+                        Ok(edit_message_params)
+                    }
+                    ChatSessionAction::DeleteDrive(Some(deleted_drive_id)) => {
+                        let drive = self
+                            .drive_service
+                            .delete_drive(&chat_id, deleted_drive_id)?;
+                        self.share_service
+                            .delete_drive_shares(&chat_id, deleted_drive_id);
+                        self.journal_service
+                            .delete_drive_entries(&chat_id, Some(deleted_drive_id));
+                        cs.clear_action();
+                        if cs.drive_id() == Some(deleted_drive_id) {
+                            cs.set_drive_id(None);
+                        }
+
+                        edit_message_params.set_text(drive_deleted_message(drive.name));
+
+                        Ok(edit_message_params)
+                    }
+                    _ => action_not_supported_error(),
+                },
+                ChatSessionAction::Cancel => match current_action {
+                    ChatSessionAction::ForgetMe(_) => {
+                        cs.clear_action();
+
+                        edit_message_params.set_text(forget_me_cancelled_message());
+
+                        Ok(edit_message_params)
+                    }
+                    ChatSessionAction::DeleteDrive(_) => {
+                        self.set_drives_menu(&chat_id, cs, &mut edit_message_params);
+
+                        Ok(edit_message_params)
+                    }
+                    ChatSessionAction::SelectFiles => {
+                        cs.clear_selection();
+                        cs.set_action(ChatSessionAction::Explorer);
+
+                        edit_message_params.set_text(explorer_message(cs.current_path_string()));
+                        edit_message_params
+                            .set_inline_keyboard_markup(explorer_keyboard(&fs, cs.current_path())?);
+
+                        Ok(edit_message_params)
+                    }
+                    ChatSessionAction::Bulk(_, _) => {
+                        cs.set_action(ChatSessionAction::SelectFiles);
+                        set_selection_view(cs, &fs, &mut edit_message_params)?;
+
+                        Ok(edit_message_params)
+                    }
+                    _ => action_not_supported_error(),
+                },
+                ChatSessionAction::GrantShare(permission) => match current_action {
+                    ChatSessionAction::Share(Some(path)) => {
+                        cs.clear_action();
+
+                        let text =
+                            match self
+                                .share_service
+                                .create_share(chat_id.clone(), drive_id, &fs, &path, permission)
+                            {
+                                Ok(share) => share_created_message(
+                                    &share,
+                                    self.config_repository.get_config().bot_username,
+                                ),
+                                Err(err) => share_failed_message(err),
+                            };
+                        edit_message_params.set_text(text);
+
+                        Ok(edit_message_params)
+                    }
+                    _ => action_not_supported_error(),
+                },
+                ChatSessionAction::RevokeShare(share_id) => match current_action {
+                    ChatSessionAction::Shares => {
+                        let share = self.share_service.revoke_share(&chat_id, share_id)?;
+
+                        let shares = self.share_service.get_owned_shares(&chat_id);
+                        edit_message_params.set_text(share_revoked_message(&share, &shares));
+                        edit_message_params
+                            .set_inline_keyboard_markup(shares_inline_keyboard(&shares));
+
+                        Ok(edit_message_params)
+                    }
+                    _ => action_not_supported_error(),
+                },
+                ChatSessionAction::SwitchDrive(new_drive_id) => match current_action {
+                    ChatSessionAction::Drives => {
+                        if let Some(new_drive_id) = new_drive_id {
+                            self.drive_service
+                                .get_drive(&chat_id, new_drive_id)
+                                .ok_or_else(|| "Drive not found".to_string())?;
+                        }
+                        cs.clear_action();
+                        cs.set_drive_id(new_drive_id);
+
+                        edit_message_params.set_text(drive_switched_message(
+                            self.drive_service.get_drive_name(&chat_id, new_drive_id),
+                        ));
+
+                        Ok(edit_message_params)
+                    }
+                    _ => action_not_supported_error(),
+                },
+                ChatSessionAction::CreateDrive(None) => match current_action {
+                    ChatSessionAction::Drives => {
+                        cs.set_action(ChatSessionAction::CreateDrive(Some(
+                            ChatSessionWaitReply::DriveName,
+                        )));
+
+                        edit_message_params.set_text(ask_drive_name_message());
+                        edit_message_params.set_inline_keyboard_markup(back_inline_keyboard());
+
+                        Ok(edit_message_params)
+                    }
+                    _ => action_not_supported_error(),
+                },
+                ChatSessionAction::DeleteDrive(None) => match current_action {
+                    ChatSessionAction::Drives => {
+                        cs.set_action(ChatSessionAction::DeleteDrive(None));
+
+                        edit_message_params.set_text(delete_drive_message());
+                        edit_message_params.set_inline_keyboard_markup(
+                            delete_drive_inline_keyboard(&self.drive_service.get_drives(&chat_id)),
+                        );
+
+                        Ok(edit_message_params)
+                    }
+                    _ => action_not_supported_error(),
+                },
+                ChatSessionAction::DeleteDrive(Some(selected_drive_id)) => match current_action {
+                    ChatSessionAction::DeleteDrive(None) => {
+                        let drive = self
+                            .drive_service
+                            .get_drive(&chat_id, selected_drive_id)
+                            .ok_or_else(|| "Drive not found".to_string())?;
+                        cs.set_action(ChatSessionAction::DeleteDrive(Some(selected_drive_id)));
+
+                        edit_message_params.set_text(delete_drive_confirmation_message(drive.name));
+                        edit_message_params.set_inline_keyboard_markup(confirm_inline_keyboard());
+
+                        Ok(edit_message_params)
+                    }
+                    _ => action_not_supported_error(),
+                },
+                ChatSessionAction::KeepFileNames => match current_action {
+                    ChatSessionAction::SaveMediaGroup(
+                        Some(media_group),
+                        Some(ChatSessionWaitReply::FileName),
+                    ) => {
+                        let file_names = media_group_file_names(&media_group, None);
+                        let dir_path = cs.current_path().clone();
+                        let text = self.save_media_group_files(
+                            &chat_id,
+                            drive_id,
+                            &mut fs,
+                            &dir_path,
+                            media_group,
+                            file_names,
+                            chat_settings.conflict_policy(),
+                        );
+                        cs.reset();
+
+                        edit_message_params.set_text(text);
+                        Ok(edit_message_params)
+                    }
+                    ChatSessionAction::RefineFile(
+                        Some(from_path),
+                        Some(ChatSessionWaitReply::FileName),
+                    ) => {
+                        let to_path = cs
+                            .current_path()
+                            .join(from_path.file_name().unwrap_or_default());
+                        let text =
+                            self.refine_file(&chat_id, drive_id, &mut fs, &from_path, &to_path)?;
+                        cs.reset();
+
+                        edit_message_params.set_text(text);
+                        Ok(edit_message_params)
+                    }
+                    _ => action_not_supported_error(),
+                },
+                ChatSessionAction::RefineFile(None, _) => match current_action {
+                    ChatSessionAction::RefineFile(Some(file_path), _) => {
+                        // the file may have been moved or deleted in the meantime
+                        fs.get_node(&file_path)?;
+                        cs.set_current_path(existing_dir_or_root(
+                            &fs,
+                            file_path.parent().unwrap_or(&root_path()).to_path_buf(),
+                        ));
+                        set_refine_file_view(cs, &fs, &file_path, &mut edit_message_params)?;
+                        cs.set_action(ChatSessionAction::RefineFile(Some(file_path), None));
+
+                        Ok(edit_message_params)
+                    }
+                    _ => action_not_supported_error(),
+                },
+                ChatSessionAction::SelectFiles => match current_action {
+                    ChatSessionAction::Explorer => {
+                        cs.clear_selection();
+                        cs.set_action(ChatSessionAction::SelectFiles);
+                        set_selection_view(cs, &fs, &mut edit_message_params)?;
+
+                        Ok(edit_message_params)
+                    }
+                    _ => action_not_supported_error(),
+                },
+                ChatSessionAction::Bulk(operation, None) => match current_action {
+                    ChatSessionAction::SelectFiles => {
+                        let selected_count = cs.selection().len();
+                        if selected_count == 0 {
+                            return Ok(MessageParams::new_callback_query_alert(
+                                query.id.clone(),
+                                empty_selection_message(),
+                            ));
+                        }
+
+                        match operation {
+                            BulkOperation::Move | BulkOperation::Copy => {
+                                cs.set_action(ChatSessionAction::Bulk(operation, None));
+                                set_bulk_destination_view(
+                                    cs,
+                                    &fs,
+                                    operation,
+                                    &mut edit_message_params,
+                                )?;
+                            }
+                            BulkOperation::Delete => {
+                                cs.set_action(ChatSessionAction::Bulk(operation, None));
+                                edit_message_params
+                                    .set_text(bulk_delete_confirmation_message(selected_count));
+                                edit_message_params
+                                    .set_inline_keyboard_markup(confirm_inline_keyboard());
+                            }
+                            BulkOperation::Tag => {
+                                cs.set_action(ChatSessionAction::Bulk(
+                                    operation,
+                                    Some(ChatSessionWaitReply::TagName),
+                                ));
+                                edit_message_params.set_text(ask_tag_message(selected_count));
+                                edit_message_params
+                                    .set_inline_keyboard_markup(back_inline_keyboard());
+                            }
+                        }
+
+                        Ok(edit_message_params)
+                    }
+                    _ => action_not_supported_error(),
+                },
+                ChatSessionAction::SetLanguage(language) => match current_action {
+                    ChatSessionAction::Languages => {
+                        cs.clear_action();
+                        self.set_chat_language(&chat_id, language, Some(&query.from));
+
+                        edit_message_params.set_text(language_set_message(language));
+
+                        Ok(edit_message_params)
+                    }
+                    ChatSessionAction::EditSetting(Setting::Language) => {
+                        self.set_chat_language(&chat_id, language, Some(&query.from));
+                        self.set_settings_menu(&chat_id, cs, &mut edit_message_params);
+
+                        Ok(edit_message_params)
+                    }
+                    _ => action_not_supported_error(),
+                },
+                ChatSessionAction::EditSetting(setting) => match current_action {
+                    ChatSessionAction::Settings => {
+                        cs.set_action(ChatSessionAction::EditSetting(setting));
+                        match setting {
+                            Setting::SaveDir => {
+                                cs.set_current_path(existing_dir_or_root(
+                                    &fs,
+                                    chat_settings.save_dir(),
+                                ));
+                                set_save_dir_setting_view(cs, &fs, &mut edit_message_params)?;
+                            }
+                            Setting::Inbox => {
+                                self.chat_settings_service.update_chat_settings(
+                                    chat_id.clone(),
+                                    |chat_settings| {
+                                        chat_settings.inbox = Some(!chat_settings.inbox())
+                                    },
+                                );
+                                self.set_settings_menu(&chat_id, cs, &mut edit_message_params);
+                            }
+                            Setting::SortOrder => {
+                                edit_message_params.set_text(sort_order_setting_message());
+                                edit_message_params.set_inline_keyboard_markup(
+                                    sort_order_inline_keyboard(chat_settings.sort_order()),
+                                );
+                            }
+                            Setting::ConflictPolicy => {
+                                edit_message_params.set_text(conflict_policy_setting_message());
+                                edit_message_params.set_inline_keyboard_markup(
+                                    conflict_policy_inline_keyboard(
+                                        chat_settings.conflict_policy(),
+                                    ),
+                                );
+                            }
+                            Setting::Language => {
+                                edit_message_params
+                                    .set_text(language_message(chat_settings.language));
+                                edit_message_params.set_inline_keyboard_markup(
+                                    language_setting_inline_keyboard(chat_settings.language),
+                                );
+                            }
+                            Setting::TimeZone => {
+                                edit_message_params
+                                    .set_text(ask_time_zone_message(chat_settings.time_zone()));
+                                edit_message_params
+                                    .set_inline_keyboard_markup(back_inline_keyboard());
+                            }
+                            Setting::AutoNaming => {
+                                edit_message_params.set_text(auto_naming_setting_message());
+                                edit_message_params.set_inline_keyboard_markup(
+                                    auto_naming_inline_keyboard(chat_settings.auto_naming()),
+                                );
+                            }
+                            Setting::NameTemplate => {
+                                edit_message_params.set_text(ask_name_template_message(
+                                    chat_settings.name_template(),
+                                ));
+                                edit_message_params
+                                    .set_inline_keyboard_markup(back_inline_keyboard());
+                            }
+                        }
+
+                        Ok(edit_message_params)
+                    }
+                    _ => action_not_supported_error(),
+                },
+                ChatSessionAction::SetSortOrder(sort_order) => match current_action {
+                    ChatSessionAction::EditSetting(Setting::SortOrder) => {
+                        self.chat_settings_service
+                            .update_chat_settings(chat_id.clone(), |chat_settings| {
+                                chat_settings.sort_order = Some(sort_order)
+                            });
+                        self.set_settings_menu(&chat_id, cs, &mut edit_message_params);
+
+                        Ok(edit_message_params)
+                    }
+                    _ => action_not_supported_error(),
+                },
+                ChatSessionAction::SetConflictPolicy(conflict_policy) => match current_action {
+                    ChatSessionAction::EditSetting(Setting::ConflictPolicy) => {
+                        self.chat_settings_service
+                            .update_chat_settings(chat_id.clone(), |chat_settings| {
+                                chat_settings.conflict_policy = Some(conflict_policy)
+                            });
+                        self.set_settings_menu(&chat_id, cs, &mut edit_message_params);
+
+                        Ok(edit_message_params)
+                    }
+                    _ => action_not_supported_error(),
+                },
+                ChatSessionAction::SetAutoNaming(auto_naming) => match current_action {
+                    ChatSessionAction::EditSetting(Setting::AutoNaming) => {
+                        self.chat_settings_service
+                            .update_chat_settings(chat_id.clone(), |chat_settings| {
+                                chat_settings.auto_naming = Some(auto_naming)
+                            });
+                        self.set_settings_menu(&chat_id, cs, &mut edit_message_params);
+
+                        Ok(edit_message_params)
+                    }
                     _ => action_not_supported_error(),
                 },
                 ChatSessionAction::DeleteDir
+                | ChatSessionAction::Bulk(_, Some(_))
+                | ChatSessionAction::Drives
+                | ChatSessionAction::Languages
+                | ChatSessionAction::Settings
+                | ChatSessionAction::RefineFile(Some(_), _)
+                | ChatSessionAction::CreateDrive(Some(_))
+                | ChatSessionAction::Share(_)
+                | ChatSessionAction::Shares
                 | ChatSessionAction::Explorer
                 | ChatSessionAction::MoveFile(_)
                 | ChatSessionAction::DeleteFile
                 | ChatSessionAction::SaveFile(_, _)
+                | ChatSessionAction::SaveMediaGroup(_, _)
                 | ChatSessionAction::RenameFile(_)
-                | ChatSessionAction::MkDir(_) => Err("invalid action".to_string()),
+                | ChatSessionAction::MkDir(_)
+                | ChatSessionAction::ForgetMe(_) => Err("invalid action".to_string()),
+            }
+        })
+        .map(|params| match keyboard_message_id {
+            Some(keyboard_message_id) => {
+                anchor_keyboard(&chat_id, &mut chat_session, params, keyboard_message_id)
             }
+            None => params,
         });
 
-        self.save_chat_session_and_filesystem(chat_id, chat_session, fs);
+        if is_chat_data_deleted {
+            // writing the chat session back would store the chat id again
+            self.pending_journal_operations.take();
+            return res;
+        }
 
-        res
+        if fs.is_corrupted() {
+            return Ok(self.recover_corrupted_filesystem(chat_id, drive_id));
+        }
+
+        if res.is_err() {
+            self.discard_failed_update(chat_member_id, &original_chat_session);
+            return res;
+        }
+
+        self.save_chat_session_and_filesystem(
+            chat_member_id,
+            chat_session,
+            &original_chat_session,
+            drive_id,
+            fs,
+        );
+
+        res
+    }
+
+    fn handle_update_content_edited_message(&self, chat_id: ChatId, msg: Message) {
+        // only the text of the messages can be edited, the files keep their content
+        let Some(text) = msg.text else {
+            return;
+        };
+
+        let drive_ids = std::iter::once(None).chain(
+            self.drive_service
+                .get_drives(&chat_id)
+                .into_iter()
+                .map(|(drive_id, _)| Some(drive_id)),
+        );
+        for drive_id in drive_ids {
+            let mut fs = self
+                .filesystem_service
+                .get_or_create_filesystem(&chat_id, drive_id);
+            // the same folders are mounted on every drive, they're only updated once
+            if drive_id.is_none() {
+                self.share_service.mount_shares(&chat_id, &mut fs);
+            }
+
+            // a new filesystem is dirty, it must not be stored if no file was updated
+            match fs.update_text_files(msg.message_id, text.len() as u64) {
+                Ok(0) => continue,
+                Ok(updated_count) => {
+                    custom_print!(
+                        "Updated {} files of message {} in chat {}",
+                        updated_count,
+                        msg.message_id,
+                        chat_id
+                    );
+                }
+                Err(err) => {
+                    custom_print!("Error updating the files of an edited message: {}", err);
+                    continue;
+                }
+            }
+            if fs.is_corrupted() {
+                continue;
+            }
+
+            for (owner_chat_id, owner_drive_id, mounted_filesystem) in fs.take_dirty_mounts() {
+                self.filesystem_service.update_filesystem(
+                    &owner_chat_id,
+                    owner_drive_id,
+                    mounted_filesystem,
+                );
+            }
+            if fs.is_dirty() {
+                self.filesystem_service
+                    .update_filesystem(&chat_id, drive_id, fs);
+            }
+        }
+    }
+
+    fn handle_update_content_inline_query(
+        &self,
+        chat_id: ChatId,
+        query: InlineQuery,
+    ) -> Result<MessageParams, String> {
+        let chat_member_id = ChatMemberId::new(chat_id.clone(), UserId(query.from.id));
+        // the session is only read, nothing is stored for the users that never used the bot
+        let mut chat_session = self
+            .chat_session_repository
+            .get_chat_session(&chat_member_id)
+            .unwrap_or_default();
+        let drive_id = self.get_active_drive_id(&chat_id, &mut chat_session);
+        let fs = self
+            .filesystem_service
+            .get_or_create_filesystem(&chat_id, drive_id);
+
+        // only the files with a file id can be sent, the others are skipped
+        let results = fs
+            .search_telegram_files(query.query.trim(), MAX_INLINE_QUERY_RESULTS)?
+            .into_iter()
+            .enumerate()
+            .map(|(index, (path, telegram_file))| {
+                inline_query_result(index.to_string(), &path, telegram_file)
+            })
+            .collect();
+
+        Ok(MessageParams::new_inline_query_answer(
+            query.id,
+            results,
+            INLINE_QUERY_CACHE_TIME_SECONDS,
+        ))
+    }
+
+    fn handle_update_content_chat_member(
+        &self,
+        chat_member_updated: ChatMemberUpdated,
+        is_bot: bool,
+    ) {
+        self.chat_role_service
+            .handle_chat_member_update(&chat_member_updated, is_bot);
     }
 }
 
-impl<T: ChatSessionRepository, F: FilesystemService> ChatSessionServiceImpl<T, F> {
-    fn new(chat_session_repository: T, filesystem_service: F) -> Self {
+impl<
+        T: ChatSessionRepository,
+        F: FilesystemService,
+        J: JournalService,
+        U: ProcessedUpdateService,
+        R: ChatRoleService,
+        S: ShareService,
+        D: DriveService,
+        C: ConfigRepository,
+        L: ChatSettingsService,
+        M: RateLimitService,
+    > ChatSessionServiceImpl<T, F, J, U, R, S, D, C, L, M>
+{
+    // one argument per dependency, as in the other services
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        chat_session_repository: T,
+        filesystem_service: F,
+        journal_service: J,
+        processed_update_service: U,
+        chat_role_service: R,
+        share_service: S,
+        drive_service: D,
+        config_repository: C,
+        chat_settings_service: L,
+        rate_limit_service: M,
+    ) -> Self {
         Self {
             chat_session_repository,
             filesystem_service,
+            journal_service,
+            processed_update_service,
+            chat_role_service,
+            share_service,
+            drive_service,
+            config_repository,
+            chat_settings_service,
+            rate_limit_service,
+            pending_journal_operations: RefCell::default(),
         }
     }
 
-    fn save_chat_session_and_filesystem(
+    /// Returns the drive of the session, switching the session back to the main drive
+    /// if its drive has been deleted in the meantime, for example by another member.
+    fn get_active_drive_id(
+        &self,
+        chat_id: &ChatId,
+        chat_session: &mut ChatSession,
+    ) -> Option<DriveId> {
+        let drive_id = chat_session.drive_id()?;
+        if self.drive_service.get_drive(chat_id, drive_id).is_none() {
+            chat_session.set_drive_id(None);
+            return None;
+        }
+        Some(drive_id)
+    }
+
+    /// Stores the language of the chat, and replies in it from now on.
+    fn set_chat_language(&self, chat_id: &ChatId, language: Option<Language>, user: Option<&User>) {
+        self.chat_settings_service
+            .set_language(chat_id.clone(), language);
+        set_language(self.chat_settings_service.get_language(chat_id, user));
+    }
+
+    /// Opens the menu to switch, create and delete the drives.
+    fn set_drives_menu(
+        &self,
+        chat_id: &ChatId,
+        chat_session: &mut ChatSession,
+        message_params: &mut MessageParams,
+    ) {
+        chat_session.set_action(ChatSessionAction::Drives);
+
+        let drives = self.drive_service.get_drives(chat_id);
+        message_params.set_text(drives_message(chat_session.drive_id(), &drives));
+        message_params
+            .set_inline_keyboard_markup(drives_inline_keyboard(chat_session.drive_id(), &drives));
+    }
+
+    /// Opens the menu of the settings of the chat.
+    fn set_settings_menu(
+        &self,
+        chat_id: &ChatId,
+        chat_session: &mut ChatSession,
+        message_params: &mut MessageParams,
+    ) {
+        chat_session.set_action(ChatSessionAction::Settings);
+
+        let chat_settings = self.chat_settings_service.get_chat_settings(chat_id);
+        message_params.set_text(settings_message(&chat_settings));
+        message_params.set_inline_keyboard_markup(settings_inline_keyboard());
+    }
+
+    /// Sets the chat's filesystem aside for inspection and resets the sessions of its members,
+    /// so that the chat starts again from a new filesystem, without the journal entries of the old one.
+    /// The drives are left as they are, so that they can be deleted, and the members are switched back to the main drive.
+    fn recover_corrupted_filesystem(
         &self,
         chat_id: ChatId,
+        drive_id: Option<DriveId>,
+    ) -> MessageParams {
+        self.pending_journal_operations.take();
+        if let Some(drive_id) = drive_id {
+            custom_print!("Found corrupted drive {drive_id} of chat_id: {chat_id}");
+            self.chat_session_repository
+                .remove_chat_sessions_by_chat_id(&chat_id);
+
+            let mut send_message_params = MessageParams::new_send(chat_id);
+            send_message_params.set_text(corrupted_drive_message());
+            return send_message_params;
+        }
+
+        let quarantined_nodes_count = self.filesystem_service.quarantine_filesystem(&chat_id);
+        custom_print!(
+            "Quarantined corrupted filesystem of chat_id: {}, nodes: {}",
+            chat_id,
+            quarantined_nodes_count
+        );
+        self.journal_service.delete_drive_entries(&chat_id, None);
+        self.chat_session_repository
+            .remove_chat_sessions_by_chat_id(&chat_id);
+
+        let mut send_message_params = MessageParams::new_send(chat_id);
+        send_message_params.set_text(corrupted_filesystem_message());
+        send_message_params
+    }
+
+    /// Saves the file at `file_path`, solving the conflicts with the existing files as the policy says,
+    /// and records the save. Returns the path the file was saved at.
+    fn save_file(
+        &self,
+        chat_id: &ChatId,
+        drive_id: Option<DriveId>,
+        fs: &mut FileSystem,
+        file_path: &Path,
+        file_node: FileSystemNode,
+        conflict_policy: ConflictPolicy,
+    ) -> Result<PathBuf, String> {
+        let file_path = file_path_with_extension(file_path, &file_node);
+        let (file_path, replaced_node) = fs.resolve_conflict(&file_path, conflict_policy)?;
+        // the replaced file can be restored by undoing the save and then the removal
+        if let Some(node) = replaced_node {
+            self.record_operation(
+                chat_id,
+                drive_id,
+                JournalOperation::DeleteFile {
+                    path: file_path.clone(),
+                    node,
+                },
+            );
+        }
+
+        let message_id = file_node.file_message_id();
+        let final_file_path = fs.create_file_from_node(&file_path, file_node)?;
+        if let Some(message_id) = message_id {
+            self.record_operation(
+                chat_id,
+                drive_id,
+                JournalOperation::SaveFile {
+                    path: final_file_path.clone(),
+                    message_id,
+                },
+            );
+        }
+        Ok(final_file_path)
+    }
+
+    /// Saves the received file in the save directory right away, named after the name it was sent with,
+    /// its caption or the name template, and offers to move and rename it.
+    #[allow(clippy::too_many_arguments)]
+    fn save_to_inbox(
+        &self,
+        chat_id: &ChatId,
+        drive_id: Option<DriveId>,
+        fs: &mut FileSystem,
+        chat_session: &mut ChatSession,
+        chat_settings: &ChatSettings,
+        file: ReceivedFile,
+        caption: Option<&str>,
+    ) -> Result<MessageParams, String> {
+        chat_session.reset();
+
+        let dir_path = existing_dir_or_root(fs, chat_settings.save_dir());
+        let (file_name, conflict_policy) = match file
+            .file_name
+            .or_else(|| caption.and_then(caption_file_name))
+        {
+            Some(file_name) => (file_name, chat_settings.conflict_policy()),
+            // the names from the template only change every second, so they are numbered instead
+            None => (
+                template_file_name(chat_settings, &file.node),
+                ConflictPolicy::Rename,
+            ),
+        };
+        let file_path = self.save_file(
+            chat_id,
+            drive_id,
+            fs,
+            &dir_path.join(file_name),
+            file.node,
+            conflict_policy,
+        )?;
+
+        let mut send_message_params = MessageParams::new_send(chat_id.clone());
+        send_message_params.set_text(created_file_success_message(
+            file_path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string(),
+            dir_path.to_string_lossy().to_string(),
+        ));
+        send_message_params.set_inline_keyboard_markup(refine_file_inline_keyboard());
+        chat_session.set_action(ChatSessionAction::RefineFile(Some(file_path), None));
+
+        Ok(send_message_params)
+    }
+
+    /// Moves the file saved in the inbox to `to_path`, and records the move.
+    fn refine_file(
+        &self,
+        chat_id: &ChatId,
+        drive_id: Option<DriveId>,
+        fs: &mut FileSystem,
+        from_path: &Path,
+        to_path: &Path,
+    ) -> Result<String, String> {
+        let file_name = to_path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        let dir_path = to_path
+            .parent()
+            .unwrap_or(&root_path())
+            .to_string_lossy()
+            .to_string();
+        if from_path == to_path {
+            return Ok(created_file_success_message(file_name, dir_path));
+        }
+        if fs.get_node(to_path).is_ok() {
+            return Err(destination_taken_error());
+        }
+
+        fs.mv(from_path, to_path)?;
+        if from_path.parent() == to_path.parent() {
+            self.record_operation(
+                chat_id,
+                drive_id,
+                JournalOperation::RenameFile {
+                    from: from_path.to_path_buf(),
+                    to: to_path.to_path_buf(),
+                },
+            );
+            Ok(renamed_file_success_message(
+                from_path
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string(),
+                file_name,
+                dir_path,
+            ))
+        } else {
+            self.record_operation(
+                chat_id,
+                drive_id,
+                JournalOperation::MoveFile {
+                    from: from_path.to_path_buf(),
+                    to: to_path.to_path_buf(),
+                },
+            );
+            Ok(moved_file_success_message(
+                file_name,
+                from_path.to_string_lossy().to_string(),
+                to_path.to_string_lossy().to_string(),
+            ))
+        }
+    }
+
+    /// Saves the files of an album in `dir_path`, with the given names, and records each save.
+    /// The files that can't be saved are reported, without stopping the others.
+    #[allow(clippy::too_many_arguments)]
+    fn save_media_group_files(
+        &self,
+        chat_id: &ChatId,
+        drive_id: Option<DriveId>,
+        fs: &mut FileSystem,
+        dir_path: &Path,
+        media_group: MediaGroup,
+        file_names: Vec<String>,
+        conflict_policy: ConflictPolicy,
+    ) -> String {
+        let mut saved_file_names = vec![];
+        let mut errors = vec![];
+        for (file, file_name) in media_group.files.into_iter().zip(file_names) {
+            match self.save_file(
+                chat_id,
+                drive_id,
+                fs,
+                &dir_path.join(&file_name),
+                file.node,
+                conflict_policy,
+            ) {
+                Ok(final_file_path) => {
+                    saved_file_names.push(
+                        final_file_path
+                            .file_name()
+                            .unwrap_or_default()
+                            .to_string_lossy()
+                            .to_string(),
+                    );
+                }
+                Err(err) => errors.push((file_name, err)),
+            }
+        }
+
+        created_media_group_message(
+            &saved_file_names,
+            &errors,
+            dir_path.to_string_lossy().to_string(),
+        )
+    }
+
+    /// Runs the commands that take paths as arguments, resolved against the working directory
+    /// of the session. The failures are reported in the returned text.
+    fn run_path_command(
+        &self,
+        chat_id: &ChatId,
+        drive_id: Option<DriveId>,
+        chat_session: &mut ChatSession,
+        fs: &mut FileSystem,
+        command: Command,
+        msg: &Message,
+    ) -> String {
+        let result = command_arguments(msg).and_then(|arguments| {
+            // the working directory may have been moved or deleted by another member
+            let working_dir = existing_dir_or_root(fs, chat_session.working_dir());
+            let paths = arguments
+                .iter()
+                .map(|argument| resolve_path(&working_dir, argument))
+                .collect::<Vec<_>>();
+
+            match (command, paths.as_slice()) {
+                (Command::Pwd, []) => Ok(working_dir_message(
+                    working_dir.to_string_lossy().to_string(),
+                )),
+                (Command::Cd, []) => {
+                    chat_session.set_working_dir(root_path());
+                    Ok(working_dir_message(
+                        root_path().to_string_lossy().to_string(),
+                    ))
+                }
+                (Command::Cd, [path]) => {
+                    if !fs.get_node(path)?.is_directory() {
+                        return Err(not_a_directory_error(path.to_string_lossy().to_string()));
+                    }
+                    chat_session.set_working_dir(path.clone());
+                    Ok(working_dir_message(path.to_string_lossy().to_string()))
+                }
+                (Command::Ls, []) => list_directory(fs, &working_dir),
+                (Command::Ls, [path]) => list_directory(fs, path),
+                (Command::MkDir, [path]) => self.mkdir_with_parents(chat_id, drive_id, fs, path),
+                (Command::Mv, [from_path, to_path]) => {
+                    self.move_path(chat_id, drive_id, chat_session, fs, from_path, to_path)
+                }
+                _ => Err(wrong_arguments_error(command.usage().unwrap_or_default())),
+            }
+        });
+        result.unwrap_or_else(path_command_failed_message)
+    }
+
+    /// Creates the directory and its missing parents, recording each of them.
+    fn mkdir_with_parents(
+        &self,
+        chat_id: &ChatId,
+        drive_id: Option<DriveId>,
+        fs: &mut FileSystem,
+        path: &Path,
+    ) -> Result<String, String> {
+        if fs.get_node(path).is_ok() {
+            return Err(path_exists_error());
+        }
+
+        let mut dir_paths = path.ancestors().collect::<Vec<_>>();
+        dir_paths.reverse();
+        let mut created_paths = vec![];
+        for dir_path in dir_paths {
+            match fs.get_node(dir_path) {
+                Ok(node) if node.is_directory() => continue,
+                Ok(_) => {
+                    return Err(not_a_directory_error(
+                        dir_path.to_string_lossy().to_string(),
+                    ))
+                }
+                Err(_) => {}
+            }
+            fs.mkdir(dir_path)?;
+            self.record_operation(
+                chat_id,
+                drive_id,
+                JournalOperation::MkDir {
+                    path: dir_path.to_path_buf(),
+                },
+            );
+            created_paths.push(dir_path.to_string_lossy().to_string());
+        }
+
+        Ok(created_directories_message(&created_paths))
+    }
+
+    /// Moves or renames the file or directory at `from_path`, along with the working directory
+    /// if it's inside it. When `to_path` is a directory, the file or directory is moved into it.
+    fn move_path(
+        &self,
+        chat_id: &ChatId,
+        drive_id: Option<DriveId>,
+        chat_session: &mut ChatSession,
+        fs: &mut FileSystem,
+        from_path: &Path,
+        to_path: &Path,
+    ) -> Result<String, String> {
+        fs.get_node(from_path)?;
+        let to_path = match fs.get_node(to_path) {
+            Ok(node) if node.is_directory() => {
+                to_path.join(from_path.file_name().ok_or("Invalid path")?)
+            }
+            _ => to_path.to_path_buf(),
+        };
+        if fs.get_node(&to_path).is_ok() {
+            return Err(destination_taken_error());
+        }
+        let to_parent = to_path.parent().ok_or("Invalid path")?;
+        if !fs.get_node(to_parent).is_ok_and(|node| node.is_directory()) {
+            return Err(destination_not_found_error());
+        }
+
+        fs.mv(from_path, &to_path)?;
+        chat_session.move_working_dir(from_path, &to_path);
+        let operation = if from_path.parent() == Some(to_parent) {
+            JournalOperation::RenameFile {
+                from: from_path.to_path_buf(),
+                to: to_path.clone(),
+            }
+        } else {
+            JournalOperation::MoveFile {
+                from: from_path.to_path_buf(),
+                to: to_path.clone(),
+            }
+        };
+        self.record_operation(chat_id, drive_id, operation);
+
+        Ok(moved_path_message(
+            from_path.to_string_lossy().to_string(),
+            to_path.to_string_lossy().to_string(),
+        ))
+    }
+
+    /// Applies the action to all the selected files, or to none of them if it fails for any,
+    /// and records the changes as a single operation, so that they are undone together.
+    /// The whole selection is checked first, so that all the files the action fails for are reported.
+    fn apply_bulk_action(
+        &self,
+        chat_id: &ChatId,
+        drive_id: Option<DriveId>,
+        fs: &mut FileSystem,
+        bulk_action: BulkAction,
+        selection: BTreeSet<PathBuf>,
+    ) -> String {
+        let path_string = |path: &PathBuf| path.to_string_lossy().to_string();
+        let errors: Vec<_> = selection
+            .iter()
+            .filter_map(|path| {
+                let err = bulk_action.validate(fs, path).err()?;
+                Some((path_string(path), err))
+            })
+            .collect();
+        // the selected files can still conflict with each other, e.g. when they have the same name
+        let result = match errors.is_empty() {
+            true => fs.transaction(|fs| {
+                selection
+                    .iter()
+                    .map(|path| {
+                        bulk_action
+                            .apply(fs, path)
+                            .map_err(|err| vec![(path_string(path), err)])
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            }),
+            false => Err(errors),
+        };
+
+        match result {
+            Ok(operations) => {
+                let operations: Vec<_> = operations.into_iter().flatten().collect();
+                if !operations.is_empty() {
+                    self.record_operation(
+                        chat_id,
+                        drive_id,
+                        JournalOperation::Bulk {
+                            operation: bulk_action.operation(),
+                            operations,
+                        },
+                    );
+                }
+                let paths: Vec<_> = selection.iter().map(path_string).collect();
+                bulk_operation_message(bulk_action.operation(), &paths)
+            }
+            Err(errors) => bulk_operation_failed_message(bulk_action.operation(), &errors),
+        }
+    }
+
+    /// Records the operation in the journal once the update has been saved,
+    /// so that the operations of a failed update are never recorded.
+    fn record_operation(
+        &self,
+        chat_id: &ChatId,
+        drive_id: Option<DriveId>,
+        operation: JournalOperation,
+    ) {
+        self.pending_journal_operations
+            .borrow_mut()
+            .push((chat_id.clone(), drive_id, operation));
+    }
+
+    /// Leaves the filesystem and the journal as they were before the failed update,
+    /// only dropping the pending action of the chat session.
+    fn discard_failed_update(
+        &self,
+        chat_member_id: ChatMemberId,
+        original_chat_session: &ChatSession,
+    ) {
+        self.pending_journal_operations.take();
+        let mut chat_session = original_chat_session.clone();
+        chat_session.clear_action();
+        if &chat_session != original_chat_session {
+            self.update_chat_session(chat_member_id, chat_session);
+        }
+    }
+
+    /// Persists the chat session and the filesystem, skipping the ones that haven't changed,
+    /// and records the operations of the update.
+    fn save_chat_session_and_filesystem(
+        &self,
+        chat_member_id: ChatMemberId,
         chat_session: ChatSession,
-        filesystem: FileSystem,
+        original_chat_session: &ChatSession,
+        drive_id: Option<DriveId>,
+        mut filesystem: FileSystem,
     ) {
-        self.update_chat_session(chat_id.clone(), chat_session);
-        self.filesystem_service
-            .update_filesystem(&chat_id, filesystem);
+        let chat_id = chat_member_id.chat_id.clone();
+        let instructions_before_save = instruction_counter();
+
+        // the changes to the shared directories are written to the filesystems of their owners
+        for (owner_chat_id, owner_drive_id, mounted_filesystem) in filesystem.take_dirty_mounts() {
+            self.filesystem_service.update_filesystem(
+                &owner_chat_id,
+                owner_drive_id,
+                mounted_filesystem,
+            );
+        }
+        let is_chat_session_dirty = &chat_session != original_chat_session;
+        if is_chat_session_dirty {
+            self.update_chat_session(chat_member_id, chat_session);
+        }
+        let is_filesystem_dirty = filesystem.is_dirty();
+        if is_filesystem_dirty {
+            self.filesystem_service
+                .update_filesystem(&chat_id, drive_id, filesystem);
+        }
+        for (chat_id, drive_id, operation) in self.pending_journal_operations.take() {
+            self.journal_service
+                .record_operation(&chat_id, drive_id, operation);
+        }
+
+        let instructions_after_save = instruction_counter();
+        custom_print!(
+            "Saved chat_id: {}, chat session written: {}, filesystem written: {}, instructions: total {} (save {})",
+            chat_id,
+            is_chat_session_dirty,
+            is_filesystem_dirty,
+            instructions_after_save,
+            instructions_after_save - instructions_before_save
+        );
     }
 }
 
 fn process_file_message(
     chat_session: &mut ChatSession,
     fs: &FileSystem,
+    chat_settings: &ChatSettings,
     chat_id: ChatId,
-    message_id: MessageId,
-    file_size: Option<u64>,
-    mime_type: Option<String>,
+    media_group_id: Option<String>,
+    file: ReceivedFile,
 ) -> Result<MessageParams, String> {
     // we reset the chat session to start the flow of saving a new file
     chat_session.reset();
+    chat_session.set_current_path(existing_dir_or_root(fs, chat_settings.save_dir()));
 
-    let file_node = FileSystemNode::new_file(message_id, file_size.unwrap_or(0), mime_type);
-    chat_session.set_action(ChatSessionAction::SaveFile(Some(file_node), None));
+    let text = match media_group_id {
+        // the other files of the album are added to the batch as they arrive
+        Some(media_group_id) => {
+            chat_session.set_action(ChatSessionAction::SaveMediaGroup(
+                Some(MediaGroup {
+                    media_group_id,
+                    files: vec![file],
+                }),
+                None,
+            ));
+            create_media_group_message(chat_session.current_path_string())
+        }
+        None => {
+            chat_session.set_action(ChatSessionAction::SaveFile(Some(file.node), None));
+            chat_session.set_received_file_name(file.file_name);
+            create_file_message(chat_session.current_path_string())
+        }
+    };
 
     let mut send_message_params = MessageParams::new_send(chat_id.clone());
-    send_message_params.set_text(create_file_message(chat_session.current_path_string()));
+    send_message_params.set_text(text);
     let keyboard = KeyboardDirectoryBuilder::new(fs, chat_session.current_path())?
         .with_current_dir_button()
         .build();
@@ -746,6 +2748,2220 @@ fn process_file_message(
     Ok(send_message_params)
 }
 
+/// The directory at `path`, or the root if there's no directory at `path` anymore.
+fn existing_dir_or_root(fs: &FileSystem, path: PathBuf) -> PathBuf {
+    if fs.get_node(&path).is_ok_and(|node| node.is_directory()) {
+        path
+    } else {
+        root_path()
+    }
+}
+
+/// The name to save a received file with without asking for it, if the chat settings allow it.
+fn auto_file_name(
+    chat_settings: &ChatSettings,
+    received_file_name: Option<String>,
+) -> Option<String> {
+    match chat_settings.auto_naming() {
+        AutoNaming::Ask => None,
+        AutoNaming::OriginalName => received_file_name,
+        AutoNaming::Timestamp => Some(timestamp_file_name(chat_settings)),
+    }
+}
+
+/// Like [auto_file_name], for the files of an album.
+fn auto_media_group_file_names(
+    chat_settings: &ChatSettings,
+    media_group: &MediaGroup,
+) -> Option<Vec<String>> {
+    match chat_settings.auto_naming() {
+        AutoNaming::Ask => None,
+        AutoNaming::OriginalName => Some(media_group_file_names(media_group, None)),
+        AutoNaming::Timestamp => Some(media_group_file_names(
+            media_group,
+            Some(&timestamp_file_name(chat_settings)),
+        )),
+    }
+}
+
+/// The current time in the time zone of the chat.
+fn timestamp_file_name(chat_settings: &ChatSettings) -> String {
+    format_file_timestamp(get_current_time(), &chat_settings.time_zone())
+}
+
+/// The name of a file saved in the inbox without a name or a caption.
+fn template_file_name(chat_settings: &ChatSettings, file_node: &FileSystemNode) -> String {
+    chat_settings
+        .name_template()
+        .replace(DATE_PLACEHOLDER, &timestamp_file_name(chat_settings))
+        .replace(TYPE_PLACEHOLDER, &file_type_name(file_node))
+}
+
+/// The type of a file in the names made from the name template, e.g. `photo` or `text`.
+fn file_type_name(file_node: &FileSystemNode) -> String {
+    let mime_type = file_node.file_mime_type().unwrap_or_default();
+    if let Some(tg_type) = mime_type.strip_prefix(TG_FILE_MIME_TYPE_PREFIX) {
+        return tg_type.to_string();
+    }
+    match mime_type.split('/').next() {
+        Some("image") => "photo",
+        Some("video") => "video",
+        Some("audio") => "audio",
+        _ => "document",
+    }
+    .to_string()
+}
+
+/// The first line of a caption, without the `/` characters that the names can't include.
+fn caption_file_name(caption: &str) -> Option<String> {
+    let file_name = caption
+        .lines()
+        .next()?
+        .replace('/', "-")
+        .chars()
+        .take(MAX_AUTO_FILE_NAME_LENGTH)
+        .collect::<String>()
+        .trim()
+        .to_string();
+    (!file_name.is_empty()).then_some(file_name)
+}
+
+/// The file carried by a message that isn't a text, if any.
+fn received_file(msg: &Message) -> Option<ReceivedFile> {
+    let (file_size, mime_type, telegram_file, file_name) = if let Some(document) = &msg.document {
+        (
+            document.file_size,
+            document.mime_type.clone(),
+            Some(TelegramFile {
+                file_id: document.file_id.clone(),
+                kind: TelegramFileKind::Document,
+            }),
+            document.file_name.clone(),
+        )
+    } else if let Some(photos) = &msg.photo {
+        // the sizes are sorted, the largest one is sent again
+        let photo = photos.last()?;
+        (
+            photo.file_size,
+            Some("image/jpeg".to_string()),
+            Some(TelegramFile {
+                file_id: photo.file_id.clone(),
+                kind: TelegramFileKind::Photo,
+            }),
+            None,
+        )
+    } else if let Some(video) = &msg.video {
+        (
+            video.file_size,
+            video.mime_type.clone(),
+            Some(TelegramFile {
+                file_id: video.file_id.clone(),
+                kind: TelegramFileKind::Video,
+            }),
+            video.file_name.clone(),
+        )
+    } else if let Some(video_note) = &msg.video_note {
+        (
+            video_note.file_size,
+            Some(format!("{TG_FILE_MIME_TYPE_PREFIX}video_note")),
+            None,
+            None,
+        )
+    } else if let Some(audio) = &msg.audio {
+        (
+            audio.file_size,
+            audio.mime_type.clone(),
+            Some(TelegramFile {
+                file_id: audio.file_id.clone(),
+                kind: TelegramFileKind::Audio,
+            }),
+            audio.file_name.clone(),
+        )
+    } else if let Some(voice) = &msg.voice {
+        (voice.file_size, voice.mime_type.clone(), None, None)
+    } else if let Some(sticker) = &msg.sticker {
+        (
+            sticker.file_size,
+            Some(format!("{TG_FILE_MIME_TYPE_PREFIX}sticker")),
+            None,
+            None,
+        )
+    } else if msg.contact.is_some() {
+        (
+            None,
+            Some(format!("{TG_FILE_MIME_TYPE_PREFIX}contact")),
+            None,
+            None,
+        )
+    } else {
+        return None;
+    };
+
+    Some(ReceivedFile {
+        node: FileSystemNode::new_file(msg.message_id, file_size.unwrap_or(0), mime_type)
+            .with_telegram_file(telegram_file),
+        file_name,
+    })
+}
+
+/// The names of the files of an album: numbered after `pattern`,
+/// or the names they were sent with when there's no pattern.
+fn media_group_file_names(media_group: &MediaGroup, pattern: Option<&str>) -> Vec<String> {
+    media_group
+        .files
+        .iter()
+        .enumerate()
+        .map(|(index, file)| {
+            let number = (index + 1).to_string();
+            match pattern {
+                Some(pattern) if pattern.contains(FILE_NUMBER_PLACEHOLDER) => {
+                    pattern.replace(FILE_NUMBER_PLACEHOLDER, &number)
+                }
+                Some(pattern) => format!("{pattern}-{number}"),
+                // the photos are sent without a name
+                None => file.file_name.clone().unwrap_or_else(|| {
+                    format!("file-{}", file.node.file_message_id().unwrap_or_default())
+                }),
+            }
+        })
+        .collect()
+}
+
+/// A cached result, which sends the file by its id without uploading it again.
+fn inline_query_result(id: String, path: &Path, telegram_file: TelegramFile) -> InlineQueryResult {
+    let title = path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let file_id = telegram_file.file_id;
+    match telegram_file.kind {
+        TelegramFileKind::Document => InlineQueryResultCachedDocument::builder()
+            .id(id)
+            .title(title)
+            .document_file_id(file_id)
+            .build()
+            .into(),
+        TelegramFileKind::Photo => InlineQueryResultCachedPhoto::builder()
+            .id(id)
+            .title(title)
+            .photo_file_id(file_id)
+            .build()
+            .into(),
+        TelegramFileKind::Video => InlineQueryResultCachedVideo::builder()
+            .id(id)
+            .title(title)
+            .video_file_id(file_id)
+            .build()
+            .into(),
+        TelegramFileKind::Audio => InlineQueryResultCachedAudio::builder()
+            .id(id)
+            .audio_file_id(file_id)
+            .build()
+            .into(),
+    }
+}
+
+/// A [BulkOperation] with the destination directory or the tag it needs.
+enum BulkAction<'a> {
+    Move(&'a Path),
+    Copy(&'a Path),
+    Delete,
+    Tag(&'a str),
+}
+
+impl BulkAction<'_> {
+    fn operation(&self) -> BulkOperation {
+        match self {
+            Self::Move(_) => BulkOperation::Move,
+            Self::Copy(_) => BulkOperation::Copy,
+            Self::Delete => BulkOperation::Delete,
+            Self::Tag(_) => BulkOperation::Tag,
+        }
+    }
+
+    /// Checks that the action can be applied to the file at `path`, without applying it.
+    fn validate(&self, fs: &FileSystem, path: &Path) -> Result<(), String> {
+        if fs.get_node(path)?.is_directory() {
+            return Err(not_a_file_error());
+        }
+        match self {
+            Self::Move(dir_path) | Self::Copy(dir_path) => {
+                destination_path(fs, path, dir_path).map(|_| ())
+            }
+            Self::Delete | Self::Tag(_) => Ok(()),
+        }
+    }
+
+    /// Applies the action to the file at `path`, returning the operation to record, if any.
+    fn apply(&self, fs: &mut FileSystem, path: &Path) -> Result<Option<JournalOperation>, String> {
+        let node = fs.get_node(path)?;
+        if node.is_directory() {
+            return Err(not_a_file_error());
+        }
+
+        match self {
+            Self::Move(dir_path) => {
+                let to_path = destination_path(fs, path, dir_path)?;
+                fs.mv(path, &to_path)?;
+                Ok(Some(JournalOperation::MoveFile {
+                    from: path.to_path_buf(),
+                    to: to_path,
+                }))
+            }
+            Self::Copy(dir_path) => {
+                let to_path = destination_path(fs, path, dir_path)?;
+                fs.cp(path, &to_path)?;
+                Ok(node
+                    .file_message_id()
+                    .map(|message_id| JournalOperation::CopyFile {
+                        from: path.to_path_buf(),
+                        to: to_path,
+                        message_id,
+                    }))
+            }
+            Self::Delete => {
+                fs.rm(path)?;
+                Ok(Some(JournalOperation::DeleteFile {
+                    path: path.to_path_buf(),
+                    node,
+                }))
+            }
+            Self::Tag(tag) => {
+                // the tag that the file already had is kept when the action is undone
+                let is_tagged = fs.tag_file(path, tag)?;
+                Ok(is_tagged.then(|| JournalOperation::TagFile {
+                    path: path.to_path_buf(),
+                    tag: tag.to_string(),
+                }))
+            }
+        }
+    }
+}
+
+/// The path the file at `path` gets in the directory, which must be free.
+fn destination_path(fs: &FileSystem, path: &Path, dir_path: &Path) -> Result<PathBuf, String> {
+    let to_path = dir_path.join(path.file_name().ok_or("Invalid file name")?);
+    if fs.get_node(&to_path).is_ok() {
+        return Err(destination_taken_error());
+    }
+    Ok(to_path)
+}
+
+fn list_directory(fs: &FileSystem, path: &Path) -> Result<String, String> {
+    let node = fs.get_node(path)?;
+    if !node.is_directory() {
+        return Err(not_a_directory_error(path.to_string_lossy().to_string()));
+    }
+    let names = |paths: Vec<PathBuf>| {
+        paths
+            .into_iter()
+            .map(|path| path.to_string_lossy().to_string())
+            .collect::<Vec<_>>()
+    };
+
+    Ok(ls_message(
+        path.to_string_lossy().to_string(),
+        &names(node.ls_directories(fs.sort_order())?),
+        &names(node.ls_files(fs.sort_order())?),
+    ))
+}
+
+/// Strips the leading `#` of the tag, that can only contain letters, digits, `_` and `-`.
+fn parse_tag(text: &str) -> Result<String, String> {
+    let tag = text.trim().trim_start_matches('#');
+    if tag.is_empty() {
+        return Err(empty_tag_error());
+    }
+    if tag.chars().count() > MAX_TAG_LENGTH {
+        return Err(tag_too_long_error(MAX_TAG_LENGTH));
+    }
+    if !tag
+        .chars()
+        .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+    {
+        return Err(tag_characters_error());
+    }
+    Ok(tag.to_string())
+}
+
+fn parse_name_template(text: &str) -> Result<String, String> {
+    let name_template = text.trim();
+    if name_template.is_empty() {
+        return Err(empty_name_template_error());
+    }
+    if name_template.chars().count() > MAX_AUTO_FILE_NAME_LENGTH {
+        return Err(name_template_too_long_error(MAX_AUTO_FILE_NAME_LENGTH));
+    }
+    if name_template.contains('/') {
+        return Err(name_template_slash_error());
+    }
+    Ok(name_template.to_string())
+}
+
+/// The keyboard of the explorer, with the button to select the files.
+fn explorer_keyboard(fs: &FileSystem, path: &Path) -> Result<InlineKeyboardMarkup, String> {
+    Ok(KeyboardDirectoryBuilder::new(fs, path)?
+        .with_files()?
+        .with_bottom_rows(vec![vec![select_files_inline_button()]])
+        .build())
+}
+
+/// Shows the current directory with the selected files marked, and the actions to apply to them.
+fn set_selection_view(
+    chat_session: &ChatSession,
+    fs: &FileSystem,
+    params: &mut MessageParams,
+) -> Result<(), String> {
+    let selection = chat_session.selection();
+    params.set_text(select_files_message(
+        chat_session.current_path_string(),
+        selection.len(),
+    ));
+    let keyboard = KeyboardDirectoryBuilder::new(fs, chat_session.current_path())?
+        .with_selectable_files(&selection)?
+        .with_bottom_rows(selection_inline_rows())
+        .build();
+    params.set_inline_keyboard_markup(keyboard);
+    Ok(())
+}
+
+fn set_bulk_destination_view(
+    chat_session: &ChatSession,
+    fs: &FileSystem,
+    operation: BulkOperation,
+    params: &mut MessageParams,
+) -> Result<(), String> {
+    params.set_text(bulk_select_destination_message(
+        operation,
+        chat_session.selection().len(),
+        chat_session.current_path_string(),
+    ));
+    let keyboard = KeyboardDirectoryBuilder::new(fs, chat_session.current_path())?
+        .with_current_dir_button()
+        .with_bottom_rows(vec![vec![back_inline_button()]])
+        .build();
+    params.set_inline_keyboard_markup(keyboard);
+    Ok(())
+}
+
+/// Shows the current directory, to choose it as the directory in which the received files are saved.
+fn set_save_dir_setting_view(
+    chat_session: &ChatSession,
+    fs: &FileSystem,
+    params: &mut MessageParams,
+) -> Result<(), String> {
+    params.set_text(save_dir_setting_message(chat_session.current_path_string()));
+    let keyboard = KeyboardDirectoryBuilder::new(fs, chat_session.current_path())?
+        .with_current_dir_button()
+        .with_bottom_rows(vec![vec![back_inline_button()]])
+        .build();
+    params.set_inline_keyboard_markup(keyboard);
+    Ok(())
+}
+
+fn set_refine_file_view(
+    chat_session: &ChatSession,
+    fs: &FileSystem,
+    file_path: &Path,
+    params: &mut MessageParams,
+) -> Result<(), String> {
+    params.set_text(refine_file_message(
+        file_path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string(),
+        chat_session.current_path_string(),
+    ));
+    let keyboard = KeyboardDirectoryBuilder::new(fs, chat_session.current_path())?
+        .with_current_dir_button()
+        .build();
+    params.set_inline_keyboard_markup(keyboard);
+    Ok(())
+}
+
+/// In groups, the new keyboards reply to the message that opened them, by default the one
+/// being handled, and the session remembers it to reject the buttons pressed by the other members.
+fn anchor_keyboard(
+    chat_id: &ChatId,
+    chat_session: &mut ChatSession,
+    mut params: MessageParams,
+    default_reply_to_message_id: MessageId,
+) -> MessageParams {
+    // edited messages keep replying to the same message
+    if chat_id.is_private()
+        || !params.has_inline_keyboard()
+        || !matches!(params, MessageParams::Send(_))
+    {
+        return params;
+    }
+
+    let reply_to_message_id = match params.reply_to_message_id() {
+        Some(reply_to_message_id) => reply_to_message_id,
+        None => {
+            // sent messages always accept a reply
+            params
+                .set_reply_to_message_id(default_reply_to_message_id)
+                .unwrap();
+            default_reply_to_message_id
+        }
+    };
+    chat_session.set_keyboard_anchor(reply_to_message_id);
+    params
+}
+
+/// Replies to the message of a file, unless it's in another chat, as for the files of the shared directories.
+fn reply_to_file_message(
+    params: &mut MessageParams,
+    (source_chat_id, message_id): (Option<ChatId>, MessageId),
+) -> Result<(), String> {
+    match source_chat_id {
+        Some(_) => Ok(()),
+        None => params.set_reply_to_message_id(message_id),
+    }
+}
+
+fn permission_denied_message_params(
+    chat_id: ChatId,
+    role: ChatRole,
+    required_role: ChatRole,
+) -> MessageParams {
+    let mut send_message_params = MessageParams::new_send(chat_id);
+    send_message_params.set_text(permission_denied_message(role, required_role));
+    send_message_params
+}
+
 fn action_not_supported_error() -> Result<MessageParams, String> {
     Err("current action not supported by this action".to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::{
+        ChatRoleRepository, ChatSettingsRepository, ConfigRepository, DriveRepository,
+        DriveRepositoryImpl, FilesystemRepository, JournalRepository, LegacyFileSystem,
+        RateLimitRepository, SharePermission, ShareRepository, SortOrder, StoredFileSystemNode,
+        UpdateId, VersionedValue, MAIN_DRIVE_NAME, ROOT_NODE_ID,
+    };
+    use frankenstein::types::{
+        Chat, ChatType, Document, MessageEntity, MessageEntityType, PhotoSize, User,
+    };
+    use rstest::*;
+    use std::path::PathBuf;
+
+    const CHAT_ID: i64 = 123;
+    const OTHER_CHAT_ID: i64 = 456;
+    const GROUP_CHAT_ID: i64 = -789;
+    const GROUP_ADMIN_USER_ID: u64 = 1;
+    const GROUP_MEMBER_USER_ID: u64 = 2;
+    const UPDATE_ID: UpdateId = 1_000;
+
+    fn user(user_id: u64) -> User {
+        User::builder()
+            .id(user_id)
+            .is_bot(false)
+            .first_name("John")
+            .build()
+    }
+
+    /// In private chats, the sender is the user of the chat.
+    fn text_message(chat_id: i64, message_id: i32, text: &str) -> Message {
+        text_message_from(chat_id, chat_id as u64, message_id, text)
+    }
+
+    fn text_message_from(chat_id: i64, user_id: u64, message_id: i32, text: &str) -> Message {
+        let entities = text.starts_with('/').then(|| {
+            vec![MessageEntity::builder()
+                .type_field(MessageEntityType::BotCommand)
+                .offset(0)
+                .length(text.split(' ').next().unwrap().len() as u16)
+                .build()]
+        });
+        let chat_type = if chat_id > 0 {
+            ChatType::Private
+        } else {
+            ChatType::Group
+        };
+
+        Message::builder()
+            .message_id(message_id)
+            .date(0)
+            .chat(Box::new(
+                Chat::builder().id(chat_id).type_field(chat_type).build(),
+            ))
+            .from(user(user_id))
+            .text(text)
+            .maybe_entities(entities)
+            .build()
+    }
+
+    fn callback_query(chat_id: i64, message_id: i32, action: ChatSessionAction) -> CallbackQuery {
+        callback_query_from(chat_id, chat_id as u64, message_id, action)
+    }
+
+    fn callback_query_from(
+        chat_id: i64,
+        user_id: u64,
+        message_id: i32,
+        action: ChatSessionAction,
+    ) -> CallbackQuery {
+        CallbackQuery::builder()
+            .id("callback-query-id")
+            .from(user(user_id))
+            .message(MaybeInaccessibleMessage::Message(text_message(
+                chat_id, message_id, "",
+            )))
+            .chat_instance("chat-instance")
+            .data(action.to_string())
+            .build()
+    }
+
+    /// A callback query on a keyboard that replies to `reply_to_message_id`, as the keyboards in groups do.
+    fn group_callback_query(
+        user_id: u64,
+        message_id: i32,
+        reply_to_message_id: i32,
+        action: ChatSessionAction,
+    ) -> CallbackQuery {
+        let mut keyboard_message = text_message(GROUP_CHAT_ID, message_id, "");
+        keyboard_message.reply_to_message = Some(Box::new(text_message_from(
+            GROUP_CHAT_ID,
+            user_id,
+            reply_to_message_id,
+            "/mkdir",
+        )));
+        let mut query = callback_query_from(GROUP_CHAT_ID, user_id, message_id, action);
+        query.message = Some(MaybeInaccessibleMessage::Message(keyboard_message));
+        query
+    }
+
+    fn chat_member_id(chat_id: i64) -> ChatMemberId {
+        ChatMemberId::new(ChatId::from(chat_id), UserId(chat_id as u64))
+    }
+
+    fn reply_text(res: Result<MessageParams, String>) -> String {
+        res.unwrap().json_value().unwrap()["text"]
+            .as_str()
+            .unwrap()
+            .to_string()
+    }
+
+    fn save_text_file(service: &impl ChatSessionService, chat_id: i64, message_id: i32) {
+        // a command resets any pending action
+        service
+            .handle_update_content_message(
+                ChatId::from(chat_id),
+                text_message(chat_id, message_id, "/help"),
+            )
+            .unwrap();
+        service
+            .handle_update_content_message(
+                ChatId::from(chat_id),
+                text_message(chat_id, message_id, "some text"),
+            )
+            .unwrap();
+        service
+            .handle_update_content_callback_query(
+                ChatId::from(chat_id),
+                callback_query(chat_id, message_id + 1, ChatSessionAction::CurrentDir),
+            )
+            .unwrap();
+        service
+            .handle_update_content_message(
+                ChatId::from(chat_id),
+                text_message(chat_id, message_id + 2, &format!("file-{message_id}")),
+            )
+            .unwrap();
+    }
+
+    fn assert_no_chat_data(chat_id: &ChatId) {
+        let chat_session_repository = ChatSessionRepositoryImpl::default();
+        let filesystem_repository = FilesystemRepositoryImpl::default();
+        let journal_repository = JournalRepositoryImpl::default();
+
+        assert!(!chat_session_repository
+            .list_chat_ids(None, usize::MAX)
+            .contains(chat_id));
+        assert!(!filesystem_repository
+            .list_chat_ids(None, usize::MAX)
+            .contains(chat_id));
+        assert!(filesystem_repository
+            .get_node_ids_by_chat_id(chat_id)
+            .is_empty());
+        assert!(filesystem_repository
+            .get_quarantined_nodes_by_chat_id(chat_id)
+            .is_empty());
+        assert_eq!(journal_repository.get_journal_by_chat_id(chat_id), None);
+        assert_eq!(
+            ChatSettingsRepositoryImpl::default().get_chat_settings(chat_id),
+            None
+        );
+        assert_eq!(
+            ProcessedUpdateServiceImpl::default().get_processed_update(UPDATE_ID),
+            None
+        );
+        assert_eq!(
+            ChatRoleRepositoryImpl::default().get_chat_role(&ChatMemberId::new(
+                chat_id.clone(),
+                UserId(GROUP_MEMBER_USER_ID)
+            )),
+            None
+        );
+        assert!(DriveRepositoryImpl::default()
+            .get_drives_by_chat_id(chat_id)
+            .is_empty());
+        assert!(ShareRepositoryImpl::default()
+            .get_shares_by_chat_id(chat_id)
+            .is_empty());
+        assert!(filesystem_repository
+            .remove_legacy_filesystem(chat_id)
+            .is_none());
+        assert_eq!(
+            RateLimitRepositoryImpl::default().get_chat_bucket(chat_id),
+            None
+        );
+    }
+
+    /// Shares the `/Shared with others` directory of the owner's main drive, accepted by the recipient.
+    fn accepted_share(owner_chat_id: &ChatId, recipient_chat_id: &ChatId) {
+        let share_service = ShareServiceImpl::default();
+        share_service.set_token_salt(vec![7; 32]);
+        let mut fs = FileSystem::default();
+        let path = PathBuf::from("/Shared with others");
+        fs.mkdir(&path).unwrap();
+        let share = share_service
+            .create_share(
+                owner_chat_id.clone(),
+                None,
+                &fs,
+                &path,
+                SharePermission::ReadOnly,
+            )
+            .unwrap();
+        share_service
+            .accept_share(recipient_chat_id.clone(), &share.token)
+            .unwrap();
+    }
+
+    #[rstest]
+    fn forget_me_deletes_all_chat_data() {
+        let service = ChatSessionServiceImpl::default();
+        let chat_id = ChatId::from(CHAT_ID);
+        save_text_file(&service, CHAT_ID, 1);
+        ChatSettingsServiceImpl::default().set_language(chat_id.clone(), Some(Language::Italian));
+        // leaves a quarantined filesystem behind and starts a new one
+        FilesystemServiceImpl::default().quarantine_filesystem(&chat_id);
+        save_text_file(&service, CHAT_ID, 10);
+        save_text_file(&service, OTHER_CHAT_ID, 1);
+        ProcessedUpdateServiceImpl::default().record_update(UPDATE_ID, Some(chat_id.clone()));
+        ProcessedUpdateServiceImpl::default()
+            .record_update(UPDATE_ID + 1, Some(ChatId::from(OTHER_CHAT_ID)));
+        // left behind by a group the chat was migrated from
+        ChatRoleRepositoryImpl::default().set_chat_role(
+            ChatMemberId::new(chat_id.clone(), UserId(GROUP_MEMBER_USER_ID)),
+            ChatRole::Viewer,
+        );
+        DriveServiceImpl::default()
+            .create_drive(chat_id.clone(), "Work".to_string())
+            .unwrap();
+        let other_chat_id = ChatId::from(OTHER_CHAT_ID);
+        accepted_share(&chat_id, &other_chat_id);
+        accepted_share(&other_chat_id, &chat_id);
+        assert_eq!(
+            ShareRepositoryImpl::default()
+                .get_shares_by_chat_id(&chat_id)
+                .len(),
+            2
+        );
+
+        let text = reply_text(service.handle_update_content_message(
+            chat_id.clone(),
+            text_message(CHAT_ID, 20, "/forget_me"),
+        ));
+        assert_eq!(text, forget_me_message());
+        let text = reply_text(service.handle_update_content_callback_query(
+            chat_id.clone(),
+            callback_query(CHAT_ID, 21, ChatSessionAction::Confirm),
+        ));
+        assert_eq!(text, forget_me_final_confirmation_message());
+        // nothing is deleted before the second confirmation
+        assert!(!FilesystemRepositoryImpl::default()
+            .get_node_ids_by_chat_id(&chat_id)
+            .is_empty());
+        let text = reply_text(service.handle_update_content_callback_query(
+            chat_id.clone(),
+            callback_query(CHAT_ID, 21, ChatSessionAction::Confirm),
+        ));
+        assert_eq!(text, forgotten_message());
+
+        assert_no_chat_data(&chat_id);
+        // the shares from and to the forgotten chat are gone for the other chat too
+        assert!(ShareRepositoryImpl::default()
+            .get_shares_by_chat_id(&other_chat_id)
+            .is_empty());
+        assert!(ChatSessionRepositoryImpl::default()
+            .get_chat_session(&chat_member_id(OTHER_CHAT_ID))
+            .is_some());
+        assert!(JournalRepositoryImpl::default()
+            .get_journal_by_chat_id(&other_chat_id)
+            .is_some());
+        assert!(ProcessedUpdateServiceImpl::default()
+            .get_processed_update(UPDATE_ID + 1)
+            .is_some());
+    }
+
+    #[rstest]
+    fn delete_chat_data_before_legacy_migration() {
+        let service = ChatSessionServiceImpl::default();
+        let chat_id = ChatId::from(CHAT_ID);
+        FilesystemRepositoryImpl::default().insert_legacy_filesystem(
+            chat_id.clone(),
+            VersionedValue::new(&LegacyFileSystem::new(
+                FileSystem::default().export().unwrap(),
+            )),
+        );
+        RateLimitServiceImpl::default().check_update(Some(&chat_id));
+        assert!(RateLimitRepositoryImpl::default()
+            .get_chat_bucket(&chat_id)
+            .is_some());
+
+        service.delete_chat_data(&chat_id);
+
+        assert_no_chat_data(&chat_id);
+        assert_eq!(
+            FilesystemServiceImpl::default().migrate_legacy_filesystems(1),
+            0
+        );
+    }
+
+    #[rstest]
+    fn forget_me_cancel() {
+        let service = ChatSessionServiceImpl::default();
+        let chat_id = ChatId::from(CHAT_ID);
+        save_text_file(&service, CHAT_ID, 1);
+
+        service
+            .handle_update_content_message(chat_id.clone(), text_message(CHAT_ID, 20, "/forget_me"))
+            .unwrap();
+        service
+            .handle_update_content_callback_query(
+                chat_id.clone(),
+                callback_query(CHAT_ID, 21, ChatSessionAction::Confirm),
+            )
+            .unwrap();
+        let text = reply_text(service.handle_update_content_callback_query(
+            chat_id.clone(),
+            callback_query(CHAT_ID, 21, ChatSessionAction::Cancel),
+        ));
+        assert_eq!(text, forget_me_cancelled_message());
+
+        assert_eq!(
+            service
+                .get_or_create_chat_session(&chat_member_id(CHAT_ID))
+                .action(),
+            None
+        );
+        assert_eq!(
+            JournalServiceImpl::default()
+                .get_recent_entries(&chat_id)
+                .len(),
+            1
+        );
+    }
+
+    #[rstest]
+    fn corrupted_filesystem() {
+        let service = ChatSessionServiceImpl::default();
+        let filesystem_repository = FilesystemRepositoryImpl::default();
+        let journal_service = JournalServiceImpl::default();
+        let chat_id = ChatId::from(CHAT_ID);
+        journal_service.record_operation(
+            &chat_id,
+            Some(1),
+            JournalOperation::MkDir {
+                path: PathBuf::from("/Projects"),
+            },
+        );
+
+        for generation in 0..2 {
+            save_text_file(&service, CHAT_ID, 1);
+            filesystem_repository.corrupt_node(&chat_id, ROOT_NODE_ID);
+
+            let text = reply_text(service.handle_update_content_message(
+                chat_id.clone(),
+                text_message(CHAT_ID, 10, "/explorer"),
+            ));
+            assert_eq!(text, corrupted_filesystem_message());
+            assert!(filesystem_repository
+                .get_node_ids_by_chat_id(&chat_id)
+                .is_empty());
+            // the entries of the quarantined filesystem can't be undone anymore
+            assert_eq!(
+                journal_service
+                    .get_recent_entries(&chat_id)
+                    .iter()
+                    .map(|entry| entry.drive_id())
+                    .collect::<Vec<_>>(),
+                vec![Some(1)]
+            );
+            // the nodes of the earlier quarantines are kept
+            let generations = filesystem_repository
+                .get_quarantined_nodes_by_chat_id(&chat_id)
+                .into_iter()
+                .map(|node| node.generation)
+                .collect::<BTreeSet<_>>();
+            assert_eq!(generations, (0..=generation).collect());
+        }
+    }
+
+    #[rstest]
+    fn undo_disabled() {
+        let config_repository = ConfigRepositoryImpl::default();
+        let mut config = config_repository.get_config();
+        config.features.undo = false;
+        config_repository.set_config(config);
+        let service = ChatSessionServiceImpl::default();
+        let chat_id = ChatId::from(CHAT_ID);
+        save_text_file(&service, CHAT_ID, 1);
+
+        assert_eq!(
+            JournalRepositoryImpl::default().get_journal_by_chat_id(&chat_id),
+            None
+        );
+        for command in ["/undo", "/history"] {
+            let text = reply_text(service.handle_update_content_message(
+                chat_id.clone(),
+                text_message(CHAT_ID, 10, command),
+            ));
+            assert_eq!(text, undo_disabled_message());
+        }
+    }
+
+    #[rstest]
+    fn group_roles() {
+        let service = ChatSessionServiceImpl::default();
+        let chat_id = ChatId::from(GROUP_CHAT_ID);
+        let admin_id = ChatMemberId::new(chat_id.clone(), UserId(GROUP_ADMIN_USER_ID));
+        let member_id = ChatMemberId::new(chat_id.clone(), UserId(GROUP_MEMBER_USER_ID));
+        ChatRoleServiceImpl::default()
+            .set_role(admin_id.clone(), ChatRole::Admin)
+            .unwrap();
+
+        // the members have their own sessions
+        let text = reply_text(service.handle_update_content_message(
+            chat_id.clone(),
+            text_message_from(GROUP_CHAT_ID, GROUP_ADMIN_USER_ID, 1, "/mkdir"),
+        ));
+        assert_eq!(text, mkdir_message("/".to_string()));
+        let text = reply_text(service.handle_update_content_message(
+            chat_id.clone(),
+            text_message_from(GROUP_CHAT_ID, GROUP_MEMBER_USER_ID, 2, "/explorer"),
+        ));
+        assert_eq!(text, explorer_message("/".to_string()));
+        assert_eq!(
+            service.get_or_create_chat_session(&admin_id).action(),
+            Some(ChatSessionAction::MkDir(None))
+        );
+        assert_eq!(
+            service.get_or_create_chat_session(&member_id).action(),
+            Some(ChatSessionAction::Explorer)
+        );
+
+        // only the admins can set the roles
+        let text = reply_text(service.handle_update_content_message(
+            chat_id.clone(),
+            text_message_from(GROUP_CHAT_ID, GROUP_MEMBER_USER_ID, 3, "/role admin"),
+        ));
+        assert_eq!(
+            text,
+            permission_denied_message(ChatRole::Editor, ChatRole::Admin)
+        );
+        let mut role_message =
+            text_message_from(GROUP_CHAT_ID, GROUP_ADMIN_USER_ID, 4, "/role viewer");
+        role_message.reply_to_message = Some(Box::new(text_message_from(
+            GROUP_CHAT_ID,
+            GROUP_MEMBER_USER_ID,
+            3,
+            "some text",
+        )));
+        let text = reply_text(service.handle_update_content_message(chat_id.clone(), role_message));
+        assert_eq!(text, role_set_message("John".to_string(), ChatRole::Viewer));
+
+        // viewers can't save files or create directories
+        for text in ["some text", "/mkdir"] {
+            let reply = reply_text(service.handle_update_content_message(
+                chat_id.clone(),
+                text_message_from(GROUP_CHAT_ID, GROUP_MEMBER_USER_ID, 5, text),
+            ));
+            assert_eq!(
+                reply,
+                permission_denied_message(ChatRole::Viewer, ChatRole::Editor)
+            );
+        }
+        let text = reply_text(service.handle_update_content_message(
+            chat_id.clone(),
+            text_message_from(GROUP_CHAT_ID, GROUP_MEMBER_USER_ID, 6, "/explorer"),
+        ));
+        assert_eq!(text, explorer_message("/".to_string()));
+        assert!(FilesystemServiceImpl::default()
+            .get_or_create_filesystem(&chat_id, None)
+            .get_node(&PathBuf::from("/Documents"))
+            .is_ok_and(|node| node.is_empty_directory()));
+
+        // the replies to a pending action need the role of the action
+        ChatRoleServiceImpl::default()
+            .set_role(member_id.clone(), ChatRole::Editor)
+            .unwrap();
+        let mut chat_session = service.get_or_create_chat_session(&member_id);
+        chat_session.set_action(ChatSessionAction::EditSetting(Setting::TimeZone));
+        service.update_chat_session(member_id.clone(), chat_session);
+        let text = reply_text(service.handle_update_content_message(
+            chat_id.clone(),
+            text_message_from(GROUP_CHAT_ID, GROUP_MEMBER_USER_ID, 7, "+02:00"),
+        ));
+        assert_eq!(
+            text,
+            permission_denied_message(ChatRole::Editor, ChatRole::Admin)
+        );
+        assert_eq!(
+            service.get_or_create_chat_session(&member_id).action(),
+            None
+        );
+        assert_eq!(
+            ChatSettingsServiceImpl::default()
+                .get_chat_settings(&chat_id)
+                .time_zone,
+            None
+        );
+    }
+
+    #[rstest]
+    fn reject_conflict_keeps_existing_file() {
+        let service = ChatSessionServiceImpl::default();
+        let chat_id = ChatId::from(CHAT_ID);
+        let file_path = PathBuf::from("/file-1.tg+text");
+        save_text_file(&service, CHAT_ID, 1);
+        let file_message_id = FilesystemServiceImpl::default()
+            .get_or_create_filesystem(&chat_id, None)
+            .get_node(&file_path)
+            .unwrap()
+            .file_message_id();
+        assert!(file_message_id.is_some());
+
+        service
+            .handle_update_content_message(chat_id.clone(), text_message(CHAT_ID, 10, "/help"))
+            .unwrap();
+        service
+            .handle_update_content_message(chat_id.clone(), text_message(CHAT_ID, 10, "other text"))
+            .unwrap();
+        service
+            .handle_update_content_callback_query(
+                chat_id.clone(),
+                callback_query(CHAT_ID, 11, ChatSessionAction::CurrentDir),
+            )
+            .unwrap();
+        let res = service
+            .handle_update_content_message(chat_id.clone(), text_message(CHAT_ID, 12, "file-1"));
+        assert_eq!(
+            res.err(),
+            Some("A file with the same name already exists".to_string())
+        );
+        assert_eq!(
+            FilesystemServiceImpl::default()
+                .get_or_create_filesystem(&chat_id, None)
+                .get_node(&file_path)
+                .unwrap()
+                .file_message_id(),
+            file_message_id
+        );
+    }
+
+    #[rstest]
+    fn command_addressed_to_other_bot() {
+        let config_repository = ConfigRepositoryImpl::default();
+        let mut config = config_repository.get_config();
+        config.bot_username = Some("infinitecloud_bot".to_string());
+        config_repository.set_config(config);
+        let service = ChatSessionServiceImpl::default();
+
+        assert!(service.is_addressed_to_bot(&text_message_from(
+            GROUP_CHAT_ID,
+            GROUP_ADMIN_USER_ID,
+            1,
+            "/help"
+        )));
+        assert!(service.is_addressed_to_bot(&text_message(
+            GROUP_CHAT_ID,
+            1,
+            "/help@InfiniteCloud_bot"
+        )));
+        assert!(!service.is_addressed_to_bot(&text_message(GROUP_CHAT_ID, 1, "/help@other_bot")));
+        let text = reply_text(service.handle_update_content_message(
+            ChatId::from(GROUP_CHAT_ID),
+            text_message_from(
+                GROUP_CHAT_ID,
+                GROUP_ADMIN_USER_ID,
+                1,
+                "/help@infinitecloud_bot",
+            ),
+        ));
+        assert_eq!(text, help_message());
+    }
+
+    #[rstest]
+    fn group_foreign_keyboard() {
+        let service = ChatSessionServiceImpl::default();
+        let chat_id = ChatId::from(GROUP_CHAT_ID);
+
+        let params = service
+            .handle_update_content_message(
+                chat_id.clone(),
+                text_message_from(GROUP_CHAT_ID, GROUP_ADMIN_USER_ID, 1, "/mkdir"),
+            )
+            .unwrap();
+        assert_eq!(params.reply_to_message_id(), Some(1));
+        service
+            .handle_update_content_message(
+                chat_id.clone(),
+                text_message_from(GROUP_CHAT_ID, GROUP_MEMBER_USER_ID, 2, "/explorer"),
+            )
+            .unwrap();
+
+        // the keyboard opened by the admin replies to the admin's command
+        let value = service
+            .handle_update_content_callback_query(
+                chat_id.clone(),
+                group_callback_query(GROUP_MEMBER_USER_ID, 3, 1, ChatSessionAction::CurrentDir),
+            )
+            .unwrap()
+            .json_value()
+            .unwrap();
+        assert_eq!(value["method"], "answerCallbackQuery");
+        assert_eq!(value["text"], foreign_keyboard_message());
+        assert_eq!(value["show_alert"], true);
+        assert_eq!(
+            service
+                .get_or_create_chat_session(&ChatMemberId::new(
+                    chat_id.clone(),
+                    UserId(GROUP_MEMBER_USER_ID)
+                ))
+                .action(),
+            Some(ChatSessionAction::Explorer)
+        );
+
+        let text = reply_text(service.handle_update_content_callback_query(
+            chat_id.clone(),
+            group_callback_query(GROUP_ADMIN_USER_ID, 3, 1, ChatSessionAction::CurrentDir),
+        ));
+        assert_eq!(text, ask_directory_name_message("/".to_string()));
+    }
+
+    #[rstest]
+    fn share_directory() {
+        let service = ChatSessionServiceImpl::default();
+        ShareServiceImpl::default().set_token_salt(vec![7; 32]);
+        let owner_chat_id = ChatId::from(CHAT_ID);
+        let recipient_chat_id = ChatId::from(OTHER_CHAT_ID);
+        let documents_path = PathBuf::from("/Documents");
+
+        service
+            .handle_update_content_message(
+                owner_chat_id.clone(),
+                text_message(CHAT_ID, 1, "/share"),
+            )
+            .unwrap();
+        service
+            .handle_update_content_callback_query(
+                owner_chat_id.clone(),
+                callback_query(
+                    CHAT_ID,
+                    2,
+                    ChatSessionAction::FileOrDir(documents_path.clone()),
+                ),
+            )
+            .unwrap();
+        let text = reply_text(service.handle_update_content_callback_query(
+            owner_chat_id.clone(),
+            callback_query(CHAT_ID, 2, ChatSessionAction::CurrentDir),
+        ));
+        assert_eq!(text, share_permission_message("/Documents".to_string()));
+        let text = reply_text(service.handle_update_content_callback_query(
+            owner_chat_id.clone(),
+            callback_query(
+                CHAT_ID,
+                2,
+                ChatSessionAction::GrantShare(SharePermission::ReadWrite),
+            ),
+        ));
+        let (_, share) = ShareServiceImpl::default()
+            .get_owned_shares(&owner_chat_id)
+            .remove(0);
+        assert_eq!(
+            text,
+            share_created_message(
+                &share,
+                ConfigRepositoryImpl::default().get_config().bot_username
+            )
+        );
+
+        let text = reply_text(service.handle_update_content_message(
+            recipient_chat_id.clone(),
+            text_message(OTHER_CHAT_ID, 1, &format!("/start {}", share.token)),
+        ));
+        assert_eq!(
+            text,
+            share_accepted_message("/Shared/Documents".to_string(), SharePermission::ReadWrite)
+        );
+
+        // the directories created in the shared folder are stored in the owner's filesystem
+        service
+            .handle_update_content_message(
+                recipient_chat_id.clone(),
+                text_message(OTHER_CHAT_ID, 2, "/mkdir"),
+            )
+            .unwrap();
+        for path in ["/Shared", "/Shared/Documents"] {
+            service
+                .handle_update_content_callback_query(
+                    recipient_chat_id.clone(),
+                    callback_query(
+                        OTHER_CHAT_ID,
+                        3,
+                        ChatSessionAction::FileOrDir(PathBuf::from(path)),
+                    ),
+                )
+                .unwrap();
+        }
+        service
+            .handle_update_content_callback_query(
+                recipient_chat_id.clone(),
+                callback_query(OTHER_CHAT_ID, 3, ChatSessionAction::CurrentDir),
+            )
+            .unwrap();
+        service
+            .handle_update_content_message(
+                recipient_chat_id.clone(),
+                text_message(OTHER_CHAT_ID, 4, "Reports"),
+            )
+            .unwrap();
+
+        let owner_filesystem =
+            FilesystemServiceImpl::default().get_or_create_filesystem(&owner_chat_id, None);
+        assert!(owner_filesystem
+            .get_node(&documents_path.join("Reports"))
+            .unwrap()
+            .is_directory());
+        let recipient_filesystem =
+            FilesystemServiceImpl::default().get_or_create_filesystem(&recipient_chat_id, None);
+        assert!(recipient_filesystem
+            .get_node(&PathBuf::from("/Shared/Documents/Reports"))
+            .is_err());
+    }
+
+    #[rstest]
+    fn drives() {
+        let service = ChatSessionServiceImpl::default();
+        let chat_id = ChatId::from(CHAT_ID);
+        let open_drives_menu = |message_id| {
+            service
+                .handle_update_content_message(
+                    chat_id.clone(),
+                    text_message(CHAT_ID, message_id, "/drive"),
+                )
+                .unwrap()
+        };
+
+        open_drives_menu(1);
+        let text = reply_text(service.handle_update_content_callback_query(
+            chat_id.clone(),
+            callback_query(CHAT_ID, 2, ChatSessionAction::CreateDrive(None)),
+        ));
+        assert_eq!(text, ask_drive_name_message());
+        let text = reply_text(
+            service
+                .handle_update_content_message(chat_id.clone(), text_message(CHAT_ID, 3, "Work")),
+        );
+        assert_eq!(text, drive_created_message("Work".to_string()));
+        let (drive_id, _) = DriveServiceImpl::default().get_drives(&chat_id)[0].clone();
+        assert_eq!(
+            service
+                .get_or_create_chat_session(&chat_member_id(CHAT_ID))
+                .drive_id(),
+            Some(drive_id)
+        );
+
+        // the file is saved in the active drive only
+        save_text_file(&service, CHAT_ID, 10);
+        let file_path = PathBuf::from("/file-10.tg+text");
+        let filesystem_service = FilesystemServiceImpl::default();
+        assert!(filesystem_service
+            .get_or_create_filesystem(&chat_id, Some(drive_id))
+            .get_node(&file_path)
+            .is_ok());
+        assert!(filesystem_service
+            .get_or_create_filesystem(&chat_id, None)
+            .get_node(&file_path)
+            .is_err());
+
+        open_drives_menu(20);
+        let text = reply_text(service.handle_update_content_callback_query(
+            chat_id.clone(),
+            callback_query(CHAT_ID, 21, ChatSessionAction::SwitchDrive(None)),
+        ));
+        assert_eq!(text, drive_switched_message(MAIN_DRIVE_NAME.to_string()));
+        // the operations of the other drives are left for when they are active again
+        let text = reply_text(
+            service
+                .handle_update_content_message(chat_id.clone(), text_message(CHAT_ID, 22, "/undo")),
+        );
+        assert_eq!(text, nothing_to_undo_message());
+        open_drives_menu(23);
+        service
+            .handle_update_content_callback_query(
+                chat_id.clone(),
+                callback_query(CHAT_ID, 24, ChatSessionAction::SwitchDrive(Some(drive_id))),
+            )
+            .unwrap();
+        let text = reply_text(
+            service
+                .handle_update_content_message(chat_id.clone(), text_message(CHAT_ID, 25, "/undo")),
+        );
+        assert_eq!(
+            text,
+            undo_success_message(&JournalOperation::SaveFile {
+                path: file_path.clone(),
+                message_id: 10,
+            })
+        );
+        assert!(filesystem_service
+            .get_or_create_filesystem(&chat_id, Some(drive_id))
+            .get_node(&file_path)
+            .is_err());
+        save_text_file(&service, CHAT_ID, 26);
+        let journal_service = JournalServiceImpl::default();
+        assert!(journal_service
+            .get_latest_entry(&chat_id, Some(drive_id))
+            .is_some());
+
+        open_drives_menu(30);
+        service
+            .handle_update_content_callback_query(
+                chat_id.clone(),
+                callback_query(CHAT_ID, 31, ChatSessionAction::DeleteDrive(None)),
+            )
+            .unwrap();
+        let text = reply_text(service.handle_update_content_callback_query(
+            chat_id.clone(),
+            callback_query(CHAT_ID, 31, ChatSessionAction::DeleteDrive(Some(drive_id))),
+        ));
+        assert_eq!(text, delete_drive_confirmation_message("Work".to_string()));
+        let text = reply_text(service.handle_update_content_callback_query(
+            chat_id.clone(),
+            callback_query(CHAT_ID, 31, ChatSessionAction::Confirm),
+        ));
+        assert_eq!(text, drive_deleted_message("Work".to_string()));
+        assert!(DriveServiceImpl::default().get_drives(&chat_id).is_empty());
+        assert!(FilesystemRepositoryImpl::default()
+            .get_drive_filesystem(&chat_id, drive_id)
+            .is_none());
+        assert!(journal_service
+            .get_latest_entry(&chat_id, Some(drive_id))
+            .is_none());
+        // a new drive doesn't get the id of the deleted one, nor its operations
+        let new_drive_id = DriveServiceImpl::default()
+            .create_drive(chat_id.clone(), "Work".to_string())
+            .unwrap();
+        assert_ne!(new_drive_id, drive_id);
+    }
+
+    #[rstest]
+    fn language() {
+        let service = ChatSessionServiceImpl::default();
+        let chat_id = ChatId::from(CHAT_ID);
+        let chat_settings_service = ChatSettingsServiceImpl::default();
+
+        let text =
+            reply_text(service.handle_update_content_message(
+                chat_id.clone(),
+                text_message(CHAT_ID, 1, "/language"),
+            ));
+        assert_eq!(text, language_message(None));
+        let text = reply_text(service.handle_update_content_callback_query(
+            chat_id.clone(),
+            callback_query(
+                CHAT_ID,
+                2,
+                ChatSessionAction::SetLanguage(Some(Language::Italian)),
+            ),
+        ));
+        // the reply is already in the new language
+        assert_eq!(crate::utils::language(), Language::Italian);
+        assert_eq!(text, language_set_message(Some(Language::Italian)));
+        assert_eq!(
+            chat_settings_service.get_chat_settings(&chat_id).language,
+            Some(Language::Italian)
+        );
+        let text = reply_text(
+            service
+                .handle_update_content_message(chat_id.clone(), text_message(CHAT_ID, 3, "/help")),
+        );
+        assert_eq!(text, help_message());
+        assert!(text.starts_with("*SALVARE FILE*"));
+
+        let text = reply_text(service.handle_update_content_message(
+            chat_id.clone(),
+            text_message(CHAT_ID, 4, "/language klingon"),
+        ));
+        assert_eq!(text, language_unknown_message("klingon".to_string()));
+
+        // the test user has no language, so the bot falls back to English
+        let text = reply_text(service.handle_update_content_message(
+            chat_id.clone(),
+            text_message(CHAT_ID, 5, "/language auto"),
+        ));
+        assert_eq!(crate::utils::language(), Language::English);
+        assert_eq!(text, language_set_message(None));
+        assert_eq!(
+            chat_settings_service.get_chat_settings(&chat_id).language,
+            None
+        );
+    }
+
+    #[rstest]
+    fn settings() {
+        let service = ChatSessionServiceImpl::default();
+        let chat_id = ChatId::from(CHAT_ID);
+        let chat_settings_service = ChatSettingsServiceImpl::default();
+        let callback = |message_id, action| {
+            reply_text(service.handle_update_content_callback_query(
+                chat_id.clone(),
+                callback_query(CHAT_ID, message_id, action),
+            ))
+        };
+        let document_message = |message_id: i32, file_name: &str| {
+            let mut msg = text_message(CHAT_ID, message_id, "");
+            msg.text = None;
+            msg.document = Some(Box::new(
+                Document::builder()
+                    .file_id(format!("file-id-{message_id}"))
+                    .file_unique_id(format!("unique-id-{message_id}"))
+                    .file_name(file_name)
+                    .build(),
+            ));
+            msg
+        };
+
+        let text =
+            reply_text(service.handle_update_content_message(
+                chat_id.clone(),
+                text_message(CHAT_ID, 1, "/settings"),
+            ));
+        assert_eq!(text, settings_message(&ChatSettings::default()));
+
+        let text = callback(2, ChatSessionAction::EditSetting(Setting::SaveDir));
+        assert_eq!(text, save_dir_setting_message("/".to_string()));
+        callback(2, ChatSessionAction::FileOrDir(PathBuf::from("/Documents")));
+        callback(2, ChatSessionAction::CurrentDir);
+        callback(2, ChatSessionAction::EditSetting(Setting::ConflictPolicy));
+        callback(
+            2,
+            ChatSessionAction::SetConflictPolicy(ConflictPolicy::Rename),
+        );
+        callback(2, ChatSessionAction::EditSetting(Setting::AutoNaming));
+        callback(
+            2,
+            ChatSessionAction::SetAutoNaming(AutoNaming::OriginalName),
+        );
+        callback(2, ChatSessionAction::EditSetting(Setting::SortOrder));
+        callback(
+            2,
+            ChatSessionAction::SetSortOrder(SortOrder::NameDescending),
+        );
+        // the settings are only changed by choosing a value
+        callback(2, ChatSessionAction::EditSetting(Setting::SortOrder));
+        let text = callback(2, ChatSessionAction::Back);
+        assert!(text.starts_with("*SETTINGS*"));
+
+        callback(2, ChatSessionAction::EditSetting(Setting::TimeZone));
+        let text = reply_text(
+            service
+                .handle_update_content_message(chat_id.clone(), text_message(CHAT_ID, 3, "Mars")),
+        );
+        assert_eq!(
+            text,
+            invalid_time_zone_message("Invalid UTC offset \"Mars\"".to_string())
+        );
+        let text = reply_text(
+            service
+                .handle_update_content_message(chat_id.clone(), text_message(CHAT_ID, 4, "+02:00")),
+        );
+        let chat_settings = chat_settings_service.get_chat_settings(&chat_id);
+        assert_eq!(text, settings_message(&chat_settings));
+        assert_eq!(
+            chat_settings,
+            ChatSettings {
+                language: None,
+                save_dir: Some(PathBuf::from("/Documents")),
+                sort_order: Some(SortOrder::NameDescending),
+                conflict_policy: Some(ConflictPolicy::Rename),
+                time_zone: Some("+02:00".parse().unwrap()),
+                auto_naming: Some(AutoNaming::OriginalName),
+                inbox: None,
+                name_template: None,
+            }
+        );
+
+        // the received files are saved in the save folder with their names, without overwriting
+        for message_id in [10, 20] {
+            let text = reply_text(service.handle_update_content_message(
+                chat_id.clone(),
+                document_message(message_id, "report.pdf"),
+            ));
+            assert_eq!(text, create_file_message("/Documents".to_string()));
+            callback(message_id + 1, ChatSessionAction::CurrentDir);
+        }
+        let text = reply_text(service.handle_update_content_message(
+            chat_id.clone(),
+            text_message(CHAT_ID, 30, "/ls /Documents"),
+        ));
+        assert_eq!(
+            text,
+            ls_message(
+                "/Documents".to_string(),
+                &[],
+                &["report.pdf".to_string(), "report (1).pdf".to_string()]
+            )
+        );
+
+        let text =
+            reply_text(service.handle_update_content_message(
+                chat_id.clone(),
+                text_message(CHAT_ID, 31, "/history"),
+            ));
+        assert!(text.contains(" UTC+02:00_"), "{text}");
+    }
+
+    #[rstest]
+    fn inbox() {
+        let service = ChatSessionServiceImpl::default();
+        let chat_id = ChatId::from(CHAT_ID);
+        let callback = |message_id, action| {
+            reply_text(service.handle_update_content_callback_query(
+                chat_id.clone(),
+                callback_query(CHAT_ID, message_id, action),
+            ))
+        };
+        let message = |msg| service.handle_update_content_message(chat_id.clone(), msg);
+        let file_message = |message_id: i32, file_name: Option<&str>, caption: Option<&str>| {
+            let mut msg = text_message(CHAT_ID, message_id, "");
+            msg.text = None;
+            msg.caption = caption.map(str::to_string);
+            match file_name {
+                Some(file_name) => {
+                    msg.document = Some(Box::new(
+                        Document::builder()
+                            .file_id(format!("file-id-{message_id}"))
+                            .file_unique_id(format!("unique-id-{message_id}"))
+                            .file_name(file_name)
+                            .build(),
+                    ));
+                }
+                None => {
+                    msg.photo = Some(vec![PhotoSize::builder()
+                        .file_id(format!("file-id-{message_id}"))
+                        .file_unique_id(format!("unique-id-{message_id}"))
+                        .width(100)
+                        .height(100)
+                        .build()]);
+                }
+            }
+            msg
+        };
+        let file_exists = |path: &str| {
+            FilesystemServiceImpl::default()
+                .get_or_create_filesystem(&chat_id, None)
+                .get_node(&PathBuf::from(path))
+                .is_ok()
+        };
+
+        message(text_message(CHAT_ID, 1, "/settings")).unwrap();
+        callback(2, ChatSessionAction::EditSetting(Setting::Inbox));
+        callback(2, ChatSessionAction::EditSetting(Setting::NameTemplate));
+        let text = reply_text(message(text_message(CHAT_ID, 3, "notes/{type}")));
+        assert_eq!(
+            text,
+            invalid_name_template_message(
+                "The template cannot include the `/` character".to_string()
+            )
+        );
+        message(text_message(CHAT_ID, 4, "note-{type}")).unwrap();
+        let chat_settings = ChatSettingsServiceImpl::default().get_chat_settings(&chat_id);
+        assert!(chat_settings.inbox());
+        assert_eq!(chat_settings.name_template(), "note-{type}");
+
+        // the files are saved right away, named after their name, their caption or the template
+        let params = message(file_message(10, Some("report.pdf"), Some("Report"))).unwrap();
+        assert_eq!(
+            params.json_value().unwrap()["reply_markup"],
+            serde_json::to_value(refine_file_inline_keyboard()).unwrap()
+        );
+        assert_eq!(
+            reply_text(Ok(params)),
+            created_file_success_message("report.pdf".to_string(), "/".to_string())
+        );
+        let text = reply_text(message(text_message(CHAT_ID, 20, "buy milk")));
+        assert!(text.starts_with("File *note-text."), "{text}");
+        // the names from the template are numbered even if the conflicts are rejected
+        let text = reply_text(message(text_message(CHAT_ID, 21, "buy eggs")));
+        assert!(text.starts_with("File *note-text (1)."), "{text}");
+        let text = reply_text(message(file_message(
+            30,
+            None,
+            Some("Holiday in Rome\nday 1"),
+        )));
+        assert_eq!(
+            text,
+            created_file_success_message("Holiday in Rome.jpg".to_string(), "/".to_string())
+        );
+
+        // the last saved file can be moved and renamed
+        let text = callback(31, ChatSessionAction::RefineFile(None, None));
+        assert_eq!(
+            text,
+            refine_file_message("Holiday in Rome.jpg".to_string(), "/".to_string())
+        );
+        callback(
+            31,
+            ChatSessionAction::FileOrDir(PathBuf::from("/Documents")),
+        );
+        let text = callback(31, ChatSessionAction::CurrentDir);
+        assert_eq!(
+            text,
+            ask_refine_file_name_message(
+                "Holiday in Rome.jpg".to_string(),
+                "/Documents".to_string()
+            )
+        );
+        let text = reply_text(message(text_message(CHAT_ID, 32, "Rome")));
+        assert_eq!(
+            text,
+            moved_file_success_message(
+                "Rome.jpg".to_string(),
+                "/Holiday in Rome.jpg".to_string(),
+                "/Documents/Rome.jpg".to_string()
+            )
+        );
+        assert!(file_exists("/Documents/Rome.jpg"));
+        assert!(!file_exists("/Holiday in Rome.jpg"));
+
+        message(file_message(40, Some("notes.txt"), None)).unwrap();
+        callback(41, ChatSessionAction::RefineFile(None, None));
+        callback(41, ChatSessionAction::CurrentDir);
+        let text = callback(41, ChatSessionAction::KeepFileNames);
+        assert_eq!(
+            text,
+            created_file_success_message("notes.txt".to_string(), "/".to_string())
+        );
+        // the file can't be refined twice
+        assert!(service
+            .handle_update_content_callback_query(
+                chat_id.clone(),
+                callback_query(CHAT_ID, 41, ChatSessionAction::RefineFile(None, None)),
+            )
+            .is_err());
+
+        // the new name can't take an existing path or leave the chosen directory
+        for (message_id, new_name, error) in [
+            (50, "Rome", destination_taken_error()),
+            (60, "../Rome", file_name_slash_error()),
+        ] {
+            let file_name = format!("plan-{message_id}.jpg");
+            message(file_message(message_id, Some(&file_name), None)).unwrap();
+            callback(message_id + 1, ChatSessionAction::RefineFile(None, None));
+            callback(
+                message_id + 1,
+                ChatSessionAction::FileOrDir(PathBuf::from("/Documents")),
+            );
+            callback(message_id + 1, ChatSessionAction::CurrentDir);
+            assert_eq!(
+                message(text_message(CHAT_ID, message_id + 2, new_name)).err(),
+                Some(error)
+            );
+            assert!(file_exists(&format!("/{file_name}")));
+            assert!(!file_exists("/Rome.jpg"));
+        }
+    }
+
+    #[rstest]
+    fn inline_query() {
+        let service = ChatSessionServiceImpl::default();
+        let chat_id = ChatId::from(CHAT_ID);
+        save_text_file(&service, CHAT_ID, 1);
+        let mut msg = text_message(CHAT_ID, 10, "");
+        msg.text = None;
+        msg.document = Some(Box::new(
+            Document::builder()
+                .file_id("document-file-id")
+                .file_unique_id("document-unique-id")
+                .build(),
+        ));
+        service
+            .handle_update_content_message(chat_id.clone(), msg)
+            .unwrap();
+        service
+            .handle_update_content_callback_query(
+                chat_id.clone(),
+                callback_query(CHAT_ID, 11, ChatSessionAction::CurrentDir),
+            )
+            .unwrap();
+        service
+            .handle_update_content_message(chat_id.clone(), text_message(CHAT_ID, 12, "Report"))
+            .unwrap();
+
+        let inline_query = |user_id: u64, query: &str| {
+            InlineQuery::builder()
+                .id("inline-query-id")
+                .from(user(user_id))
+                .query(query)
+                .offset("")
+                .build()
+        };
+        let results = |params: MessageParams| params.json_value().unwrap()["results"].clone();
+
+        // the text files have no file id and can't be sent
+        let params = service
+            .handle_update_content_inline_query(chat_id.clone(), inline_query(CHAT_ID as u64, ""))
+            .unwrap();
+        assert_eq!(
+            results(params),
+            serde_json::json!([{
+                "type": "document",
+                "id": "0",
+                "title": "Report",
+                "document_file_id": "document-file-id",
+            }])
+        );
+        let params = service
+            .handle_update_content_inline_query(
+                chat_id.clone(),
+                inline_query(CHAT_ID as u64, "FILE"),
+            )
+            .unwrap();
+        assert_eq!(results(params), serde_json::json!([]));
+
+        // the other users search their own files
+        let params = service
+            .handle_update_content_inline_query(
+                ChatId::from(OTHER_CHAT_ID),
+                inline_query(OTHER_CHAT_ID as u64, ""),
+            )
+            .unwrap();
+        assert_eq!(results(params), serde_json::json!([]));
+        assert_eq!(
+            ChatSessionRepositoryImpl::default().get_chat_session(&chat_member_id(OTHER_CHAT_ID)),
+            None
+        );
+    }
+
+    #[rstest]
+    fn edited_message() {
+        let service = ChatSessionServiceImpl::default();
+        let chat_id = ChatId::from(CHAT_ID);
+        save_text_file(&service, CHAT_ID, 10);
+        let file_size = || match FilesystemServiceImpl::default()
+            .get_or_create_filesystem(&chat_id, None)
+            .get_node(&PathBuf::from("/file-10.tg+text"))
+            .unwrap()
+        {
+            StoredFileSystemNode::File { size, .. } => size,
+            StoredFileSystemNode::Directory { .. } => panic!("Not a file"),
+        };
+        assert_eq!(file_size(), "some text".len() as u64);
+
+        service.handle_update_content_edited_message(
+            chat_id.clone(),
+            text_message(CHAT_ID, 10, "some edited text"),
+        );
+        assert_eq!(file_size(), "some edited text".len() as u64);
+
+        // the edits of the messages that aren't saved are ignored
+        service.handle_update_content_edited_message(
+            chat_id.clone(),
+            text_message(CHAT_ID, 99, "not saved"),
+        );
+        service.handle_update_content_edited_message(
+            ChatId::from(OTHER_CHAT_ID),
+            text_message(OTHER_CHAT_ID, 10, "not saved"),
+        );
+        assert_eq!(file_size(), "some edited text".len() as u64);
+        assert!(!FilesystemRepositoryImpl::default()
+            .list_chat_ids(None, usize::MAX)
+            .contains(&ChatId::from(OTHER_CHAT_ID)));
+    }
+
+    #[rstest]
+    fn media_group() {
+        let service = ChatSessionServiceImpl::default();
+        let chat_id = ChatId::from(CHAT_ID);
+        let album_message = |message_id: i32, media_group_id: &str, file_name: Option<&str>| {
+            let mut msg = text_message(CHAT_ID, message_id, "");
+            msg.text = None;
+            msg.media_group_id = Some(media_group_id.to_string());
+            match file_name {
+                Some(file_name) => {
+                    msg.document = Some(Box::new(
+                        Document::builder()
+                            .file_id(format!("file-id-{message_id}"))
+                            .file_unique_id(format!("unique-id-{message_id}"))
+                            .file_name(file_name)
+                            .build(),
+                    ));
+                }
+                None => {
+                    msg.photo = Some(vec![PhotoSize::builder()
+                        .file_id(format!("file-id-{message_id}"))
+                        .file_unique_id(format!("unique-id-{message_id}"))
+                        .width(100)
+                        .height(100)
+                        .build()]);
+                }
+            }
+            msg
+        };
+        let file_exists = |path: &str| {
+            FilesystemServiceImpl::default()
+                .get_or_create_filesystem(&chat_id, None)
+                .get_node(&PathBuf::from(path))
+                .is_ok()
+        };
+
+        // only the first file of the album is answered
+        assert!(!service.add_media_group_file(&chat_id, &album_message(10, "photos", None)));
+        let text = reply_text(
+            service
+                .handle_update_content_message(chat_id.clone(), album_message(10, "photos", None)),
+        );
+        assert_eq!(text, create_media_group_message("/".to_string()));
+        assert!(service.add_media_group_file(&chat_id, &album_message(11, "photos", None)));
+        assert!(service.add_media_group_file(&chat_id, &album_message(12, "photos", None)));
+        assert!(!service.add_media_group_file(&chat_id, &album_message(13, "other", None)));
+
+        let text = reply_text(service.handle_update_content_callback_query(
+            chat_id.clone(),
+            callback_query(CHAT_ID, 14, ChatSessionAction::CurrentDir),
+        ));
+        assert_eq!(text, ask_media_group_file_names_message(3, "/".to_string()));
+        let text =
+            reply_text(service.handle_update_content_message(
+                chat_id.clone(),
+                text_message(CHAT_ID, 15, "Holiday"),
+            ));
+        assert!(text.starts_with("3 files SAVED"));
+        for path in ["/Holiday-1.jpg", "/Holiday-2.jpg", "/Holiday-3.jpg"] {
+            assert!(file_exists(path), "{path} not saved");
+        }
+
+        // the documents can keep their names
+        for (message_id, file_name) in [(20, "report.pdf"), (21, "notes.txt")] {
+            let msg = album_message(message_id, "documents", Some(file_name));
+            if !service.add_media_group_file(&chat_id, &msg) {
+                service
+                    .handle_update_content_message(chat_id.clone(), msg)
+                    .unwrap();
+            }
+        }
+        service
+            .handle_update_content_callback_query(
+                chat_id.clone(),
+                callback_query(CHAT_ID, 22, ChatSessionAction::CurrentDir),
+            )
+            .unwrap();
+        let text = reply_text(service.handle_update_content_callback_query(
+            chat_id.clone(),
+            callback_query(CHAT_ID, 22, ChatSessionAction::KeepFileNames),
+        ));
+        assert_eq!(
+            text,
+            created_media_group_message(
+                &["report.pdf".to_string(), "notes.txt".to_string()],
+                &[],
+                "/".to_string()
+            )
+        );
+        assert!(file_exists("/report.pdf"));
+        assert!(file_exists("/notes.txt"));
+        assert_eq!(
+            service
+                .get_or_create_chat_session(&chat_member_id(CHAT_ID))
+                .action(),
+            None
+        );
+    }
+
+    #[rstest]
+    fn bulk_operations() {
+        let service = ChatSessionServiceImpl::default();
+        let chat_id = ChatId::from(CHAT_ID);
+        for message_id in [1, 10, 20] {
+            save_text_file(&service, CHAT_ID, message_id);
+        }
+        let callback = |action: ChatSessionAction| {
+            service.handle_update_content_callback_query(
+                chat_id.clone(),
+                callback_query(CHAT_ID, 31, action),
+            )
+        };
+        let get_node = |path: &str| {
+            FilesystemServiceImpl::default()
+                .get_or_create_filesystem(&chat_id, None)
+                .get_node(&PathBuf::from(path))
+        };
+        let file_path = |message_id: i32| PathBuf::from(format!("/file-{message_id}.tg+text"));
+        let start_selection = || {
+            service
+                .handle_update_content_message(
+                    chat_id.clone(),
+                    text_message(CHAT_ID, 30, "/explorer"),
+                )
+                .unwrap();
+            let text = reply_text(callback(ChatSessionAction::SelectFiles));
+            assert_eq!(text, select_files_message("/".to_string(), 0));
+        };
+
+        // nothing can be done without selecting files
+        start_selection();
+        let value = callback(ChatSessionAction::Bulk(BulkOperation::Move, None))
+            .unwrap()
+            .json_value()
+            .unwrap();
+        assert_eq!(value["text"], empty_selection_message());
+        assert_eq!(value["show_alert"], true);
+
+        // clicking a file twice unselects it
+        callback(ChatSessionAction::FileOrDir(file_path(1))).unwrap();
+        callback(ChatSessionAction::FileOrDir(file_path(10))).unwrap();
+        callback(ChatSessionAction::FileOrDir(file_path(20))).unwrap();
+        let text = reply_text(callback(ChatSessionAction::FileOrDir(file_path(20))));
+        assert_eq!(text, select_files_message("/".to_string(), 2));
+
+        callback(ChatSessionAction::Bulk(BulkOperation::Copy, None)).unwrap();
+        callback(ChatSessionAction::FileOrDir(PathBuf::from("/Documents"))).unwrap();
+        let text = reply_text(callback(ChatSessionAction::CurrentDir));
+        assert_eq!(
+            text,
+            bulk_operation_message(
+                BulkOperation::Copy,
+                &[
+                    "/file-1.tg+text".to_string(),
+                    "/file-10.tg+text".to_string()
+                ]
+            )
+        );
+        assert!(get_node("/Documents/file-1.tg+text").unwrap().is_file());
+        assert!(get_node("/Documents/file-10.tg+text").unwrap().is_file());
+        assert!(get_node("/file-1.tg+text").unwrap().is_file());
+
+        // nothing is moved if any file can't be, and the selection is cleared anyway
+        start_selection();
+        callback(ChatSessionAction::FileOrDir(file_path(10))).unwrap();
+        callback(ChatSessionAction::FileOrDir(file_path(20))).unwrap();
+        callback(ChatSessionAction::Bulk(BulkOperation::Move, None)).unwrap();
+        callback(ChatSessionAction::FileOrDir(PathBuf::from("/Documents"))).unwrap();
+        let text = reply_text(callback(ChatSessionAction::CurrentDir));
+        assert_eq!(
+            text,
+            bulk_operation_failed_message(
+                BulkOperation::Move,
+                &[(
+                    "/file-10.tg+text".to_string(),
+                    "Destination is already taken".to_string()
+                )]
+            )
+        );
+        assert!(get_node("/file-20.tg+text").unwrap().is_file());
+        assert!(get_node("/Documents/file-20.tg+text").is_err());
+
+        // the selected files can conflict with each other, the ones already moved are moved back
+        start_selection();
+        callback(ChatSessionAction::FileOrDir(file_path(1))).unwrap();
+        callback(ChatSessionAction::FileOrDir(PathBuf::from("/Documents"))).unwrap();
+        callback(ChatSessionAction::FileOrDir(PathBuf::from(
+            "/Documents/file-1.tg+text",
+        )))
+        .unwrap();
+        callback(ChatSessionAction::Bulk(BulkOperation::Move, None)).unwrap();
+        callback(ChatSessionAction::FileOrDir(PathBuf::from("/Images"))).unwrap();
+        let text = reply_text(callback(ChatSessionAction::CurrentDir));
+        assert_eq!(
+            text,
+            bulk_operation_failed_message(
+                BulkOperation::Move,
+                &[(
+                    "/file-1.tg+text".to_string(),
+                    "Destination is already taken".to_string()
+                )]
+            )
+        );
+        assert!(get_node("/Documents/file-1.tg+text").unwrap().is_file());
+        assert!(get_node("/Images/file-1.tg+text").is_err());
+
+        // the moved files are moved back together
+        start_selection();
+        callback(ChatSessionAction::FileOrDir(file_path(1))).unwrap();
+        callback(ChatSessionAction::FileOrDir(file_path(20))).unwrap();
+        callback(ChatSessionAction::Bulk(BulkOperation::Move, None)).unwrap();
+        callback(ChatSessionAction::FileOrDir(PathBuf::from("/Images"))).unwrap();
+        let text = reply_text(callback(ChatSessionAction::CurrentDir));
+        assert!(text.starts_with("2 files MOVED"));
+        assert!(get_node("/Images/file-20.tg+text").unwrap().is_file());
+        let text = reply_text(
+            service
+                .handle_update_content_message(chat_id.clone(), text_message(CHAT_ID, 31, "/undo")),
+        );
+        assert!(text.starts_with("UNDONE:\n2 files MOVED"));
+        assert!(get_node("/file-1.tg+text").unwrap().is_file());
+        assert!(get_node("/file-20.tg+text").unwrap().is_file());
+        assert!(get_node("/Images/file-20.tg+text").is_err());
+
+        start_selection();
+        callback(ChatSessionAction::FileOrDir(file_path(1))).unwrap();
+        callback(ChatSessionAction::FileOrDir(file_path(10))).unwrap();
+        callback(ChatSessionAction::Bulk(BulkOperation::Tag, None)).unwrap();
+        let text = reply_text(service.handle_update_content_message(
+            chat_id.clone(),
+            text_message(CHAT_ID, 32, "two words"),
+        ));
+        assert!(text.starts_with("Cannot add the tag"));
+        service
+            .handle_update_content_message(chat_id.clone(), text_message(CHAT_ID, 33, "#work"))
+            .unwrap();
+        assert_eq!(
+            get_node("/file-10.tg+text").unwrap().file_tags(),
+            BTreeSet::from(["work".to_string()])
+        );
+
+        // the tags are removed only from the files that didn't have them
+        start_selection();
+        callback(ChatSessionAction::FileOrDir(file_path(10))).unwrap();
+        callback(ChatSessionAction::FileOrDir(file_path(20))).unwrap();
+        callback(ChatSessionAction::Bulk(BulkOperation::Tag, None)).unwrap();
+        service
+            .handle_update_content_message(chat_id.clone(), text_message(CHAT_ID, 34, "#work"))
+            .unwrap();
+        service
+            .handle_update_content_message(chat_id.clone(), text_message(CHAT_ID, 35, "/undo"))
+            .unwrap();
+        assert_eq!(
+            get_node("/file-10.tg+text").unwrap().file_tags(),
+            BTreeSet::from(["work".to_string()])
+        );
+        assert!(get_node("/file-20.tg+text").unwrap().file_tags().is_empty());
+
+        start_selection();
+        callback(ChatSessionAction::FileOrDir(file_path(1))).unwrap();
+        callback(ChatSessionAction::FileOrDir(file_path(10))).unwrap();
+        callback(ChatSessionAction::Bulk(BulkOperation::Delete, None)).unwrap();
+        let text = reply_text(callback(ChatSessionAction::Confirm));
+        assert!(text.starts_with("2 files DELETED"));
+        assert!(get_node("/file-10.tg+text").is_err());
+
+        // the deleted files are restored together, with their tags
+        service
+            .handle_update_content_message(chat_id.clone(), text_message(CHAT_ID, 36, "/undo"))
+            .unwrap();
+        assert!(get_node("/file-1.tg+text").unwrap().is_file());
+        assert_eq!(
+            get_node("/file-10.tg+text").unwrap().file_tags(),
+            BTreeSet::from(["work".to_string()])
+        );
+    }
+
+    #[rstest]
+    fn failed_update_is_discarded() {
+        let service = ChatSessionServiceImpl::default();
+        let chat_id = ChatId::from(CHAT_ID);
+        let mut chat_session = service.get_or_create_chat_session(&chat_member_id(CHAT_ID));
+        chat_session.set_action(ChatSessionAction::MkDir(None));
+
+        service.record_operation(
+            &chat_id,
+            None,
+            JournalOperation::MkDir {
+                path: PathBuf::from("/Projects"),
+            },
+        );
+        service.discard_failed_update(chat_member_id(CHAT_ID), &chat_session);
+        assert_eq!(
+            service
+                .get_or_create_chat_session(&chat_member_id(CHAT_ID))
+                .action(),
+            None
+        );
+        assert!(service
+            .journal_service
+            .get_recent_entries(&chat_id)
+            .is_empty());
+
+        // the operations of the next update are recorded once it has been saved
+        save_text_file(&service, CHAT_ID, 1);
+        assert_eq!(
+            service
+                .journal_service
+                .get_recent_entries(&chat_id)
+                .iter()
+                .map(|entry| entry.operation().clone())
+                .collect::<Vec<_>>(),
+            vec![JournalOperation::SaveFile {
+                path: PathBuf::from("/file-1.tg+text"),
+                message_id: 1,
+            }]
+        );
+    }
+
+    #[rstest]
+    fn undo_conflict_keeps_entry() {
+        let service = ChatSessionServiceImpl::default();
+        let filesystem_service = FilesystemServiceImpl::default();
+        let chat_id = ChatId::from(CHAT_ID);
+        let blocking_path = PathBuf::from("/file-1.tg+text");
+        save_text_file(&service, CHAT_ID, 1);
+        let run = |message_id, text: &str| {
+            reply_text(service.handle_update_content_message(
+                chat_id.clone(),
+                text_message(CHAT_ID, message_id, text),
+            ))
+        };
+        run(10, "/mv /file-1.tg+text /Documents");
+        // another file takes the place the moved file would go back to
+        let mut fs = filesystem_service.get_or_create_filesystem(&chat_id, None);
+        fs.create_file_from_node(
+            &blocking_path,
+            FileSystemNode::new_file(20, 0, Some("text/plain".to_string())),
+        )
+        .unwrap();
+        filesystem_service.update_filesystem(&chat_id, None, fs);
+
+        let text = run(11, "/undo");
+        assert!(text.contains("Destination is already taken"), "{text}");
+        assert_eq!(
+            service.journal_service.get_recent_entries(&chat_id).len(),
+            2
+        );
+
+        let mut fs = filesystem_service.get_or_create_filesystem(&chat_id, None);
+        fs.rm(&blocking_path).unwrap();
+        filesystem_service.update_filesystem(&chat_id, None, fs);
+        let text = run(12, "/undo");
+        assert_eq!(
+            text,
+            undo_success_message(&JournalOperation::MoveFile {
+                from: blocking_path.clone(),
+                to: PathBuf::from("/Documents/file-1.tg+text"),
+            })
+        );
+        let fs = filesystem_service.get_or_create_filesystem(&chat_id, None);
+        assert_eq!(
+            fs.get_node(&blocking_path).unwrap().file_message_id(),
+            Some(1)
+        );
+        assert_eq!(
+            service.journal_service.get_recent_entries(&chat_id).len(),
+            1
+        );
+    }
+
+    #[rstest]
+    fn path_commands() {
+        let service = ChatSessionServiceImpl::default();
+        let chat_id = ChatId::from(CHAT_ID);
+        save_text_file(&service, CHAT_ID, 1);
+        let run =
+            |text: &str| {
+                reply_text(service.handle_update_content_message(
+                    chat_id.clone(),
+                    text_message(CHAT_ID, 10, text),
+                ))
+            };
+
+        assert_eq!(
+            run("/mkdir \"/My Docs/2026/Q1\""),
+            created_directories_message(&[
+                "/My Docs".to_string(),
+                "/My Docs/2026".to_string(),
+                "/My Docs/2026/Q1".to_string()
+            ])
+        );
+        assert_eq!(
+            run("/mkdir '/My Docs/2026'"),
+            path_command_failed_message("Path already exists".to_string())
+        );
+
+        assert_eq!(
+            run("/cd \"/My Docs\""),
+            working_dir_message("/My Docs".to_string())
+        );
+        // the working directory is kept by the other commands
+        run("/help");
+        assert_eq!(run("/pwd"), working_dir_message("/My Docs".to_string()));
+        assert_eq!(
+            run("/cd ../Missing"),
+            path_command_failed_message("Path not found".to_string())
+        );
+
+        assert_eq!(
+            run("/mv /file-1.tg+text 2026/"),
+            moved_path_message(
+                "/file-1.tg+text".to_string(),
+                "/My Docs/2026/file-1.tg+text".to_string()
+            )
+        );
+        assert_eq!(
+            run("/ls 2026"),
+            ls_message(
+                "/My Docs/2026".to_string(),
+                &["Q1".to_string()],
+                &["file-1.tg+text".to_string()]
+            )
+        );
+        assert_eq!(
+            run("/mv 2026/file-1.tg+text"),
+            path_command_failed_message("Wrong arguments, use `/mv <from> <to>`".to_string())
+        );
+        assert_eq!(
+            run("/mv 2026 2026/Q1"),
+            path_command_failed_message("A directory can't be moved into itself".to_string())
+        );
+
+        run("/undo");
+        assert_eq!(
+            run("/ls /"),
+            ls_message(
+                "/".to_string(),
+                &[
+                    "Documents".to_string(),
+                    "Images".to_string(),
+                    "My Docs".to_string(),
+                    "Trash".to_string(),
+                    "Videos".to_string()
+                ],
+                &["file-1.tg+text".to_string()]
+            )
+        );
+
+        // the working directory follows the moves of its parents
+        run("/cd \"/My Docs/2026\"");
+        assert_eq!(
+            run("/mv \"/My Docs\" /Archive"),
+            moved_path_message("/My Docs".to_string(), "/Archive".to_string())
+        );
+        assert_eq!(
+            run("/pwd"),
+            working_dir_message("/Archive/2026".to_string())
+        );
+        // and falls back to the root when it's not there anymore
+        run("/undo");
+        assert_eq!(run("/pwd"), working_dir_message("/".to_string()));
+    }
+}
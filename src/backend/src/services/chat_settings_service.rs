@@ -0,0 +1,149 @@
+use frankenstein::types::User;
+
+use crate::repositories::{
+    ChatId, ChatSettings, ChatSettingsRepository, ChatSettingsRepositoryImpl, Language,
+};
+
+pub trait ChatSettingsService {
+    /// Returns the default settings if the chat hasn't changed any.
+    fn get_chat_settings(&self, chat_id: &ChatId) -> ChatSettings;
+
+    /// Changes the settings of the chat with `update`, starting from the defaults if the chat hasn't changed any.
+    fn update_chat_settings(&self, chat_id: ChatId, update: impl FnOnce(&mut ChatSettings));
+
+    /// Overrides the language of the members of the chat, or lets each of them use their own when `None`.
+    fn set_language(&self, chat_id: ChatId, language: Option<Language>);
+
+    /// The language chosen for the chat, or else the language of the user's Telegram app if it is supported.
+    fn get_language(&self, chat_id: &ChatId, user: Option<&User>) -> Language;
+
+    fn delete_chat_settings(&self, chat_id: &ChatId);
+}
+
+pub struct ChatSettingsServiceImpl<T: ChatSettingsRepository> {
+    chat_settings_repository: T,
+}
+
+impl Default for ChatSettingsServiceImpl<ChatSettingsRepositoryImpl> {
+    fn default() -> Self {
+        Self::new(ChatSettingsRepositoryImpl::default())
+    }
+}
+
+impl<T: ChatSettingsRepository> ChatSettingsService for ChatSettingsServiceImpl<T> {
+    fn get_chat_settings(&self, chat_id: &ChatId) -> ChatSettings {
+        self.chat_settings_repository
+            .get_chat_settings(chat_id)
+            .unwrap_or_default()
+    }
+
+    fn update_chat_settings(&self, chat_id: ChatId, update: impl FnOnce(&mut ChatSettings)) {
+        let mut chat_settings = self.get_chat_settings(&chat_id);
+        update(&mut chat_settings);
+        self.chat_settings_repository
+            .set_chat_settings(chat_id, chat_settings);
+    }
+
+    fn set_language(&self, chat_id: ChatId, language: Option<Language>) {
+        self.update_chat_settings(chat_id, |chat_settings| chat_settings.language = language);
+    }
+
+    fn get_language(&self, chat_id: &ChatId, user: Option<&User>) -> Language {
+        self.get_chat_settings(chat_id)
+            .language
+            .or_else(|| {
+                user.and_then(|user| user.language_code.as_deref())
+                    .and_then(Language::from_code)
+            })
+            .unwrap_or_default()
+    }
+
+    fn delete_chat_settings(&self, chat_id: &ChatId) {
+        self.chat_settings_repository.remove_chat_settings(chat_id);
+    }
+}
+
+impl<T: ChatSettingsRepository> ChatSettingsServiceImpl<T> {
+    fn new(chat_settings_repository: T) -> Self {
+        Self {
+            chat_settings_repository,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::SortOrder;
+    use rstest::*;
+
+    const CHAT_ID: i64 = 1;
+
+    fn user(language_code: Option<&str>) -> User {
+        User::builder()
+            .id(1)
+            .is_bot(false)
+            .first_name("John")
+            .maybe_language_code(language_code.map(str::to_string))
+            .build()
+    }
+
+    #[rstest]
+    #[case(None, None, Language::English)]
+    #[case(None, Some("it-IT"), Language::Italian)]
+    #[case(None, Some("de"), Language::English)]
+    #[case(Some(Language::English), Some("it"), Language::English)]
+    #[case(Some(Language::Italian), None, Language::Italian)]
+    fn get_language(
+        #[case] chat_language: Option<Language>,
+        #[case] user_language_code: Option<&str>,
+        #[case] expected: Language,
+    ) {
+        let service = ChatSettingsServiceImpl::default();
+        let chat_id = ChatId::from(CHAT_ID);
+        if chat_language.is_some() {
+            service.set_language(chat_id.clone(), chat_language);
+        }
+
+        assert_eq!(
+            service.get_language(&chat_id, Some(&user(user_language_code))),
+            expected
+        );
+    }
+
+    #[rstest]
+    fn get_language_without_user() {
+        let service = ChatSettingsServiceImpl::default();
+
+        assert_eq!(
+            service.get_language(&ChatId::from(CHAT_ID), None),
+            Language::English
+        );
+    }
+
+    #[rstest]
+    fn update_chat_settings() {
+        let service = ChatSettingsServiceImpl::default();
+        let chat_id = ChatId::from(CHAT_ID);
+        service.set_language(chat_id.clone(), Some(Language::Italian));
+
+        service.update_chat_settings(chat_id.clone(), |chat_settings| {
+            chat_settings.sort_order = Some(SortOrder::Newest)
+        });
+
+        let chat_settings = service.get_chat_settings(&chat_id);
+        assert_eq!(chat_settings.sort_order(), SortOrder::Newest);
+        assert_eq!(chat_settings.language, Some(Language::Italian));
+    }
+
+    #[rstest]
+    fn delete_chat_settings() {
+        let service = ChatSettingsServiceImpl::default();
+        let chat_id = ChatId::from(CHAT_ID);
+        service.set_language(chat_id.clone(), Some(Language::Italian));
+
+        service.delete_chat_settings(&chat_id);
+
+        assert_eq!(service.get_chat_settings(&chat_id), ChatSettings::default());
+    }
+}
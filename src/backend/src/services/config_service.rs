@@ -0,0 +1,64 @@
+use crate::repositories::{Config, ConfigRepository, ConfigRepositoryImpl};
+
+pub trait ConfigService {
+    fn get_config(&self) -> Config;
+
+    fn set_config(&self, config: Config) -> Result<(), String>;
+}
+
+pub struct ConfigServiceImpl<T: ConfigRepository> {
+    config_repository: T,
+}
+
+impl Default for ConfigServiceImpl<ConfigRepositoryImpl> {
+    fn default() -> Self {
+        Self::new(ConfigRepositoryImpl::default())
+    }
+}
+
+impl<T: ConfigRepository> ConfigService for ConfigServiceImpl<T> {
+    fn get_config(&self) -> Config {
+        self.config_repository.get_config()
+    }
+
+    fn set_config(&self, config: Config) -> Result<(), String> {
+        config.validate()?;
+
+        self.config_repository.set_config(config);
+        Ok(())
+    }
+}
+
+impl<T: ConfigRepository> ConfigServiceImpl<T> {
+    fn new(config_repository: T) -> Self {
+        Self { config_repository }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    #[rstest]
+    fn set_config() {
+        let service = ConfigServiceImpl::default();
+        assert_eq!(service.get_config(), Config::default());
+
+        let config = Config {
+            bot_username: Some("infinitecloud_bot".to_string()),
+            default_directories: vec!["Inbox".to_string()],
+            ..Config::default()
+        };
+        service.set_config(config.clone()).unwrap();
+        assert_eq!(service.get_config(), config);
+
+        let mut invalid_config = config.clone();
+        invalid_config.limits.max_processed_updates = 0;
+        assert_eq!(
+            service.set_config(invalid_config),
+            Err("The maximum number of processed updates must be positive".to_string())
+        );
+        assert_eq!(service.get_config(), config);
+    }
+}
@@ -0,0 +1,192 @@
+use crate::{
+    custom_print,
+    repositories::{
+        ChatId, ConfigRepositoryImpl, Drive, DriveId, DriveRepository, DriveRepositoryImpl,
+        FilesystemRepositoryImpl, MAIN_DRIVE_NAME,
+    },
+    utils::{get_current_time, MAX_DRIVES_PER_CHAT, MAX_DRIVE_NAME_LENGTH},
+};
+
+use super::{FilesystemService, FilesystemServiceImpl};
+
+pub trait DriveService {
+    /// Returns the drives of the chat, without its main drive.
+    fn get_drives(&self, chat_id: &ChatId) -> Vec<(DriveId, Drive)>;
+
+    fn get_drive(&self, chat_id: &ChatId, drive_id: DriveId) -> Option<Drive>;
+
+    /// The name of the drive, or of the main drive when `drive_id` is `None`.
+    fn get_drive_name(&self, chat_id: &ChatId, drive_id: Option<DriveId>) -> String;
+
+    fn create_drive(&self, chat_id: ChatId, name: String) -> Result<DriveId, String>;
+
+    /// Removes the drive and its filesystem. Returns the removed drive.
+    fn delete_drive(&self, chat_id: &ChatId, drive_id: DriveId) -> Result<Drive, String>;
+
+    /// Removes the drives of the chat. Their filesystems are removed with the chat's filesystem.
+    fn delete_chat_drives(&self, chat_id: &ChatId);
+}
+
+pub struct DriveServiceImpl<T: DriveRepository, F: FilesystemService> {
+    drive_repository: T,
+    filesystem_service: F,
+}
+
+impl Default
+    for DriveServiceImpl<
+        DriveRepositoryImpl,
+        FilesystemServiceImpl<FilesystemRepositoryImpl, ConfigRepositoryImpl>,
+    >
+{
+    fn default() -> Self {
+        Self::new(
+            DriveRepositoryImpl::default(),
+            FilesystemServiceImpl::default(),
+        )
+    }
+}
+
+impl<T: DriveRepository, F: FilesystemService> DriveService for DriveServiceImpl<T, F> {
+    fn get_drives(&self, chat_id: &ChatId) -> Vec<(DriveId, Drive)> {
+        self.drive_repository.get_drives_by_chat_id(chat_id)
+    }
+
+    fn get_drive(&self, chat_id: &ChatId, drive_id: DriveId) -> Option<Drive> {
+        self.drive_repository.get_drive(chat_id, drive_id)
+    }
+
+    fn get_drive_name(&self, chat_id: &ChatId, drive_id: Option<DriveId>) -> String {
+        drive_id
+            .and_then(|drive_id| self.get_drive(chat_id, drive_id))
+            .map_or_else(|| MAIN_DRIVE_NAME.to_string(), |drive| drive.name)
+    }
+
+    fn create_drive(&self, chat_id: ChatId, name: String) -> Result<DriveId, String> {
+        let name = name.trim().to_string();
+        if name.is_empty() {
+            return Err("The name can't be empty".to_string());
+        }
+        if name.chars().count() > MAX_DRIVE_NAME_LENGTH {
+            return Err(format!(
+                "The name can't be longer than {MAX_DRIVE_NAME_LENGTH} characters"
+            ));
+        }
+
+        let drives = self.get_drives(&chat_id);
+        if drives.len() >= MAX_DRIVES_PER_CHAT {
+            return Err(format!(
+                "A chat can't have more than {MAX_DRIVES_PER_CHAT} drives"
+            ));
+        }
+        if name.eq_ignore_ascii_case(MAIN_DRIVE_NAME)
+            || drives
+                .iter()
+                .any(|(_, drive)| drive.name.eq_ignore_ascii_case(&name))
+        {
+            return Err(format!("A drive named {name} already exists"));
+        }
+
+        Ok(self.drive_repository.create_drive(
+            chat_id,
+            Drive {
+                name,
+                created_at: get_current_time(),
+            },
+        ))
+    }
+
+    fn delete_drive(&self, chat_id: &ChatId, drive_id: DriveId) -> Result<Drive, String> {
+        let drive = self
+            .get_drive(chat_id, drive_id)
+            .ok_or_else(|| "Drive not found".to_string())?;
+        self.filesystem_service
+            .delete_drive_filesystem(chat_id, drive_id);
+        self.drive_repository.remove_drive(chat_id, drive_id);
+        custom_print!("Deleted drive {drive_id} of chat {chat_id}");
+        Ok(drive)
+    }
+
+    fn delete_chat_drives(&self, chat_id: &ChatId) {
+        self.drive_repository.remove_drives_by_chat_id(chat_id);
+    }
+}
+
+impl<T: DriveRepository, F: FilesystemService> DriveServiceImpl<T, F> {
+    fn new(drive_repository: T, filesystem_service: F) -> Self {
+        Self {
+            drive_repository,
+            filesystem_service,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::FilesystemRepository;
+    use rstest::*;
+    use std::path::PathBuf;
+
+    const CHAT_ID: u64 = 123;
+
+    #[rstest]
+    fn drive_lifecycle() {
+        let service = DriveServiceImpl::default();
+        let chat_id = ChatId(CHAT_ID);
+
+        assert_eq!(
+            service.create_drive(chat_id.clone(), " ".to_string()),
+            Err("The name can't be empty".to_string())
+        );
+        assert_eq!(
+            service.create_drive(chat_id.clone(), "main".to_string()),
+            Err("A drive named main already exists".to_string())
+        );
+        let drive_id = service
+            .create_drive(chat_id.clone(), " Work ".to_string())
+            .unwrap();
+        assert_eq!(
+            service.create_drive(chat_id.clone(), "work".to_string()),
+            Err("A drive named work already exists".to_string())
+        );
+        assert_eq!(service.get_drive_name(&chat_id, Some(drive_id)), "Work");
+        assert_eq!(service.get_drive_name(&chat_id, None), MAIN_DRIVE_NAME);
+
+        // the drives have their own filesystems
+        let mut filesystem = service
+            .filesystem_service
+            .get_or_create_filesystem(&chat_id, Some(drive_id));
+        filesystem.mkdir(&PathBuf::from("/Projects")).unwrap();
+        service
+            .filesystem_service
+            .update_filesystem(&chat_id, Some(drive_id), filesystem);
+        assert!(service
+            .filesystem_service
+            .get_or_create_filesystem(&chat_id, None)
+            .get_node(&PathBuf::from("/Projects"))
+            .is_err());
+
+        let drive = service.delete_drive(&chat_id, drive_id).unwrap();
+        assert_eq!(drive.name, "Work");
+        assert!(service.get_drives(&chat_id).is_empty());
+        assert!(FilesystemRepositoryImpl::default()
+            .get_drive_filesystem(&chat_id, drive_id)
+            .is_none());
+        assert_eq!(
+            service.delete_drive(&chat_id, drive_id),
+            Err("Drive not found".to_string())
+        );
+        // the id of the deleted drive is not reused
+        let new_drive_id = service
+            .create_drive(chat_id.clone(), "Work".to_string())
+            .unwrap();
+        assert!(new_drive_id > drive_id);
+
+        // the ids start again once the chat's data is deleted
+        service.delete_chat_drives(&chat_id);
+        assert_eq!(
+            service.create_drive(chat_id.clone(), "Work".to_string()),
+            Ok(drive_id)
+        );
+    }
+}
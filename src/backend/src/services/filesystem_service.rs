@@ -1,46 +1,376 @@
-use crate::repositories::{ChatId, FileSystem, FilesystemRepository, FilesystemRepositoryImpl};
+use crate::{
+    custom_print,
+    repositories::{
+        ChatId, ConfigRepository, ConfigRepositoryImpl, DriveId, FileSystem, FilesystemRepository,
+        FilesystemRepositoryImpl, LegacyFileSystem, TextFileIndexProgress, VersionedValue,
+    },
+};
 
 pub trait FilesystemService {
-    fn get_or_create_filesystem(&self, chat_id: &ChatId) -> FileSystem;
-    fn update_filesystem(&self, chat_id: &ChatId, filesystem: FileSystem);
+    /// Returns the filesystem of the chat's drive, or of its main drive when `drive_id` is `None`.
+    fn get_or_create_filesystem(&self, chat_id: &ChatId, drive_id: Option<DriveId>) -> FileSystem;
+    fn update_filesystem(
+        &self,
+        chat_id: &ChatId,
+        drive_id: Option<DriveId>,
+        filesystem: FileSystem,
+    );
+
+    /// Moves up to `limit` filesystems stored with the legacy whole-tree layout
+    /// to the per-node layout, quarantining the ones that can't be decoded.
+    /// Returns the number of migrated filesystems.
+    fn migrate_legacy_filesystems(&self, limit: usize) -> u64;
+
+    fn has_legacy_filesystems(&self) -> bool;
+
+    /// Indexes the text files of up to `limit` chats that were stored before the
+    /// index was kept, so that they are found when their message is edited.
+    /// Returns the number of indexed chats.
+    fn index_text_files(&self, limit: usize) -> u64;
+
+    fn has_unindexed_text_files(&self) -> bool;
+
+    /// Moves the chat's filesystem to the quarantine region, so that a new one
+    /// is created on the next update. Returns the number of quarantined nodes.
+    fn quarantine_filesystem(&self, chat_id: &ChatId) -> u64;
+
+    /// Removes the chat's filesystem, including its quarantined nodes and its drives.
+    fn delete_filesystem(&self, chat_id: &ChatId);
+
+    fn delete_drive_filesystem(&self, chat_id: &ChatId, drive_id: DriveId);
 }
 
-pub struct FilesystemServiceImpl<T: FilesystemRepository> {
+pub struct FilesystemServiceImpl<T: FilesystemRepository, C: ConfigRepository> {
     filesystem_repository: T,
+    config_repository: C,
 }
 
-impl Default for FilesystemServiceImpl<FilesystemRepositoryImpl> {
+impl Default for FilesystemServiceImpl<FilesystemRepositoryImpl, ConfigRepositoryImpl> {
     fn default() -> Self {
-        Self::new(FilesystemRepositoryImpl::default())
+        Self::new(
+            FilesystemRepositoryImpl::default(),
+            ConfigRepositoryImpl::default(),
+        )
     }
 }
 
-impl<T: FilesystemRepository> FilesystemService for FilesystemServiceImpl<T> {
-    fn get_or_create_filesystem(&self, chat_id: &ChatId) -> FileSystem {
-        match self
-            .filesystem_repository
-            .get_filesystem_by_chat_id(chat_id)
-        {
-            Some(filesystem) => filesystem,
-            None => {
-                let fs = FileSystem::default();
-                self.filesystem_repository
-                    .set_filesystem_by_chat_id(chat_id.clone(), fs.clone());
-                fs
+impl<T: FilesystemRepository, C: ConfigRepository> FilesystemService
+    for FilesystemServiceImpl<T, C>
+{
+    fn get_or_create_filesystem(&self, chat_id: &ChatId, drive_id: Option<DriveId>) -> FileSystem {
+        // the legacy filesystems are migrated in batches, the chat's one may still be waiting
+        if drive_id.is_none() {
+            if let Some(legacy_filesystem) =
+                self.filesystem_repository.remove_legacy_filesystem(chat_id)
+            {
+                self.migrate_legacy_filesystem(chat_id.clone(), legacy_filesystem);
             }
         }
+
+        // a new filesystem is persisted when it's updated for the first time
+        match drive_id {
+            Some(drive_id) => self
+                .filesystem_repository
+                .get_drive_filesystem(chat_id, drive_id),
+            None => self
+                .filesystem_repository
+                .get_filesystem_by_chat_id(chat_id),
+        }
+        .unwrap_or_else(|| self.new_filesystem())
+    }
+
+    fn update_filesystem(
+        &self,
+        chat_id: &ChatId,
+        drive_id: Option<DriveId>,
+        filesystem: FileSystem,
+    ) {
+        match drive_id {
+            Some(drive_id) => self.filesystem_repository.set_drive_filesystem(
+                chat_id.clone(),
+                drive_id,
+                filesystem,
+            ),
+            None => self
+                .filesystem_repository
+                .set_filesystem_by_chat_id(chat_id.clone(), filesystem),
+        }
+    }
+
+    fn migrate_legacy_filesystems(&self, limit: usize) -> u64 {
+        let mut count = 0;
+        while count < limit {
+            let Some((chat_id, legacy_filesystem)) =
+                self.filesystem_repository.pop_legacy_filesystem()
+            else {
+                break;
+            };
+            self.migrate_legacy_filesystem(chat_id, legacy_filesystem);
+            count += 1;
+        }
+        count as u64
     }
 
-    fn update_filesystem(&self, chat_id: &ChatId, filesystem: FileSystem) {
+    fn has_legacy_filesystems(&self) -> bool {
+        self.filesystem_repository.has_legacy_filesystems()
+    }
+
+    fn index_text_files(&self, limit: usize) -> u64 {
+        let start_after = match self.filesystem_repository.get_text_file_index_progress() {
+            TextFileIndexProgress::NotStarted => None,
+            TextFileIndexProgress::IndexedUpTo(chat_id) => Some(chat_id),
+            TextFileIndexProgress::Done => return 0,
+        };
+        let chat_ids = self
+            .filesystem_repository
+            .index_stored_text_files(start_after.as_ref(), limit);
+        let count = chat_ids.len();
+        let progress = match chat_ids.into_iter().next_back() {
+            Some(chat_id) if count == limit => TextFileIndexProgress::IndexedUpTo(chat_id),
+            _ => TextFileIndexProgress::Done,
+        };
+        self.filesystem_repository
+            .set_text_file_index_progress(progress);
+        count as u64
+    }
+
+    fn has_unindexed_text_files(&self) -> bool {
+        self.filesystem_repository.get_text_file_index_progress() != TextFileIndexProgress::Done
+    }
+
+    fn quarantine_filesystem(&self, chat_id: &ChatId) -> u64 {
         self.filesystem_repository
-            .set_filesystem_by_chat_id(chat_id.clone(), filesystem);
+            .quarantine_filesystem_by_chat_id(chat_id)
+    }
+
+    fn delete_filesystem(&self, chat_id: &ChatId) {
+        self.filesystem_repository
+            .remove_filesystem_by_chat_id(chat_id);
+    }
+
+    fn delete_drive_filesystem(&self, chat_id: &ChatId, drive_id: DriveId) {
+        self.filesystem_repository
+            .remove_drive_filesystem(chat_id, drive_id);
     }
 }
 
-impl<T: FilesystemRepository> FilesystemServiceImpl<T> {
-    fn new(filesystem_repository: T) -> Self {
+impl<T: FilesystemRepository, C: ConfigRepository> FilesystemServiceImpl<T, C> {
+    fn new(filesystem_repository: T, config_repository: C) -> Self {
         Self {
             filesystem_repository,
+            config_repository,
         }
     }
+
+    fn migrate_legacy_filesystem(
+        &self,
+        chat_id: ChatId,
+        legacy_filesystem: VersionedValue<LegacyFileSystem>,
+    ) {
+        match legacy_filesystem.decode() {
+            Ok(legacy_filesystem) => self
+                .filesystem_repository
+                .set_filesystem_by_chat_id(chat_id, FileSystem::from(legacy_filesystem)),
+            Err(err) => {
+                custom_print!(
+                    "Quarantining the undecodable legacy filesystem of chat {chat_id}: {err}"
+                );
+                self.filesystem_repository
+                    .quarantine_legacy_filesystem(chat_id, legacy_filesystem);
+            }
+        }
+    }
+
+    fn new_filesystem(&self) -> FileSystem {
+        let default_directories = self.config_repository.get_config().default_directories;
+        FileSystem::new_with_directories(&default_directories)
+            .inspect_err(|err| {
+                custom_print!("Creating a filesystem without the configured directories: {err}");
+            })
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        repositories::{FileSystemNode, MessageId, StoredFileSystemNode},
+        utils::TG_FILE_MIME_TYPE_PREFIX,
+    };
+    use ic_stable_structures::Storable;
+    use rstest::*;
+    use std::{borrow::Cow, path::PathBuf};
+
+    fn legacy_filesystem(dir_name: &str) -> VersionedValue<LegacyFileSystem> {
+        let mut filesystem = FileSystem::new_with_directories(&[dir_name]).unwrap();
+        filesystem
+            .create_file_from_node(
+                &PathBuf::from(format!("/{dir_name}/report.pdf")),
+                FileSystemNode::new_file(1, 0, None),
+            )
+            .unwrap();
+        VersionedValue::new(&LegacyFileSystem::new(filesystem.export().unwrap()))
+    }
+
+    fn save_text_file(chat_id: &ChatId, drive_id: Option<DriveId>, message_id: MessageId) {
+        let service = FilesystemServiceImpl::default();
+        let mut filesystem = service.get_or_create_filesystem(chat_id, drive_id);
+        filesystem
+            .create_file_from_node(
+                &PathBuf::from(format!("/file-{message_id}.tg+text")),
+                FileSystemNode::new_file(
+                    message_id,
+                    4,
+                    Some(format!("{TG_FILE_MIME_TYPE_PREFIX}text")),
+                ),
+            )
+            .unwrap();
+        service.update_filesystem(chat_id, drive_id, filesystem);
+    }
+
+    #[rstest]
+    fn migrate_legacy_filesystems_in_batches() {
+        let repository = FilesystemRepositoryImpl::default();
+        let service = FilesystemServiceImpl::default();
+        repository.insert_legacy_filesystem(ChatId(1), legacy_filesystem("First"));
+        repository.insert_legacy_filesystem(
+            ChatId(2),
+            VersionedValue::from_bytes(Cow::Owned(b"DIDL-corrupted".to_vec())),
+        );
+        repository.insert_legacy_filesystem(ChatId(3), legacy_filesystem("Third"));
+
+        assert_eq!(service.migrate_legacy_filesystems(2), 2);
+        assert!(service.has_legacy_filesystems());
+        assert!(service
+            .get_or_create_filesystem(&ChatId(1), None)
+            .get_node(&PathBuf::from("/First/report.pdf"))
+            .is_ok());
+        // the undecodable filesystem is kept for inspection, and the chat starts over
+        let quarantined_nodes = repository.get_quarantined_nodes_by_chat_id(&ChatId(2));
+        assert_eq!(quarantined_nodes.len(), 1);
+        assert_eq!(quarantined_nodes[0].bytes, b"DIDL-corrupted".to_vec());
+        assert!(repository.get_filesystem_by_chat_id(&ChatId(2)).is_none());
+
+        // a chat is migrated when it's used, before its batch
+        assert!(service
+            .get_or_create_filesystem(&ChatId(3), None)
+            .get_node(&PathBuf::from("/Third/report.pdf"))
+            .is_ok());
+        assert!(!service.has_legacy_filesystems());
+        assert_eq!(service.migrate_legacy_filesystems(2), 0);
+    }
+
+    #[rstest]
+    fn removed_node_ids_are_not_reused() {
+        let service = FilesystemServiceImpl::default();
+        let chat_id = ChatId(1);
+        let path = PathBuf::from("/Projects");
+
+        for drive_id in [None, Some(1)] {
+            let mut filesystem = service.get_or_create_filesystem(&chat_id, drive_id);
+            filesystem.mkdir(&path).unwrap();
+            service.update_filesystem(&chat_id, drive_id, filesystem);
+            let next_node_id = service
+                .get_or_create_filesystem(&chat_id, drive_id)
+                .next_node_id();
+
+            // the removed directory had the highest id
+            let mut filesystem = service.get_or_create_filesystem(&chat_id, drive_id);
+            filesystem.rm(&path).unwrap();
+            service.update_filesystem(&chat_id, drive_id, filesystem);
+
+            assert_eq!(
+                service
+                    .get_or_create_filesystem(&chat_id, drive_id)
+                    .next_node_id(),
+                next_node_id
+            );
+        }
+    }
+
+    #[rstest]
+    fn text_files_are_indexed() {
+        let repository = FilesystemRepositoryImpl::default();
+        let service = FilesystemServiceImpl::default();
+        let chat_id = ChatId(1);
+        let path = PathBuf::from("/file-10.tg+text");
+
+        for drive_id in [None, Some(1)] {
+            save_text_file(&chat_id, drive_id, 10);
+            assert_eq!(
+                repository
+                    .get_text_file_node_ids(&chat_id, drive_id, 10)
+                    .len(),
+                1
+            );
+
+            let mut filesystem = service.get_or_create_filesystem(&chat_id, drive_id);
+            assert_eq!(filesystem.update_text_files(10, 20), Ok(1));
+            assert_eq!(filesystem.update_text_files(11, 20), Ok(0));
+            service.update_filesystem(&chat_id, drive_id, filesystem);
+            assert!(matches!(
+                service
+                    .get_or_create_filesystem(&chat_id, drive_id)
+                    .get_node(&path),
+                Ok(StoredFileSystemNode::File { size: 20, .. })
+            ));
+
+            let mut filesystem = service.get_or_create_filesystem(&chat_id, drive_id);
+            filesystem.rm(&path).unwrap();
+            service.update_filesystem(&chat_id, drive_id, filesystem);
+            assert!(repository
+                .get_text_file_node_ids(&chat_id, drive_id, 10)
+                .is_empty());
+        }
+
+        // the index entries are removed along with the filesystems
+        save_text_file(&chat_id, None, 10);
+        save_text_file(&chat_id, Some(1), 10);
+        service.delete_drive_filesystem(&chat_id, 1);
+        assert!(repository
+            .get_text_file_node_ids(&chat_id, Some(1), 10)
+            .is_empty());
+        assert_eq!(
+            repository.get_text_file_node_ids(&chat_id, None, 10).len(),
+            1
+        );
+        service.delete_filesystem(&chat_id);
+        assert!(repository
+            .get_text_file_node_ids(&chat_id, None, 10)
+            .is_empty());
+    }
+
+    #[rstest]
+    fn index_text_files_in_batches() {
+        let repository = FilesystemRepositoryImpl::default();
+        let service = FilesystemServiceImpl::default();
+        save_text_file(&ChatId(1), None, 10);
+        save_text_file(&ChatId(2), Some(1), 20);
+        save_text_file(&ChatId(3), None, 30);
+        repository.clear_text_file_index();
+
+        assert!(service.has_unindexed_text_files());
+        assert_eq!(service.index_text_files(2), 2);
+        assert!(service.has_unindexed_text_files());
+        assert_eq!(
+            repository
+                .get_text_file_node_ids(&ChatId(2), Some(1), 20)
+                .len(),
+            1
+        );
+        assert!(repository
+            .get_text_file_node_ids(&ChatId(3), None, 30)
+            .is_empty());
+
+        assert_eq!(service.index_text_files(2), 1);
+        assert!(!service.has_unindexed_text_files());
+        assert_eq!(
+            repository
+                .get_text_file_node_ids(&ChatId(3), None, 30)
+                .len(),
+            1
+        );
+        assert_eq!(service.index_text_files(2), 0);
+    }
 }
@@ -0,0 +1,128 @@
+use crate::repositories::{
+    ChatId, ConfigRepository, ConfigRepositoryImpl, DriveId, JournalEntry, JournalOperation,
+    JournalRepository, JournalRepositoryImpl,
+};
+
+pub trait JournalService {
+    /// Whether the operations are recorded and can be undone.
+    fn is_enabled(&self) -> bool;
+
+    /// Does nothing if the journal is disabled in the config.
+    fn record_operation(
+        &self,
+        chat_id: &ChatId,
+        drive_id: Option<DriveId>,
+        operation: JournalOperation,
+    );
+
+    /// Returns the latest entry of the drive, the main drive when `None`.
+    fn get_latest_entry(&self, chat_id: &ChatId, drive_id: Option<DriveId>)
+        -> Option<JournalEntry>;
+
+    /// Removes the latest entry of the drive, e.g. once it has been undone.
+    fn pop_latest_entry(&self, chat_id: &ChatId, drive_id: Option<DriveId>)
+        -> Option<JournalEntry>;
+
+    fn get_recent_entries(&self, chat_id: &ChatId) -> Vec<JournalEntry>;
+
+    fn delete_journal(&self, chat_id: &ChatId);
+
+    /// Removes the entries of the drive, e.g. when its filesystem is replaced,
+    /// so that they can't be undone on a filesystem they don't belong to.
+    fn delete_drive_entries(&self, chat_id: &ChatId, drive_id: Option<DriveId>);
+}
+
+pub struct JournalServiceImpl<T: JournalRepository, C: ConfigRepository> {
+    journal_repository: T,
+    config_repository: C,
+}
+
+impl Default for JournalServiceImpl<JournalRepositoryImpl, ConfigRepositoryImpl> {
+    fn default() -> Self {
+        Self::new(
+            JournalRepositoryImpl::default(),
+            ConfigRepositoryImpl::default(),
+        )
+    }
+}
+
+impl<T: JournalRepository, C: ConfigRepository> JournalService for JournalServiceImpl<T, C> {
+    fn is_enabled(&self) -> bool {
+        self.config_repository.get_config().features.undo
+    }
+
+    fn record_operation(
+        &self,
+        chat_id: &ChatId,
+        drive_id: Option<DriveId>,
+        operation: JournalOperation,
+    ) {
+        let config = self.config_repository.get_config();
+        if !config.features.undo {
+            return;
+        }
+
+        let mut journal = self
+            .journal_repository
+            .get_journal_by_chat_id(chat_id)
+            .unwrap_or_default();
+        journal.push(
+            JournalEntry::new(operation).with_drive_id(drive_id),
+            config.limits.max_journal_entries as usize,
+        );
+        self.journal_repository
+            .set_journal_by_chat_id(chat_id.clone(), journal);
+    }
+
+    fn get_latest_entry(
+        &self,
+        chat_id: &ChatId,
+        drive_id: Option<DriveId>,
+    ) -> Option<JournalEntry> {
+        self.journal_repository
+            .get_journal_by_chat_id(chat_id)?
+            .latest_drive_entry(drive_id)
+            .cloned()
+    }
+
+    fn pop_latest_entry(
+        &self,
+        chat_id: &ChatId,
+        drive_id: Option<DriveId>,
+    ) -> Option<JournalEntry> {
+        let mut journal = self.journal_repository.get_journal_by_chat_id(chat_id)?;
+        let entry = journal.pop_drive_entry(drive_id);
+        self.journal_repository
+            .set_journal_by_chat_id(chat_id.clone(), journal);
+        entry
+    }
+
+    fn get_recent_entries(&self, chat_id: &ChatId) -> Vec<JournalEntry> {
+        self.journal_repository
+            .get_journal_by_chat_id(chat_id)
+            .map(|journal| journal.entries().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    fn delete_journal(&self, chat_id: &ChatId) {
+        self.journal_repository.remove_journal_by_chat_id(chat_id);
+    }
+
+    fn delete_drive_entries(&self, chat_id: &ChatId, drive_id: Option<DriveId>) {
+        let Some(mut journal) = self.journal_repository.get_journal_by_chat_id(chat_id) else {
+            return;
+        };
+        journal.remove_drive_entries(drive_id);
+        self.journal_repository
+            .set_journal_by_chat_id(chat_id.clone(), journal);
+    }
+}
+
+impl<T: JournalRepository, C: ConfigRepository> JournalServiceImpl<T, C> {
+    fn new(journal_repository: T, config_repository: C) -> Self {
+        Self {
+            journal_repository,
+            config_repository,
+        }
+    }
+}
@@ -1,7 +1,29 @@
 mod access_control_service;
+mod access_policy_service;
+mod admin_service;
+mod backup_service;
+mod chat_role_service;
 mod chat_session_service;
+mod chat_settings_service;
+mod config_service;
+mod drive_service;
 mod filesystem_service;
+mod journal_service;
+mod processed_update_service;
+mod rate_limit_service;
+mod share_service;
 
 pub use access_control_service::*;
+pub use access_policy_service::*;
+pub use admin_service::*;
+pub use backup_service::*;
+pub use chat_role_service::*;
 pub use chat_session_service::*;
+pub use chat_settings_service::*;
+pub use config_service::*;
+pub use drive_service::*;
 pub use filesystem_service::*;
+pub use journal_service::*;
+pub use processed_update_service::*;
+pub use rate_limit_service::*;
+pub use share_service::*;
@@ -0,0 +1,196 @@
+use crate::{
+    repositories::{
+        ChatId, ConfigRepository, ConfigRepositoryImpl, ProcessedUpdate, ProcessedUpdateRepository,
+        ProcessedUpdateRepositoryImpl, UpdateId,
+    },
+    utils::{get_current_time, UPDATE_ID_SEQUENCE_RESET_INTERVAL_NANOS},
+};
+
+pub trait ProcessedUpdateService {
+    fn get_processed_update(&self, update_id: UpdateId) -> Option<ProcessedUpdate>;
+
+    /// Records the update before it's processed, forgetting the oldest updates over the limit.
+    fn record_update(&self, update_id: UpdateId, chat_id: Option<ChatId>);
+
+    /// Caches the response of a recorded update. Does nothing if the record
+    /// has been removed while processing the update, e.g. by `/forget_me`.
+    fn set_update_response(&self, update_id: UpdateId, response_body: Vec<u8>);
+
+    fn delete_processed_updates(&self, chat_id: &ChatId);
+}
+
+pub struct ProcessedUpdateServiceImpl<T: ProcessedUpdateRepository, C: ConfigRepository> {
+    processed_update_repository: T,
+    config_repository: C,
+}
+
+impl Default for ProcessedUpdateServiceImpl<ProcessedUpdateRepositoryImpl, ConfigRepositoryImpl> {
+    fn default() -> Self {
+        Self::new(
+            ProcessedUpdateRepositoryImpl::default(),
+            ConfigRepositoryImpl::default(),
+        )
+    }
+}
+
+impl<T: ProcessedUpdateRepository, C: ConfigRepository> ProcessedUpdateService
+    for ProcessedUpdateServiceImpl<T, C>
+{
+    fn get_processed_update(&self, update_id: UpdateId) -> Option<ProcessedUpdate> {
+        self.processed_update_repository
+            .get_processed_update(update_id)
+    }
+
+    fn record_update(&self, update_id: UpdateId, chat_id: Option<ChatId>) {
+        let now = get_current_time();
+
+        // a lower id after a long time without updates means that Telegram restarted the sequence,
+        // so the records of the previous sequence would be evicted only after the new ones
+        if let Some((latest_update_id, latest_processed_update)) = self
+            .processed_update_repository
+            .get_latest_processed_update()
+        {
+            if update_id < latest_update_id
+                && now.saturating_sub(latest_processed_update.processed_at)
+                    >= UPDATE_ID_SEQUENCE_RESET_INTERVAL_NANOS
+            {
+                while self
+                    .processed_update_repository
+                    .get_processed_updates_count()
+                    > 0
+                {
+                    self.processed_update_repository
+                        .remove_oldest_processed_update();
+                }
+            }
+        }
+
+        self.processed_update_repository.set_processed_update(
+            update_id,
+            ProcessedUpdate {
+                chat_id,
+                processed_at: now,
+                response_body: None,
+            },
+        );
+        let max_processed_updates = self
+            .config_repository
+            .get_config()
+            .limits
+            .max_processed_updates;
+        while self
+            .processed_update_repository
+            .get_processed_updates_count()
+            > max_processed_updates
+        {
+            self.processed_update_repository
+                .remove_oldest_processed_update();
+        }
+    }
+
+    fn set_update_response(&self, update_id: UpdateId, response_body: Vec<u8>) {
+        if let Some(mut processed_update) = self
+            .processed_update_repository
+            .get_processed_update(update_id)
+        {
+            processed_update.response_body = Some(response_body);
+            self.processed_update_repository
+                .set_processed_update(update_id, processed_update);
+        }
+    }
+
+    fn delete_processed_updates(&self, chat_id: &ChatId) {
+        self.processed_update_repository
+            .remove_processed_updates_by_chat_id(chat_id);
+    }
+}
+
+impl<T: ProcessedUpdateRepository, C: ConfigRepository> ProcessedUpdateServiceImpl<T, C> {
+    fn new(processed_update_repository: T, config_repository: C) -> Self {
+        Self {
+            processed_update_repository,
+            config_repository,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::Config;
+    use rstest::*;
+
+    #[rstest]
+    fn record_update() {
+        let service = ProcessedUpdateServiceImpl::default();
+        assert_eq!(service.get_processed_update(1), None);
+
+        service.record_update(1, Some(ChatId(123)));
+        let processed_update = service.get_processed_update(1).unwrap();
+        assert_eq!(processed_update.chat_id, Some(ChatId(123)));
+        assert_eq!(processed_update.response_body, None);
+
+        service.set_update_response(1, b"response".to_vec());
+        assert_eq!(
+            service.get_processed_update(1).unwrap().response_body,
+            Some(b"response".to_vec())
+        );
+    }
+
+    #[rstest]
+    fn record_update_evicts_oldest_updates() {
+        let max_processed_updates = 10;
+        let mut config = Config::default();
+        config.limits.max_processed_updates = u64::from(max_processed_updates);
+        ConfigRepositoryImpl::default().set_config(config);
+        let service = ProcessedUpdateServiceImpl::default();
+
+        for update_id in 1..=max_processed_updates + 2 {
+            service.record_update(update_id, None);
+        }
+
+        assert_eq!(service.get_processed_update(1), None);
+        assert_eq!(service.get_processed_update(2), None);
+        assert!(service.get_processed_update(3).is_some());
+        assert!(service
+            .get_processed_update(max_processed_updates + 2)
+            .is_some());
+    }
+
+    #[rstest]
+    fn record_update_after_sequence_reset() {
+        let repository = ProcessedUpdateRepositoryImpl::default();
+        repository.set_processed_update(
+            1_000,
+            ProcessedUpdate {
+                chat_id: None,
+                processed_at: get_current_time() - UPDATE_ID_SEQUENCE_RESET_INTERVAL_NANOS,
+                response_body: None,
+            },
+        );
+        let service = ProcessedUpdateServiceImpl::default();
+
+        service.record_update(1, None);
+        assert_eq!(service.get_processed_update(1_000), None);
+        assert!(service.get_processed_update(1).is_some());
+
+        // a lower id shortly after the latest update doesn't reset the records
+        service.record_update(3, None);
+        service.record_update(2, None);
+        assert!(service.get_processed_update(1).is_some());
+        assert!(service.get_processed_update(3).is_some());
+    }
+
+    #[rstest]
+    fn set_update_response_after_delete() {
+        let service = ProcessedUpdateServiceImpl::default();
+        service.record_update(1, Some(ChatId(123)));
+        service.record_update(2, Some(ChatId(456)));
+
+        service.delete_processed_updates(&ChatId(123));
+        service.set_update_response(1, b"response".to_vec());
+
+        assert_eq!(service.get_processed_update(1), None);
+        assert!(service.get_processed_update(2).is_some());
+    }
+}
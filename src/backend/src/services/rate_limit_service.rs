@@ -0,0 +1,218 @@
+use crate::{
+    repositories::{
+        ChatId, ConfigRepository, ConfigRepositoryImpl, RateLimitConfig, RateLimitCounters,
+        RateLimitDecision, RateLimitRepository, RateLimitRepositoryImpl, TokenBucket,
+    },
+    utils::{get_current_time, MAX_RATE_LIMITED_CHATS},
+};
+
+pub trait RateLimitService {
+    /// Takes a token from the chat's bucket and from the global one,
+    /// unless one of them is empty. Allows every update if rate limiting is disabled in the config.
+    fn check_update(&self, chat_id: Option<&ChatId>) -> RateLimitDecision;
+
+    fn get_counters(&self) -> RateLimitCounters;
+
+    fn delete_chat_bucket(&self, chat_id: &ChatId);
+}
+
+pub struct RateLimitServiceImpl<T: RateLimitRepository, C: ConfigRepository> {
+    rate_limit_repository: T,
+    config_repository: C,
+}
+
+impl Default for RateLimitServiceImpl<RateLimitRepositoryImpl, ConfigRepositoryImpl> {
+    fn default() -> Self {
+        Self::new(
+            RateLimitRepositoryImpl::default(),
+            ConfigRepositoryImpl::default(),
+        )
+    }
+}
+
+impl<T: RateLimitRepository, C: ConfigRepository> RateLimitService for RateLimitServiceImpl<T, C> {
+    fn check_update(&self, chat_id: Option<&ChatId>) -> RateLimitDecision {
+        let config = self.config_repository.get_config();
+        if !config.features.rate_limiting {
+            return RateLimitDecision::Allowed;
+        }
+
+        let now = get_current_time();
+        let config = config.limits.rate_limits;
+
+        let mut global_bucket = self
+            .rate_limit_repository
+            .get_global_bucket()
+            .unwrap_or_else(|| TokenBucket::new_full(&config.global, now));
+        global_bucket.refill(&config.global, now);
+
+        let mut chat_bucket = chat_id.map(|chat_id| {
+            let mut chat_bucket = self
+                .rate_limit_repository
+                .get_chat_bucket(chat_id)
+                .unwrap_or_else(|| TokenBucket::new_full(&config.per_chat, now));
+            chat_bucket.refill(&config.per_chat, now);
+            chat_bucket
+        });
+
+        let decision = if chat_bucket
+            .as_ref()
+            .is_some_and(|bucket| !bucket.has_token())
+        {
+            RateLimitDecision::ChatThrottled
+        } else if !global_bucket.has_token() {
+            RateLimitDecision::GlobalThrottled
+        } else {
+            global_bucket.take_token();
+            if let Some(chat_bucket) = chat_bucket.as_mut() {
+                chat_bucket.take_token();
+            }
+            RateLimitDecision::Allowed
+        };
+
+        self.rate_limit_repository.set_global_bucket(global_bucket);
+        if let (Some(chat_id), Some(chat_bucket)) = (chat_id, chat_bucket) {
+            self.rate_limit_repository
+                .set_chat_bucket(chat_id.clone(), chat_bucket);
+        }
+        self.prune_chat_buckets(&config, now);
+
+        let mut counters = self.rate_limit_repository.get_counters();
+        match decision {
+            RateLimitDecision::Allowed => counters.allowed_updates += 1,
+            RateLimitDecision::ChatThrottled => counters.throttled_chat_updates += 1,
+            RateLimitDecision::GlobalThrottled => counters.throttled_global_updates += 1,
+        }
+        self.rate_limit_repository.set_counters(counters);
+
+        decision
+    }
+
+    fn get_counters(&self) -> RateLimitCounters {
+        RateLimitCounters {
+            tracked_chats: self.rate_limit_repository.get_chat_buckets_count(),
+            ..self.rate_limit_repository.get_counters()
+        }
+    }
+
+    fn delete_chat_bucket(&self, chat_id: &ChatId) {
+        self.rate_limit_repository.remove_chat_bucket(chat_id);
+    }
+}
+
+impl<T: RateLimitRepository, C: ConfigRepository> RateLimitServiceImpl<T, C> {
+    fn new(rate_limit_repository: T, config_repository: C) -> Self {
+        Self {
+            rate_limit_repository,
+            config_repository,
+        }
+    }
+
+    /// Forgets the buckets that have been refilled, as a missing bucket is the same as a full one.
+    fn prune_chat_buckets(&self, config: &RateLimitConfig, now: u64) {
+        if self.rate_limit_repository.get_chat_buckets_count() <= MAX_RATE_LIMITED_CHATS {
+            return;
+        }
+
+        self.rate_limit_repository.retain_chat_buckets(|_, bucket| {
+            let mut bucket = bucket.clone();
+            bucket.refill(&config.per_chat, now);
+            !bucket.is_full(&config.per_chat)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::{Config, RateLimit};
+    use rstest::*;
+
+    fn set_config(per_chat_capacity: u32, global_capacity: u32) {
+        let mut config = Config::default();
+        // refills too slowly to affect the tests
+        config.limits.rate_limits = RateLimitConfig {
+            per_chat: RateLimit {
+                capacity: per_chat_capacity,
+                refill_per_second: 1,
+            },
+            global: RateLimit {
+                capacity: global_capacity,
+                refill_per_second: 1,
+            },
+        };
+        ConfigRepositoryImpl::default().set_config(config);
+    }
+
+    #[rstest]
+    fn check_update_chat_throttled() {
+        set_config(2, 100);
+        let service = RateLimitServiceImpl::default();
+        let chat_id = ChatId(123);
+
+        assert_eq!(
+            service.check_update(Some(&chat_id)),
+            RateLimitDecision::Allowed
+        );
+        assert_eq!(
+            service.check_update(Some(&chat_id)),
+            RateLimitDecision::Allowed
+        );
+        assert_eq!(
+            service.check_update(Some(&chat_id)),
+            RateLimitDecision::ChatThrottled
+        );
+        // other chats are not affected
+        assert_eq!(
+            service.check_update(Some(&ChatId(456))),
+            RateLimitDecision::Allowed
+        );
+
+        assert_eq!(
+            service.get_counters(),
+            RateLimitCounters {
+                allowed_updates: 3,
+                throttled_chat_updates: 1,
+                throttled_global_updates: 0,
+                tracked_chats: 2,
+            }
+        );
+    }
+
+    #[rstest]
+    fn check_update_global_throttled() {
+        set_config(100, 2);
+        let service = RateLimitServiceImpl::default();
+
+        assert_eq!(
+            service.check_update(Some(&ChatId(1))),
+            RateLimitDecision::Allowed
+        );
+        assert_eq!(service.check_update(None), RateLimitDecision::Allowed);
+        assert_eq!(
+            service.check_update(Some(&ChatId(2))),
+            RateLimitDecision::GlobalThrottled
+        );
+
+        let counters = service.get_counters();
+        assert_eq!(counters.allowed_updates, 2);
+        assert_eq!(counters.throttled_global_updates, 1);
+    }
+
+    #[rstest]
+    fn check_update_disabled() {
+        set_config(1, 1);
+        let mut config = ConfigRepositoryImpl::default().get_config();
+        config.features.rate_limiting = false;
+        ConfigRepositoryImpl::default().set_config(config);
+        let service = RateLimitServiceImpl::default();
+
+        for _ in 0..3 {
+            assert_eq!(
+                service.check_update(Some(&ChatId(123))),
+                RateLimitDecision::Allowed
+            );
+        }
+        assert_eq!(service.get_counters(), RateLimitCounters::default());
+    }
+}
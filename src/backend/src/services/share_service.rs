@@ -0,0 +1,428 @@
+use std::path::Path;
+
+use sha2::{Digest, Sha256};
+
+use crate::{
+    custom_print,
+    repositories::{
+        ChatId, ConfigRepositoryImpl, DriveId, FileSystem, FileSystemMount,
+        FilesystemRepositoryImpl, Share, ShareId, SharePermission, ShareRecipient, ShareRepository,
+        ShareRepositoryImpl,
+    },
+    utils::{filesystem::root_path, get_current_time},
+};
+
+use super::{constant_time_eq, FilesystemService, FilesystemServiceImpl};
+
+/// The number of hex characters of the digest kept in the tokens.
+const SHARE_TOKEN_DIGEST_LENGTH: usize = 24;
+
+pub trait ShareService {
+    /// Shares the directory at `path` of the filesystem of the owner's drive.
+    /// The returned share has the token of the deep link, to be sent to the recipient.
+    fn create_share(
+        &self,
+        owner_chat_id: ChatId,
+        drive_id: Option<DriveId>,
+        filesystem: &FileSystem,
+        path: &Path,
+        permission: SharePermission,
+    ) -> Result<Share, String>;
+
+    /// Mounts the share of the token in the chat's `/Shared` directory. Each token can only be used once.
+    /// Returns the accepted share.
+    fn accept_share(&self, chat_id: ChatId, token: &str) -> Result<Share, String>;
+
+    /// Mounts the folders shared with the chat in its filesystem.
+    fn mount_shares(&self, chat_id: &ChatId, filesystem: &mut FileSystem);
+
+    /// Returns the shares owned by the chat, including the ones that haven't been accepted yet.
+    fn get_owned_shares(&self, chat_id: &ChatId) -> Vec<(ShareId, Share)>;
+
+    /// Removes a share owned by the chat, unmounting it from the recipient's filesystem.
+    fn revoke_share(&self, chat_id: &ChatId, share_id: ShareId) -> Result<Share, String>;
+
+    /// Removes the shares of the directories of the chat's drive.
+    fn delete_drive_shares(&self, chat_id: &ChatId, drive_id: DriveId);
+
+    /// Removes the shares owned or accepted by the chat.
+    fn delete_chat_shares(&self, chat_id: &ChatId);
+
+    /// Whether the salt of the tokens has been generated, the shares can't be created before.
+    fn has_token_salt(&self) -> bool;
+
+    /// Sets the salt of the tokens, to be generated with the randomness of the management canister.
+    fn set_token_salt(&self, salt: Vec<u8>);
+}
+
+pub struct ShareServiceImpl<T: ShareRepository, F: FilesystemService> {
+    share_repository: T,
+    filesystem_service: F,
+}
+
+impl Default
+    for ShareServiceImpl<
+        ShareRepositoryImpl,
+        FilesystemServiceImpl<FilesystemRepositoryImpl, ConfigRepositoryImpl>,
+    >
+{
+    fn default() -> Self {
+        Self::new(
+            ShareRepositoryImpl::default(),
+            FilesystemServiceImpl::default(),
+        )
+    }
+}
+
+impl<T: ShareRepository, F: FilesystemService> ShareService for ShareServiceImpl<T, F> {
+    fn create_share(
+        &self,
+        owner_chat_id: ChatId,
+        drive_id: Option<DriveId>,
+        filesystem: &FileSystem,
+        path: &Path,
+        permission: SharePermission,
+    ) -> Result<Share, String> {
+        if path == root_path() {
+            return Err("The root directory can't be shared".to_string());
+        }
+        if filesystem.is_mounted(path) {
+            return Err("Shared folders can't be shared again".to_string());
+        }
+        if !filesystem.get_node(path)?.is_directory() {
+            return Err("Only directories can be shared".to_string());
+        }
+        let token_salt = self
+            .share_repository
+            .get_token_salt()
+            .ok_or_else(|| "Sharing is not available yet, try again in a minute".to_string())?;
+
+        let mut share = Share {
+            owner_chat_id,
+            drive_id,
+            path: path.to_path_buf(),
+            permission,
+            token: String::new(),
+            created_at: get_current_time(),
+            recipient: None,
+        };
+        let share_id = self.share_repository.create_share(share.clone());
+        share.token = generate_token(&token_salt, share_id, &share);
+        self.share_repository.update_share(share_id, share.clone());
+        Ok(share)
+    }
+
+    fn accept_share(&self, chat_id: ChatId, token: &str) -> Result<Share, String> {
+        let invalid_token_error = || "This link is not valid anymore".to_string();
+        let share_id = token
+            .split_once('_')
+            .and_then(|(share_id, _)| share_id.parse::<ShareId>().ok())
+            .ok_or_else(invalid_token_error)?;
+        let mut share = self
+            .share_repository
+            .get_share(share_id)
+            .filter(|share| constant_time_eq(token, &share.token) && !share.is_accepted())
+            .ok_or_else(invalid_token_error)?;
+        if share.owner_chat_id == chat_id {
+            return Err("You can't open the links of your own shares".to_string());
+        }
+
+        let mounted_names: Vec<String> = self
+            .get_accepted_shares(&chat_id)
+            .into_iter()
+            .filter_map(|(_, share)| share.recipient.map(|recipient| recipient.name))
+            .collect();
+        let dir_name = share
+            .path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        let name = (1..)
+            .map(|n| match n {
+                1 => dir_name.clone(),
+                n => format!("{dir_name} ({n})"),
+            })
+            .find(|name| !mounted_names.contains(name))
+            .unwrap();
+
+        share.recipient = Some(ShareRecipient { chat_id, name });
+        self.share_repository.update_share(share_id, share.clone());
+        Ok(share)
+    }
+
+    fn mount_shares(&self, chat_id: &ChatId, filesystem: &mut FileSystem) {
+        for (_, share) in self.get_accepted_shares(chat_id) {
+            let Some(recipient) = share.recipient else {
+                continue;
+            };
+            let (owner_chat_id, drive_id) = (share.owner_chat_id.clone(), share.drive_id);
+            filesystem.mount(
+                recipient.name,
+                FileSystemMount::new(
+                    share.owner_chat_id,
+                    share.drive_id,
+                    chat_id.clone(),
+                    share.path,
+                    share.permission.is_read_only(),
+                ),
+                || {
+                    self.filesystem_service
+                        .get_or_create_filesystem(&owner_chat_id, drive_id)
+                },
+            );
+        }
+    }
+
+    fn get_owned_shares(&self, chat_id: &ChatId) -> Vec<(ShareId, Share)> {
+        self.share_repository
+            .get_shares_by_chat_id(chat_id)
+            .into_iter()
+            .filter(|(_, share)| &share.owner_chat_id == chat_id)
+            .collect()
+    }
+
+    fn revoke_share(&self, chat_id: &ChatId, share_id: ShareId) -> Result<Share, String> {
+        let share = self
+            .share_repository
+            .get_share(share_id)
+            .filter(|share| &share.owner_chat_id == chat_id)
+            .ok_or_else(|| "Share not found".to_string())?;
+        self.share_repository.remove_share(share_id);
+        custom_print!("Revoked share {share_id} of chat {chat_id}");
+        Ok(share)
+    }
+
+    fn delete_drive_shares(&self, chat_id: &ChatId, drive_id: DriveId) {
+        for (share_id, _) in self
+            .get_owned_shares(chat_id)
+            .into_iter()
+            .filter(|(_, share)| share.drive_id == Some(drive_id))
+        {
+            self.share_repository.remove_share(share_id);
+        }
+    }
+
+    fn delete_chat_shares(&self, chat_id: &ChatId) {
+        for (share_id, _) in self.share_repository.get_shares_by_chat_id(chat_id) {
+            self.share_repository.remove_share(share_id);
+        }
+    }
+
+    fn has_token_salt(&self) -> bool {
+        self.share_repository.get_token_salt().is_some()
+    }
+
+    fn set_token_salt(&self, salt: Vec<u8>) {
+        self.share_repository.set_token_salt(salt);
+    }
+}
+
+impl<T: ShareRepository, F: FilesystemService> ShareServiceImpl<T, F> {
+    fn new(share_repository: T, filesystem_service: F) -> Self {
+        Self {
+            share_repository,
+            filesystem_service,
+        }
+    }
+
+    fn get_accepted_shares(&self, chat_id: &ChatId) -> Vec<(ShareId, Share)> {
+        self.share_repository
+            .get_shares_by_chat_id(chat_id)
+            .into_iter()
+            .filter(|(_, share)| {
+                share
+                    .recipient
+                    .as_ref()
+                    .is_some_and(|recipient| &recipient.chat_id == chat_id)
+            })
+            .collect()
+    }
+}
+
+/// The token starts with the share id, to find the share, followed by a digest that can't be
+/// guessed without the salt. The salt is random and only readable by the canister controllers,
+/// e.g. in a backup of the stable memory.
+fn generate_token(token_salt: &[u8], share_id: ShareId, share: &Share) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(token_salt);
+    hasher.update(share_id.to_be_bytes());
+    hasher.update(share.owner_chat_id.0.to_be_bytes());
+    hasher.update(share.created_at.to_be_bytes());
+    let digest: String = hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    format!("{share_id}_{}", &digest[..SHARE_TOKEN_DIGEST_LENGTH])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::FileSystemNode;
+    use rstest::*;
+    use std::path::PathBuf;
+
+    const OWNER_CHAT_ID: u64 = 123;
+    const RECIPIENT_CHAT_ID: u64 = 456;
+
+    #[rstest]
+    fn share_lifecycle() {
+        let service = ShareServiceImpl::default();
+        let owner_chat_id = ChatId(OWNER_CHAT_ID);
+        let recipient_chat_id = ChatId(RECIPIENT_CHAT_ID);
+        let mut owner_filesystem = FileSystem::default();
+        owner_filesystem
+            .create_file_from_node(
+                &PathBuf::from("/Documents/report.pdf"),
+                FileSystemNode::new_file(42, 0, None),
+            )
+            .unwrap();
+
+        assert_eq!(
+            service.create_share(
+                owner_chat_id.clone(),
+                None,
+                &owner_filesystem,
+                &PathBuf::from("/Documents/report.pdf"),
+                SharePermission::ReadOnly,
+            ),
+            Err("Only directories can be shared".to_string())
+        );
+        // the tokens can't be generated before the salt
+        assert!(!service.has_token_salt());
+        assert_eq!(
+            service.create_share(
+                owner_chat_id.clone(),
+                None,
+                &owner_filesystem,
+                &PathBuf::from("/Documents"),
+                SharePermission::ReadOnly,
+            ),
+            Err("Sharing is not available yet, try again in a minute".to_string())
+        );
+        service.set_token_salt(vec![7; 32]);
+        let share = service
+            .create_share(
+                owner_chat_id.clone(),
+                None,
+                &owner_filesystem,
+                &PathBuf::from("/Documents"),
+                SharePermission::ReadOnly,
+            )
+            .unwrap();
+        service
+            .filesystem_service
+            .update_filesystem(&owner_chat_id, None, owner_filesystem);
+
+        assert_eq!(
+            service.accept_share(owner_chat_id.clone(), &share.token),
+            Err("You can't open the links of your own shares".to_string())
+        );
+        assert_eq!(
+            service.accept_share(recipient_chat_id.clone(), "0_guessed"),
+            Err("This link is not valid anymore".to_string())
+        );
+        let accepted_share = service
+            .accept_share(recipient_chat_id.clone(), &share.token)
+            .unwrap();
+        assert_eq!(
+            accepted_share.recipient.map(|recipient| recipient.name),
+            Some("Documents".to_string())
+        );
+        // the token can only be used once
+        assert_eq!(
+            service.accept_share(ChatId(789), &share.token),
+            Err("This link is not valid anymore".to_string())
+        );
+
+        let mut recipient_filesystem = FileSystem::default();
+        service.mount_shares(&recipient_chat_id, &mut recipient_filesystem);
+        let shared_file_path = PathBuf::from("/Shared/Documents/report.pdf");
+        assert_eq!(
+            recipient_filesystem.get_file_message(&shared_file_path),
+            Ok((Some(owner_chat_id.clone()), 42))
+        );
+        assert_eq!(
+            recipient_filesystem.rm(&shared_file_path),
+            Err("The shared folder Documents is read-only".to_string())
+        );
+
+        let (share_id, _) = service.get_owned_shares(&owner_chat_id)[0].clone();
+        assert_eq!(
+            service.revoke_share(&recipient_chat_id, share_id),
+            Err("Share not found".to_string())
+        );
+        service.revoke_share(&owner_chat_id, share_id).unwrap();
+        assert!(service.get_owned_shares(&owner_chat_id).is_empty());
+        let mut recipient_filesystem = FileSystem::default();
+        service.mount_shares(&recipient_chat_id, &mut recipient_filesystem);
+        assert_eq!(
+            recipient_filesystem.get_node(&shared_file_path),
+            Err("Path not found".to_string())
+        );
+    }
+
+    #[rstest]
+    fn mount_shares_of_the_same_filesystem() {
+        let service = ShareServiceImpl::default();
+        let owner_chat_id = ChatId(OWNER_CHAT_ID);
+        let recipient_chat_id = ChatId(RECIPIENT_CHAT_ID);
+        service.set_token_salt(vec![7; 32]);
+        let owner_filesystem = FileSystem::default();
+        for dir_name in ["/Documents", "/Images"] {
+            let share = service
+                .create_share(
+                    owner_chat_id.clone(),
+                    None,
+                    &owner_filesystem,
+                    &PathBuf::from(dir_name),
+                    SharePermission::ReadWrite,
+                )
+                .unwrap();
+            service
+                .accept_share(recipient_chat_id.clone(), &share.token)
+                .unwrap();
+        }
+        service
+            .filesystem_service
+            .update_filesystem(&owner_chat_id, None, owner_filesystem);
+
+        let mut recipient_filesystem = FileSystem::default();
+        service.mount_shares(&recipient_chat_id, &mut recipient_filesystem);
+        for (path, message_id) in [
+            ("/Shared/Documents/report.pdf", 1),
+            ("/Shared/Images/photo.jpg", 2),
+        ] {
+            recipient_filesystem
+                .create_file_from_node(
+                    &PathBuf::from(path),
+                    FileSystemNode::new_file(message_id, 0, None),
+                )
+                .unwrap();
+        }
+        // both folders are changed in a single view of the owner's filesystem
+        let dirty_mounts = recipient_filesystem.take_dirty_mounts();
+        assert_eq!(dirty_mounts.len(), 1);
+        for (owner_chat_id, owner_drive_id, filesystem) in dirty_mounts {
+            service.filesystem_service.update_filesystem(
+                &owner_chat_id,
+                owner_drive_id,
+                filesystem,
+            );
+        }
+
+        let owner_filesystem = service
+            .filesystem_service
+            .get_or_create_filesystem(&owner_chat_id, None);
+        assert_eq!(
+            owner_filesystem.get_file_message(&PathBuf::from("/Documents/report.pdf")),
+            Ok((Some(recipient_chat_id.clone()), 1))
+        );
+        assert_eq!(
+            owner_filesystem.get_file_message(&PathBuf::from("/Images/photo.jpg")),
+            Ok((Some(recipient_chat_id), 2))
+        );
+    }
+}
@@ -1,167 +1,311 @@
 use const_format::formatcp;
 
+use crate::repositories::{ChatRole, RateLimit, RateLimitConfig};
+
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 pub const TG_FILE_EXTENSION_PREFIX: &str = "tg+";
 pub const TG_FILE_MIME_TYPE_PREFIX: &str = formatcp!("application/{}", TG_FILE_EXTENSION_PREFIX);
 
+/// The directories created in the root of every new filesystem, unless configured otherwise.
+pub const DEFAULT_DIRECTORIES: [&str; 4] = ["Documents", "Images", "Videos", "Trash"];
+
+/// The maximum number of operations kept in each chat's journal, unless configured otherwise.
+pub const DEFAULT_MAX_JOURNAL_ENTRIES: u32 = 20;
+
+/// The role of the group members that are not Telegram admins and haven't been given a role explicitly.
+pub const DEFAULT_GROUP_MEMBER_ROLE: ChatRole = ChatRole::Editor;
+
+/// The number of chat ids returned by a page of the admin API, when not specified.
+pub const DEFAULT_CHAT_IDS_PAGE_SIZE: u32 = 100;
+/// The maximum number of chat ids returned by a page of the admin API.
+pub const MAX_CHAT_IDS_PAGE_SIZE: u32 = 1_000;
+
+/// The size of the stable memory chunks returned by a backup, well below the response size limit.
+pub const BACKUP_CHUNK_SIZE: u64 = 1024 * 1024;
+
+/// The maximum number of processed updates remembered to ignore the webhook deliveries retried by Telegram,
+/// unless configured otherwise.
+pub const DEFAULT_MAX_PROCESSED_UPDATES: u64 = 1_000;
+/// After this interval without updates, Telegram picks the id of the next update randomly instead of sequentially.
+pub const UPDATE_ID_SEQUENCE_RESET_INTERVAL_NANOS: u64 = 7 * 24 * 60 * 60 * 1_000_000_000;
+
+/// The rate limits applied unless configured otherwise.
+pub const DEFAULT_RATE_LIMIT_CONFIG: RateLimitConfig = RateLimitConfig {
+    per_chat: RateLimit {
+        capacity: 20,
+        refill_per_second: 1,
+    },
+    global: RateLimit {
+        capacity: 200,
+        refill_per_second: 50,
+    },
+};
+/// How long the previous webhook secret is still accepted after a new one is set, unless configured otherwise.
+pub const DEFAULT_WEBHOOK_SECRET_ROTATION_PERIOD_SECONDS: u64 = 24 * 60 * 60;
+
+/// Above this number of tracked chats, the buckets that have been refilled are forgotten.
+pub const MAX_RATE_LIMITED_CHATS: u64 = 10_000;
+
+/// The maximum number of drives of a chat, next to its main drive.
+pub const MAX_DRIVES_PER_CHAT: usize = 10;
+/// The maximum length of a drive name, to keep the buttons readable.
+pub const MAX_DRIVE_NAME_LENGTH: usize = 32;
+
+/// The maximum length of a tag, to keep the file messages readable.
+pub const MAX_TAG_LENGTH: usize = 32;
+
+/// Where the number of each file goes in the names of the files of an album.
+pub const FILE_NUMBER_PLACEHOLDER: &str = "{n}";
+
+/// Where the date and the time of the save go in the names made from the name template.
+pub const DATE_PLACEHOLDER: &str = "{date}";
+/// Where the type of the file, e.g. `photo`, goes in the names made from the name template.
+pub const TYPE_PLACEHOLDER: &str = "{type}";
+/// The maximum length of the name template and of the names taken from the captions.
+pub const MAX_AUTO_FILE_NAME_LENGTH: usize = 64;
+
+/// The maximum number of results of an inline query, as allowed by Telegram.
+pub const MAX_INLINE_QUERY_RESULTS: usize = 50;
+/// How long Telegram caches the results of an inline query, short so that new files show up soon.
+pub const INLINE_QUERY_CACHE_TIME_SECONDS: u32 = 10;
+
 pub mod messages {
+    use std::collections::BTreeSet;
+
     use frankenstein::types::{InlineKeyboardButton, InlineKeyboardMarkup};
 
-    use crate::repositories::ChatSessionAction;
+    use crate::{
+        repositories::{
+            AutoNaming, BulkOperation, ChatRole, ChatSessionAction, ChatSettings, ConflictPolicy,
+            Drive, DriveId, JournalOperation, Language, Setting, Share, ShareId, SharePermission,
+            SortOrder, UtcOffset, MAIN_DRIVE_NAME,
+        },
+        utils::{t, tf},
+    };
 
     use super::*;
 
     const GITHUB_REPO_URL: &str = "https://github.com/ilbertt/ic_infinitecloud_bot";
 
-    pub const COMING_SOON_TEXT: &str = "Coming soon...";
+    pub fn coming_soon_message() -> String {
+        t("coming_soon").to_string()
+    }
 
     pub fn help_message() -> String {
-        format!(
-            r#"*SAVE FILES*:
-1. send ONE FILE AT A TIME to the bot (the file could be any type of message: _text_, _audio_, _video_, _image_, _sticker_, etc.)
-2. navigate to the directory you want to save it
-3. click _HERE_ to select the current directory where to save the file
-4. when asked, send the file name (the name cannot include `/` character) WITHOUT extension
-
-The file will be saved as with the following extension:
-_image_ -> _.jpg_
-_video_ -> _.mp4_
-_audio_ -> same extension of the file you sent
-_document_ -> same extension of the file you sent
-_other_ -> _.tg+(type-of-message-you-sent)_
-
-*CREATE DIRECTORY* (/mkdir):
-Click the _HERE_ button where you want to create the directory and send the directory name when asked (the name cannot include `/` character).
-
-*MOVE FILES* (/move\_file):
-The flow is almost the same as to save files.
-
-*RENAME FILES* (/rename\_file):
-The flow is almost the same as to save files.
-
-*EXPLORE FILES AND DIRECTORIES* (/explorer):
-Click on directories to navigate into them.
-Click on files to get the reference to the file.
-
-*DELETE FILES/DIRECTORIES* (/delete\_file and /delete\_dir):
-{COMING_SOON_TEXT}
-
-Troubles? Open an issue on GitHub: [{GITHUB_REPO_URL}/issues]({GITHUB_REPO_URL}/issues)"#
+        tf(
+            "help",
+            &[
+                ("here", t("button.current_dir")),
+                ("keep_file_names", t("button.keep_file_names")),
+                ("select_files", t("button.select_files")),
+                ("refine_file", t("button.refine_file")),
+                ("coming_soon", t("coming_soon")),
+                ("main_drive", MAIN_DRIVE_NAME),
+                ("repo_url", GITHUB_REPO_URL),
+            ],
         )
     }
 
     pub fn start_message(user_first_name: Option<String>) -> String {
         let greet = if let Some(first_name) = user_first_name {
-            format!("Hello {first_name}!")
+            tf("start.greet_name", &[("first_name", &first_name)])
         } else {
-            "Hello!".to_string()
+            t("start.greet").to_string()
         };
 
-        format!(
-            r#"{greet}
-Welcome on *Infinite Cloud*!
+        tf("start", &[("greet", &greet), ("help", &help_message())])
+    }
 
-Here's some help to start:
+    pub fn info_message() -> String {
+        tf(
+            "info",
+            &[("repo_url", GITHUB_REPO_URL), ("version", VERSION)],
+        )
+    }
 
-{}
+    /* INLINE BUTTONS */
+    /// The label of the language, or of the automatic choice when `None`.
+    pub fn language_button_text(language: Option<Language>) -> String {
+        match language {
+            Some(language) => language.native_name().to_string(),
+            None => t("button.language_auto").to_string(),
+        }
+    }
 
-To see this help message again, use the /help command"#,
-            help_message()
-        )
+    /* NAMES */
+    fn role_text(role: ChatRole) -> &'static str {
+        match role {
+            ChatRole::Viewer => t("role.viewer"),
+            ChatRole::Editor => t("role.editor"),
+            ChatRole::Admin => t("role.admin"),
+        }
     }
 
-    pub fn info_message() -> String {
-        format!(
-            r#"*Infinite Cloud Bot* - infinite free cloud storage on Telegram (powered by the [Internet Computer](https://internetcomputer.org/))
+    fn permission_text(permission: SharePermission) -> &'static str {
+        match permission {
+            SharePermission::ReadOnly => t("permission.read_only"),
+            SharePermission::ReadWrite => t("permission.read_write"),
+        }
+    }
 
-Usage instructions: /help
+    pub fn setting_text(setting: Setting) -> &'static str {
+        match setting {
+            Setting::SaveDir => t("setting.save_dir"),
+            Setting::Inbox => t("setting.inbox"),
+            Setting::SortOrder => t("setting.sort_order"),
+            Setting::ConflictPolicy => t("setting.conflict_policy"),
+            Setting::Language => t("setting.language"),
+            Setting::TimeZone => t("setting.time_zone"),
+            Setting::AutoNaming => t("setting.auto_naming"),
+            Setting::NameTemplate => t("setting.name_template"),
+        }
+    }
 
-More info and source code: [{GITHUB_REPO_URL}]({GITHUB_REPO_URL})
+    pub fn sort_order_text(sort_order: SortOrder) -> &'static str {
+        match sort_order {
+            SortOrder::Name => t("sort_order.name"),
+            SortOrder::NameDescending => t("sort_order.name_descending"),
+            SortOrder::Newest => t("sort_order.newest"),
+            SortOrder::Oldest => t("sort_order.oldest"),
+        }
+    }
 
-_Version: {VERSION}_"#
-        )
+    pub fn conflict_policy_text(conflict_policy: ConflictPolicy) -> &'static str {
+        match conflict_policy {
+            ConflictPolicy::Reject => t("conflict_policy.reject"),
+            ConflictPolicy::Rename => t("conflict_policy.rename"),
+            ConflictPolicy::Overwrite => t("conflict_policy.overwrite"),
+        }
     }
 
-    /* INLINE BUTTONS */
-    pub const MKDIR_BUTTON_TEXT: &str = "+ New Directory";
-    pub const CURRENT_DIR_BUTTON_TEXT: &str = "HERE";
-    pub const PARENT_DIR_BUTTON_TEXT: &str = "..";
-    pub const DELETE_DIR_BUTTON_TEXT: &str = "🗑️ DELETE THIS DIR";
-    pub const BACK_BUTTON_TEXT: &str = "<< BACK";
+    pub fn auto_naming_text(auto_naming: AutoNaming) -> &'static str {
+        match auto_naming {
+            AutoNaming::Ask => t("auto_naming.ask"),
+            AutoNaming::OriginalName => t("auto_naming.original_name"),
+            AutoNaming::Timestamp => t("auto_naming.timestamp"),
+        }
+    }
 
     /* SYSTEM MESSAGES */
-    const CURRENT_PATH_TEXT: &str = "CURRENT PATH:";
-    const CREATE_DIR_TEXT: &str = formatcp!(
-        "Navigate to the directory in which you want to CREATE the new directory and click _{}_",
-        CURRENT_DIR_BUTTON_TEXT
-    );
-    const CREATE_FILE_TEXT: &str = formatcp!(
-        "Navigate to the directory in which you want to SAVE the new file and click _{}_",
-        CURRENT_DIR_BUTTON_TEXT
-    );
-    const ASK_DIRECTORY_NAME_TEXT: &str = "Send me the name of the new DIRECTORY";
-    const ASK_FILE_NAME_TEXT: &str = "Send me the name of the new FILE";
-    const RENAME_FILE_TEXT: &str = "Select the file you want to RENAME";
-    const MOVE_FILE_SELECT_FILE_TEXT: &str = "Select the file you want to MOVE";
-    // const DELETE_DIR_TEXT: &str = "Select the directory you want to DELETE";
-    // const DELETE_FILE_TEXT: &str = "Select the file you want to DELETE";
-    const GENERIC_ERROR_TEXT: &str = "An error has occurred. Please try again.";
-
     fn current_path_text(path: String) -> String {
-        format!(
-            r#"{CURRENT_PATH_TEXT}
+        tf("current_path", &[("path", &path)])
+    }
 
-`{path}`"#
+    /// The current path, followed by the text of the key.
+    fn current_path_message(path: String, key: &str) -> String {
+        format!(
+            "{}\n\n{}",
+            current_path_text(path),
+            tf(key, &[("here", t("button.current_dir"))])
         )
     }
 
     pub fn mkdir_message(path: String) -> String {
-        format!(
-            r#"{}
-
-{CREATE_DIR_TEXT}"#,
-            current_path_text(path)
-        )
+        current_path_message(path, "create_dir")
     }
 
     pub fn create_file_message(path: String) -> String {
-        format!(
-            r#"{}
+        current_path_message(path, "create_file")
+    }
 
-{CREATE_FILE_TEXT}"#,
-            current_path_text(path)
-        )
+    pub fn create_media_group_message(path: String) -> String {
+        current_path_message(path, "create_media_group")
     }
 
-    pub fn ask_directory_name_message(path: String) -> String {
+    pub fn ask_media_group_file_names_message(files_count: usize, path: String) -> String {
         format!(
-            r#"{}
-
-{ASK_DIRECTORY_NAME_TEXT}"#,
-            current_path_text(path)
+            "{}\n\n{}",
+            current_path_text(path),
+            tf(
+                "ask_media_group_file_names",
+                &[
+                    ("count", &files_count.to_string()),
+                    ("placeholder", FILE_NUMBER_PLACEHOLDER),
+                    ("keep_file_names", t("button.keep_file_names")),
+                ],
+            )
         )
     }
 
+    pub fn ask_directory_name_message(path: String) -> String {
+        current_path_message(path, "ask_directory_name")
+    }
+
     pub fn ask_file_name_message(path: String) -> String {
+        current_path_message(path, "ask_file_name")
+    }
+
+    pub fn refine_file_message(file_name: String, path: String) -> String {
         format!(
-            r#"{}
+            "{}\n\n{}",
+            current_path_text(path),
+            tf(
+                "refine_file",
+                &[("file_name", &file_name), ("here", t("button.current_dir"))]
+            )
+        )
+    }
 
-{ASK_FILE_NAME_TEXT}"#,
-            current_path_text(path)
+    pub fn ask_refine_file_name_message(file_name: String, path: String) -> String {
+        format!(
+            "{}\n\n{}",
+            current_path_text(path),
+            tf(
+                "ask_refine_file_name",
+                &[
+                    ("file_name", &file_name),
+                    ("keep_file_names", t("button.keep_file_names")),
+                ]
+            )
         )
     }
 
     pub fn ask_rename_file_message(file_name: String, path: String) -> String {
-        format!("RENAME *{file_name}* at `{path}`\n\nSend me the new NAME:")
+        tf(
+            "ask_rename_file",
+            &[("file_name", &file_name), ("path", &path)],
+        )
     }
 
     pub fn created_directory_success_message(dir_name: String, path: String) -> String {
-        format!("Directory *{dir_name}* CREATED at `{path}`")
+        tf(
+            "created_directory",
+            &[("dir_name", &dir_name), ("path", &path)],
+        )
     }
 
     pub fn created_file_success_message(file_name: String, path: String) -> String {
-        format!("File *{file_name}* CREATED at `{path}`")
+        tf(
+            "created_file",
+            &[("file_name", &file_name), ("path", &path)],
+        )
+    }
+
+    /// Lists the saved files, and the ones that couldn't be saved with the reason.
+    pub fn created_media_group_message(
+        file_names: &[String],
+        errors: &[(String, String)],
+        path: String,
+    ) -> String {
+        let mut text = tf(
+            "created_media_group",
+            &[("count", &file_names.len().to_string()), ("path", &path)],
+        );
+        for file_name in file_names {
+            text.push_str(&format!("\n- *{file_name}*"));
+        }
+        if !errors.is_empty() {
+            text.push_str("\n\n");
+            text.push_str(&tf(
+                "created_media_group.errors",
+                &[("count", &errors.len().to_string())],
+            ));
+            for (file_name, err) in errors {
+                text.push_str(&format!("\n- *{file_name}*: {err}"));
+            }
+        }
+        text
     }
 
     pub fn renamed_file_success_message(
@@ -169,7 +313,14 @@ _Version: {VERSION}_"#
         new_file_name: String,
         path: String,
     ) -> String {
-        format!("File *{old_file_name}* RENAMED.\n\nNew name: *{new_file_name}*\nPath: `{path}`")
+        tf(
+            "renamed_file",
+            &[
+                ("old_file_name", &old_file_name),
+                ("new_file_name", &new_file_name),
+                ("path", &path),
+            ],
+        )
     }
 
     pub fn moved_file_success_message(
@@ -177,64 +328,635 @@ _Version: {VERSION}_"#
         from_path: String,
         to_path: String,
     ) -> String {
-        format!("File *{file_name}* MOVED.\n\nFrom: `{from_path}`\nTo: `{to_path}`")
+        tf(
+            "moved_file",
+            &[
+                ("file_name", &file_name),
+                ("from", &from_path),
+                ("to", &to_path),
+            ],
+        )
+    }
+
+    pub fn journal_operation_text(operation: &JournalOperation) -> String {
+        let path_text = |key, path: &std::path::Path| tf(key, &[("path", &path.to_string_lossy())]);
+        let move_text = |key, from: &std::path::Path, to: &std::path::Path| {
+            tf(
+                key,
+                &[
+                    ("from", &from.to_string_lossy()),
+                    ("to", &to.to_string_lossy()),
+                ],
+            )
+        };
+        match operation {
+            JournalOperation::MkDir { path } => path_text("journal.mkdir", path),
+            JournalOperation::SaveFile { path, .. } => path_text("journal.save_file", path),
+            JournalOperation::MoveFile { from, to } => move_text("journal.move_file", from, to),
+            JournalOperation::RenameFile { from, to } => move_text("journal.rename_file", from, to),
+            JournalOperation::CopyFile { from, to, .. } => move_text("journal.copy_file", from, to),
+            JournalOperation::DeleteFile { path, .. } => path_text("journal.delete_file", path),
+            JournalOperation::TagFile { path, tag } => tf(
+                "journal.tag_file",
+                &[("path", &path.to_string_lossy()), ("tag", tag)],
+            ),
+            JournalOperation::Bulk {
+                operation,
+                operations,
+            } => {
+                let mut text = tf(
+                    "journal.bulk",
+                    &[
+                        ("count", &operations.len().to_string()),
+                        ("operation", bulk_operation_done_text(*operation)),
+                    ],
+                );
+                for operation in operations {
+                    text.push_str(&format!("\n- {}", journal_operation_text(operation)));
+                }
+                text
+            }
+        }
+    }
+
+    pub fn undo_success_message(operation: &JournalOperation) -> String {
+        tf(
+            "undo_success",
+            &[("operation", &journal_operation_text(operation))],
+        )
+    }
+
+    pub fn undo_conflict_message(operation: &JournalOperation, reason: String) -> String {
+        tf(
+            "undo_conflict",
+            &[
+                ("operation", &journal_operation_text(operation)),
+                ("reason", &reason),
+            ],
+        )
+    }
+
+    pub fn nothing_to_undo_message() -> String {
+        t("nothing_to_undo").to_string()
+    }
+
+    pub fn undo_disabled_message() -> String {
+        t("undo_disabled").to_string()
+    }
+
+    pub fn forget_me_message() -> String {
+        tf("forget_me", &[("confirm", t("button.confirm"))])
+    }
+
+    pub fn forget_me_final_confirmation_message() -> String {
+        tf(
+            "forget_me.final_confirmation",
+            &[("confirm", t("button.confirm"))],
+        )
+    }
+
+    pub fn forgotten_message() -> String {
+        t("forgotten").to_string()
+    }
+
+    pub fn forget_me_cancelled_message() -> String {
+        t("forget_me.cancelled").to_string()
+    }
+
+    pub fn corrupted_filesystem_message() -> String {
+        t("corrupted_filesystem").to_string()
+    }
+
+    pub fn corrupted_drive_message() -> String {
+        t("corrupted_drive").to_string()
+    }
+
+    pub fn chat_throttled_message() -> String {
+        t("chat_throttled").to_string()
+    }
+
+    pub fn global_throttled_message() -> String {
+        t("global_throttled").to_string()
+    }
+
+    pub fn access_denied_message() -> String {
+        t("access_denied").to_string()
+    }
+
+    pub fn enrolled_message() -> String {
+        t("enrolled").to_string()
+    }
+
+    pub fn already_enrolled_message() -> String {
+        t("already_enrolled").to_string()
+    }
+
+    pub fn permission_denied_message(role: ChatRole, required_role: ChatRole) -> String {
+        tf(
+            "permission_denied",
+            &[
+                ("role", role_text(role)),
+                ("required_role", role_text(required_role)),
+            ],
+        )
+    }
+
+    pub fn foreign_keyboard_message() -> String {
+        t("foreign_keyboard").to_string()
+    }
+
+    pub fn role_usage_message() -> String {
+        t("role_usage").to_string()
+    }
+
+    pub fn role_not_in_group_message() -> String {
+        t("role_not_in_group").to_string()
+    }
+
+    pub fn role_set_message(first_name: String, role: ChatRole) -> String {
+        tf(
+            "role_set",
+            &[("first_name", &first_name), ("role", role_text(role))],
+        )
+    }
+
+    /// Expects the entries as `(formatted timestamp, operation)` pairs,
+    /// from the most recent to the oldest.
+    pub fn history_message(entries: Vec<(String, &JournalOperation)>) -> String {
+        if entries.is_empty() {
+            return t("empty_history").to_string();
+        }
+
+        let lines = entries
+            .into_iter()
+            .map(|(timestamp, operation)| {
+                format!("_{timestamp}_\n{}", journal_operation_text(operation))
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n");
+
+        tf("history", &[("entries", &lines)])
     }
 
     pub fn explorer_message(path: String) -> String {
         current_path_text(path)
     }
 
-    pub fn explorer_file_message(file_name: String, path: String) -> String {
-        format!("File: *{file_name}*\nPath: `{path}`")
+    pub fn explorer_file_message(
+        file_name: String,
+        path: String,
+        tags: &BTreeSet<String>,
+    ) -> String {
+        let mut text = tf(
+            "explorer_file",
+            &[("file_name", &file_name), ("path", &path)],
+        );
+        if !tags.is_empty() {
+            let tags = tags
+                .iter()
+                .map(|tag| format!("`#{tag}`"))
+                .collect::<Vec<_>>()
+                .join(" ");
+            text.push('\n');
+            text.push_str(&tf("explorer_file.tags", &[("tags", &tags)]));
+        }
+        text
     }
 
-    pub fn rename_file_message(path: String) -> String {
+    pub fn select_files_message(path: String, selected_count: usize) -> String {
         format!(
-            r#"{}
-
-{RENAME_FILE_TEXT}"#,
-            current_path_text(path)
+            "{}\n\n{}",
+            current_path_text(path),
+            tf("select_files", &[("count", &selected_count.to_string())])
         )
     }
 
-    pub fn move_file_select_file_message(path: String) -> String {
+    pub fn invalid_tag_message(reason: String) -> String {
+        tf("invalid_tag", &[("reason", &reason)])
+    }
+
+    pub fn empty_tag_error() -> String {
+        t("invalid_tag.empty").to_string()
+    }
+
+    pub fn tag_too_long_error(max_length: usize) -> String {
+        tf("invalid_tag.too_long", &[("max", &max_length.to_string())])
+    }
+
+    pub fn tag_characters_error() -> String {
+        t("invalid_tag.characters").to_string()
+    }
+
+    pub fn empty_selection_message() -> String {
+        t("empty_selection").to_string()
+    }
+
+    fn bulk_operation_text(operation: BulkOperation) -> &'static str {
+        match operation {
+            BulkOperation::Move => t("bulk.move"),
+            BulkOperation::Copy => t("bulk.copy"),
+            BulkOperation::Delete => t("bulk.delete"),
+            BulkOperation::Tag => t("bulk.tag"),
+        }
+    }
+
+    fn bulk_operation_done_text(operation: BulkOperation) -> &'static str {
+        match operation {
+            BulkOperation::Move => t("bulk.moved"),
+            BulkOperation::Copy => t("bulk.copied"),
+            BulkOperation::Delete => t("bulk.deleted"),
+            BulkOperation::Tag => t("bulk.tagged"),
+        }
+    }
+
+    pub fn bulk_select_destination_message(
+        operation: BulkOperation,
+        selected_count: usize,
+        path: String,
+    ) -> String {
         format!(
-            r#"{}
+            "{}\n\n{}",
+            current_path_text(path),
+            tf(
+                "bulk_select_destination",
+                &[
+                    ("operation", bulk_operation_text(operation)),
+                    ("count", &selected_count.to_string()),
+                    ("here", t("button.current_dir")),
+                ],
+            )
+        )
+    }
 
-{MOVE_FILE_SELECT_FILE_TEXT}"#,
-            current_path_text(path)
+    pub fn bulk_delete_confirmation_message(selected_count: usize) -> String {
+        tf(
+            "bulk_delete_confirmation",
+            &[
+                ("count", &selected_count.to_string()),
+                ("confirm", t("button.confirm")),
+            ],
         )
     }
 
+    pub fn ask_tag_message(selected_count: usize) -> String {
+        tf("ask_tag", &[("count", &selected_count.to_string())])
+    }
+
+    /// Lists the files the operation has been applied to, and the ones it failed for with the reason.
+    pub fn bulk_operation_message(operation: BulkOperation, paths: &[String]) -> String {
+        let mut text = tf(
+            "bulk_operation",
+            &[
+                ("count", &paths.len().to_string()),
+                ("operation", bulk_operation_done_text(operation)),
+            ],
+        );
+        for path in paths {
+            text.push_str(&format!("\n- `{path}`"));
+        }
+        text
+    }
+
+    /// The operation is applied to none of the selected files if it can't be applied to all of them.
+    pub fn bulk_operation_failed_message(
+        operation: BulkOperation,
+        errors: &[(String, String)],
+    ) -> String {
+        let mut text = tf(
+            "bulk_operation.failed",
+            &[
+                ("count", &errors.len().to_string()),
+                ("operation", bulk_operation_done_text(operation)),
+            ],
+        );
+        for (path, err) in errors {
+            text.push_str(&format!("\n- `{path}`: {err}"));
+        }
+        text
+    }
+
+    pub fn rename_file_message(path: String) -> String {
+        current_path_message(path, "rename_file")
+    }
+
+    pub fn move_file_select_file_message(path: String) -> String {
+        current_path_message(path, "move_file.select_file")
+    }
+
     pub fn move_file_select_destination_message(path: String) -> String {
-        format!(
-            r#"File to MOVE:
-`{path}`
+        tf(
+            "move_file.select_destination",
+            &[("path", &path), ("here", t("button.current_dir"))],
+        )
+    }
+
+    pub fn share_message(path: String) -> String {
+        current_path_message(path, "share")
+    }
+
+    pub fn share_permission_message(path: String) -> String {
+        tf("share_permission", &[("path", &path)])
+    }
+
+    /// Without the bot username, the recipient has to send the `/start` command with the token.
+    pub fn share_created_message(share: &Share, bot_username: Option<String>) -> String {
+        let link = match bot_username {
+            Some(bot_username) => format!("https://t.me/{bot_username}?start={}", share.token),
+            None => format!("/start {}", share.token),
+        };
+        tf(
+            "share_created",
+            &[
+                ("path", &share.path.to_string_lossy()),
+                ("permission", permission_text(share.permission)),
+                ("link", &link),
+            ],
+        )
+    }
 
-Select the directory in which you want to move the file and click _{CURRENT_DIR_BUTTON_TEXT}_"#,
+    pub fn share_failed_message(reason: String) -> String {
+        tf("share_failed", &[("reason", &reason)])
+    }
+
+    pub fn share_accepted_message(path: String, permission: SharePermission) -> String {
+        tf(
+            "share_accepted",
+            &[("path", &path), ("permission", permission_text(permission))],
+        )
+    }
+
+    pub fn share_not_accepted_message(reason: String) -> String {
+        tf("share_not_accepted", &[("reason", &reason)])
+    }
+
+    fn share_recipient_text(share: &Share) -> String {
+        match &share.recipient {
+            Some(recipient) => tf(
+                "share.opened",
+                &[("chat_id", &recipient.chat_id.to_string())],
+            ),
+            None => t("share.not_opened").to_string(),
+        }
+    }
+
+    /// Expects the shares owned by the chat, from the oldest to the most recent.
+    pub fn shares_message(shares: &[(ShareId, Share)]) -> String {
+        if shares.is_empty() {
+            return t("no_shares").to_string();
+        }
+
+        let lines = shares
+            .iter()
+            .enumerate()
+            .map(|(index, (_, share))| {
+                tf(
+                    "shares.entry",
+                    &[
+                        ("index", &(index + 1).to_string()),
+                        ("path", &share.path.to_string_lossy()),
+                        ("permission", permission_text(share.permission)),
+                        ("recipient", &share_recipient_text(share)),
+                    ],
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        tf("shares", &[("shares", &lines)])
+    }
+
+    pub fn share_revoked_message(share: &Share, shares: &[(ShareId, Share)]) -> String {
+        tf(
+            "share_revoked",
+            &[
+                ("path", &share.path.to_string_lossy()),
+                ("shares", &shares_message(shares)),
+            ],
+        )
+    }
+
+    fn drive_name(drive_id: Option<DriveId>, drives: &[(DriveId, Drive)]) -> &str {
+        drive_id
+            .and_then(|drive_id| drives.iter().find(|(id, _)| *id == drive_id))
+            .map_or(MAIN_DRIVE_NAME, |(_, drive)| drive.name.as_str())
+    }
+
+    /// Expects the drives of the chat, without its main drive.
+    pub fn drives_message(active_drive_id: Option<DriveId>, drives: &[(DriveId, Drive)]) -> String {
+        tf("drives", &[("name", drive_name(active_drive_id, drives))])
+    }
+
+    pub fn ask_drive_name_message() -> String {
+        t("ask_drive_name").to_string()
+    }
+
+    pub fn drive_created_message(name: String) -> String {
+        tf("drive_created", &[("name", &name)])
+    }
+
+    pub fn drive_not_created_message(reason: String) -> String {
+        tf("drive_not_created", &[("reason", &reason)])
+    }
+
+    pub fn drive_switched_message(name: String) -> String {
+        tf("drive_switched", &[("name", &name)])
+    }
+
+    pub fn delete_drive_message() -> String {
+        t("delete_drive").to_string()
+    }
+
+    pub fn delete_drive_confirmation_message(name: String) -> String {
+        tf(
+            "delete_drive_confirmation",
+            &[("name", &name), ("confirm", t("button.confirm"))],
         )
     }
 
-    //     pub fn delete_dir_message(path: String) -> String {
-    //         format!(
-    //             r#"{}
+    pub fn drive_deleted_message(name: String) -> String {
+        tf("drive_deleted", &[("name", &name)])
+    }
+
+    pub fn working_dir_message(path: String) -> String {
+        tf("working_dir", &[("path", &path)])
+    }
+
+    /// Lists the directories first, then the files.
+    pub fn ls_message(path: String, dir_names: &[String], file_names: &[String]) -> String {
+        if dir_names.is_empty() && file_names.is_empty() {
+            return tf("ls.empty", &[("path", &path)]);
+        }
 
-    // {DELETE_DIR_TEXT}"#,
-    //             current_path_text(path)
-    //         )
-    //     }
+        let lines = dir_names
+            .iter()
+            .map(|dir_name| format!("📁 {dir_name}"))
+            .chain(file_names.iter().cloned())
+            .collect::<Vec<_>>()
+            .join("\n");
+        format!("`{path}`\n```\n{lines}\n```")
+    }
 
-    //     pub fn delete_file_message(path: String) -> String {
-    //         format!(
-    //             r#"{}
+    pub fn created_directories_message(paths: &[String]) -> String {
+        paths
+            .iter()
+            .map(|path| tf("created_directories.entry", &[("path", path)]))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
 
-    // {DELETE_FILE_TEXT}"#,
-    //             current_path_text(path)
-    //         )
-    //     }
+    pub fn moved_path_message(from_path: String, to_path: String) -> String {
+        tf("moved_path", &[("from", &from_path), ("to", &to_path)])
+    }
+
+    pub fn path_command_failed_message(reason: String) -> String {
+        tf("path_command_failed", &[("reason", &reason)])
+    }
+
+    pub fn path_exists_error() -> String {
+        t("path_exists").to_string()
+    }
+
+    pub fn not_a_directory_error(path: String) -> String {
+        tf("not_a_directory", &[("path", &path)])
+    }
+
+    pub fn not_a_file_error() -> String {
+        t("not_a_file").to_string()
+    }
+
+    pub fn destination_taken_error() -> String {
+        t("destination_taken").to_string()
+    }
+
+    pub fn destination_not_found_error() -> String {
+        t("destination_not_found").to_string()
+    }
+
+    pub fn file_name_slash_error() -> String {
+        t("file_name_slash").to_string()
+    }
+
+    pub fn wrong_arguments_error(usage: &str) -> String {
+        tf("wrong_arguments", &[("usage", usage)])
+    }
+
+    pub fn unclosed_quote_error() -> String {
+        t("unclosed_quote").to_string()
+    }
+
+    /// Shows the language chosen for the chat, the automatic choice when `None`.
+    pub fn language_message(language: Option<Language>) -> String {
+        let language = match language {
+            Some(language) => language.native_name(),
+            None => t("language.auto"),
+        };
+        tf(
+            "language",
+            &[("language", language), ("auto", t("button.language_auto"))],
+        )
+    }
+
+    pub fn language_set_message(language: Option<Language>) -> String {
+        match language {
+            Some(language) => tf("language_set", &[("language", language.native_name())]),
+            None => t("language_auto_set").to_string(),
+        }
+    }
+
+    /// Lists the value of each setting, the default for the ones that weren't changed.
+    pub fn settings_message(chat_settings: &ChatSettings) -> String {
+        let value = |setting| match setting {
+            Setting::SaveDir => format!("`{}`", chat_settings.save_dir().to_string_lossy()),
+            Setting::Inbox => match chat_settings.inbox() {
+                true => t("inbox.on").to_string(),
+                false => t("inbox.off").to_string(),
+            },
+            Setting::SortOrder => sort_order_text(chat_settings.sort_order()).to_string(),
+            Setting::ConflictPolicy => {
+                conflict_policy_text(chat_settings.conflict_policy()).to_string()
+            }
+            Setting::Language => chat_settings
+                .language
+                .map_or(t("language.auto"), |language| language.native_name())
+                .to_string(),
+            Setting::TimeZone => chat_settings.time_zone().to_string(),
+            Setting::AutoNaming => auto_naming_text(chat_settings.auto_naming()).to_string(),
+            Setting::NameTemplate => format!("`{}`", chat_settings.name_template()),
+        };
+        let entries = Setting::ALL
+            .into_iter()
+            .map(|setting| format!("{}: {}", setting_text(setting), value(setting)))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        tf("settings", &[("entries", &entries)])
+    }
+
+    pub fn save_dir_setting_message(path: String) -> String {
+        current_path_message(path, "save_dir_setting")
+    }
+
+    pub fn sort_order_setting_message() -> String {
+        t("sort_order_setting").to_string()
+    }
+
+    pub fn conflict_policy_setting_message() -> String {
+        t("conflict_policy_setting").to_string()
+    }
+
+    pub fn auto_naming_setting_message() -> String {
+        t("auto_naming_setting").to_string()
+    }
+
+    pub fn ask_time_zone_message(time_zone: UtcOffset) -> String {
+        tf("ask_time_zone", &[("time_zone", &time_zone.to_string())])
+    }
+
+    pub fn invalid_time_zone_message(reason: String) -> String {
+        tf("invalid_time_zone", &[("reason", &reason)])
+    }
+
+    pub fn ask_name_template_message(name_template: String) -> String {
+        tf("ask_name_template", &[("name_template", &name_template)])
+    }
+
+    pub fn invalid_name_template_message(reason: String) -> String {
+        tf("invalid_name_template", &[("reason", &reason)])
+    }
+
+    pub fn empty_name_template_error() -> String {
+        t("invalid_name_template.empty").to_string()
+    }
+
+    pub fn name_template_too_long_error(max_length: usize) -> String {
+        tf(
+            "invalid_name_template.too_long",
+            &[("max", &max_length.to_string())],
+        )
+    }
+
+    pub fn name_template_slash_error() -> String {
+        t("invalid_name_template.slash").to_string()
+    }
+
+    pub fn language_unknown_message(language: String) -> String {
+        let languages = Language::ALL
+            .iter()
+            .map(|language| format!("`{}` ({language})", language.code()))
+            .collect::<Vec<_>>()
+            .join(", ");
+        tf(
+            "language_unknown",
+            &[("language", &language), ("languages", &languages)],
+        )
+    }
 
     pub fn generic_error_message() -> String {
-        GENERIC_ERROR_TEXT.to_string()
+        t("generic_error").to_string()
+    }
+
+    pub fn update_error_message(error: String) -> String {
+        tf("update_error", &[("error", &error)])
     }
 
     pub fn current_dir_inline_button() -> InlineKeyboardButton {
@@ -265,6 +987,262 @@ Select the directory in which you want to move the file and click _{CURRENT_DIR_
             .build()
     }
 
+    pub fn confirm_inline_button() -> InlineKeyboardButton {
+        InlineKeyboardButton::builder()
+            .text(ChatSessionAction::Confirm.beautified())
+            .callback_data(ChatSessionAction::Confirm)
+            .build()
+    }
+
+    pub fn cancel_inline_button() -> InlineKeyboardButton {
+        InlineKeyboardButton::builder()
+            .text(ChatSessionAction::Cancel.beautified())
+            .callback_data(ChatSessionAction::Cancel)
+            .build()
+    }
+
+    pub fn confirm_inline_keyboard() -> InlineKeyboardMarkup {
+        InlineKeyboardMarkup {
+            inline_keyboard: vec![vec![cancel_inline_button(), confirm_inline_button()]],
+        }
+    }
+
+    pub fn share_permission_inline_keyboard() -> InlineKeyboardMarkup {
+        let grant_share_inline_button = |permission| {
+            let action = ChatSessionAction::GrantShare(permission);
+            InlineKeyboardButton::builder()
+                .text(action.beautified())
+                .callback_data(action)
+                .build()
+        };
+        InlineKeyboardMarkup {
+            inline_keyboard: vec![
+                vec![
+                    grant_share_inline_button(SharePermission::ReadOnly),
+                    grant_share_inline_button(SharePermission::ReadWrite),
+                ],
+                vec![back_inline_button()],
+            ],
+        }
+    }
+
+    /// One button per share, numbered as in [shares_message].
+    pub fn shares_inline_keyboard(shares: &[(ShareId, Share)]) -> InlineKeyboardMarkup {
+        InlineKeyboardMarkup {
+            inline_keyboard: shares
+                .iter()
+                .enumerate()
+                .map(|(index, (share_id, share))| {
+                    vec![InlineKeyboardButton::builder()
+                        .text(format!(
+                            "🗑️ {}. {}",
+                            index + 1,
+                            share.path.to_string_lossy()
+                        ))
+                        .callback_data(ChatSessionAction::RevokeShare(*share_id))
+                        .build()]
+                })
+                .collect(),
+        }
+    }
+
+    /// One button per drive, the active one marked, followed by the buttons to create and delete the drives.
+    pub fn drives_inline_keyboard(
+        active_drive_id: Option<DriveId>,
+        drives: &[(DriveId, Drive)],
+    ) -> InlineKeyboardMarkup {
+        let drive_ids = std::iter::once(None).chain(drives.iter().map(|(id, _)| Some(*id)));
+        let mut inline_keyboard: Vec<Vec<InlineKeyboardButton>> = drive_ids
+            .map(|drive_id| {
+                let name = drive_name(drive_id, drives);
+                let text = if drive_id == active_drive_id {
+                    format!("💾 {name} ✓")
+                } else {
+                    format!("💾 {name}")
+                };
+                vec![InlineKeyboardButton::builder()
+                    .text(text)
+                    .callback_data(ChatSessionAction::SwitchDrive(drive_id))
+                    .build()]
+            })
+            .collect();
+
+        let mut actions_row = vec![InlineKeyboardButton::builder()
+            .text(ChatSessionAction::CreateDrive(None).beautified())
+            .callback_data(ChatSessionAction::CreateDrive(None))
+            .build()];
+        if !drives.is_empty() {
+            actions_row.push(
+                InlineKeyboardButton::builder()
+                    .text(ChatSessionAction::DeleteDrive(None).beautified())
+                    .callback_data(ChatSessionAction::DeleteDrive(None))
+                    .build(),
+            );
+        }
+        inline_keyboard.push(actions_row);
+
+        InlineKeyboardMarkup { inline_keyboard }
+    }
+
+    pub fn delete_drive_inline_keyboard(drives: &[(DriveId, Drive)]) -> InlineKeyboardMarkup {
+        let mut inline_keyboard: Vec<Vec<InlineKeyboardButton>> = drives
+            .iter()
+            .map(|(drive_id, drive)| {
+                vec![InlineKeyboardButton::builder()
+                    .text(format!("🗑️ {}", drive.name))
+                    .callback_data(ChatSessionAction::DeleteDrive(Some(*drive_id)))
+                    .build()]
+            })
+            .collect();
+        inline_keyboard.push(vec![back_inline_button()]);
+
+        InlineKeyboardMarkup { inline_keyboard }
+    }
+
+    /// The button of one of the choices of a setting, marked when it's the chosen one.
+    fn choice_inline_button(action: ChatSessionAction, is_chosen: bool) -> InlineKeyboardButton {
+        let text = if is_chosen {
+            format!("{} ✓", action.beautified())
+        } else {
+            action.beautified()
+        };
+        InlineKeyboardButton::builder()
+            .text(text)
+            .callback_data(action)
+            .build()
+    }
+
+    /// One button for the automatic choice and one per language, the chosen one marked.
+    pub fn languages_inline_keyboard(chosen_language: Option<Language>) -> InlineKeyboardMarkup {
+        let languages = std::iter::once(None).chain(Language::ALL.into_iter().map(Some));
+        InlineKeyboardMarkup {
+            inline_keyboard: languages
+                .map(|language| {
+                    vec![choice_inline_button(
+                        ChatSessionAction::SetLanguage(language),
+                        language == chosen_language,
+                    )]
+                })
+                .collect(),
+        }
+    }
+
+    /// One button per setting.
+    pub fn settings_inline_keyboard() -> InlineKeyboardMarkup {
+        InlineKeyboardMarkup {
+            inline_keyboard: Setting::ALL
+                .into_iter()
+                .map(|setting| {
+                    let action = ChatSessionAction::EditSetting(setting);
+                    vec![InlineKeyboardButton::builder()
+                        .text(action.beautified())
+                        .callback_data(action)
+                        .build()]
+                })
+                .collect(),
+        }
+    }
+
+    /// One button per choice, the chosen one marked, followed by the back button.
+    fn choices_inline_keyboard(
+        choices: impl Iterator<Item = (ChatSessionAction, bool)>,
+    ) -> InlineKeyboardMarkup {
+        InlineKeyboardMarkup {
+            inline_keyboard: choices
+                .map(|(action, is_chosen)| vec![choice_inline_button(action, is_chosen)])
+                .chain(std::iter::once(vec![back_inline_button()]))
+                .collect(),
+        }
+    }
+
+    pub fn sort_order_inline_keyboard(chosen: SortOrder) -> InlineKeyboardMarkup {
+        choices_inline_keyboard(SortOrder::ALL.into_iter().map(|sort_order| {
+            (
+                ChatSessionAction::SetSortOrder(sort_order),
+                sort_order == chosen,
+            )
+        }))
+    }
+
+    pub fn conflict_policy_inline_keyboard(chosen: ConflictPolicy) -> InlineKeyboardMarkup {
+        choices_inline_keyboard(ConflictPolicy::ALL.into_iter().map(|conflict_policy| {
+            (
+                ChatSessionAction::SetConflictPolicy(conflict_policy),
+                conflict_policy == chosen,
+            )
+        }))
+    }
+
+    pub fn auto_naming_inline_keyboard(chosen: AutoNaming) -> InlineKeyboardMarkup {
+        choices_inline_keyboard(AutoNaming::ALL.into_iter().map(|auto_naming| {
+            (
+                ChatSessionAction::SetAutoNaming(auto_naming),
+                auto_naming == chosen,
+            )
+        }))
+    }
+
+    /// Like [languages_inline_keyboard], with the button to go back to the settings.
+    pub fn language_setting_inline_keyboard(
+        chosen_language: Option<Language>,
+    ) -> InlineKeyboardMarkup {
+        let mut keyboard = languages_inline_keyboard(chosen_language);
+        keyboard.inline_keyboard.push(vec![back_inline_button()]);
+        keyboard
+    }
+
+    pub fn keep_file_names_inline_keyboard() -> InlineKeyboardMarkup {
+        InlineKeyboardMarkup {
+            inline_keyboard: vec![
+                vec![InlineKeyboardButton::builder()
+                    .text(ChatSessionAction::KeepFileNames.beautified())
+                    .callback_data(ChatSessionAction::KeepFileNames)
+                    .build()],
+                vec![back_inline_button()],
+            ],
+        }
+    }
+
+    /// The button under the confirmation of a file saved in the inbox.
+    pub fn refine_file_inline_keyboard() -> InlineKeyboardMarkup {
+        let action = ChatSessionAction::RefineFile(None, None);
+        InlineKeyboardMarkup {
+            inline_keyboard: vec![vec![InlineKeyboardButton::builder()
+                .text(action.beautified())
+                .callback_data(action)
+                .build()]],
+        }
+    }
+
+    pub fn select_files_inline_button() -> InlineKeyboardButton {
+        InlineKeyboardButton::builder()
+            .text(ChatSessionAction::SelectFiles.beautified())
+            .callback_data(ChatSessionAction::SelectFiles)
+            .build()
+    }
+
+    /// The action bar shown below the files while selecting them.
+    pub fn selection_inline_rows() -> Vec<Vec<InlineKeyboardButton>> {
+        let bulk_inline_button = |operation| {
+            let action = ChatSessionAction::Bulk(operation, None);
+            InlineKeyboardButton::builder()
+                .text(action.beautified())
+                .callback_data(action)
+                .build()
+        };
+        vec![
+            vec![
+                bulk_inline_button(BulkOperation::Move),
+                bulk_inline_button(BulkOperation::Copy),
+            ],
+            vec![
+                bulk_inline_button(BulkOperation::Delete),
+                bulk_inline_button(BulkOperation::Tag),
+            ],
+            vec![cancel_inline_button()],
+        ]
+    }
+
     pub fn back_inline_keyboard() -> InlineKeyboardMarkup {
         InlineKeyboardMarkup {
             inline_keyboard: vec![vec![back_inline_button()]],
@@ -288,6 +1266,16 @@ pub mod http {
         }
     }
 
+    pub fn error503() -> HttpResponse {
+        HttpResponse {
+            status_code: 503,
+            headers: vec![],
+            body: "Service Unavailable".as_bytes().to_vec(),
+            upgrade: Some(false),
+            streaming_strategy: None,
+        }
+    }
+
     pub fn error500(err: Option<impl std::fmt::Display>) -> HttpResponse {
         HttpResponse {
             status_code: 500,
@@ -309,6 +1297,11 @@ pub mod filesystem {
         PathBuf::from("/")
     }
 
+    /// The directory where the folders shared by other chats are mounted.
+    pub fn shared_path() -> PathBuf {
+        root_path().join("Shared")
+    }
+
     #[cfg(test)]
     mod tests {
         use crate::utils::is_absolute;
@@ -0,0 +1,255 @@
+use std::cell::Cell;
+
+use crate::repositories::Language;
+
+mod english;
+mod italian;
+
+thread_local! {
+    /// The language of the replies to the update that is being handled.
+    static LANGUAGE: Cell<Language> = Cell::new(Language::default());
+}
+
+/// Sets the language of the replies, for the rest of the update.
+pub fn set_language(language: Language) {
+    LANGUAGE.set(language);
+}
+
+pub fn language() -> Language {
+    LANGUAGE.get()
+}
+
+fn catalog(language: Language) -> &'static [(&'static str, &'static str)] {
+    match language {
+        Language::English => english::CATALOG,
+        Language::Italian => italian::CATALOG,
+    }
+}
+
+fn lookup(language: Language, key: &str) -> Option<&'static str> {
+    catalog(language)
+        .iter()
+        .find(|(entry_key, _)| *entry_key == key)
+        .map(|(_, text)| *text)
+}
+
+/// Returns the text of the key in the current language, falling back to English.
+pub fn t(key: &str) -> &'static str {
+    lookup(language(), key)
+        .or_else(|| lookup(Language::English, key))
+        .unwrap_or_else(|| {
+            // the tests go through most of the texts, so that the missing keys are caught early
+            #[cfg(test)]
+            panic!("Missing translation key {key:?}");
+            #[cfg(not(test))]
+            {
+                crate::custom_print!("Missing translation key {key:?}");
+                ""
+            }
+        })
+}
+
+/// Like [t], replacing the `{name}` placeholders with the values of the arguments.
+/// The values are not scanned for placeholders, so they can contain any text.
+pub fn tf(key: &str, args: &[(&str, &str)]) -> String {
+    let mut text = String::new();
+    let mut rest = t(key);
+    while let Some(start) = rest.find('{') {
+        text.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let value = rest.find('}').and_then(|end| {
+            args.iter()
+                .find(|(name, _)| *name == &rest[1..end])
+                .map(|(_, value)| (end, *value))
+        });
+        match value {
+            Some((end, value)) => {
+                text.push_str(value);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                text.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    text.push_str(rest);
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeSet, fs, path::Path};
+
+    use super::*;
+    use rstest::*;
+
+    /// The functions that take a key: [t], [tf] and the helpers of the messages
+    /// that pass a key on to them. A new helper of this kind must be added here.
+    const KEY_FUNCTIONS: [&str; 5] = ["t", "tf", "current_path_message", "path_text", "move_text"];
+
+    /// Returns the string literals passed directly to the [KEY_FUNCTIONS] in the source,
+    /// skipping the ones nested in other expressions, e.g. the names of the placeholders.
+    fn source_keys(source: &str) -> Vec<String> {
+        let chars = source.chars().collect::<Vec<_>>();
+        let is_identifier_char = |c: char| c.is_alphanumeric() || c == '_';
+        let mut keys = vec![];
+        let mut i = 0;
+        while i < chars.len() {
+            if !is_identifier_char(chars[i]) || (i > 0 && is_identifier_char(chars[i - 1])) {
+                i += 1;
+                continue;
+            }
+            let start = i;
+            while i < chars.len() && is_identifier_char(chars[i]) {
+                i += 1;
+            }
+            let name = chars[start..i].iter().collect::<String>();
+            if !KEY_FUNCTIONS.contains(&name.as_str()) || chars.get(i) != Some(&'(') {
+                continue;
+            }
+
+            let mut depth = 0;
+            while i < chars.len() {
+                match chars[i] {
+                    '(' | '[' | '{' => depth += 1,
+                    ')' | ']' | '}' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            break;
+                        }
+                    }
+                    '"' => {
+                        let literal_start = i + 1;
+                        i += 1;
+                        while i < chars.len() && chars[i] != '"' {
+                            if chars[i] == '\\' {
+                                i += 1;
+                            }
+                            i += 1;
+                        }
+                        if depth == 1 {
+                            keys.push(chars[literal_start..i].iter().collect());
+                        }
+                    }
+                    _ => {}
+                }
+                i += 1;
+            }
+        }
+        keys
+    }
+
+    fn source_files(dir: &Path) -> Vec<std::path::PathBuf> {
+        let mut files = vec![];
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                files.extend(source_files(&path));
+            } else if path.extension().is_some_and(|extension| extension == "rs") {
+                files.push(path);
+            }
+        }
+        files
+    }
+
+    fn placeholders(text: &str) -> BTreeSet<&str> {
+        text.split('{')
+            .skip(1)
+            .filter_map(|part| part.split_once('}').map(|(name, _)| name))
+            .filter(|name| {
+                !name.is_empty() && name.chars().all(|c| c.is_ascii_lowercase() || c == '_')
+            })
+            .collect()
+    }
+
+    #[rstest]
+    fn catalogs_have_unique_keys() {
+        for language in Language::ALL {
+            let keys = catalog(language)
+                .iter()
+                .map(|(key, _)| *key)
+                .collect::<BTreeSet<_>>();
+            assert_eq!(keys.len(), catalog(language).len(), "{language:?}");
+        }
+    }
+
+    #[rstest]
+    fn catalogs_have_all_the_keys() {
+        for language in Language::ALL {
+            for (key, text) in english::CATALOG {
+                let translation = lookup(language, key)
+                    .unwrap_or_else(|| panic!("Missing key {key:?} in {language:?}"));
+                assert_eq!(
+                    placeholders(translation),
+                    placeholders(text),
+                    "Different placeholders for key {key:?} in {language:?}"
+                );
+            }
+            for (key, _) in catalog(language) {
+                assert!(
+                    lookup(Language::English, key).is_some(),
+                    "Unknown key {key:?} in {language:?}"
+                );
+            }
+        }
+    }
+
+    #[rstest]
+    fn source_keys_are_in_the_catalog() {
+        let src_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+        let mut keys_count = 0;
+        for path in source_files(&src_dir) {
+            // the tests of this module use missing keys on purpose
+            if path.ends_with("utils/i18n.rs") {
+                continue;
+            }
+            for key in source_keys(&fs::read_to_string(&path).unwrap()) {
+                assert!(
+                    lookup(Language::English, &key).is_some(),
+                    "Missing key {key:?} used in {}",
+                    path.display()
+                );
+                keys_count += 1;
+            }
+        }
+        // makes sure that the keys are found at all
+        assert!(keys_count > english::CATALOG.len() / 2);
+    }
+
+    #[rstest]
+    fn source_keys_skip_the_nested_literals() {
+        assert_eq!(
+            source_keys(
+                r#"tf("moved_path", &[("from", "/a")]); current_path_message(path, "create_dir"); format!("{}", t("button.back"))"#
+            ),
+            vec!["moved_path", "create_dir", "button.back"]
+        );
+        assert!(source_keys(r#"list("not_a_key"); fn t(key: &str)"#).is_empty());
+    }
+
+    #[rstest]
+    fn t_uses_the_current_language() {
+        assert_eq!(t("button.cancel"), "CANCEL");
+
+        set_language(Language::Italian);
+        assert_eq!(t("button.cancel"), "ANNULLA");
+
+        set_language(Language::English);
+    }
+
+    #[rstest]
+    #[should_panic(expected = "Missing translation key \"missing\"")]
+    fn t_missing_key() {
+        t("missing");
+    }
+
+    #[rstest]
+    fn tf_replaces_the_placeholders() {
+        assert_eq!(
+            tf("moved_path", &[("from", "/{to}"), ("to", "/Documents/{n}")]),
+            "MOVED `/{to}` -> `/Documents/{n}`"
+        );
+        assert_eq!(tf("moved_path", &[("from", "/a")]), "MOVED `/a` -> `{to}`");
+    }
+}
@@ -0,0 +1,460 @@
+/// The texts of the bot, in the legacy Markdown of Telegram.
+/// The other catalogs must have the same keys and the same `{name}` placeholders.
+pub(super) const CATALOG: &[(&str, &str)] = &[
+    /* INLINE BUTTONS */
+    ("button.mkdir", "+ New Directory"),
+    ("button.current_dir", "HERE"),
+    ("button.parent_dir", ".."),
+    ("button.delete_dir", "🗑️ DELETE THIS DIR"),
+    ("button.back", "<< BACK"),
+    ("button.confirm", "YES, I'M SURE"),
+    ("button.cancel", "CANCEL"),
+    ("button.read_only", "READ-ONLY"),
+    ("button.read_write", "READ-WRITE"),
+    ("button.create_drive", "+ New Drive"),
+    ("button.delete_drive", "🗑️ DELETE A DRIVE"),
+    ("button.keep_file_names", "KEEP ORIGINAL NAMES"),
+    ("button.select_files", "☑️ SELECT FILES"),
+    ("button.bulk_move", "MOVE"),
+    ("button.bulk_copy", "COPY"),
+    ("button.bulk_delete", "🗑️ DELETE"),
+    ("button.bulk_tag", "🏷️ TAG"),
+    ("button.language_auto", "🌐 AUTOMATIC"),
+    ("button.refine_file", "✏️ MOVE/RENAME"),
+    /* NAMES */
+    ("role.viewer", "viewer"),
+    ("role.editor", "editor"),
+    ("role.admin", "admin"),
+    ("permission.read_only", "read-only"),
+    ("permission.read_write", "read-write"),
+    ("bulk.move", "MOVE"),
+    ("bulk.copy", "COPY"),
+    ("bulk.delete", "DELETE"),
+    ("bulk.tag", "TAG"),
+    ("bulk.moved", "MOVED"),
+    ("bulk.copied", "COPIED"),
+    ("bulk.deleted", "DELETED"),
+    ("bulk.tagged", "TAGGED"),
+    ("setting.save_dir", "📁 Save folder"),
+    ("setting.inbox", "📥 Inbox"),
+    ("setting.sort_order", "🔃 Sort order"),
+    ("setting.conflict_policy", "⚠️ Name conflicts"),
+    ("setting.language", "🌐 Language"),
+    ("setting.time_zone", "🕒 Time zone"),
+    ("setting.auto_naming", "🏷️ File names"),
+    ("setting.name_template", "🧩 Name template"),
+    ("sort_order.name", "A → Z"),
+    ("sort_order.name_descending", "Z → A"),
+    ("sort_order.newest", "Newest first"),
+    ("sort_order.oldest", "Oldest first"),
+    ("conflict_policy.reject", "Don't save the file"),
+    ("conflict_policy.rename", "Add a number to the name"),
+    ("conflict_policy.overwrite", "Replace the old file"),
+    ("auto_naming.ask", "Ask for the name"),
+    ("auto_naming.original_name", "Keep the original name"),
+    ("auto_naming.timestamp", "Name after the date"),
+    ("inbox.on", "on"),
+    ("inbox.off", "off"),
+    /* COMMANDS */
+    (
+        "help",
+        r#"*SAVE FILES*:
+1. send a file to the bot (the file could be any type of message: _text_, _audio_, _video_, _image_, _sticker_, etc.)
+2. navigate to the directory you want to save it
+3. click _{here}_ to select the current directory where to save the file
+4. when asked, send the file name (the name cannot include `/` character) WITHOUT extension
+
+The file will be saved as with the following extension:
+_image_ -> _.jpg_
+_video_ -> _.mp4_
+_audio_ -> same extension of the file you sent
+_document_ -> same extension of the file you sent
+_other_ -> _.tg+(type-of-message-you-sent)_
+
+*SAVE ALBUMS*:
+The files of an album are saved together: select the directory once, then send a name for all of them (they will be numbered) or click _{keep_file_names}_.
+
+*CREATE DIRECTORY* (/mkdir):
+Click the _{here}_ button where you want to create the directory and send the directory name when asked (the name cannot include `/` character).
+
+*MOVE FILES* (/move\_file):
+The flow is almost the same as to save files.
+
+*RENAME FILES* (/rename\_file):
+The flow is almost the same as to save files.
+
+*EXPLORE FILES AND DIRECTORIES* (/explorer):
+Click on directories to navigate into them.
+Click on files to get the reference to the file.
+Click _{select_files}_ to select several files, then MOVE, COPY, DELETE or TAG all of them at once.
+
+*DELETE FILES/DIRECTORIES* (/delete\_file and /delete\_dir):
+{coming_soon}
+
+*PATH COMMANDS*:
+Type the paths instead of clicking: /ls lists a directory, /cd changes the current directory and /pwd shows it. `/mkdir <path>` creates a directory with its missing parents, `/mv <from> <to>` moves or renames a file or a directory.
+The paths are relative to the current directory, put the ones with spaces between quotes, e.g. `/mv "My file.pdf" /Archive`.
+
+*UNDO* (/undo):
+Reverts the last operation (directory creation, file save, move, rename, copy or delete). The operation is not reverted if the files and directories involved have changed in the meantime.
+
+*HISTORY* (/history):
+Lists the latest operations that can be reverted with /undo.
+
+*FORGET ME* (/forget\_me):
+Deletes all the data stored about this chat: files, directories and history. You will be asked to confirm twice.
+
+*SHARE DIRECTORIES* (/share and /shares):
+Click the _{here}_ button in the directory you want to share, choose whether the other chat can only read it or also change it, and send it the link you get. The link can only be opened once, and the directory appears in the _Shared_ directory of the other chat. Use /shares to list your shares and revoke them.
+
+*DRIVES* (/drive):
+Keep your files in separate drives, each with its own directories. Click a drive to switch to it: all the commands work on the active drive. Every chat has a _{main_drive}_ drive, that cannot be deleted.
+
+*GROUPS*:
+The members of a group share the same files. _Viewers_ can only explore them, _editors_ can also save, move and rename files and create directories, _admins_ can also delete the group's data.
+Group admins are admins of the bot too. To give a role to a member, reply to one of their messages with /role followed by _viewer_, _editor_ or _admin_.
+
+*LANGUAGE* (/language):
+The bot speaks the language of your Telegram app when it knows it, and English otherwise. Choose another language for the whole chat with /language.
+
+*SETTINGS* (/settings):
+Choose the folder in which the received files are saved, how the files are listed, what to do when a file with the same name already exists, the language, the time zone of the dates and how the received files are named.
+
+*INBOX*:
+Turn on the inbox in /settings to save the received files in the save folder right away. Each file keeps the name it was sent with, or takes the first line of its caption, or a name made from the name template, where `{date}` and `{type}` become the date and the type of the file. Click _{refine_file}_ under the confirmation to move or rename the file.
+
+Troubles? Open an issue on GitHub: [{repo_url}/issues]({repo_url}/issues)"#,
+    ),
+    ("start.greet", "Hello!"),
+    ("start.greet_name", "Hello {first_name}!"),
+    (
+        "start",
+        r#"{greet}
+Welcome on *Infinite Cloud*!
+
+Here's some help to start:
+
+{help}
+
+To see this help message again, use the /help command"#,
+    ),
+    (
+        "info",
+        r#"*Infinite Cloud Bot* - infinite free cloud storage on Telegram (powered by the [Internet Computer](https://internetcomputer.org/))
+
+Usage instructions: /help
+
+More info and source code: [{repo_url}]({repo_url})
+
+_Version: {version}_"#,
+    ),
+    ("coming_soon", "Coming soon..."),
+    ("generic_error", "An error has occurred. Please try again."),
+    ("update_error", "Error processing update content: {error}"),
+    /* SYSTEM MESSAGES */
+    ("current_path", "CURRENT PATH:\n\n`{path}`"),
+    (
+        "create_dir",
+        "Navigate to the directory in which you want to CREATE the new directory and click _{here}_",
+    ),
+    (
+        "create_file",
+        "Navigate to the directory in which you want to SAVE the new file and click _{here}_",
+    ),
+    (
+        "create_media_group",
+        "Navigate to the directory in which you want to SAVE the files of the album and click _{here}_",
+    ),
+    (
+        "ask_media_group_file_names",
+        "Send me the NAME of the {count} files: they will be numbered, e.g. _name-1_, or put `{placeholder}` where the number goes.\nClick _{keep_file_names}_ to save them with the names they were sent with.",
+    ),
+    ("ask_directory_name", "Send me the name of the new DIRECTORY"),
+    ("ask_file_name", "Send me the name of the new FILE"),
+    (
+        "refine_file",
+        "Navigate to the directory in which you want to MOVE *{file_name}* and click _{here}_",
+    ),
+    (
+        "ask_refine_file_name",
+        "Send me the new NAME of *{file_name}* (without extension to keep the current one), or click _{keep_file_names}_",
+    ),
+    (
+        "ask_rename_file",
+        "RENAME *{file_name}* at `{path}`\n\nSend me the new NAME:",
+    ),
+    ("created_directory", "Directory *{dir_name}* CREATED at `{path}`"),
+    ("created_file", "File *{file_name}* CREATED at `{path}`"),
+    ("created_media_group", "{count} files SAVED at `{path}`"),
+    ("created_media_group.errors", "{count} files NOT SAVED:"),
+    (
+        "renamed_file",
+        "File *{old_file_name}* RENAMED.\n\nNew name: *{new_file_name}*\nPath: `{path}`",
+    ),
+    (
+        "moved_file",
+        "File *{file_name}* MOVED.\n\nFrom: `{from}`\nTo: `{to}`",
+    ),
+    ("rename_file", "Select the file you want to RENAME"),
+    ("move_file.select_file", "Select the file you want to MOVE"),
+    (
+        "move_file.select_destination",
+        "File to MOVE:\n`{path}`\n\nSelect the directory in which you want to move the file and click _{here}_",
+    ),
+    /* HISTORY */
+    ("journal.mkdir", "Directory CREATED: `{path}`"),
+    ("journal.save_file", "File CREATED: `{path}`"),
+    ("journal.move_file", "File MOVED: `{from}` -> `{to}`"),
+    ("journal.rename_file", "File RENAMED: `{from}` -> `{to}`"),
+    ("journal.copy_file", "File COPIED: `{from}` -> `{to}`"),
+    ("journal.delete_file", "File DELETED: `{path}`"),
+    ("journal.tag_file", "File TAGGED with #{tag}: `{path}`"),
+    ("journal.bulk", "{count} files {operation}:"),
+    ("undo_success", "UNDONE:\n{operation}"),
+    (
+        "undo_conflict",
+        "Cannot undo:\n{operation}\n\nReason: {reason}. The operation has been removed from the history.",
+    ),
+    ("nothing_to_undo", "There is nothing to undo."),
+    ("undo_disabled", "Undo and history are disabled for this bot."),
+    ("empty_history", "There are no operations in the history."),
+    ("history", "*HISTORY* (most recent first):\n\n{entries}"),
+    /* FORGET ME */
+    (
+        "forget_me",
+        r#"You are about to DELETE ALL the data stored about this chat: files, directories and history.
+
+The files you sent remain in this chat, but the bot won't know about them anymore.
+
+Click _{confirm}_ to continue."#,
+    ),
+    (
+        "forget_me.final_confirmation",
+        "This CANNOT be undone. Click _{confirm}_ again to delete all your data.",
+    ),
+    (
+        "forgotten",
+        "All the data stored about this chat has been deleted. Send /start to begin again.",
+    ),
+    ("forget_me.cancelled", "Nothing has been deleted."),
+    (
+        "corrupted_filesystem",
+        r#"Sorry, your filesystem could not be read anymore.
+
+Its data has been set aside, so that an administrator can inspect it and try to recover it.
+A new empty filesystem has been created for you, you can keep using the bot as usual."#,
+    ),
+    (
+        "corrupted_drive",
+        r#"Sorry, this drive could not be read anymore.
+
+You have been switched back to the main drive. Use /drive to delete the unreadable drive."#,
+    ),
+    /* ACCESS */
+    (
+        "chat_throttled",
+        "You're sending messages too quickly. Please slow down and try again in a few seconds.",
+    ),
+    (
+        "global_throttled",
+        "The bot is receiving too many messages right now. Please try again in a few seconds.",
+    ),
+    (
+        "access_denied",
+        "Sorry, this bot is private. Ask its owners for access, or send /join followed by your invite code if you have one.",
+    ),
+    (
+        "enrolled",
+        "Welcome! You can now use the bot. Send /help to get started.",
+    ),
+    ("already_enrolled", "You can already use the bot."),
+    /* GROUPS */
+    (
+        "permission_denied",
+        "You are a *{role}* of this group, only a *{required_role}* can do this.",
+    ),
+    (
+        "foreign_keyboard",
+        "This keyboard was opened by another member. Send a command to open your own.",
+    ),
+    (
+        "role_usage",
+        "Reply to a message of the member with /role followed by viewer, editor or admin.",
+    ),
+    ("role_not_in_group", "Roles can only be set in groups."),
+    ("role_set", "{first_name} is now a *{role}* of this group."),
+    /* EXPLORER */
+    ("explorer_file", "File: *{file_name}*\nPath: `{path}`"),
+    ("explorer_file.tags", "Tags: {tags}"),
+    (
+        "select_files",
+        "Click the files to SELECT them, then choose what to do with the selected files\n\nSelected files: *{count}*",
+    ),
+    ("empty_selection", "Select at least one file first."),
+    (
+        "invalid_tag",
+        "Cannot add the tag: {reason}. Send me another TAG.",
+    ),
+    ("invalid_tag.empty", "the tag is empty"),
+    ("invalid_tag.too_long", "the tag is longer than {max} characters"),
+    (
+        "invalid_tag.characters",
+        "the tag can only contain letters, digits, _ and -",
+    ),
+    (
+        "bulk_select_destination",
+        "Navigate to the directory in which you want to {operation} the {count} selected files and click _{here}_",
+    ),
+    (
+        "bulk_delete_confirmation",
+        "You are about to DELETE the {count} selected files.\n\nClick _{confirm}_ to continue.",
+    ),
+    (
+        "ask_tag",
+        "Send me the TAG to add to the {count} selected files, e.g. _#work_",
+    ),
+    ("bulk_operation", "{count} files {operation}"),
+    (
+        "bulk_operation.failed",
+        "Operation cancelled, {count} files can't be {operation}:",
+    ),
+    /* SHARES */
+    (
+        "share",
+        "Navigate to the directory you want to SHARE and click _{here}_",
+    ),
+    (
+        "share_permission",
+        "Directory to SHARE:\n`{path}`\n\nCan the other chat only read it, or also change it?",
+    ),
+    (
+        "share_created",
+        r#"Directory `{path}` SHARED as *{permission}*.
+
+Send this link to the chat you want to share it with, it can only be opened once:
+`{link}`
+
+Use /shares to revoke it."#,
+    ),
+    ("share_failed", "Cannot share the directory: {reason}."),
+    (
+        "share_accepted",
+        "The shared directory is now available at `{path}` as *{permission}*. Open it with /explorer.",
+    ),
+    (
+        "share_not_accepted",
+        "Cannot open the shared directory: {reason}.",
+    ),
+    ("share.opened", "opened by chat {chat_id}"),
+    ("share.not_opened", "link not opened yet"),
+    (
+        "no_shares",
+        "You haven't shared any directory yet. Use /share to share one.",
+    ),
+    ("shares.entry", "{index}. `{path}` as *{permission}*, {recipient}"),
+    (
+        "shares",
+        "*SHARES*:\n\n{shares}\n\nClick a share to REVOKE it.",
+    ),
+    ("share_revoked", "Share of `{path}` REVOKED.\n\n{shares}"),
+    /* DRIVES */
+    (
+        "drives",
+        "*DRIVES*:\n\nActive drive: *{name}*\n\nClick a drive to SWITCH to it.",
+    ),
+    ("ask_drive_name", "Send me the name of the new DRIVE"),
+    (
+        "drive_created",
+        "Drive *{name}* CREATED. It's now the active drive.",
+    ),
+    ("drive_not_created", "Cannot create the drive: {reason}."),
+    ("drive_switched", "Switched to the drive *{name}*."),
+    (
+        "delete_drive",
+        "Select the drive you want to DELETE. The main drive cannot be deleted.",
+    ),
+    (
+        "delete_drive_confirmation",
+        r#"You are about to DELETE the drive *{name}* with all its directories, and to revoke its shares.
+
+The files you saved in it remain in this chat, but the bot won't know about them anymore.
+
+Click _{confirm}_ to continue."#,
+    ),
+    ("drive_deleted", "Drive *{name}* DELETED."),
+    /* PATH COMMANDS */
+    ("working_dir", "Current directory: `{path}`"),
+    ("ls.empty", "`{path}` is empty"),
+    ("created_directories.entry", "Directory `{path}` CREATED"),
+    ("moved_path", "MOVED `{from}` -> `{to}`"),
+    ("path_command_failed", "Cannot run the command: {reason}."),
+    ("path_exists", "Path already exists"),
+    ("not_a_directory", "`{path}` is not a directory"),
+    ("not_a_file", "Not a file"),
+    ("destination_taken", "Destination is already taken"),
+    ("destination_not_found", "Destination directory not found"),
+    (
+        "file_name_slash",
+        "The file name cannot include the `/` character",
+    ),
+    ("wrong_arguments", "Wrong arguments, use `{usage}`"),
+    ("unclosed_quote", "A quote is not closed"),
+    /* LANGUAGE */
+    (
+        "language",
+        "*LANGUAGE*: {language}\n\nChoose the language of the bot in this chat, or _{auto}_ to use the language of the Telegram app of each member.",
+    ),
+    ("language.auto", "automatic"),
+    ("language_set", "The bot now speaks *{language}* in this chat."),
+    (
+        "language_auto_set",
+        "The bot now speaks the language of the Telegram app of each member.",
+    ),
+    (
+        "language_unknown",
+        "Unknown language {language}. Choose one of: {languages}.",
+    ),
+    /* SETTINGS */
+    ("settings", "*SETTINGS*\n\n{entries}\n\nChoose a setting to change it."),
+    (
+        "save_dir_setting",
+        "Navigate to the directory in which to SAVE the received files and click _{here}_",
+    ),
+    (
+        "sort_order_setting",
+        "Choose the order of the directories and the files.",
+    ),
+    (
+        "conflict_policy_setting",
+        "Choose what to do when a received file has the name of a file that already exists.",
+    ),
+    (
+        "auto_naming_setting",
+        "Choose how the received files are named once you select their directory. The name is asked for the files sent without one.",
+    ),
+    (
+        "ask_time_zone",
+        "*TIME ZONE*: {time_zone}\n\nSend me the offset of your time zone from UTC, e.g. `+02:00` or `-5`, or `UTC`.",
+    ),
+    (
+        "invalid_time_zone",
+        "{reason}. Send me an offset like `+02:00`, or `UTC`.",
+    ),
+    (
+        "ask_name_template",
+        "*NAME TEMPLATE*: `{name_template}`\n\nSend me the name of the files saved in the inbox without a name or a caption. `{date}` becomes the date of the save and `{type}` the type of the file, e.g. _photo_.",
+    ),
+    (
+        "invalid_name_template",
+        "{reason}. Send me a template like `{date}_{type}`.",
+    ),
+    ("invalid_name_template.empty", "The template is empty"),
+    (
+        "invalid_name_template.too_long",
+        "The template is longer than {max} characters",
+    ),
+    (
+        "invalid_name_template.slash",
+        "The template cannot include the `/` character",
+    ),
+];
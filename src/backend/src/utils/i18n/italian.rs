@@ -0,0 +1,482 @@
+pub(super) const CATALOG: &[(&str, &str)] = &[
+    /* INLINE BUTTONS */
+    ("button.mkdir", "+ Nuova Directory"),
+    ("button.current_dir", "QUI"),
+    ("button.parent_dir", ".."),
+    ("button.delete_dir", "🗑️ ELIMINA QUESTA DIR"),
+    ("button.back", "<< INDIETRO"),
+    ("button.confirm", "SÌ, SONO SICURO"),
+    ("button.cancel", "ANNULLA"),
+    ("button.read_only", "SOLA LETTURA"),
+    ("button.read_write", "LETTURA E SCRITTURA"),
+    ("button.create_drive", "+ Nuovo Drive"),
+    ("button.delete_drive", "🗑️ ELIMINA UN DRIVE"),
+    ("button.keep_file_names", "MANTIENI I NOMI ORIGINALI"),
+    ("button.select_files", "☑️ SELEZIONA FILE"),
+    ("button.bulk_move", "SPOSTA"),
+    ("button.bulk_copy", "COPIA"),
+    ("button.bulk_delete", "🗑️ ELIMINA"),
+    ("button.bulk_tag", "🏷️ TAG"),
+    ("button.language_auto", "🌐 AUTOMATICA"),
+    ("button.refine_file", "✏️ SPOSTA/RINOMINA"),
+    /* NAMES */
+    ("role.viewer", "visualizzatore"),
+    ("role.editor", "editor"),
+    ("role.admin", "amministratore"),
+    ("permission.read_only", "sola lettura"),
+    ("permission.read_write", "lettura e scrittura"),
+    ("bulk.move", "SPOSTARE"),
+    ("bulk.copy", "COPIARE"),
+    ("bulk.delete", "ELIMINARE"),
+    ("bulk.tag", "TAGGARE"),
+    ("bulk.moved", "SPOSTATI"),
+    ("bulk.copied", "COPIATI"),
+    ("bulk.deleted", "ELIMINATI"),
+    ("bulk.tagged", "TAGGATI"),
+    ("setting.save_dir", "📁 Cartella di salvataggio"),
+    ("setting.inbox", "📥 Inbox"),
+    ("setting.sort_order", "🔃 Ordinamento"),
+    ("setting.conflict_policy", "⚠️ Nomi in conflitto"),
+    ("setting.language", "🌐 Lingua"),
+    ("setting.time_zone", "🕒 Fuso orario"),
+    ("setting.auto_naming", "🏷️ Nomi dei file"),
+    ("setting.name_template", "🧩 Modello dei nomi"),
+    ("sort_order.name", "A → Z"),
+    ("sort_order.name_descending", "Z → A"),
+    ("sort_order.newest", "Prima i più recenti"),
+    ("sort_order.oldest", "Prima i meno recenti"),
+    ("conflict_policy.reject", "Non salvare il file"),
+    ("conflict_policy.rename", "Aggiungi un numero al nome"),
+    ("conflict_policy.overwrite", "Sostituisci il vecchio file"),
+    ("auto_naming.ask", "Chiedi il nome"),
+    ("auto_naming.original_name", "Mantieni il nome originale"),
+    ("auto_naming.timestamp", "Usa la data come nome"),
+    ("inbox.on", "attiva"),
+    ("inbox.off", "disattivata"),
+    /* COMMANDS */
+    (
+        "help",
+        r#"*SALVARE FILE*:
+1. invia un file al bot (il file può essere qualsiasi tipo di messaggio: _testo_, _audio_, _video_, _immagine_, _sticker_, ecc.)
+2. naviga nella directory in cui vuoi salvarlo
+3. clicca _{here}_ per scegliere la directory corrente in cui salvare il file
+4. quando richiesto, invia il nome del file (il nome non può contenere il carattere `/`) SENZA estensione
+
+Il file sarà salvato con la seguente estensione:
+_immagine_ -> _.jpg_
+_video_ -> _.mp4_
+_audio_ -> la stessa estensione del file inviato
+_documento_ -> la stessa estensione del file inviato
+_altro_ -> _.tg+(tipo-del-messaggio-inviato)_
+
+*SALVARE ALBUM*:
+I file di un album sono salvati insieme: scegli la directory una volta sola, poi invia un nome per tutti (saranno numerati) o clicca _{keep_file_names}_.
+
+*CREARE DIRECTORY* (/mkdir):
+Clicca il pulsante _{here}_ dove vuoi creare la directory e invia il suo nome quando richiesto (il nome non può contenere il carattere `/`).
+
+*SPOSTARE FILE* (/move\_file):
+La procedura è quasi la stessa del salvataggio dei file.
+
+*RINOMINARE FILE* (/rename\_file):
+La procedura è quasi la stessa del salvataggio dei file.
+
+*ESPLORARE FILE E DIRECTORY* (/explorer):
+Clicca sulle directory per entrarci.
+Clicca sui file per ottenere il riferimento al file.
+Clicca _{select_files}_ per selezionare più file, poi SPOSTARLI, COPIARLI, ELIMINARLI o TAGGARLI tutti insieme.
+
+*ELIMINARE FILE/DIRECTORY* (/delete\_file e /delete\_dir):
+{coming_soon}
+
+*COMANDI CON PERCORSI*:
+Scrivi i percorsi invece di cliccare: /ls elenca una directory, /cd cambia la directory corrente e /pwd la mostra. `/mkdir <percorso>` crea una directory con le directory mancanti, `/mv <da> <a>` sposta o rinomina un file o una directory.
+I percorsi sono relativi alla directory corrente, metti quelli con spazi tra virgolette, es. `/mv "Il mio file.pdf" /Archivio`.
+
+*ANNULLA* (/undo):
+Annulla l'ultima operazione (creazione di directory, salvataggio, spostamento, rinomina, copia o eliminazione di file). L'operazione non viene annullata se i file e le directory coinvolti sono cambiati nel frattempo.
+
+*CRONOLOGIA* (/history):
+Elenca le ultime operazioni che possono essere annullate con /undo.
+
+*DIMENTICAMI* (/forget\_me):
+Elimina tutti i dati salvati su questa chat: file, directory e cronologia. Ti verrà chiesto di confermare due volte.
+
+*CONDIVIDERE DIRECTORY* (/share e /shares):
+Clicca il pulsante _{here}_ nella directory che vuoi condividere, scegli se l'altra chat può solo leggerla o anche modificarla, e inviale il link che ricevi. Il link può essere aperto una volta sola, e la directory appare nella directory _Shared_ dell'altra chat. Usa /shares per elencare le tue condivisioni e revocarle.
+
+*DRIVE* (/drive):
+Tieni i tuoi file in drive separati, ognuno con le sue directory. Clicca un drive per passare a quel drive: tutti i comandi agiscono sul drive attivo. Ogni chat ha un drive _{main_drive}_, che non può essere eliminato.
+
+*GRUPPI*:
+I membri di un gruppo condividono gli stessi file. I _visualizzatori_ possono solo esplorarli, gli _editor_ possono anche salvare, spostare e rinominare file e creare directory, gli _amministratori_ possono anche eliminare i dati del gruppo.
+Gli amministratori del gruppo sono anche amministratori del bot. Per dare un ruolo a un membro, rispondi a uno dei suoi messaggi con /role seguito da _viewer_, _editor_ o _admin_.
+
+*LINGUA* (/language):
+Il bot parla la lingua della tua app di Telegram quando la conosce, altrimenti l'inglese. Scegli un'altra lingua per tutta la chat con /language.
+
+*IMPOSTAZIONI* (/settings):
+Scegli la cartella in cui sono salvati i file ricevuti, come sono elencati i file, cosa fare quando esiste già un file con lo stesso nome, la lingua, il fuso orario delle date e come sono chiamati i file ricevuti.
+
+*INBOX*:
+Attiva l'inbox in /settings per salvare subito i file ricevuti nella cartella di salvataggio. Ogni file mantiene il nome con cui è stato inviato, oppure prende la prima riga della sua didascalia, oppure un nome creato dal modello dei nomi, in cui `{date}` e `{type}` diventano la data e il tipo del file. Clicca _{refine_file}_ sotto la conferma per spostare o rinominare il file.
+
+Problemi? Apri una issue su GitHub: [{repo_url}/issues]({repo_url}/issues)"#,
+    ),
+    ("start.greet", "Ciao!"),
+    ("start.greet_name", "Ciao {first_name}!"),
+    (
+        "start",
+        r#"{greet}
+Benvenuto su *Infinite Cloud*!
+
+Ecco un po' di aiuto per iniziare:
+
+{help}
+
+Per rivedere questo messaggio di aiuto, usa il comando /help"#,
+    ),
+    (
+        "info",
+        r#"*Infinite Cloud Bot* - spazio cloud infinito e gratuito su Telegram (basato sull'[Internet Computer](https://internetcomputer.org/))
+
+Istruzioni per l'uso: /help
+
+Altre informazioni e codice sorgente: [{repo_url}]({repo_url})
+
+_Versione: {version}_"#,
+    ),
+    ("coming_soon", "In arrivo..."),
+    ("generic_error", "Si è verificato un errore. Riprova."),
+    (
+        "update_error",
+        "Errore durante l'elaborazione dell'aggiornamento: {error}",
+    ),
+    /* SYSTEM MESSAGES */
+    ("current_path", "PERCORSO CORRENTE:\n\n`{path}`"),
+    (
+        "create_dir",
+        "Naviga nella directory in cui vuoi CREARE la nuova directory e clicca _{here}_",
+    ),
+    (
+        "create_file",
+        "Naviga nella directory in cui vuoi SALVARE il nuovo file e clicca _{here}_",
+    ),
+    (
+        "create_media_group",
+        "Naviga nella directory in cui vuoi SALVARE i file dell'album e clicca _{here}_",
+    ),
+    (
+        "ask_media_group_file_names",
+        "Inviami il NOME dei {count} file: saranno numerati, es. _nome-1_, oppure metti `{placeholder}` dove va il numero.\nClicca _{keep_file_names}_ per salvarli con i nomi con cui sono stati inviati.",
+    ),
+    ("ask_directory_name", "Inviami il nome della nuova DIRECTORY"),
+    ("ask_file_name", "Inviami il nome del nuovo FILE"),
+    (
+        "refine_file",
+        "Naviga nella directory in cui vuoi SPOSTARE *{file_name}* e clicca _{here}_",
+    ),
+    (
+        "ask_refine_file_name",
+        "Inviami il nuovo NOME di *{file_name}* (senza estensione per mantenere quella attuale), oppure clicca _{keep_file_names}_",
+    ),
+    (
+        "ask_rename_file",
+        "RINOMINA *{file_name}* in `{path}`\n\nInviami il nuovo NOME:",
+    ),
+    ("created_directory", "Directory *{dir_name}* CREATA in `{path}`"),
+    ("created_file", "File *{file_name}* CREATO in `{path}`"),
+    ("created_media_group", "{count} file SALVATI in `{path}`"),
+    ("created_media_group.errors", "{count} file NON SALVATI:"),
+    (
+        "renamed_file",
+        "File *{old_file_name}* RINOMINATO.\n\nNuovo nome: *{new_file_name}*\nPercorso: `{path}`",
+    ),
+    (
+        "moved_file",
+        "File *{file_name}* SPOSTATO.\n\nDa: `{from}`\nA: `{to}`",
+    ),
+    ("rename_file", "Seleziona il file che vuoi RINOMINARE"),
+    ("move_file.select_file", "Seleziona il file che vuoi SPOSTARE"),
+    (
+        "move_file.select_destination",
+        "File da SPOSTARE:\n`{path}`\n\nSeleziona la directory in cui vuoi spostare il file e clicca _{here}_",
+    ),
+    /* HISTORY */
+    ("journal.mkdir", "Directory CREATA: `{path}`"),
+    ("journal.save_file", "File CREATO: `{path}`"),
+    ("journal.move_file", "File SPOSTATO: `{from}` -> `{to}`"),
+    ("journal.rename_file", "File RINOMINATO: `{from}` -> `{to}`"),
+    ("journal.copy_file", "File COPIATO: `{from}` -> `{to}`"),
+    ("journal.delete_file", "File ELIMINATO: `{path}`"),
+    ("journal.tag_file", "File TAGGATO con #{tag}: `{path}`"),
+    ("journal.bulk", "{count} file {operation}:"),
+    ("undo_success", "ANNULLATO:\n{operation}"),
+    (
+        "undo_conflict",
+        "Impossibile annullare:\n{operation}\n\nMotivo: {reason}. L'operazione è stata rimossa dalla cronologia.",
+    ),
+    ("nothing_to_undo", "Non c'è niente da annullare."),
+    (
+        "undo_disabled",
+        "Annulla e cronologia sono disattivati per questo bot.",
+    ),
+    ("empty_history", "Non ci sono operazioni nella cronologia."),
+    (
+        "history",
+        "*CRONOLOGIA* (dalla più recente):\n\n{entries}",
+    ),
+    /* FORGET ME */
+    (
+        "forget_me",
+        r#"Stai per ELIMINARE TUTTI i dati salvati su questa chat: file, directory e cronologia.
+
+I file che hai inviato restano in questa chat, ma il bot non ne saprà più nulla.
+
+Clicca _{confirm}_ per continuare."#,
+    ),
+    (
+        "forget_me.final_confirmation",
+        "Questa operazione NON può essere annullata. Clicca di nuovo _{confirm}_ per eliminare tutti i tuoi dati.",
+    ),
+    (
+        "forgotten",
+        "Tutti i dati salvati su questa chat sono stati eliminati. Invia /start per ricominciare.",
+    ),
+    ("forget_me.cancelled", "Non è stato eliminato niente."),
+    (
+        "corrupted_filesystem",
+        r#"Purtroppo il tuo filesystem non può più essere letto.
+
+I suoi dati sono stati messi da parte, in modo che un amministratore possa esaminarli e provare a recuperarli.
+È stato creato per te un nuovo filesystem vuoto, puoi continuare a usare il bot come sempre."#,
+    ),
+    (
+        "corrupted_drive",
+        r#"Purtroppo questo drive non può più essere letto.
+
+Sei stato riportato al drive principale. Usa /drive per eliminare il drive illeggibile."#,
+    ),
+    /* ACCESS */
+    (
+        "chat_throttled",
+        "Stai inviando messaggi troppo velocemente. Rallenta e riprova tra qualche secondo.",
+    ),
+    (
+        "global_throttled",
+        "Il bot sta ricevendo troppi messaggi in questo momento. Riprova tra qualche secondo.",
+    ),
+    (
+        "access_denied",
+        "Purtroppo questo bot è privato. Chiedi l'accesso ai suoi proprietari, o invia /join seguito dal tuo codice di invito se ne hai uno.",
+    ),
+    (
+        "enrolled",
+        "Benvenuto! Ora puoi usare il bot. Invia /help per iniziare.",
+    ),
+    ("already_enrolled", "Puoi già usare il bot."),
+    /* GROUPS */
+    (
+        "permission_denied",
+        "Sei un *{role}* di questo gruppo, solo un *{required_role}* può farlo.",
+    ),
+    (
+        "foreign_keyboard",
+        "Questa tastiera è stata aperta da un altro membro. Invia un comando per aprire la tua.",
+    ),
+    (
+        "role_usage",
+        "Rispondi a un messaggio del membro con /role seguito da viewer, editor o admin.",
+    ),
+    (
+        "role_not_in_group",
+        "I ruoli possono essere assegnati solo nei gruppi.",
+    ),
+    ("role_set", "{first_name} ora è un *{role}* di questo gruppo."),
+    /* EXPLORER */
+    ("explorer_file", "File: *{file_name}*\nPercorso: `{path}`"),
+    ("explorer_file.tags", "Tag: {tags}"),
+    (
+        "select_files",
+        "Clicca i file per SELEZIONARLI, poi scegli cosa fare con i file selezionati\n\nFile selezionati: *{count}*",
+    ),
+    ("empty_selection", "Prima seleziona almeno un file."),
+    (
+        "invalid_tag",
+        "Impossibile aggiungere il tag: {reason}. Inviami un altro TAG.",
+    ),
+    ("invalid_tag.empty", "il tag è vuoto"),
+    ("invalid_tag.too_long", "il tag è più lungo di {max} caratteri"),
+    (
+        "invalid_tag.characters",
+        "il tag può contenere solo lettere, cifre, _ e -",
+    ),
+    (
+        "bulk_select_destination",
+        "Naviga nella directory in cui vuoi {operation} i {count} file selezionati e clicca _{here}_",
+    ),
+    (
+        "bulk_delete_confirmation",
+        "Stai per ELIMINARE i {count} file selezionati.\n\nClicca _{confirm}_ per continuare.",
+    ),
+    (
+        "ask_tag",
+        "Inviami il TAG da aggiungere ai {count} file selezionati, es. _#lavoro_",
+    ),
+    ("bulk_operation", "{count} file {operation}"),
+    (
+        "bulk_operation.failed",
+        "Operazione annullata, {count} file non possono essere {operation}:",
+    ),
+    /* SHARES */
+    (
+        "share",
+        "Naviga nella directory che vuoi CONDIVIDERE e clicca _{here}_",
+    ),
+    (
+        "share_permission",
+        "Directory da CONDIVIDERE:\n`{path}`\n\nL'altra chat può solo leggerla, o anche modificarla?",
+    ),
+    (
+        "share_created",
+        r#"Directory `{path}` CONDIVISA in *{permission}*.
+
+Invia questo link alla chat con cui vuoi condividerla, può essere aperto una volta sola:
+`{link}`
+
+Usa /shares per revocarla."#,
+    ),
+    (
+        "share_failed",
+        "Impossibile condividere la directory: {reason}.",
+    ),
+    (
+        "share_accepted",
+        "La directory condivisa ora è disponibile in `{path}` in *{permission}*. Aprila con /explorer.",
+    ),
+    (
+        "share_not_accepted",
+        "Impossibile aprire la directory condivisa: {reason}.",
+    ),
+    ("share.opened", "aperta dalla chat {chat_id}"),
+    ("share.not_opened", "link non ancora aperto"),
+    (
+        "no_shares",
+        "Non hai ancora condiviso nessuna directory. Usa /share per condividerne una.",
+    ),
+    ("shares.entry", "{index}. `{path}` in *{permission}*, {recipient}"),
+    (
+        "shares",
+        "*CONDIVISIONI*:\n\n{shares}\n\nClicca una condivisione per REVOCARLA.",
+    ),
+    (
+        "share_revoked",
+        "Condivisione di `{path}` REVOCATA.\n\n{shares}",
+    ),
+    /* DRIVES */
+    (
+        "drives",
+        "*DRIVE*:\n\nDrive attivo: *{name}*\n\nClicca un drive per PASSARE a quel drive.",
+    ),
+    ("ask_drive_name", "Inviami il nome del nuovo DRIVE"),
+    (
+        "drive_created",
+        "Drive *{name}* CREATO. Ora è il drive attivo.",
+    ),
+    ("drive_not_created", "Impossibile creare il drive: {reason}."),
+    ("drive_switched", "Sei passato al drive *{name}*."),
+    (
+        "delete_drive",
+        "Seleziona il drive che vuoi ELIMINARE. Il drive principale non può essere eliminato.",
+    ),
+    (
+        "delete_drive_confirmation",
+        r#"Stai per ELIMINARE il drive *{name}* con tutte le sue directory, e per revocare le sue condivisioni.
+
+I file che hai salvato nel drive restano in questa chat, ma il bot non ne saprà più nulla.
+
+Clicca _{confirm}_ per continuare."#,
+    ),
+    ("drive_deleted", "Drive *{name}* ELIMINATO."),
+    /* PATH COMMANDS */
+    ("working_dir", "Directory corrente: `{path}`"),
+    ("ls.empty", "`{path}` è vuota"),
+    ("created_directories.entry", "Directory `{path}` CREATA"),
+    ("moved_path", "SPOSTATO `{from}` -> `{to}`"),
+    (
+        "path_command_failed",
+        "Impossibile eseguire il comando: {reason}.",
+    ),
+    ("path_exists", "Il percorso esiste già"),
+    ("not_a_directory", "`{path}` non è una directory"),
+    ("not_a_file", "Non è un file"),
+    ("destination_taken", "La destinazione è già occupata"),
+    ("destination_not_found", "Directory di destinazione non trovata"),
+    (
+        "file_name_slash",
+        "Il nome del file non può contenere il carattere `/`",
+    ),
+    ("wrong_arguments", "Argomenti errati, usa `{usage}`"),
+    ("unclosed_quote", "Una virgoletta non è chiusa"),
+    /* LANGUAGE */
+    (
+        "language",
+        "*LINGUA*: {language}\n\nScegli la lingua del bot in questa chat, o _{auto}_ per usare la lingua dell'app di Telegram di ogni membro.",
+    ),
+    ("language.auto", "automatica"),
+    ("language_set", "Ora il bot parla *{language}* in questa chat."),
+    (
+        "language_auto_set",
+        "Ora il bot parla la lingua dell'app di Telegram di ogni membro.",
+    ),
+    (
+        "language_unknown",
+        "Lingua {language} sconosciuta. Scegline una tra: {languages}.",
+    ),
+    /* SETTINGS */
+    (
+        "settings",
+        "*IMPOSTAZIONI*\n\n{entries}\n\nScegli un'impostazione per cambiarla.",
+    ),
+    (
+        "save_dir_setting",
+        "Naviga nella directory in cui SALVARE i file ricevuti e clicca _{here}_",
+    ),
+    (
+        "sort_order_setting",
+        "Scegli l'ordine delle directory e dei file.",
+    ),
+    (
+        "conflict_policy_setting",
+        "Scegli cosa fare quando un file ricevuto ha il nome di un file che esiste già.",
+    ),
+    (
+        "auto_naming_setting",
+        "Scegli come chiamare i file ricevuti una volta scelta la loro directory. Il nome viene chiesto per i file inviati senza nome.",
+    ),
+    (
+        "ask_time_zone",
+        "*FUSO ORARIO*: {time_zone}\n\nInviami la differenza del tuo fuso orario da UTC, es. `+02:00` o `-5`, oppure `UTC`.",
+    ),
+    (
+        "invalid_time_zone",
+        "{reason}. Inviami una differenza come `+02:00`, oppure `UTC`.",
+    ),
+    (
+        "ask_name_template",
+        "*MODELLO DEI NOMI*: `{name_template}`\n\nInviami il nome dei file salvati nell'inbox senza un nome o una didascalia. `{date}` diventa la data del salvataggio e `{type}` il tipo del file, es. _photo_.",
+    ),
+    (
+        "invalid_name_template",
+        "{reason}. Inviami un modello come `{date}_{type}`.",
+    ),
+    ("invalid_name_template.empty", "Il modello è vuoto"),
+    (
+        "invalid_name_template.too_long",
+        "Il modello è più lungo di {max} caratteri",
+    ),
+    (
+        "invalid_name_template.slash",
+        "Il modello non può contenere il carattere `/`",
+    ),
+];
@@ -1,10 +1,14 @@
 mod defaults;
+mod i18n;
 mod path;
 mod reply;
+mod time;
 
 pub use defaults::*;
+pub use i18n::*;
 pub use path::*;
 pub use reply::*;
+pub use time::*;
 
 #[macro_export]
 macro_rules! custom_print {
@@ -35,3 +39,15 @@ pub(crate) fn get_current_time() -> u64 {
         ic_cdk::api::time()
     }
 }
+
+/// Returns the number of instructions executed so far in the current message.
+pub(crate) fn instruction_counter() -> u64 {
+    #[cfg(test)]
+    {
+        0
+    }
+    #[cfg(not(test))]
+    {
+        ic_cdk::api::instruction_counter()
+    }
+}
@@ -1,8 +1,8 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use frankenstein::types::InlineKeyboardButton;
 
-use crate::repositories::ChatSessionAction;
+use crate::{repositories::ChatSessionAction, utils::filesystem::root_path};
 
 /// Using the default `.is_absolute()` method is not possible because
 /// the `wasm32-unknown-unknown` target does not implement it.
@@ -11,6 +11,26 @@ pub fn is_absolute(path: &Path) -> bool {
     path_str.starts_with('/')
 }
 
+/// Resolves a path typed by the user, absolute or relative to `base`.
+/// The `.` and `..` components are resolved as well, without going above the root.
+pub fn resolve_path(base: &Path, path: &str) -> PathBuf {
+    let mut resolved = if path.starts_with('/') {
+        root_path()
+    } else {
+        base.to_path_buf()
+    };
+    for component in path.split('/') {
+        match component {
+            "" | "." => {}
+            ".." => {
+                resolved.pop();
+            }
+            name => resolved.push(name),
+        }
+    }
+    resolved
+}
+
 /// Creates an inline keyboard button for a given path.
 /// Use this function to create directory and files buttons for the file system explorer.
 pub fn path_button(path: &Path, is_dir: bool) -> InlineKeyboardButton {
@@ -42,6 +62,17 @@ mod tests {
         assert!(!is_absolute(Path::new("Documents/file.txt")));
     }
 
+    #[rstest]
+    #[case("/", "/Docs/2026", "/Docs/2026")]
+    #[case("/Docs", "2026/Q1/", "/Docs/2026/Q1")]
+    #[case("/Docs/2026", "../Archive", "/Docs/Archive")]
+    #[case("/Docs", "./a b.pdf", "/Docs/a b.pdf")]
+    #[case("/Docs", "../../..", "/")]
+    #[case("/Docs", "", "/Docs")]
+    fn test_resolve_path(#[case] base: &str, #[case] path: &str, #[case] expected: &str) {
+        assert_eq!(resolve_path(Path::new(base), path), PathBuf::from(expected));
+    }
+
     #[rstest]
     fn test_path_button() {
         let string_path = "/test_file.txt".to_string();
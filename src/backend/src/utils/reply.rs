@@ -1,5 +1,9 @@
 use frankenstein::{
-    methods::{EditMessageTextParams, SendMessageParams},
+    inline_mode::InlineQueryResult,
+    methods::{
+        AnswerCallbackQueryParams, AnswerInlineQueryParams, CopyMessageParams,
+        EditMessageTextParams, SendMessageParams,
+    },
     types::{InlineKeyboardMarkup, LinkPreviewOptions, ReplyMarkup, ReplyParameters},
     ParseMode,
 };
@@ -18,6 +22,12 @@ fn add_method(value: &mut Value, method: String) {
 pub enum MessageParams {
     Send(SendMessageParams),
     Edit(EditMessageTextParams),
+    /// Shows an alert to the user that pressed a button, without sending any message to the chat.
+    AnswerCallbackQuery(AnswerCallbackQueryParams),
+    /// Sends a copy of a message of another chat, e.g. a file of a shared directory.
+    Copy(CopyMessageParams),
+    /// Answers an inline query, which is not sent from a chat.
+    AnswerInlineQuery(AnswerInlineQueryParams),
 }
 
 impl MessageParams {
@@ -31,10 +41,45 @@ impl MessageParams {
         MessageParams::Edit(params)
     }
 
+    pub fn new_callback_query_alert(callback_query_id: String, text: String) -> Self {
+        let params = AnswerCallbackQueryParams::builder()
+            .callback_query_id(callback_query_id)
+            .text(text)
+            .show_alert(true)
+            .build();
+        MessageParams::AnswerCallbackQuery(params)
+    }
+
+    pub fn new_copy(chat_id: ChatId, from_chat_id: ChatId, message_id: MessageId) -> Self {
+        let params = CopyMessageParams::builder()
+            .chat_id(chat_id.into_tg_chat_id())
+            .from_chat_id(from_chat_id.into_tg_chat_id())
+            .message_id(message_id)
+            .build();
+        MessageParams::Copy(params)
+    }
+
+    pub fn new_inline_query_answer(
+        inline_query_id: String,
+        results: Vec<InlineQueryResult>,
+        cache_time: u32,
+    ) -> Self {
+        let params = AnswerInlineQueryParams::builder()
+            .inline_query_id(inline_query_id)
+            .results(results)
+            .cache_time(cache_time)
+            .is_personal(true)
+            .build();
+        MessageParams::AnswerInlineQuery(params)
+    }
+
     fn method(&self) -> String {
         match self {
             Self::Send(_) => "sendMessage".to_string(),
             Self::Edit(_) => "editMessageText".to_string(),
+            Self::AnswerCallbackQuery(_) => "answerCallbackQuery".to_string(),
+            Self::Copy(_) => "copyMessage".to_string(),
+            Self::AnswerInlineQuery(_) => "answerInlineQuery".to_string(),
         }
     }
 
@@ -42,6 +87,9 @@ impl MessageParams {
         let mut value = match self {
             Self::Send(params) => serde_json::to_value(params),
             Self::Edit(params) => serde_json::to_value(params),
+            Self::AnswerCallbackQuery(params) => serde_json::to_value(params),
+            Self::Copy(params) => serde_json::to_value(params),
+            Self::AnswerInlineQuery(params) => serde_json::to_value(params),
         }
         .map_err(|err| err.to_string())?;
 
@@ -54,6 +102,9 @@ impl MessageParams {
         match self {
             Self::Send(params) => params.text = text,
             Self::Edit(params) => params.text = text,
+            Self::AnswerCallbackQuery(params) => params.text = Some(text),
+            // the copy keeps the content of the original message
+            Self::Copy(_) | Self::AnswerInlineQuery(_) => {}
         }
     }
 
@@ -63,6 +114,20 @@ impl MessageParams {
                 params.reply_markup = Some(ReplyMarkup::InlineKeyboardMarkup(keyboard))
             }
             Self::Edit(params) => params.reply_markup = Some(keyboard),
+            Self::Copy(params) => {
+                params.reply_markup = Some(ReplyMarkup::InlineKeyboardMarkup(keyboard))
+            }
+            // alerts and inline results can't have a keyboard
+            Self::AnswerCallbackQuery(_) | Self::AnswerInlineQuery(_) => {}
+        }
+    }
+
+    pub fn has_inline_keyboard(&self) -> bool {
+        match self {
+            Self::Send(params) => params.reply_markup.is_some(),
+            Self::Edit(params) => params.reply_markup.is_some(),
+            Self::Copy(params) => params.reply_markup.is_some(),
+            Self::AnswerCallbackQuery(_) | Self::AnswerInlineQuery(_) => false,
         }
     }
 
@@ -70,6 +135,8 @@ impl MessageParams {
         match self {
             Self::Send(params) => params.parse_mode = parse_mode,
             Self::Edit(params) => params.parse_mode = parse_mode,
+            // alerts are always plain text, copies keep the formatting of the original message
+            Self::AnswerCallbackQuery(_) | Self::Copy(_) | Self::AnswerInlineQuery(_) => {}
         }
     }
 
@@ -80,9 +147,34 @@ impl MessageParams {
                     Some(ReplyParameters::builder().message_id(message_id).build());
                 Ok(())
             }
+            Self::Copy(params) => {
+                params.reply_parameters =
+                    Some(ReplyParameters::builder().message_id(message_id).build());
+                Ok(())
+            }
             Self::Edit(_) => {
                 Err("editMessageText does not support reply_to_message_id".to_string())
             }
+            Self::AnswerCallbackQuery(_) => {
+                Err("answerCallbackQuery does not support reply_to_message_id".to_string())
+            }
+            Self::AnswerInlineQuery(_) => {
+                Err("answerInlineQuery does not support reply_to_message_id".to_string())
+            }
+        }
+    }
+
+    pub fn reply_to_message_id(&self) -> Option<MessageId> {
+        match self {
+            Self::Send(params) => params
+                .reply_parameters
+                .as_ref()
+                .map(|reply_parameters| reply_parameters.message_id),
+            Self::Copy(params) => params
+                .reply_parameters
+                .as_ref()
+                .map(|reply_parameters| reply_parameters.message_id),
+            Self::Edit(_) | Self::AnswerCallbackQuery(_) | Self::AnswerInlineQuery(_) => None,
         }
     }
 
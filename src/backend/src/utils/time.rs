@@ -0,0 +1,100 @@
+use crate::repositories::UtcOffset;
+
+const NANOS_PER_SECOND: u64 = 1_000_000_000;
+const SECONDS_PER_MINUTE: u64 = 60;
+const SECONDS_PER_HOUR: u64 = 60 * SECONDS_PER_MINUTE;
+const SECONDS_PER_DAY: u64 = 24 * SECONDS_PER_HOUR;
+
+/// Converts the number of days since the Unix epoch to a `(year, month, day)` civil date.
+/// See http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let day_of_era = z - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day)
+}
+
+/// A date and time, as `(year, month, day, hours, minutes, seconds)`.
+type DateTime = (u64, u64, u64, u64, u64, u64);
+
+/// Converts a timestamp in nanoseconds since the Unix epoch
+/// to the date and time at the given offset from UTC.
+fn date_time(timestamp_nanos: u64, utc_offset: &UtcOffset) -> DateTime {
+    let offset_seconds = i64::from(utc_offset.minutes()) * SECONDS_PER_MINUTE as i64;
+    let seconds = (timestamp_nanos / NANOS_PER_SECOND).saturating_add_signed(offset_seconds);
+    let (year, month, day) = civil_from_days(seconds / SECONDS_PER_DAY);
+    let seconds_of_day = seconds % SECONDS_PER_DAY;
+    let hours = seconds_of_day / SECONDS_PER_HOUR;
+    let minutes = (seconds_of_day % SECONDS_PER_HOUR) / SECONDS_PER_MINUTE;
+
+    (
+        year,
+        month,
+        day,
+        hours,
+        minutes,
+        seconds_of_day % SECONDS_PER_MINUTE,
+    )
+}
+
+/// Formats a timestamp in nanoseconds since the Unix epoch
+/// as `YYYY-MM-DD HH:MM UTC+HH:MM`, in the given time zone.
+pub fn format_timestamp(timestamp_nanos: u64, utc_offset: &UtcOffset) -> String {
+    let (year, month, day, hours, minutes, _) = date_time(timestamp_nanos, utc_offset);
+
+    format!("{year:04}-{month:02}-{day:02} {hours:02}:{minutes:02} {utc_offset}")
+}
+
+/// Formats a timestamp in nanoseconds since the Unix epoch as `YYYY-MM-DD_HH-MM-SS`,
+/// in the given time zone, to be used in file names.
+pub fn format_file_timestamp(timestamp_nanos: u64, utc_offset: &UtcOffset) -> String {
+    let (year, month, day, hours, minutes, seconds) = date_time(timestamp_nanos, utc_offset);
+
+    format!("{year:04}-{month:02}-{day:02}_{hours:02}-{minutes:02}-{seconds:02}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    #[rstest]
+    #[case(0, "1970-01-01 00:00 UTC")]
+    #[case(951_782_400_000_000_000, "2000-02-29 00:00 UTC")]
+    #[case(1_700_000_000_000_000_000, "2023-11-14 22:13 UTC")]
+    #[case(1_792_324_799_000_000_000, "2026-10-18 11:59 UTC")]
+    fn test_format_timestamp(#[case] timestamp_nanos: u64, #[case] expected: &str) {
+        assert_eq!(
+            format_timestamp(timestamp_nanos, &UtcOffset::default()),
+            expected
+        );
+    }
+
+    #[rstest]
+    #[case("+02:00", "2026-10-18 13:59 UTC+02:00")]
+    #[case("-12:00", "2026-10-17 23:59 UTC-12:00")]
+    #[case("+05:30", "2026-10-18 17:29 UTC+05:30")]
+    fn test_format_timestamp_with_offset(#[case] utc_offset: &str, #[case] expected: &str) {
+        let utc_offset = utc_offset.parse().unwrap();
+        assert_eq!(
+            format_timestamp(1_792_324_799_000_000_000, &utc_offset),
+            expected
+        );
+    }
+
+    #[rstest]
+    fn test_format_file_timestamp() {
+        assert_eq!(
+            format_file_timestamp(1_792_324_799_000_000_000, &"+01:00".parse().unwrap()),
+            "2026-10-18_12-59-59"
+        );
+    }
+}
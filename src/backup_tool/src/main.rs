@@ -0,0 +1,448 @@
+use std::{
+    env,
+    fs::{self, File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    process,
+};
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_agent::{
+    identity::{BasicIdentity, Secp256k1Identity},
+    Agent, Identity,
+};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+const DEFAULT_REPLICA_URL: &str = "http://127.0.0.1:4943";
+const MANIFEST_FILE_NAME: &str = "manifest.json";
+
+const USAGE: &str = "Usage:
+  backup_tool backup --canister-id <id> --output <dir> [--replica-url <url>] [--identity-pem <path>]
+  backup_tool restore --canister-id <id> --input <dir> [--replica-url <url>] [--identity-pem <path>]
+
+The identity defaults to the dfx default identity, which must be a controller of the canister.";
+
+// Mirrors of the backup types exposed by the backend canister, see src/backend/backend.did
+
+#[derive(CandidType, Deserialize, Debug, Clone)]
+struct MemoryRegionInfo {
+    memory_id: u8,
+    size: u64,
+}
+
+#[derive(CandidType, Deserialize, Debug, Clone)]
+struct BackupManifest {
+    chunk_size: u64,
+    regions: Vec<MemoryRegionInfo>,
+}
+
+#[derive(CandidType, Deserialize, Debug)]
+struct BackupChunkRequest {
+    memory_id: u8,
+    offset: u64,
+}
+
+#[derive(CandidType, Deserialize, Debug)]
+struct BackupChunk {
+    memory_id: u8,
+    offset: u64,
+    data: Vec<u8>,
+    sha256: Vec<u8>,
+}
+
+/// The manifest written next to the region files, with the digest of each whole region.
+#[derive(Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
+struct BackupFileManifest {
+    chunk_size: u64,
+    regions: Vec<BackupFileRegion>,
+}
+
+#[derive(Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
+struct BackupFileRegion {
+    memory_id: u8,
+    size: u64,
+    sha256: String,
+}
+
+enum Command {
+    Backup { output: PathBuf },
+    Restore { input: PathBuf },
+}
+
+struct Args {
+    command: Command,
+    canister_id: Principal,
+    replica_url: String,
+    identity_pem: PathBuf,
+}
+
+impl Args {
+    fn parse() -> Result<Self, String> {
+        let mut args = env::args().skip(1);
+        let command = args.next().ok_or("Missing command")?;
+
+        let mut canister_id = None;
+        let mut dir = None;
+        let mut replica_url = DEFAULT_REPLICA_URL.to_string();
+        let mut identity_pem = default_identity_pem()?;
+        while let Some(flag) = args.next() {
+            let value = args
+                .next()
+                .ok_or_else(|| format!("Missing value for {flag}"))?;
+            match (command.as_str(), flag.as_str()) {
+                (_, "--canister-id") => {
+                    canister_id = Some(
+                        Principal::from_text(&value)
+                            .map_err(|err| format!("Invalid canister id: {err}"))?,
+                    )
+                }
+                ("backup", "--output") | ("restore", "--input") => dir = Some(PathBuf::from(value)),
+                (_, "--replica-url") => replica_url = value,
+                (_, "--identity-pem") => identity_pem = PathBuf::from(value),
+                _ => return Err(format!("Unknown argument {flag}")),
+            }
+        }
+
+        let canister_id = canister_id.ok_or("Missing --canister-id")?;
+        let command = match command.as_str() {
+            "backup" => Command::Backup {
+                output: dir.ok_or("Missing --output")?,
+            },
+            "restore" => Command::Restore {
+                input: dir.ok_or("Missing --input")?,
+            },
+            _ => return Err(format!("Unknown command {command}")),
+        };
+
+        Ok(Self {
+            command,
+            canister_id,
+            replica_url,
+            identity_pem,
+        })
+    }
+}
+
+fn default_identity_pem() -> Result<PathBuf, String> {
+    let home = env::var("HOME").map_err(|_| "HOME is not set".to_string())?;
+    Ok(PathBuf::from(home).join(".config/dfx/identity/default/identity.pem"))
+}
+
+fn load_identity(path: &Path) -> Result<Box<dyn Identity>, String> {
+    if let Ok(identity) = Secp256k1Identity::from_pem_file(path) {
+        return Ok(Box::new(identity));
+    }
+    BasicIdentity::from_pem_file(path)
+        .map(|identity| Box::new(identity) as Box<dyn Identity>)
+        .map_err(|err| format!("Failed to load identity from {}: {err}", path.display()))
+}
+
+struct BackendClient {
+    agent: Agent,
+    canister_id: Principal,
+}
+
+impl BackendClient {
+    async fn new(args: &Args) -> Result<Self, String> {
+        let agent = Agent::builder()
+            .with_url(&args.replica_url)
+            .with_boxed_identity(load_identity(&args.identity_pem)?)
+            .build()
+            .map_err(|err| format!("Failed to create agent: {err}"))?;
+        // the tool is meant for a local replica, whose root key isn't hardcoded in the agent
+        agent
+            .fetch_root_key()
+            .await
+            .map_err(|err| format!("Failed to fetch the root key: {err}"))?;
+
+        Ok(Self {
+            agent,
+            canister_id: args.canister_id,
+        })
+    }
+
+    async fn update<R: CandidType + for<'de> Deserialize<'de>>(
+        &self,
+        method: &str,
+        arg: Vec<u8>,
+    ) -> Result<R, String> {
+        let response = self
+            .agent
+            .update(&self.canister_id, method)
+            .with_arg(arg)
+            .call_and_wait()
+            .await
+            .map_err(|err| format!("Failed to call {method}: {err}"))?;
+        Decode!(&response, Result<R, String>)
+            .map_err(|err| format!("Failed to decode the response of {method}: {err}"))?
+    }
+
+    async fn query<R: CandidType + for<'de> Deserialize<'de>>(
+        &self,
+        method: &str,
+        arg: Vec<u8>,
+    ) -> Result<R, String> {
+        let response = self
+            .agent
+            .query(&self.canister_id, method)
+            .with_arg(arg)
+            .call()
+            .await
+            .map_err(|err| format!("Failed to call {method}: {err}"))?;
+        Decode!(&response, Result<R, String>)
+            .map_err(|err| format!("Failed to decode the response of {method}: {err}"))?
+    }
+}
+
+fn encode<T: CandidType>(value: &T) -> Result<Vec<u8>, String> {
+    Encode!(value).map_err(|err| format!("Failed to encode the argument: {err}"))
+}
+
+fn region_file_path(dir: &Path, memory_id: u8) -> PathBuf {
+    dir.join(format!("region-{memory_id}.bin"))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn file_sha256(path: &Path) -> Result<String, String> {
+    let mut file =
+        File::open(path).map_err(|err| format!("Failed to open {}: {err}", path.display()))?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0; 1024 * 1024];
+    loop {
+        let read = file
+            .read(&mut buf)
+            .map_err(|err| format!("Failed to read {}: {err}", path.display()))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buf[..read]);
+    }
+    Ok(hex(&hasher.finalize()))
+}
+
+fn read_file_manifest(dir: &Path) -> Result<Option<BackupFileManifest>, String> {
+    let path = dir.join(MANIFEST_FILE_NAME);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content =
+        fs::read(&path).map_err(|err| format!("Failed to read {}: {err}", path.display()))?;
+    serde_json::from_slice(&content)
+        .map(Some)
+        .map_err(|err| format!("Failed to parse {}: {err}", path.display()))
+}
+
+fn write_file_manifest(dir: &Path, manifest: &BackupFileManifest) -> Result<(), String> {
+    let path = dir.join(MANIFEST_FILE_NAME);
+    let content = serde_json::to_vec_pretty(manifest)
+        .map_err(|err| format!("Failed to serialize the manifest: {err}"))?;
+    fs::write(&path, content).map_err(|err| format!("Failed to write {}: {err}", path.display()))
+}
+
+/// Returns the offset to resume the download of a region from, dropping any partial chunk.
+fn resume_offset(file: &File, region: &MemoryRegionInfo, chunk_size: u64) -> Result<u64, String> {
+    let len = file
+        .metadata()
+        .map_err(|err| format!("Failed to read the region file metadata: {err}"))?
+        .len();
+    Ok((len.min(region.size) / chunk_size) * chunk_size)
+}
+
+async fn backup(client: &BackendClient, output: &Path) -> Result<(), String> {
+    fs::create_dir_all(output)
+        .map_err(|err| format!("Failed to create {}: {err}", output.display()))?;
+
+    let manifest: BackupManifest = client.update("start_backup", encode(&())?).await?;
+
+    // a previous run with the same manifest can be resumed, as the stable memory
+    // doesn't change while the canister is in maintenance mode
+    let can_resume = read_file_manifest(output)?.is_some_and(|file_manifest| {
+        file_manifest.chunk_size == manifest.chunk_size
+            && file_manifest
+                .regions
+                .iter()
+                .map(|region| (region.memory_id, region.size))
+                .eq(manifest
+                    .regions
+                    .iter()
+                    .map(|region| (region.memory_id, region.size)))
+    });
+
+    if !can_resume {
+        // the digests are only known at the end, but the sizes are enough to resume
+        write_file_manifest(
+            output,
+            &BackupFileManifest {
+                chunk_size: manifest.chunk_size,
+                regions: manifest
+                    .regions
+                    .iter()
+                    .map(|region| BackupFileRegion {
+                        memory_id: region.memory_id,
+                        size: region.size,
+                        sha256: String::new(),
+                    })
+                    .collect(),
+            },
+        )?;
+    }
+
+    let mut file_regions = vec![];
+    for region in &manifest.regions {
+        let path = region_file_path(output, region.memory_id);
+        let mut file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .read(true)
+            .write(true)
+            .open(&path)
+            .map_err(|err| format!("Failed to open {}: {err}", path.display()))?;
+
+        let mut offset = if can_resume {
+            resume_offset(&file, region, manifest.chunk_size)?
+        } else {
+            0
+        };
+        file.set_len(offset)
+            .and_then(|_| file.seek(SeekFrom::Start(offset)).map(|_| ()))
+            .map_err(|err| format!("Failed to prepare {}: {err}", path.display()))?;
+
+        while offset < region.size {
+            let chunk: BackupChunk = client
+                .query(
+                    "get_backup_chunk",
+                    encode(&BackupChunkRequest {
+                        memory_id: region.memory_id,
+                        offset,
+                    })?,
+                )
+                .await?;
+            if chunk.memory_id != region.memory_id || chunk.offset != offset {
+                return Err(format!(
+                    "Unexpected chunk at offset {} of memory region {}",
+                    chunk.offset, chunk.memory_id
+                ));
+            }
+            if Sha256::digest(&chunk.data)[..] != chunk.sha256[..] {
+                return Err(format!(
+                    "Checksum mismatch for chunk at offset {offset} of memory region {}",
+                    region.memory_id
+                ));
+            }
+
+            file.write_all(&chunk.data)
+                .map_err(|err| format!("Failed to write {}: {err}", path.display()))?;
+            offset += chunk.data.len() as u64;
+            println!(
+                "Memory region {}: {offset}/{} bytes",
+                region.memory_id, region.size
+            );
+        }
+        file.sync_all()
+            .map_err(|err| format!("Failed to sync {}: {err}", path.display()))?;
+
+        file_regions.push(BackupFileRegion {
+            memory_id: region.memory_id,
+            size: region.size,
+            sha256: file_sha256(&path)?,
+        });
+    }
+    write_file_manifest(
+        output,
+        &BackupFileManifest {
+            chunk_size: manifest.chunk_size,
+            regions: file_regions,
+        },
+    )?;
+
+    client.update::<()>("finish_backup", encode(&())?).await?;
+    println!("Backup written to {}", output.display());
+    Ok(())
+}
+
+async fn restore(client: &BackendClient, input: &Path) -> Result<(), String> {
+    let file_manifest = read_file_manifest(input)?
+        .ok_or_else(|| format!("No {MANIFEST_FILE_NAME} in {}", input.display()))?;
+
+    for region in &file_manifest.regions {
+        let path = region_file_path(input, region.memory_id);
+        let sha256 = file_sha256(&path)?;
+        if sha256 != region.sha256 {
+            return Err(format!(
+                "Checksum mismatch for {}, the backup is incomplete or corrupted",
+                path.display()
+            ));
+        }
+    }
+
+    let manifest = BackupManifest {
+        chunk_size: file_manifest.chunk_size,
+        regions: file_manifest
+            .regions
+            .iter()
+            .map(|region| MemoryRegionInfo {
+                memory_id: region.memory_id,
+                size: region.size,
+            })
+            .collect(),
+    };
+    client
+        .update::<()>("start_restore", encode(&manifest)?)
+        .await?;
+
+    for region in &manifest.regions {
+        let path = region_file_path(input, region.memory_id);
+        let mut file =
+            File::open(&path).map_err(|err| format!("Failed to open {}: {err}", path.display()))?;
+
+        let mut offset = 0;
+        while offset < region.size {
+            let length = manifest.chunk_size.min(region.size - offset);
+            let mut data = vec![0; length as usize];
+            file.read_exact(&mut data)
+                .map_err(|err| format!("Failed to read {}: {err}", path.display()))?;
+
+            let chunk = BackupChunk {
+                memory_id: region.memory_id,
+                offset,
+                sha256: Sha256::digest(&data).to_vec(),
+                data,
+            };
+            client
+                .update::<()>("restore_chunk", encode(&chunk)?)
+                .await?;
+            offset += length;
+            println!(
+                "Memory region {}: {offset}/{} bytes",
+                region.memory_id, region.size
+            );
+        }
+    }
+
+    client.update::<()>("finish_restore", encode(&())?).await?;
+    println!("Backup restored from {}", input.display());
+    println!("Upgrade the canister to load the restored memory, it rejects the updates until then");
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() {
+    let result = async {
+        let args = Args::parse().map_err(|err| format!("{err}\n\n{USAGE}"))?;
+        let client = BackendClient::new(&args).await?;
+        match &args.command {
+            Command::Backup { output } => backup(&client, output).await,
+            Command::Restore { input } => restore(&client, input).await,
+        }
+    }
+    .await;
+
+    if let Err(err) = result {
+        eprintln!("Error: {err}");
+        process::exit(1);
+    }
+}